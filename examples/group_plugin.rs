@@ -0,0 +1,120 @@
+// A strategy as a plugin, see src/plugin.rs for the ABI. Build it with
+//
+//     cargo build --example group_plugin
+//
+// and name target/debug/examples/libgroup_plugin.so as the path of a plugin
+// player. It leaves the table as it is and lays out every group it can make
+// from its rack without jokers, or draws when there is none or the groups are
+// not worth the 30 points of an opening.
+
+use std::ffi::c_char;
+
+const PLUGIN_DRAW: i32 = -1;
+const OPENING_POINTS: u32 = 30;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CTile {
+    pub color: u8,
+    pub number: u8,
+    pub is_joker: u8,
+}
+
+#[repr(C)]
+pub struct CPosition {
+    pub rack: *const CTile,
+    pub rack_len: usize,
+    pub table: *const CTile,
+    pub table_set_lengths: *const u8,
+    pub table_sets: usize,
+    pub opened: u8,
+    pub must_play: u8,
+}
+
+#[repr(C)]
+pub struct PluginVTable {
+    pub abi_version: u32,
+    pub name: extern "C" fn() -> *const c_char,
+    pub choose_move:
+        extern "C" fn(position: *const CPosition, tiles: *mut CTile, set_lengths: *mut u8) -> i32,
+}
+
+static VTABLE: PluginVTable = PluginVTable {
+    abi_version: 1,
+    name,
+    choose_move,
+};
+
+#[no_mangle]
+pub extern "C" fn rummikub_plugin_v1() -> *const PluginVTable {
+    &VTABLE
+}
+
+extern "C" fn name() -> *const c_char {
+    c"Groups".as_ptr()
+}
+
+extern "C" fn choose_move(
+    position: *const CPosition,
+    tiles: *mut CTile,
+    set_lengths: *mut u8,
+) -> i32 {
+    // SAFETY: the engine hands over a valid position for the length of the call
+    let Some(position) = (unsafe { position.as_ref() }) else {
+        return -2;
+    };
+    // SAFETY: the engine's buffers hold as many tiles and sets as it says
+    let (rack, table_set_lengths) = unsafe {
+        (
+            std::slice::from_raw_parts(position.rack, position.rack_len),
+            std::slice::from_raw_parts(position.table_set_lengths, position.table_sets),
+        )
+    };
+    let table_len = table_set_lengths
+        .iter()
+        .map(|&length| length as usize)
+        .sum();
+    // SAFETY: the table holds the tiles of every set one after another
+    let table = unsafe { std::slice::from_raw_parts(position.table, table_len) };
+
+    // One tile of each color for every number held in at least three colors
+    let mut groups = Vec::new();
+    for number in 1..=13 {
+        let group = (0..4)
+            .filter_map(|color| {
+                rack.iter()
+                    .find(|tile| tile.is_joker == 0 && tile.number == number && tile.color == color)
+            })
+            .copied()
+            .collect::<Vec<CTile>>();
+        if group.len() >= 3 {
+            groups.push(group);
+        }
+    }
+    let points = groups
+        .iter()
+        .flatten()
+        .map(|tile| tile.number as u32)
+        .sum::<u32>();
+    if groups.is_empty() || (position.opened == 0 && points < OPENING_POINTS) {
+        return PLUGIN_DRAW;
+    }
+
+    // The table as it was, then the new groups
+    let new_tiles = table.iter().chain(groups.iter().flatten());
+    let new_lengths = table_set_lengths
+        .iter()
+        .copied()
+        .chain(groups.iter().map(|group| group.len() as u8));
+    for (index, tile) in new_tiles.enumerate() {
+        // SAFETY: the engine's buffer has room for every tile of the pool
+        unsafe { tiles.add(index).write(*tile) };
+    }
+    let mut sets = 0;
+    for (index, length) in new_lengths.enumerate() {
+        // SAFETY: the engine's buffer has room for every set the pool makes
+        unsafe { set_lengths.add(index).write(length) };
+        sets += 1;
+    }
+    sets
+}
@@ -0,0 +1,36 @@
+use rummikub_bot::diagnostic::{self, Span};
+use rummikub_bot::notation;
+
+// Every span of an error has to be the text at its place in the input
+fn check_spans(text: &str, error: Option<color_eyre::Report>) {
+    for span in error.iter().flat_map(diagnostic::spans) {
+        assert_eq!(
+            text.get(span.start..span.end),
+            Some(span.text.as_str()),
+            "{:?}",
+            text
+        );
+    }
+}
+
+// The parsers of tiles, sets, racks and tables take any text without
+// panicking, and Span::of finds the slices of a text and nothing else
+pub fn parsers(text: &str) {
+    check_spans(text, notation::parse_tiles(text).err());
+    check_spans(text, notation::parse_rack(text).err());
+    check_spans(text, notation::parse_rack_with_ghosts(text).err());
+    check_spans(text, notation::parse_set(text).err());
+    check_spans(text, notation::parse_table(text).err());
+
+    let middle = (0..=text.len() / 2)
+        .rev()
+        .find(|&index| text.is_char_boundary(index))
+        .unwrap_or(0);
+    let (left, right) = text.split_at(middle);
+    let span = Span::of(text, right).map(|span| (span.start, span.end));
+    assert_eq!(span, Some((left.len(), text.len())));
+    assert!(right.is_empty() || Span::of(left, right).is_none());
+    assert!(Span::of(right, left).is_none() || left.is_empty());
+    let copy = text.to_string();
+    assert!(text.is_empty() || Span::of(text, &copy).is_none());
+}
@@ -0,0 +1,14 @@
+// `cargo +nightly fuzz run parsers` from the root of the repository. The
+// checks are in check.rs, which tests/fuzz.rs runs on generated inputs too.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+#[path = "check.rs"]
+mod check;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(text) = std::str::from_utf8(data) {
+        check::parsers(text);
+    }
+});
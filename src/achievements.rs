@@ -0,0 +1,230 @@
+use crate::cli::Args;
+use crate::config;
+use color_eyre::{eyre::bail, eyre::Report, eyre::Result};
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+// What a user did in the interactive modes, summed over every session
+#[derive(Default, Clone, Copy)]
+pub struct Record {
+    pub games: u32,
+    pub wins: u32,
+    // Points left on the rack at the end of the games, summed
+    pub points_left: u32,
+    // Points of the most a single turn laid out
+    pub best_meld: u32,
+    pub puzzles_tried: u32,
+    pub puzzles_solved: u32,
+}
+
+const KEYS: [&str; 7] = [
+    "name",
+    "games",
+    "wins",
+    "points_left",
+    "best_meld",
+    "puzzles_tried",
+    "puzzles_solved",
+];
+
+// Whether a record has earned an achievement
+type Earned = fn(&Record) -> bool;
+
+// Earned once the record gets there, in the order they are shown
+const ACHIEVEMENTS: [(&str, Earned); 7] = [
+    ("First game", |record| record.games >= 1),
+    ("First win", |record| record.wins >= 1),
+    ("Regular: 10 games played", |record| record.games >= 10),
+    ("Champion: 10 games won", |record| record.wins >= 10),
+    ("Big opener: 50 points in one turn", |record| {
+        record.best_meld >= 50
+    }),
+    ("First puzzle solved", |record| record.puzzles_solved >= 1),
+    ("Puzzler: 25 puzzles solved", |record| {
+        record.puzzles_solved >= 25
+    }),
+];
+
+impl Record {
+    fn achievements(&self) -> Vec<&'static str> {
+        ACHIEVEMENTS
+            .iter()
+            .filter(|(_, earned)| earned(self))
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+// $XDG_DATA_HOME/rummikubbot/stats.toml, ~/.local/share when it is not set
+fn stats_path() -> Result<PathBuf> {
+    let dir = match env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => match env::var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(".local").join("share"),
+            None => bail!("cannot find the home directory to keep the statistics in"),
+        },
+    };
+    Ok(dir.join("rummikubbot").join("stats.toml"))
+}
+
+// The user the statistics of a session count for: `--user`, else the login
+// name. None with `--no-stats`.
+pub fn user(args: &Args) -> Option<String> {
+    if args.flag("--no-stats") {
+        return None;
+    }
+    args.value("--user")
+        .map(str::to_string)
+        .or_else(|| env::var("USER").ok())
+        .filter(|name| !name.is_empty())
+}
+
+// The records of every user on this machine, kept in a file like
+//
+//     [[user]]
+//     name = "alice"
+//     games = 12
+//     wins = 5
+//     points_left = 130
+//     best_meld = 42
+//     puzzles_tried = 9
+//     puzzles_solved = 7
+pub struct Records {
+    path: PathBuf,
+    users: Vec<(String, Record)>,
+}
+
+impl Records {
+    pub fn load() -> Result<Records> {
+        let path = stats_path()?;
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error.into()),
+        };
+        let mut users = Vec::new();
+        for table in config::parse(&text)? {
+            match table.name.as_str() {
+                "" => table.check_keys(&[])?,
+                "user" => {
+                    table.check_keys(&KEYS)?;
+                    let Some(name) = table.string("name")? else {
+                        bail!(
+                            "line {}: a user needs a name in {}",
+                            table.line,
+                            path.display()
+                        );
+                    };
+                    let count =
+                        |key: &str| -> Result<u32> { Ok(table.number(key)?.unwrap_or(0.0) as u32) };
+                    let record = Record {
+                        games: count("games")?,
+                        wins: count("wins")?,
+                        points_left: count("points_left")?,
+                        best_meld: count("best_meld")?,
+                        puzzles_tried: count("puzzles_tried")?,
+                        puzzles_solved: count("puzzles_solved")?,
+                    };
+                    users.push((name.to_string(), record));
+                }
+                other => bail!(
+                    "line {}: unknown table [{}] in {}",
+                    table.line,
+                    other,
+                    path.display()
+                ),
+            }
+        }
+        Ok(Records { path, users })
+    }
+
+    fn save(&self) -> Result<()> {
+        let mut text = String::new();
+        for (name, record) in &self.users {
+            text.push_str(&format!(
+                "[[user]]\nname = \"{}\"\ngames = {}\nwins = {}\npoints_left = {}\nbest_meld = {}\npuzzles_tried = {}\npuzzles_solved = {}\n\n",
+                config::escape(name),
+                record.games,
+                record.wins,
+                record.points_left,
+                record.best_meld,
+                record.puzzles_tried,
+                record.puzzles_solved
+            ));
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, text)?;
+        Ok(())
+    }
+
+    // Changes the record of the user, saves it and returns the achievements
+    // it earned by that
+    pub fn update(
+        &mut self,
+        name: &str,
+        change: impl FnOnce(&mut Record),
+    ) -> Result<Vec<&'static str>> {
+        let index = match self.users.iter().position(|(user, _)| user == name) {
+            Some(index) => index,
+            None => {
+                self.users.push((name.to_string(), Record::default()));
+                self.users.len() - 1
+            }
+        };
+        let record = &mut self.users[index].1;
+        let before = record.achievements();
+        change(record);
+        let earned = record
+            .achievements()
+            .into_iter()
+            .filter(|achievement| !before.contains(achievement))
+            .collect();
+        self.save()?;
+        Ok(earned)
+    }
+}
+
+pub fn print_earned(name: &str, earned: &[&str]) {
+    for achievement in earned {
+        println!("{} earned an achievement: {}", name, achievement);
+    }
+}
+
+// Shows the statistics and achievements of `--user`, or of every user
+pub fn run(args: &Args) -> Result<(), Report> {
+    let records = Records::load()?;
+    let users = records
+        .users
+        .iter()
+        .filter(|(name, _)| args.value("--user").is_none_or(|user| user == name))
+        .collect::<Vec<_>>();
+    if users.is_empty() {
+        println!("No statistics yet, they are kept for games of play --names and for puzzles");
+    }
+    for (name, record) in users {
+        println!(
+            "{}: {} games played, {} won",
+            name, record.games, record.wins
+        );
+        if record.games > 0 {
+            println!(
+                "  points left on the rack: {:.1} a game",
+                record.points_left as f64 / record.games as f64
+            );
+        }
+        println!("  best turn: {} points", record.best_meld);
+        println!(
+            "  puzzles: {} of {} solved",
+            record.puzzles_solved, record.puzzles_tried
+        );
+        let achievements = record.achievements();
+        if !achievements.is_empty() {
+            println!("  achievements: {}", achievements.join(", "));
+        }
+    }
+    Ok(())
+}
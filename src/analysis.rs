@@ -0,0 +1,75 @@
+use crate::cli::Args;
+use crate::deal;
+use crate::solver;
+use color_eyre::{eyre::bail, eyre::Report, eyre::Result};
+use std::fs;
+
+struct MeldProbability {
+    rack_size: u32,
+    trials: u32,
+    hits: u32,
+}
+
+impl MeldProbability {
+    fn probability(&self) -> f64 {
+        self.hits as f64 / self.trials as f64
+    }
+
+    fn std_error(&self) -> f64 {
+        let p = self.probability();
+        (p * (1.0 - p) / self.trials as f64).sqrt()
+    }
+}
+
+// Monte Carlo estimate of how often a random rack of the given size holds a
+// valid initial meld
+fn estimate(rack_size: u32, trials: u32) -> MeldProbability {
+    let hits = (0..trials)
+        .filter(|_| solver::has_initial_meld(&deal::random_rack(rack_size)))
+        .count() as u32;
+
+    MeldProbability {
+        rack_size,
+        trials,
+        hits,
+    }
+}
+
+pub fn run(args: &Args) -> Result<(), Report> {
+    let trials: u32 = args.parse_or("--trials", 10000)?;
+    let min: u32 = args.parse_or("--min", 14)?;
+    let max: u32 = args.parse_or("--max", 30)?;
+    if trials == 0 {
+        bail!("--trials must be positive");
+    }
+    if min > max {
+        bail!("--min of {} is above --max of {}", min, max);
+    }
+
+    println!("Tiles | Probability | Std. error");
+    let mut csv = String::from("tiles,trials,hits,probability,std_error\n");
+    for rack_size in min..=max {
+        let estimate = estimate(rack_size, trials);
+        println!(
+            "{:5} | {:11.4} | {:10.4}",
+            rack_size,
+            estimate.probability(),
+            estimate.std_error()
+        );
+        csv.push_str(&format!(
+            "{},{},{},{:.6},{:.6}\n",
+            estimate.rack_size,
+            estimate.trials,
+            estimate.hits,
+            estimate.probability(),
+            estimate.std_error()
+        ));
+    }
+
+    if let Some(path) = args.value("--csv") {
+        fs::write(path, csv)?;
+        println!("Table written to {}", path);
+    }
+
+    Ok(())
+}
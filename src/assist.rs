@@ -0,0 +1,374 @@
+use crate::budget;
+use crate::cli::Args;
+use crate::ghost;
+use crate::i18n::Message;
+use crate::json::Json;
+use crate::notation;
+use crate::notify::{self, Event};
+use crate::render;
+use crate::search_trace::SearchTrace;
+use crate::solution::{self, Solution};
+use crate::solver::{self, Inventory, Objective, Origin, Set, Tile};
+use crate::speech;
+use crate::validator;
+use color_eyre::{eyre::bail, eyre::Report, eyre::Result};
+use std::fs;
+use std::io;
+
+// How much of the physical table has been entered
+#[derive(PartialEq, Clone, Copy)]
+pub enum Knowledge {
+    Complete,
+    // The table holds at least the entered sets, possibly more
+    Partial,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Change {
+    // Entered set left as it was
+    Kept,
+    // Set that reuses tiles of entered sets
+    Rearranged,
+    // Set made only of rack tiles
+    New,
+}
+
+impl Change {
+    fn label(&self) -> Message {
+        match self {
+            Change::Kept => Message::Kept,
+            Change::Rearranged => Message::Rearranged,
+            Change::New => Message::New,
+        }
+    }
+}
+
+// How every set of the new table came about. Sets equal to an old set are kept,
+// the others are new unless they hold tiles of the old sets that were broken up.
+pub fn classify(table: &[Set], new_table: &[Set]) -> Vec<Change> {
+    let sorted = |set: &Set| {
+        let mut tiles = set.tiles;
+        tiles.sort_by_key(|tile| (tile.number, tile.color, tile.is_joker));
+        tiles
+    };
+
+    let mut old_sets = table.iter().map(sorted).collect::<Vec<_>>();
+    let mut changes = new_table
+        .iter()
+        .map(
+            |set| match old_sets.iter().position(|old| *old == sorted(set)) {
+                Some(index) => {
+                    old_sets.swap_remove(index);
+                    Change::Kept
+                }
+                None => Change::New,
+            },
+        )
+        .collect::<Vec<Change>>();
+
+    let mut broken_up = Inventory::from_sets(
+        &old_sets
+            .into_iter()
+            .map(|tiles| Set { tiles })
+            .collect::<Vec<Set>>(),
+    );
+    for (set, change) in new_table.iter().zip(changes.iter_mut()) {
+        let set_tiles = std::slice::from_ref(set);
+        if *change == Change::New
+            && solver::take_table_first(&mut broken_up, &mut Inventory::from_sets(set_tiles), set)
+                < set.tiles.len() as u32
+        {
+            *change = Change::Rearranged;
+        }
+    }
+    changes
+}
+
+// The rack picked on the grid of the editor, None if cancelled
+#[cfg(feature = "tui")]
+fn edit_rack(table: &[Set], rack: Inventory) -> Result<Option<Inventory>> {
+    validator::check_position(table, &rack)?;
+    let mut available = Inventory::new(2);
+    available.subtract(&Inventory::from_sets(table));
+    crate::editor::edit("Your rack", rack, available)
+}
+
+#[cfg(not(feature = "tui"))]
+fn edit_rack(_: &[Set], _: Inventory) -> Result<Option<Inventory>> {
+    bail!("the grid editor needs a build with `--features tui`")
+}
+
+#[cfg(feature = "png")]
+fn write_png(path: &str, table: &[Set], width: usize) -> Result<()> {
+    fs::write(path, render::png(table, width))?;
+    Ok(())
+}
+
+#[cfg(not(feature = "png"))]
+fn write_png(_: &str, _: &[Set], _: usize) -> Result<()> {
+    bail!("pictures need a build with `--features png`")
+}
+
+// The traced search as a Chrome trace for a `.json` file, Graphviz otherwise,
+// e.g. `dot -Tsvg search.dot -o search.svg`
+fn write_trace(path: &str, trace: &SearchTrace) -> Result<()> {
+    match path.ends_with(".json") {
+        true => fs::write(path, format!("{}\n", trace.to_chrome_trace()))?,
+        false => fs::write(path, trace.to_dot())?,
+    }
+    match trace.dropped() {
+        0 => println!("Trace of {} positions written to {}", trace.nodes(), path),
+        dropped => println!(
+            "Trace of the first {} positions written to {}, {} more were not traced, see --trace-nodes",
+            trace.nodes(),
+            path,
+            dropped
+        ),
+    }
+    Ok(())
+}
+
+// The tiles of every pick, e.g. `B5, R7+R8 or J`, cut short after a few
+fn picks_text(picks: &[Vec<Tile>]) -> String {
+    const SHOWN: usize = 8;
+    let mut names = picks
+        .iter()
+        .take(SHOWN)
+        .map(|pick| {
+            pick.iter()
+                .map(Tile::to_string)
+                .collect::<Vec<String>>()
+                .join("+")
+        })
+        .collect::<Vec<String>>();
+    if picks.len() > SHOWN {
+        names.push(format!("{} others", picks.len() - SHOWN));
+    }
+    match names.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+        _ => names.concat(),
+    }
+}
+
+// The best move for what the unknown tiles could be, most likely first
+fn print_ghost_outcomes(outcomes: &[ghost::Outcome], ghosts: u32, total: u32) {
+    let unknown = if ghosts == 1 { "tile is" } else { "tiles are" };
+    for outcome in outcomes {
+        let condition = format!(
+            "{:5.1}% if the unknown {} {}",
+            outcome.chance * 100.0,
+            unknown,
+            picks_text(&outcome.tiles)
+        );
+        match &outcome.play {
+            Some((new_table, played)) => {
+                println!("{}: play {} of {} tiles", condition, played, total);
+                new_table
+                    .iter()
+                    .for_each(|set| println!("         {}", set));
+            }
+            None => println!("{}: draw a tile", condition),
+        }
+    }
+}
+
+// Suggest a move for a physical game from the rack and the sets on the table
+pub fn run(args: &Args) -> Result<(), Report> {
+    // A board file of `-` is read from standard input, e.g. pasted from the clipboard
+    let board_file = args.value("--board-file").or(args.value("--table-file"));
+    let table = match (args.value("--table"), board_file) {
+        (Some(text), _) => notation::parse_table(text)?,
+        (None, Some("-")) => notation::parse_table(&io::read_to_string(io::stdin())?)?,
+        (None, Some(path)) => notation::parse_table(&fs::read_to_string(path)?)?,
+        (None, None) => Vec::new(),
+    };
+    // `?` stands for a tile that is there but cannot be read
+    let (rack, ghosts) = match args.value("--rack") {
+        Some(rack) => notation::parse_rack_with_ghosts(rack)?,
+        None if args.flag("--edit") => (Inventory::new(0), 0),
+        None => {
+            bail!("missing --rack, e.g. --rack \"R5 R6 R7 B10 J\", or --edit to pick the tiles")
+        }
+    };
+    // Pick the rack tiles on a grid, starting from --rack if given
+    let rack = if args.flag("--edit") {
+        match edit_rack(&table, rack)? {
+            Some(rack) => rack,
+            None => return Ok(()),
+        }
+    } else {
+        rack
+    };
+    let opened = args.flag("--opened");
+    let knowledge = if args.flag("--partial") {
+        Knowledge::Partial
+    } else {
+        Knowledge::Complete
+    };
+
+    // Without the initial meld the table is never touched, so missing sets do not matter
+    let depends_on_table = opened && knowledge == Knowledge::Partial;
+    if depends_on_table {
+        println!(
+            "Only the entered sets are rearranged, sets that were not entered stay as they are"
+        );
+    }
+
+    let objective = args
+        .value("--objective")
+        .map(Objective::parse)
+        .transpose()?;
+
+    let limits = budget::search_limits(args)?;
+    if ghosts > 0 {
+        validator::check_position(&table, &rack)?;
+        let (outcomes, exceeded) =
+            solver::with_limits(limits, || ghost::outcomes(&table, &rack, ghosts, opened));
+        notify::send(
+            Event::SolverFinished,
+            "The moves for the unreadable tiles are ready",
+        );
+        if let Some(limit) = exceeded {
+            println!(
+                "(!) The search ran out of {}, the moves only use sets from the rack",
+                limit.name()
+            );
+        }
+        print_ghost_outcomes(&outcomes?, ghosts, rack.total_tile_count() + ghosts);
+        return Ok(());
+    }
+    // Where the search spent its work, printed after the move
+    let analyze = args.flag("--analyze-search");
+    // The first positions searched, to see why a known move was missed
+    let trace_nodes = match args.value("--trace") {
+        Some(_) => Some(args.parse_or("--trace-nodes", 2000)?),
+        None => None,
+    };
+    let (((found, exceeded), stats), trace) = solver::with_search_trace(trace_nodes, || {
+        solver::with_search_stats(analyze, || {
+            solver::with_limits(limits, || solver::best_move(&table, &rack, opened))
+        })
+    });
+    if let (Some(trace), Some(path)) = (&trace, args.value("--trace")) {
+        write_trace(path, trace)?;
+    }
+    notify::send(Event::SolverFinished, "The suggested move is ready");
+    if let Some(limit) = exceeded {
+        println!(
+            "(!) The search ran out of {}, the move only uses sets from the rack",
+            limit.name()
+        );
+    }
+    match found? {
+        Some((new_table, rest)) => {
+            // Same tiles, arranged the way the user prefers
+            let new_table = Solution::from(new_table);
+            let new_table = match objective {
+                Some(objective) => {
+                    solver::arrange(&new_table.tiles(), objective).unwrap_or(new_table)
+                }
+                None => new_table,
+            };
+            if args.flag("--json") {
+                let mut json = new_table.to_json();
+                if let Json::Object(fields) = &mut json {
+                    fields.push((
+                        "origins".to_string(),
+                        solution::origins_json(&table, &new_table),
+                    ));
+                }
+                println!("{}", json);
+                return Ok(());
+            }
+            println!("{}", Message::SuggestedTable);
+            let origins = solver::tile_origins(&table, &new_table);
+            for ((set, change), origins) in new_table
+                .iter()
+                .zip(classify(&table, &new_table))
+                .zip(origins)
+            {
+                // The tiles that leave the rack, unless the whole set does
+                let from_rack = set
+                    .tiles
+                    .iter()
+                    .zip(origins)
+                    .filter(|(_, origin)| *origin == Origin::Rack)
+                    .map(|(tile, _)| {
+                        if speech::enabled() {
+                            speech::tile(tile)
+                        } else {
+                            tile.to_string()
+                        }
+                    })
+                    .collect::<Vec<String>>();
+                let shown = if speech::enabled() {
+                    speech::set(set)
+                } else {
+                    set.to_string()
+                };
+                if from_rack.is_empty() || from_rack.len() == set.tiles.len() {
+                    println!("  {:10} {}", change.label().to_string(), shown);
+                } else {
+                    let tiles = from_rack.join(if speech::enabled() { ", " } else { " " });
+                    println!(
+                        "  {:10} {}  ({})",
+                        change.label().to_string(),
+                        shown,
+                        Message::FromRack { tiles }
+                    );
+                }
+            }
+            println!(
+                "{}",
+                Message::Plays {
+                    played: rack.total_tile_count() - rest.total_tile_count(),
+                    total: rack.total_tile_count(),
+                }
+            );
+            if depends_on_table {
+                println!("(!) The number of tiles played is only the best with the entered sets, the full table may allow more");
+            }
+
+            let width: usize = args.parse_or("--width", 80)?;
+            // The boxes mean nothing read out, the sets above already say it in words
+            if args.flag("--boxes") && !speech::enabled() {
+                print!("{}", render::unicode(&new_table, width));
+            }
+            if let Some(path) = args.value("--png") {
+                write_png(path, &new_table, width)?;
+                println!("Picture of the table written to {}", path);
+            }
+
+            if args.flag("--count") {
+                println!(
+                    "These tiles can be arranged in {} ways",
+                    solver::count_solutions(&new_table.tiles())
+                );
+            }
+
+            // Other ways to lay out the same tiles, e.g. to keep more sets as they are
+            let alternatives: usize = args.parse_or("--alternatives", 0)?;
+            let suggested = new_table.canonical();
+            let others = solver::solve_all(&new_table.tiles())
+                .filter(|solution| solution.canonical() != suggested)
+                .take(alternatives);
+            for (index, solution) in others.enumerate() {
+                println!("Alternative {}:", index + 1);
+                for set in &solution {
+                    println!("  {}", set);
+                }
+            }
+        }
+        None if args.flag("--json") => println!("null"),
+        None if depends_on_table => {
+            println!("(!) No play with the entered sets, the full table may still allow one")
+        }
+        None => println!("{}", Message::NoPlay),
+    }
+
+    if let Some(stats) = stats {
+        println!();
+        stats.report().iter().for_each(|line| println!("{}", line));
+    }
+    Ok(())
+}
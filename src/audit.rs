@@ -0,0 +1,57 @@
+use crate::solver::Inventory;
+use color_eyre::eyre::{bail, Result};
+
+// Every tile entered over a game together with the entry it came from, so the
+// first entry that needs more copies of a tile than the pool holds is caught,
+// e.g. a third red 5, along with the earlier entries holding the other copies
+pub struct Audit {
+    seen: Inventory,
+    entries: Vec<(String, Inventory)>,
+}
+
+impl Default for Audit {
+    fn default() -> Audit {
+        Audit::new()
+    }
+}
+
+impl Audit {
+    pub fn new() -> Audit {
+        Audit {
+            seen: Inventory::new(0),
+            entries: Vec::new(),
+        }
+    }
+
+    // Fails if the pool cannot hold the tiles together with everything
+    // entered before
+    pub fn check(&self, entry: &str, tiles: &Inventory) -> Result<()> {
+        let seen = self.seen.combined(tiles);
+        if let Some(tile) = Inventory::new(2).missing(&seen).first() {
+            let earlier = self
+                .entries
+                .iter()
+                .filter(|(_, tiles)| tiles.count(tile) > 0)
+                .map(|(entry, _)| entry.as_str())
+                .collect::<Vec<&str>>();
+            if earlier.is_empty() {
+                bail!("{} holds more copies of {} than the pool", entry, tile);
+            }
+            bail!(
+                "{} holds a copy of {} too many, the pool's copies are already in {}",
+                entry,
+                tile,
+                earlier.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    // Checks the tiles and adds them to what was entered
+    pub fn record(&mut self, entry: &str, tiles: &Inventory) -> Result<()> {
+        self.check(entry, tiles)?;
+        self.seen = self.seen.combined(tiles);
+        self.entries.push((entry.to_string(), *tiles));
+        Ok(())
+    }
+}
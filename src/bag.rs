@@ -0,0 +1,84 @@
+use crate::rng::uniform_index;
+use crate::solver::{Inventory, Tile};
+use rand::Rng;
+
+// Tiles waiting to be drawn. Unlike an Inventory a bag only hands tiles out,
+// either in the order it was filled, as the deal of a seeded game fixes, or
+// at random.
+#[derive(Clone, Debug, Default)]
+pub struct Bag {
+    // The next tile in order is the last one
+    tiles: Vec<Tile>,
+}
+
+impl Bag {
+    // Every tile of the pool
+    pub fn full() -> Bag {
+        Bag::of(&Inventory::new(2))
+    }
+
+    // A bag holding the tiles, e.g. the pool without the tiles already seen
+    pub fn of(tiles: &Inventory) -> Bag {
+        let mut tiles = tiles.tiles();
+        tiles.reverse();
+        Bag { tiles }
+    }
+
+    // A bag handing out the tiles in this order, the first one first
+    pub fn in_order(mut tiles: Vec<Tile>) -> Bag {
+        tiles.reverse();
+        Bag { tiles }
+    }
+
+    pub fn remaining(&self) -> u32 {
+        self.tiles.len() as u32
+    }
+
+    // The next tile in the order of the bag, None once it is empty
+    pub fn draw_next(&mut self) -> Option<Tile> {
+        self.tiles.pop()
+    }
+
+    // A tile picked at random, every tile left equally likely
+    pub fn draw(&mut self, rng: &mut impl Rng) -> Option<Tile> {
+        if self.tiles.is_empty() {
+            return None;
+        }
+        let index = self.tiles.len() - 1 - uniform_index(rng, self.tiles.len());
+        Some(self.tiles.remove(index))
+    }
+
+    // The tiles of `count` random draws, fewer if the bag runs out
+    pub fn draw_n(&mut self, count: u32, rng: &mut impl Rng) -> Inventory {
+        let mut drawn = Inventory::new(0);
+        for _ in 0..count {
+            match self.draw(rng) {
+                Some(tile) => drawn.add_tile(&tile),
+                None => break,
+            }
+        }
+        drawn
+    }
+
+    // Takes out a tile known to have been drawn, as when reading back a game.
+    // False if the bag does not hold it.
+    pub fn take(&mut self, tile: &Tile) -> bool {
+        match self.tiles.iter().rposition(|other| other == tile) {
+            Some(index) => {
+                self.tiles.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // How many copies of every tile are left, e.g. to work out the chances of
+    // a draw
+    pub fn composition(&self) -> Inventory {
+        let mut composition = Inventory::new(0);
+        self.tiles
+            .iter()
+            .for_each(|tile| composition.add_tile(tile));
+        composition
+    }
+}
@@ -0,0 +1,325 @@
+use crate::budget;
+use crate::cli::Args;
+use crate::diagnostic;
+use crate::json::{self, Json};
+use crate::position::{self, SolveRequest};
+use crate::solution::Solution;
+use crate::solver::{self, Inventory, Limits, Objective, Set, SolverCache};
+use color_eyre::{eyre::bail, eyre::Report, eyre::Result};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Instant;
+
+// How every position is solved
+struct Options {
+    objective: Option<Objective>,
+    limits: Limits,
+}
+
+// The result of one position
+struct Outcome {
+    // "done", "draw" or "failed"
+    status: &'static str,
+    played: u32,
+    limited: bool,
+    seconds: f64,
+    json: Json,
+}
+
+// The position files named on the command line, the `.json` files of a
+// directory in name order
+fn position_files(args: &Args) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for index in 0.. {
+        let Some(name) = args.positional(index) else {
+            break;
+        };
+        let path = PathBuf::from(name);
+        if path.is_dir() {
+            let mut entries = fs::read_dir(&path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<PathBuf>, _>>()?;
+            entries.retain(|entry| {
+                entry
+                    .extension()
+                    .is_some_and(|extension| extension == "json")
+            });
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(path);
+        }
+    }
+    if files.is_empty() {
+        bail!(
+            "expected position files or directories, e.g. analyze positions/*.json --out results/"
+        );
+    }
+    Ok(files)
+}
+
+// The best move of the position, most tiles played, laid out for the
+// objective if one is given
+fn solve(
+    request: &SolveRequest,
+    objective: Option<Objective>,
+    cache: &mut SolverCache,
+) -> Result<Option<(Vec<Set>, Inventory)>> {
+    let Some((table, rest)) =
+        solver::best_move_cached(&request.table, &request.rack, request.opened, cache)?
+    else {
+        return Ok(None);
+    };
+    let table = match objective {
+        Some(objective) => {
+            let tiles = Solution::from(table.clone()).tiles();
+            solver::arrange(&tiles, objective).map_or(table, Solution::into_sets)
+        }
+        None => table,
+    };
+    Ok(Some((table, rest)))
+}
+
+// Solves a position, `fields` saying where it came from go first in the
+// result. The memos of the cache are shared with the positions before it.
+fn outcome(
+    position: Result<Json>,
+    mut fields: Vec<(String, Json)>,
+    options: &Options,
+    cache: &mut SolverCache,
+) -> Outcome {
+    let start = Instant::now();
+    let request = position.and_then(|position| position::solve_request(&position));
+    let (solved, limit) = match &request {
+        Ok(request) => {
+            solver::with_limits(options.limits, || solve(request, options.objective, cache))
+        }
+        Err(_) => (Ok(None), None),
+    };
+    let (status, played) = match request.and_then(|request| solved.map(|solved| (solved, request)))
+    {
+        Ok((Some((table, rest)), request)) => {
+            fields.push(("status".to_string(), json::string("done")));
+            if let Json::Object(entries) =
+                position::move_json(&request.rack, &request.table, &table, &rest)
+            {
+                fields.extend(entries);
+            }
+            (
+                "done",
+                request.rack.total_tile_count() - rest.total_tile_count(),
+            )
+        }
+        Ok((None, _)) => {
+            fields.push(("status".to_string(), json::string("draw")));
+            ("draw", 0)
+        }
+        Err(report) => {
+            fields.push(("status".to_string(), json::string("failed")));
+            fields.push(("error".to_string(), diagnostic::to_json(&report)));
+            ("failed", 0)
+        }
+    };
+    if let Some(limit) = limit {
+        fields.push(("limit".to_string(), json::string(limit.name())));
+    }
+    let seconds = start.elapsed().as_secs_f64();
+    fields.push(("seconds".to_string(), Json::Number(seconds)));
+    Outcome {
+        status,
+        played,
+        limited: limit.is_some(),
+        seconds,
+        json: Json::Object(fields),
+    }
+}
+
+fn analyze(file: &Path, options: &Options, cache: &mut SolverCache) -> Outcome {
+    let position = fs::read_to_string(file)
+        .map_err(Report::from)
+        .and_then(|text| json::parse(&text));
+    let fields = vec![("file".to_string(), json::string(file.display().to_string()))];
+    outcome(position, fields, options, cache)
+}
+
+// One position of JSON per line of standard input, one result per line of
+// standard output as soon as it is solved, so a pipeline can keep a single
+// process with warm memos busy. An `id` of the position is sent back with its
+// result, the line number always is. Empty lines are skipped.
+fn stream(options: &Options) -> Result<()> {
+    let mut cache = SolverCache::default();
+    let mut stdout = io::stdout().lock();
+    for (index, line) in io::stdin().lock().lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let position = json::parse(&line);
+        let mut fields = vec![("line".to_string(), Json::Number(index as f64 + 1.0))];
+        if let Some(id) = position
+            .as_ref()
+            .ok()
+            .and_then(|position| position.get("id"))
+        {
+            fields.push(("id".to_string(), id.clone()));
+        }
+        let outcome = outcome(position, fields, options, &mut cache);
+        writeln!(stdout, "{}", outcome.json)?;
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+// Counts over every file
+fn summary(outcomes: &[Outcome], seconds: f64) -> Json {
+    let count = |status: &str| {
+        outcomes
+            .iter()
+            .filter(|outcome| outcome.status == status)
+            .count() as f64
+    };
+    let played = outcomes.iter().map(|outcome| outcome.played).sum::<u32>();
+    let solve_seconds = outcomes
+        .iter()
+        .map(|outcome| outcome.seconds)
+        .collect::<Vec<f64>>();
+    json::object([
+        ("positions", Json::Number(outcomes.len() as f64)),
+        ("done", Json::Number(count("done"))),
+        ("draw", Json::Number(count("draw"))),
+        ("failed", Json::Number(count("failed"))),
+        (
+            "limited",
+            Json::Number(outcomes.iter().filter(|outcome| outcome.limited).count() as f64),
+        ),
+        ("tiles_played", Json::Number(played as f64)),
+        (
+            "average_played",
+            Json::Number(played as f64 / count("done").max(1.0)),
+        ),
+        (
+            "slowest_seconds",
+            Json::Number(solve_seconds.iter().copied().fold(0.0, f64::max)),
+        ),
+        ("seconds", Json::Number(seconds)),
+    ])
+}
+
+// A thread for every core
+#[cfg(feature = "parallel")]
+fn default_jobs() -> usize {
+    thread::available_parallelism().map_or(2, |cores| cores.get())
+}
+
+#[cfg(not(feature = "parallel"))]
+fn default_jobs() -> usize {
+    1
+}
+
+// Solves every position file, JSON like the body of the server's /solve, on
+// `--jobs` threads. `--objective` lays out the best move as min-sets or
+// aesthetic, `--max-nodes` and `--max-memory` limit every search. With `--out`
+// the result of every file goes to a file of the same name in that directory
+// next to `summary.json`, otherwise both are printed. `analyze -` reads
+// positions from standard input instead, see stream.
+pub fn run(args: &Args) -> Result<(), Report> {
+    let options = Options {
+        objective: args
+            .value("--objective")
+            .map(Objective::parse)
+            .transpose()?,
+        limits: budget::search_limits(args)?,
+    };
+    if args.positional(0) == Some("-") {
+        return stream(&options);
+    }
+    let files = position_files(args)?;
+    let jobs: usize = args.parse_or("--jobs", default_jobs())?;
+    if jobs == 0 {
+        bail!("--jobs must be positive");
+    }
+    if jobs > 1 && !cfg!(feature = "parallel") {
+        bail!("more than one job needs a build with `--features parallel`");
+    }
+    let out = args.value("--out").map(PathBuf::from);
+    if let Some(out) = &out {
+        let mut names = files
+            .iter()
+            .map(|file| file.file_name())
+            .collect::<Vec<_>>();
+        names.sort();
+        if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+            bail!(
+                "two position files are named {:?}, their results would overwrite each other",
+                pair[0].unwrap_or_default()
+            );
+        }
+        fs::create_dir_all(out)?;
+    }
+
+    let start = Instant::now();
+    let next = AtomicUsize::new(0);
+    let mut outcomes = thread::scope(|scope| {
+        let workers = (0..jobs.min(files.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut cache = SolverCache::default();
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(file) = files.get(index) else {
+                            return done;
+                        };
+                        done.push((index, analyze(file, &options, &mut cache)));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect::<Vec<(usize, Outcome)>>()
+    });
+    // In the order of the command line, whichever thread finished first
+    outcomes.sort_by_key(|(index, _)| *index);
+    let outcomes = outcomes
+        .into_iter()
+        .map(|(_, outcome)| outcome)
+        .collect::<Vec<Outcome>>();
+    let summary = summary(&outcomes, start.elapsed().as_secs_f64());
+
+    for (file, outcome) in files.iter().zip(&outcomes) {
+        match &out {
+            Some(out) => {
+                let name = file.file_name().unwrap_or_default();
+                fs::write(out.join(name), format!("{}\n", outcome.json))?;
+                println!(
+                    "{}: {}, {} tiles played",
+                    file.display(),
+                    outcome.status,
+                    outcome.played
+                );
+            }
+            None => println!("{}", outcome.json),
+        }
+    }
+    match &out {
+        Some(out) => {
+            fs::write(out.join("summary.json"), format!("{}\n", summary))?;
+            println!(
+                "Results of {} positions written to {}",
+                outcomes.len(),
+                out.display()
+            );
+        }
+        None => println!("{}", summary),
+    }
+    Ok(())
+}
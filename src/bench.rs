@@ -0,0 +1,85 @@
+use crate::cli::Args;
+use crate::deal::Deal;
+use crate::memo_bench;
+use crate::solver::{self, Inventory, Set};
+use color_eyre::eyre::Report;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+// A position dealt from a seed: sets laid out from a big hand as the table,
+// and a rack from the rest of the bag
+struct Position {
+    table: Vec<Set>,
+    rack: Inventory,
+}
+
+fn position(seed: u64, table_tiles: u32, rack_size: u32) -> Position {
+    let mut deal = Deal::new(1, table_tiles, seed);
+    let (_, table) = solver::max_tiles_meld(&deal.racks[0]);
+    let mut rack = Inventory::new(0);
+    (0..rack_size)
+        .filter_map(|_| deal.bag.draw_next())
+        .for_each(|tile| rack.add_tile(&tile));
+    Position { table, rack }
+}
+
+// Runs `work` on every position and prints the time per position
+fn time(name: &str, positions: &[Position], work: impl Fn(&Position)) {
+    let start = Instant::now();
+    positions
+        .iter()
+        .for_each(|position| work(black_box(position)));
+    let elapsed = start.elapsed();
+    let each = elapsed / positions.len().max(1) as u32;
+    println!(
+        "{:32} | {:9} | {:>12}",
+        name,
+        positions.len(),
+        format_duration(each)
+    );
+}
+
+fn format_duration(duration: Duration) -> String {
+    match duration.as_nanos() {
+        nanos if nanos < 10_000 => format!("{} ns", nanos),
+        nanos if nanos < 10_000_000 => format!("{} µs", nanos / 1000),
+        nanos => format!("{} ms", nanos / 1_000_000),
+    }
+}
+
+// Times the hot paths of the solver on positions dealt from fixed seeds, so
+// runs on different versions compare the same work. `--memo` compares the
+// designs of the layout memo instead, see memo_bench. Build with `--release`.
+pub fn run(args: &Args) -> Result<(), Report> {
+    if args.flag("--memo") {
+        return memo_bench::run(args);
+    }
+    let positions: u64 = args.parse_or("--positions", 200)?;
+    let seed: u64 = args.parse_or("--seed", 0)?;
+    let small = (0..positions)
+        .map(|offset| position(seed.wrapping_add(offset), 14, 14))
+        .collect::<Vec<_>>();
+    let tables = (0..positions / 10)
+        .map(|offset| position(seed.wrapping_add(offset), 40, 8))
+        .collect::<Vec<_>>();
+
+    println!("{:32} | {:9} | {:>12}", "Benchmark", "Positions", "Each");
+    time("copy sets of a table", &tables, |position| {
+        black_box(position.table.to_vec());
+    });
+    time("max_tiles_meld, 14 tiles", &small, |position| {
+        black_box(solver::max_tiles_meld(&position.rack));
+    });
+    time("count_solutions, laid out table", &tables, |position| {
+        black_box(solver::count_solutions(&Inventory::from_sets(
+            &position.table,
+        )));
+    });
+    time("table_play, 8 tiles on the rack", &tables, |position| {
+        black_box(solver::table_play(&position.table, &position.rack));
+    });
+    time("best_move before the meld", &small, |position| {
+        black_box(solver::best_move(&[], &position.rack, false)).ok();
+    });
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use crate::assist::{self, Change};
+use crate::rules::Ruleset;
+use crate::solver::{Inventory, Set, Tile};
+use crate::validator;
+use color_eyre::eyre::{bail, Result};
+use std::fmt;
+
+// What happened to the table between two observed turns
+pub struct BoardDelta {
+    // Tiles laid out from a rack
+    pub added: Inventory,
+    // Tiles that stayed on the table but ended up in another set
+    pub moved: Inventory,
+    // Tiles taken off the table, which the rules never allow
+    pub removed: Inventory,
+}
+
+impl BoardDelta {
+    pub fn takes_tiles(&self) -> bool {
+        self.removed.total_tile_count() > 0
+    }
+}
+
+impl fmt::Display for BoardDelta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} tiles added, {} moved",
+            self.added.total_tile_count(),
+            self.moved.total_tile_count()
+        )?;
+        if self.takes_tiles() {
+            write!(
+                f,
+                ", {} taken off the table",
+                self.removed.total_tile_count()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn inventory(tiles: Vec<Tile>) -> Inventory {
+    let mut inventory = Inventory::new(0);
+    tiles.iter().for_each(|tile| inventory.add_tile(tile));
+    inventory
+}
+
+// Compares the table before and after a turn. Sets found unchanged on both
+// tables stay put, every other tile that is still on the table has moved.
+pub fn diff(previous: &[Set], next: &[Set]) -> BoardDelta {
+    let before = Inventory::from_sets(previous);
+    let after = Inventory::from_sets(next);
+    let kept = next
+        .iter()
+        .zip(assist::classify(previous, next))
+        .filter(|&(_, change)| change == Change::Kept)
+        .map(|(set, _)| *set)
+        .collect::<Vec<Set>>();
+
+    let removed = inventory(after.missing(&before));
+    let mut moved = before;
+    moved.subtract(&Inventory::from_sets(&kept));
+    moved.subtract(&removed);
+    BoardDelta {
+        added: inventory(before.missing(&after)),
+        moved,
+        removed,
+    }
+}
+
+// Whether some rack could have made the move, when only the table before and
+// after it are known, and optionally how many tiles the player says were laid
+// out. Returns the tiles that must have come from the rack, or why no rack
+// could have made the move.
+//
+// The move itself fixes which tiles were laid out. Any rack holding them
+// makes the same move, and holding more changes nothing, so checking the move
+// with exactly those tiles as the rack decides it for every possible rack.
+pub fn infer_move(
+    previous: &[Set],
+    next: &[Set],
+    opened: bool,
+    rules: &Ruleset,
+    claimed: Option<u32>,
+) -> Result<Inventory> {
+    let delta = diff(previous, next);
+    let added = delta.added.total_tile_count();
+    if let Some(claimed) = claimed.filter(|&claimed| claimed != added && !delta.takes_tiles()) {
+        bail!(
+            "{} tiles were added to the table, the player claims {}",
+            added,
+            claimed
+        );
+    }
+    validator::check_position(previous, &delta.added)?;
+    validator::validate_move(previous, &delta.added, next, opened, rules)?;
+    Ok(delta.added)
+}
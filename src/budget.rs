@@ -0,0 +1,106 @@
+use crate::cli::Args;
+use crate::solver::Limits;
+use crate::threat::THREAT_TILES;
+use color_eyre::eyre::{bail, eyre, Result};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Tiles left in the bag while the game is still in its opening
+const OPENING_BAG: u32 = 60;
+
+// What every player can see of the game when a move is due
+pub struct Phase {
+    pub bag: u32,
+    pub opened: bool,
+    // Tiles on the smallest rack of the other players
+    pub fewest_opponent_tiles: u32,
+}
+
+impl Phase {
+    // An opponent is close to going out or the bag has run dry, so every
+    // tile laid out counts
+    pub fn is_critical(&self) -> bool {
+        self.fewest_opponent_tiles <= THREAT_TILES || self.bag == 0
+    }
+}
+
+// Time a strategy may search for a move, like the time management of a chess
+// engine. The table search stops at the end of it and the move falls back to
+// sets from the rack alone, see solver::with_limits.
+pub trait TimeManager {
+    fn budget(&self, phase: &Phase) -> Duration;
+}
+
+// The same time for every move
+pub struct Fixed(pub Duration);
+
+impl TimeManager for Fixed {
+    fn budget(&self, _: &Phase) -> Duration {
+        self.0
+    }
+}
+
+// A share of the base time in the opening, when the table is small and hardly
+// worth rearranging, and a multiple of it once the game is critical
+pub struct ByPhase {
+    pub base: Duration,
+    pub opening: f64,
+    pub critical: f64,
+}
+
+impl TimeManager for ByPhase {
+    fn budget(&self, phase: &Phase) -> Duration {
+        if phase.is_critical() {
+            self.base.mul_f64(self.critical)
+        } else if !phase.opened || phase.bag > OPENING_BAG {
+            self.base.mul_f64(self.opening)
+        } else {
+            self.base
+        }
+    }
+}
+
+// `--move-time` in seconds with `--time-manager phase`, the default, or
+// `fixed`. Without `--move-time` moves are searched to the end.
+pub fn from_args(args: &Args) -> Result<Option<Arc<dyn TimeManager + Send + Sync>>> {
+    let Some(seconds) = args.value("--move-time") else {
+        if args.value("--time-manager").is_some() {
+            bail!("--time-manager needs --move-time");
+        }
+        return Ok(None);
+    };
+    let seconds: f64 = seconds
+        .parse()
+        .map_err(|_| eyre!("invalid value for --move-time: {}", seconds))?;
+    if !(seconds > 0.0 && seconds.is_finite()) {
+        bail!("--move-time must be positive, got {}", seconds);
+    }
+    let base = Duration::from_secs_f64(seconds);
+    match args.value("--time-manager").unwrap_or("phase") {
+        "phase" => Ok(Some(Arc::new(ByPhase {
+            base,
+            opening: 0.5,
+            critical: 4.0,
+        }))),
+        "fixed" => Ok(Some(Arc::new(Fixed(base)))),
+        other => bail!("unknown time manager {}, expected phase or fixed", other),
+    }
+}
+
+// `--max-nodes`, the positions one table search may visit, and `--max-memory`
+// in megabytes for its memo. A search that reaches either falls back to sets
+// from the rack alone instead of taking ever more memory.
+pub fn search_limits(args: &Args) -> Result<Limits> {
+    let nodes: Option<u64> = args.value("--max-nodes").map(str::parse).transpose()?;
+    let megabytes: Option<f64> = args.value("--max-memory").map(str::parse).transpose()?;
+    if nodes == Some(0)
+        || megabytes.is_some_and(|megabytes| !(megabytes > 0.0 && megabytes.is_finite()))
+    {
+        bail!("--max-nodes and --max-memory must be positive");
+    }
+    Ok(Limits {
+        deadline: None,
+        nodes,
+        memory: megabytes.map(|megabytes| (megabytes * 1024.0 * 1024.0) as usize),
+    })
+}
@@ -0,0 +1,312 @@
+use crate::board;
+use crate::cli::Args;
+use crate::config;
+use crate::diagnostic;
+use crate::game::GameConfig;
+use crate::json::{self, Json};
+use crate::notation;
+use crate::rules::Ruleset;
+use crate::solver::{self, Inventory, Set};
+use crate::validator;
+use color_eyre::{eyre::bail, eyre::Report, eyre::Result};
+use std::fs;
+use std::io;
+use std::process;
+
+// Exit statuses, so scripts can tell a rejected move from input they got wrong
+const ILLEGAL: i32 = 1;
+const BAD_INPUT: i32 = 2;
+
+// Scenarios of the rulebook with the verdict it gives, see the file
+const RULEBOOK: &str = include_str!("../corpus/rulebook.toml");
+
+// A path of `-` reads standard input
+fn read_file(path: &str) -> Result<String> {
+    if path == "-" {
+        Ok(io::read_to_string(io::stdin())?)
+    } else {
+        Ok(fs::read_to_string(path)?)
+    }
+}
+
+struct Position {
+    table: Vec<Set>,
+    // None for an opponent, whose rack is hidden
+    rack: Option<Inventory>,
+    // Tiles the player says were laid out
+    claimed: Option<u32>,
+    opened: bool,
+    rules: Ruleset,
+    // The new table, or None when the player draws
+    chosen: Option<String>,
+}
+
+fn read_position(args: &Args) -> Result<Position> {
+    let rules = GameConfig::from_args(args)?.rules;
+    let table = match args.value("--board") {
+        Some(path) => notation::parse_table(&read_file(path)?)?,
+        None => Vec::new(),
+    };
+    let rack = args.value("--rack").map(notation::parse_rack).transpose()?;
+    if let Some(rack) = &rack {
+        validator::check_position(&table, rack)?;
+    }
+
+    let chosen = match (args.value("--move"), args.flag("--draw")) {
+        (Some(_), true) => bail!("--move and --draw cannot be combined"),
+        (Some(path), false) => Some(read_file(path)?),
+        (None, true) => None,
+        (None, false) => bail!("missing --move with the new table, or --draw"),
+    };
+    Ok(Position {
+        table,
+        rack,
+        claimed: args
+            .value("--played")
+            .map(|_| args.parse_or("--played", 0))
+            .transpose()?,
+        opened: args.flag("--opened"),
+        rules,
+        chosen,
+    })
+}
+
+// Why the move breaks the rules, None if it is legal
+fn judge(position: &Position) -> Result<Option<Report>> {
+    let Some(text) = &position.chosen else {
+        // Without the rack, some rack that cannot play always exists
+        let Some(rack) = &position.rack else {
+            return Ok(None);
+        };
+        let can_play =
+            solver::best_move_by_rules(&position.table, rack, position.opened, &position.rules)?
+                .is_some();
+        return Ok((position.rules.must_play && can_play).then(|| {
+            diagnostic::coded(
+                "must_play",
+                "tiles can be laid out, the rules do not allow drawing instead".to_string(),
+            )
+        }));
+    };
+    // A set that is not a legal group or run makes the move illegal, it is not
+    // a mistake in the input
+    let new_table = match notation::parse_table(text) {
+        Ok(new_table) => new_table,
+        Err(error) => return Ok(Some(error)),
+    };
+    let verdict = match &position.rack {
+        Some(rack) => validator::validate_move(
+            &position.table,
+            rack,
+            &new_table,
+            position.opened,
+            &position.rules,
+        )
+        .map(|rest| {
+            let mut played = *rack;
+            played.subtract(&rest);
+            played
+        }),
+        None => board::infer_move(
+            &position.table,
+            &new_table,
+            position.opened,
+            &position.rules,
+            position.claimed,
+        ),
+    };
+    let verdict = verdict.and_then(|played| match position.claimed {
+        Some(claimed) if claimed != played.total_tile_count() => Err(diagnostic::coded(
+            "claimed_count",
+            format!(
+                "{} tiles were laid out, the player claims {}",
+                played.total_tile_count(),
+                claimed
+            ),
+        )),
+        _ => Ok(()),
+    });
+    Ok(verdict.err())
+}
+
+// A move of a corpus file with the verdict the rules give
+struct Scenario {
+    rule: String,
+    line: usize,
+    position: Position,
+    legal: bool,
+}
+
+fn read_corpus(text: &str) -> Result<Vec<Scenario>> {
+    let mut scenarios = Vec::new();
+    for table in config::parse(text)? {
+        match table.name.as_str() {
+            "" => table.check_keys(&[])?,
+            "scenario" => {
+                table.check_keys(&[
+                    "rule",
+                    "table",
+                    "rack",
+                    "opened",
+                    "move",
+                    "draw",
+                    "played",
+                    "must_play",
+                    "rearrange_on_opening",
+                    "legal",
+                ])?;
+                let (Some(rule), Some(legal)) = (table.string("rule")?, table.bool("legal")?)
+                else {
+                    bail!(
+                        "line {}: a scenario needs the rule it shows and whether the move is legal",
+                        table.line
+                    );
+                };
+                let chosen = match (table.string("move")?, table.bool("draw")?.unwrap_or(false)) {
+                    (Some(_), true) => {
+                        bail!("line {}: move and draw cannot be combined", table.line)
+                    }
+                    (Some(text), false) => Some(text.to_string()),
+                    (None, true) => None,
+                    (None, false) => bail!("line {}: missing the move, or draw = true", table.line),
+                };
+                let claimed = match table.number("played")? {
+                    Some(tiles) if tiles < 0.0 || tiles.fract() != 0.0 => {
+                        bail!(
+                            "line {}: played must be a whole number of tiles, got {}",
+                            table.line,
+                            tiles
+                        )
+                    }
+                    tiles => tiles.map(|tiles| tiles as u32),
+                };
+                let position = Position {
+                    table: notation::parse_table(table.string("table")?.unwrap_or(""))?,
+                    rack: table
+                        .string("rack")?
+                        .map(notation::parse_rack)
+                        .transpose()?,
+                    claimed,
+                    opened: table.bool("opened")?.unwrap_or(false),
+                    rules: Ruleset {
+                        must_play: table.bool("must_play")?.unwrap_or(false),
+                        rearrange_on_opening: table.bool("rearrange_on_opening")?.unwrap_or(false),
+                        ..Ruleset::default()
+                    },
+                    chosen,
+                };
+                if let Some(rack) = &position.rack {
+                    validator::check_position(&position.table, rack)?;
+                }
+                scenarios.push(Scenario {
+                    rule: rule.to_string(),
+                    line: table.line,
+                    position,
+                    legal,
+                });
+            }
+            name => bail!("line {}: unknown table [{}]", table.line, name),
+        }
+    }
+    Ok(scenarios)
+}
+
+// Judges every scenario of the corpus and fails when a verdict differs from
+// the one the rulebook gives
+fn run_corpus(text: &str) -> Result<()> {
+    let scenarios = read_corpus(text)?;
+    let mut failed = 0;
+    for scenario in &scenarios {
+        let reason = judge(&scenario.position)?;
+        let verdict = if reason.is_none() == scenario.legal {
+            "ok"
+        } else {
+            "MISMATCH"
+        };
+        println!(
+            "line {}: {}: {} {}",
+            scenario.line,
+            scenario.rule,
+            reason
+                .as_ref()
+                .map_or("legal".to_string(), Report::to_string),
+            verdict
+        );
+        failed += (reason.is_none() != scenario.legal) as usize;
+    }
+    if failed > 0 {
+        bail!(
+            "{} of {} scenarios judged differently from the rulebook",
+            failed,
+            scenarios.len()
+        );
+    }
+    Ok(())
+}
+
+// Referee for a single move: checks the table after the move against the board,
+// the rack and the rules and exits with status 0 for a legal move, 1 for an
+// illegal one and 2 when the input cannot be read. Without --rack the move of
+// an opponent is checked: legal when some rack could have made it, and with
+// --played when the player laid out as many tiles as they said. With
+// --rulebook the scenarios of the rulebook are judged instead, or with
+// --corpus those of a file in the same format.
+pub fn run(args: &Args) -> Result<(), Report> {
+    if let Some(path) = args.value("--corpus") {
+        return run_corpus(&read_file(path)?);
+    }
+    if args.flag("--rulebook") {
+        return run_corpus(RULEBOOK);
+    }
+    let json_errors = args.value("--errors") == Some("json");
+    match read_position(args).and_then(|position| judge(&position)) {
+        Ok(None) if json_errors => println!("{}", json::object([("legal", Json::Bool(true))])),
+        Ok(None) => println!("legal"),
+        Ok(Some(reason)) => {
+            if json_errors {
+                println!(
+                    "{}",
+                    json::object([
+                        ("legal", Json::Bool(false)),
+                        ("error", diagnostic::to_json(&reason))
+                    ])
+                );
+            } else {
+                println!("illegal: {}", reason);
+            }
+            process::exit(ILLEGAL);
+        }
+        Err(error) => {
+            if json_errors {
+                eprintln!("{}", json::object([("error", diagnostic::to_json(&error))]));
+            } else {
+                eprintln!("Error: {:?}", error);
+            }
+            process::exit(BAD_INPUT);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_rulebook_verdicts_hold() -> Result<()> {
+        run_corpus(RULEBOOK)
+    }
+
+    // A scenario that claims the wrong verdict is a mismatch, not a pass
+    #[test]
+    fn a_wrong_verdict_fails() {
+        let corpus = "[[scenario]]\nrule = \"Initial meld\"\nrack = \"R8 R9 R10 K1\"\nmove = \"R8 R9 R10\"\nlegal = true\n";
+        assert!(run_corpus(corpus).is_err());
+    }
+
+    #[test]
+    fn a_scenario_needs_a_verdict() {
+        let corpus = "[[scenario]]\nrule = \"Initial meld\"\nrack = \"R1\"\ndraw = true\n";
+        assert!(read_corpus(corpus).is_err());
+    }
+}
@@ -0,0 +1,55 @@
+use color_eyre::eyre::{eyre, Result};
+use std::str::FromStr;
+
+// Minimal `--name value` / `--switch` argument handling for the subcommands
+pub struct Args {
+    args: Vec<String>,
+}
+
+impl Args {
+    pub fn new(args: &[String]) -> Args {
+        Args {
+            args: args.to_vec(),
+        }
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.args
+            .iter()
+            .position(|arg| arg == name)
+            .and_then(|index| self.args.get(index + 1))
+            .map(String::as_str)
+    }
+
+    // Every value of an option given more than once, e.g. `--config a --config b`
+    pub fn values(&self, name: &str) -> Vec<&str> {
+        self.args
+            .iter()
+            .zip(self.args.iter().skip(1))
+            .filter(|(arg, _)| *arg == name)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    // The arguments before any option, e.g. the shell of `completions bash`
+    pub fn positional(&self, index: usize) -> Option<&str> {
+        self.args
+            .iter()
+            .take_while(|arg| !arg.starts_with("--"))
+            .nth(index)
+            .map(String::as_str)
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.args.iter().any(|arg| arg == name)
+    }
+
+    pub fn parse_or<T: FromStr>(&self, name: &str, default: T) -> Result<T> {
+        match self.value(name) {
+            Some(value) => value
+                .parse()
+                .map_err(|_| eyre!("invalid value for {}: {}", name, value)),
+            None => Ok(default),
+        }
+    }
+}
@@ -0,0 +1,326 @@
+use crate::cli::Args;
+use crate::completions;
+use crate::config;
+use crate::json::{self, Json};
+use crate::settings;
+use crate::stats;
+use color_eyre::{eyre::bail, eyre::Report, eyre::Result};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+// Options of analyze a configuration may not set, the comparison runs every
+// position on one thread and reads the results from standard output
+const FIXED: [&str; 3] = ["--jobs", "--out", "--errors"];
+
+// A solver configuration: the options of analyze from a TOML file, e.g.
+//
+//     objective = "min-sets"
+//     max_nodes = 200000
+//     tablebase = "small.tb"
+struct Configuration {
+    name: String,
+    args: Vec<String>,
+}
+
+fn configuration(path: &str) -> Result<Configuration> {
+    let text =
+        fs::read_to_string(path).map_err(|error| Report::msg(format!("{}: {}", path, error)))?;
+    let options = completions::options_of("analyze")
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|option| !FIXED.contains(option))
+        .collect::<Vec<_>>();
+    let keys = options
+        .iter()
+        .map(|option| settings::key_of(option))
+        .collect::<Vec<_>>();
+    let mut args = Vec::new();
+    for table in config::parse(&text)? {
+        if !table.name.is_empty() {
+            bail!(
+                "{}: line {}: a configuration has no tables, found [{}]",
+                path,
+                table.line,
+                table.name
+            );
+        }
+        table.check_keys(&keys.iter().map(String::as_str).collect::<Vec<_>>())?;
+        for key in table.keys() {
+            if let Some(value) = table.get(key) {
+                args.extend(settings::option_args(
+                    &format!("--{}", key.replace('_', "-")),
+                    value,
+                ));
+            }
+        }
+    }
+    let name = Path::new(path)
+        .file_stem()
+        .map_or(path.to_string(), |stem| stem.to_string_lossy().to_string());
+    Ok(Configuration { name, args })
+}
+
+// What one run of a configuration found for a position
+struct Solved {
+    played: f64,
+    seconds: f64,
+    failed: bool,
+}
+
+// Every position of the corpus solved once under the configuration, by file
+fn solve_corpus(corpus: &str, configuration: &Configuration) -> Result<BTreeMap<String, Solved>> {
+    let mut arguments = vec![
+        "analyze".to_string(),
+        corpus.to_string(),
+        "--jobs".to_string(),
+        "1".to_string(),
+    ];
+    arguments.extend(configuration.args.iter().cloned());
+    let output = Command::new(env::current_exe()?)
+        .args(&arguments)
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "{} failed: {}",
+            arguments.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let mut solved = BTreeMap::new();
+    for line in String::from_utf8(output.stdout)?.lines() {
+        let result = json::parse(line)?;
+        // The summary has no file
+        let Some(file) = result.get("file").and_then(Json::as_str) else {
+            continue;
+        };
+        let number = |key: &str| match result.get(key) {
+            Some(Json::Number(number)) => *number,
+            _ => 0.0,
+        };
+        solved.insert(
+            file.to_string(),
+            Solved {
+                played: number("played"),
+                seconds: number("seconds"),
+                failed: result.get("status").and_then(Json::as_str) == Some("failed"),
+            },
+        );
+    }
+    Ok(solved)
+}
+
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    match sorted.len() {
+        0 => 0.0,
+        len if len % 2 == 1 => sorted[len / 2],
+        len => (sorted[len / 2 - 1] + sorted[len / 2]) / 2.0,
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len().max(1) as f64
+}
+
+// The runs of both configurations on one position
+#[derive(Default)]
+struct Runs {
+    seconds: [Vec<f64>; 2],
+    played: [Vec<f64>; 2],
+    failed: [u32; 2],
+}
+
+impl Runs {
+    // How much faster the second configuration is, above 1 when it is
+    fn speedup(&self) -> f64 {
+        median(&self.seconds[0]) / median(&self.seconds[1]).max(1e-9)
+    }
+
+    // Runs in which the second configuration was faster, paired by run
+    fn second_faster(&self) -> (u32, u32) {
+        let pairs = self.seconds[0]
+            .iter()
+            .zip(&self.seconds[1])
+            .filter(|(a, b)| a != b);
+        let faster = pairs.clone().filter(|(a, b)| b < a).count() as u32;
+        (faster, pairs.count() as u32)
+    }
+}
+
+fn significance(p: f64) -> &'static str {
+    if p < 0.05 {
+        " significant"
+    } else {
+        ""
+    }
+}
+
+// Solves every position of `--corpus`, a position file or a directory of them
+// as analyze reads them, `--runs` times under each of two `--config` files and
+// reports for every position and overall how the second configuration
+// compares with the first: the median time of the runs and the tiles played.
+// Runs alternate between the configurations, so a machine that slows down
+// halfway does not favor either. p-values come from sign tests, of the runs of
+// a position and of the positions overall.
+pub fn run(args: &Args) -> Result<(), Report> {
+    let paths = args.values("--config");
+    let [first, second] = paths.as_slice() else {
+        bail!("expected two configurations, e.g. compare --config a.toml --config b.toml --corpus hard/");
+    };
+    let Some(corpus) = args.value("--corpus") else {
+        bail!("missing --corpus, a position file or a directory of them");
+    };
+    let runs: u32 = args.parse_or("--runs", 8)?;
+    if runs == 0 {
+        bail!("--runs must be positive");
+    }
+    let configurations = [configuration(first)?, configuration(second)?];
+
+    let mut positions: BTreeMap<String, Runs> = BTreeMap::new();
+    for _ in 0..runs {
+        for (index, configuration) in configurations.iter().enumerate() {
+            for (file, solved) in solve_corpus(corpus, configuration)? {
+                let position = positions.entry(file).or_default();
+                position.seconds[index].push(solved.seconds);
+                position.played[index].push(solved.played);
+                position.failed[index] += solved.failed as u32;
+            }
+        }
+    }
+    if positions.is_empty() {
+        bail!("no positions in {}", corpus);
+    }
+
+    let [a, b] = &configurations;
+    println!(
+        "A is {}, B is {}, {} runs of every position, a speedup above 1 means B is faster",
+        a.name, b.name, runs
+    );
+    println!(
+        "{:40} | {:>10} | {:>10} | {:>7} | {:>7} | {:>7} | {:>7}",
+        "Position", "A ms", "B ms", "Speedup", "p-value", "Tiles A", "Tiles B"
+    );
+    for (file, position) in &positions {
+        let (faster, pairs) = position.second_faster();
+        let p = stats::binomial_test(faster, pairs);
+        let name = Path::new(file)
+            .file_name()
+            .map_or(file.clone(), |name| name.to_string_lossy().to_string());
+        println!(
+            "{:40} | {:10.2} | {:10.2} | {:7.2} | {:7.3} | {:7.1} | {:7.1}{}",
+            name,
+            median(&position.seconds[0]) * 1000.0,
+            median(&position.seconds[1]) * 1000.0,
+            position.speedup(),
+            p,
+            mean(&position.played[0]),
+            mean(&position.played[1]),
+            significance(p)
+        );
+    }
+
+    // Overall: the geometric mean of the speedups, so one slow position does
+    // not outweigh the rest, and how many positions each side won
+    let speedups = positions.values().map(Runs::speedup).collect::<Vec<f64>>();
+    let geometric = mean(
+        &speedups
+            .iter()
+            .map(|speedup| speedup.ln())
+            .collect::<Vec<f64>>(),
+    )
+    .exp();
+    let quicker = speedups.iter().filter(|&&speedup| speedup > 1.0).count() as u32;
+    let slower = speedups.iter().filter(|&&speedup| speedup < 1.0).count() as u32;
+    let p = stats::binomial_test(quicker, quicker + slower);
+    println!();
+    println!(
+        "Speed: {:.2}x, {} faster on {} positions and slower on {}, p-value {:.3}{}",
+        geometric,
+        b.name,
+        quicker,
+        slower,
+        p,
+        significance(p)
+    );
+    let tiles = |index: usize| {
+        positions
+            .values()
+            .map(|position| mean(&position.played[index]))
+            .collect::<Vec<f64>>()
+    };
+    let (tiles_a, tiles_b) = (tiles(0), tiles(1));
+    let more = tiles_a.iter().zip(&tiles_b).filter(|(a, b)| b > a).count() as u32;
+    let fewer = tiles_a.iter().zip(&tiles_b).filter(|(a, b)| b < a).count() as u32;
+    let p = stats::binomial_test(more, more + fewer);
+    println!(
+        "Quality: {:.1} tiles played against {:.1}, {} plays more on {} positions and fewer on {}, p-value {:.3}{}",
+        tiles_b.iter().sum::<f64>(),
+        tiles_a.iter().sum::<f64>(),
+        b.name,
+        more,
+        fewer,
+        p,
+        significance(p)
+    );
+    let failed = |index: usize| {
+        positions
+            .values()
+            .map(|position| position.failed[index])
+            .sum::<u32>()
+    };
+    if failed(0) + failed(1) > 0 {
+        println!(
+            "Failed runs: {} with {}, {} with {}",
+            failed(0),
+            a.name,
+            failed(1),
+            b.name
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn medians_of_odd_and_even_runs() {
+        assert_eq!(median(&[]), 0.0);
+        assert_eq!(median(&[3.0, 1.0, 2.0]), 2.0);
+        assert_eq!(median(&[4.0, 1.0, 2.0, 3.0]), 2.5);
+    }
+
+    // Ties are left out of the sign test
+    #[test]
+    fn runs_are_paired_in_order() {
+        let runs = Runs {
+            seconds: [vec![2.0, 2.0, 1.0, 3.0], vec![1.0, 2.0, 2.0, 1.0]],
+            ..Runs::default()
+        };
+        assert_eq!(runs.second_faster(), (2, 3));
+        assert_eq!(runs.speedup(), 2.0 / 1.5);
+    }
+
+    #[test]
+    fn configurations_become_options_of_analyze() -> Result<()> {
+        let path = env::temp_dir().join(format!("compare-{}.toml", std::process::id()));
+        let read = |text: &str| {
+            fs::write(&path, text)?;
+            configuration(&path.to_string_lossy())
+        };
+        let configuration = read("objective = \"min-sets\"\nmax_nodes = 200\n")?;
+        assert_eq!(
+            configuration.args,
+            ["--objective", "min-sets", "--max-nodes", "200"]
+        );
+        assert!(read("jobs = 4\n").is_err());
+        assert!(read("[limits]\nmax_nodes = 200\n").is_err());
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,449 @@
+use crate::cli::Args;
+use color_eyre::{eyre::bail, eyre::Report};
+
+const BIN: &str = env!("CARGO_BIN_NAME");
+
+// Options every subcommand reads
+const GLOBAL_OPTIONS: [&str; 7] = [
+    "--errors",
+    "--lang",
+    "--notify",
+    "--rules",
+    "--screen-reader",
+    "--tablebase",
+    "--theme",
+];
+
+// Options of the game setup, for the subcommands that deal games
+const GAME_OPTIONS: [&str; 11] = [
+    "--headless",
+    "--illegal-move-draws",
+    "--move-time",
+    "--must-play",
+    "--players",
+    "--rack-size",
+    "--rearrange-on-opening",
+    "--scenario",
+    "--time-manager",
+    "--timeout-draws",
+    "--turn-time",
+];
+
+const SHELLS: [&str; 3] = ["bash", "fish", "zsh"];
+
+struct Command {
+    name: &'static str,
+    summary: &'static str,
+    options: &'static [&'static str],
+    // Whether the game setup options apply
+    game: bool,
+}
+
+// Every subcommand main dispatches to, in the order of the man page
+const COMMANDS: [Command; 28] = [
+    Command {
+        name: "analyze",
+        summary: "solve position files, or NDJSON positions from standard input with -",
+        options: &["--jobs", "--max-memory", "--max-nodes", "--objective", "--out"],
+        game: false,
+    },
+    Command {
+        name: "assist",
+        summary: "suggest a move for a physical game from the rack and the table",
+        options: &[
+            "--alternatives",
+            "--analyze-search",
+            "--board-file",
+            "--boxes",
+            "--count",
+            "--edit",
+            "--json",
+            "--max-memory",
+            "--max-nodes",
+            "--objective",
+            "--opened",
+            "--partial",
+            "--png",
+            "--rack",
+            "--table",
+            "--table-file",
+            "--trace",
+            "--trace-nodes",
+            "--width",
+        ],
+        game: false,
+    },
+    Command {
+        name: "bench",
+        summary: "time the hot paths of the solver on positions from fixed seeds",
+        options: &["--memo", "--memos", "--positions", "--seed", "--sizes"],
+        game: false,
+    },
+    Command {
+        name: "check",
+        summary: "judge a single move, or the scenarios of the rulebook",
+        options: &["--board", "--corpus", "--draw", "--move", "--opened", "--played", "--rack", "--rulebook"],
+        game: true,
+    },
+    Command {
+        name: "compare",
+        summary: "solve a corpus under two solver configurations and compare speed and tiles played",
+        options: &["--config", "--corpus", "--runs"],
+        game: false,
+    },
+    Command {
+        name: "completions",
+        summary: "print the completions for bash, fish or zsh",
+        options: &[],
+        game: false,
+    },
+    Command {
+        name: "man",
+        summary: "print this man page",
+        options: &[],
+        game: false,
+    },
+    Command {
+        name: "match",
+        summary: "play rounds until a player reaches the target score",
+        options: &["--csv", "--profiles", "--rounds", "--target", "--weights"],
+        game: true,
+    },
+    Command {
+        name: "perft",
+        summary: "count the positions the move generator reaches from a dealt game",
+        options: &["--check", "--depth", "--max-nodes", "--seed"],
+        game: true,
+    },
+    Command {
+        name: "play",
+        summary: "hot-seat game for humans sharing one terminal",
+        options: &["--hints", "--names", "--no-stats"],
+        game: true,
+    },
+    Command {
+        name: "probability",
+        summary: "estimate how often a random rack holds an initial meld",
+        options: &["--csv", "--max", "--min", "--trials"],
+        game: false,
+    },
+    Command {
+        name: "puzzle",
+        summary: "solve the puzzles of a pack, or generate a pack from bot games",
+        options: &["--count", "--name", "--no-stats", "--seed", "--user"],
+        game: true,
+    },
+    Command {
+        name: "referee",
+        summary: "check the moves of humans playing with real tiles and keep the score",
+        options: &["--csv", "--names", "--rounds"],
+        game: true,
+    },
+    Command {
+        name: "retrograde",
+        summary: "solve a small two player endgame with the bag empty",
+        options: &["--opened", "--opponent", "--opponent-opened", "--profiles", "--rack", "--table", "--weights"],
+        game: false,
+    },
+    Command {
+        name: "review",
+        summary: "find the turns of a game log where a player missed a better move",
+        options: &["--game", "--html", "--min-loss", "--player", "--provenance", "--replay", "--timeline", "--width"],
+        game: false,
+    },
+    Command {
+        name: "selfplay",
+        summary: "export self-play games for training models",
+        options: &["--games", "--out", "--profiles", "--seed", "--verify", "--weights"],
+        game: true,
+    },
+    Command {
+        name: "serve",
+        summary: "serve the solver and games over HTTP",
+        options: &[
+            "--connections",
+            "--data",
+            "--host",
+            "--lobbies",
+            "--max-memory",
+            "--max-nodes",
+            "--port",
+            "--queue",
+            "--rate",
+            "--sessions",
+            "--timeout",
+            "--users",
+            "--workers",
+        ],
+        game: false,
+    },
+    Command {
+        name: "simulate",
+        summary: "play bot games and log them",
+        options: &["--games", "--log", "--plot", "--profiles", "--replay", "--seed", "--weights"],
+        game: true,
+    },
+    Command {
+        name: "spectate",
+        summary: "follow a bot game turn by turn, in the terminal or a browser",
+        options: &["--delay", "--open-hand", "--profiles", "--seed", "--serve", "--weights"],
+        game: true,
+    },
+    Command {
+        name: "stats",
+        summary: "show the statistics and achievements of the players of this machine",
+        options: &["--user"],
+        game: false,
+    },
+    Command {
+        name: "strength",
+        summary: "rank a rack among random racks of its size",
+        options: &["--rack", "--rack-size", "--samples", "--trials"],
+        game: false,
+    },
+    Command {
+        name: "suspicion",
+        summary: "flag players whose draws fit their needs more often than chance allows",
+        options: &["--replay"],
+        game: false,
+    },
+    Command {
+        name: "tablebase",
+        summary: "build the tablebase of small endgames",
+        options: &["--build", "--tiles"],
+        game: false,
+    },
+    Command {
+        name: "tournament",
+        summary: "play strategies against each other, round robin or Swiss",
+        options: &["--duplicate", "--format", "--games", "--ladder", "--profiles", "--rounds", "--seed", "--state", "--weights"],
+        game: true,
+    },
+    Command {
+        name: "tune",
+        summary: "tune the greedy strategy with SPSA",
+        options: &["--games", "--iterations", "--joker-weight", "--out", "--perturbation", "--seed", "--step"],
+        game: true,
+    },
+    Command {
+        name: "tutorial",
+        summary: "learn the rules with exercises checked by the validator",
+        options: &["--exercises", "--lesson", "--seed"],
+        game: false,
+    },
+    Command {
+        name: "verify",
+        summary: "cross-check the solver backends, or check them against the fixtures",
+        options: &["--exhaustive", "--fixtures", "--fixtures-file", "--max", "--samples"],
+        game: false,
+    },
+    Command {
+        name: "verify-determinism",
+        summary: "run a seeded command twice, analyze once more on one thread, and compare what they print",
+        options: &[],
+        game: false,
+    },
+];
+
+impl Command {
+    fn all_options(&self) -> Vec<&'static str> {
+        let mut options = self.options.to_vec();
+        if self.game {
+            options.extend(GAME_OPTIONS);
+        }
+        options.extend(GLOBAL_OPTIONS);
+        options.sort();
+        options.dedup();
+        options
+    }
+}
+
+// The options of a subcommand, None for one main does not know
+pub fn options_of(name: &str) -> Option<Vec<&'static str>> {
+    COMMANDS
+        .iter()
+        .find(|command| command.name == name)
+        .map(Command::all_options)
+}
+
+// The names of the subcommands, in the order of the man page
+pub fn names() -> Vec<&'static str> {
+    COMMANDS.iter().map(|command| command.name).collect()
+}
+
+// Fails on an option the subcommand does not take, which it would otherwise
+// ignore, e.g. a misspelt one. verify-determinism passes its arguments on to
+// the command it runs, which checks them itself.
+pub fn check_options(name: &str, args: &[String]) -> Result<(), Report> {
+    let Some(options) = options_of(name).filter(|_| name != "verify-determinism") else {
+        return Ok(());
+    };
+    if let Some(unknown) = args
+        .iter()
+        .find(|arg| arg.starts_with("--") && !options.contains(&arg.as_str()))
+    {
+        bail!(
+            "{} does not take {}, its options are {}",
+            name,
+            unknown,
+            options.join(" ")
+        );
+    }
+    Ok(())
+}
+
+// The options of every subcommand
+pub fn every_option() -> Vec<&'static str> {
+    let mut options = COMMANDS
+        .iter()
+        .flat_map(Command::all_options)
+        .collect::<Vec<_>>();
+    options.sort();
+    options.dedup();
+    options
+}
+
+fn bash() -> String {
+    let names = COMMANDS
+        .iter()
+        .map(|command| command.name)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut script = format!(
+        "_{bin}() {{\n    local cur=${{COMP_WORDS[COMP_CWORD]}}\n    if [ \"$COMP_CWORD\" -eq 1 ]; then\n        COMPREPLY=($(compgen -W \"{names}\" -- \"$cur\"))\n        return\n    fi\n    case ${{COMP_WORDS[1]}} in\n",
+        bin = BIN,
+        names = names
+    );
+    for command in &COMMANDS {
+        let options = match command.name {
+            "completions" => SHELLS.join(" "),
+            _ => command.all_options().join(" "),
+        };
+        script += &format!(
+            "        {}) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;\n",
+            command.name, options
+        );
+    }
+    script += &format!(
+        "    esac\n}}\ncomplete -o default -F _{bin} {bin}\n",
+        bin = BIN
+    );
+    script
+}
+
+fn zsh() -> String {
+    let mut script = format!(
+        "#compdef {bin}\n\n_{bin}() {{\n    if (( CURRENT == 2 )); then\n        local -a commands\n        commands=(\n",
+        bin = BIN
+    );
+    for command in &COMMANDS {
+        script += &format!("            '{}:{}'\n", command.name, command.summary);
+    }
+    script += "        )\n        _describe command commands\n        return\n    fi\n    case $words[2] in\n";
+    for command in &COMMANDS {
+        let options = match command.name {
+            "completions" => SHELLS.join(" "),
+            _ => command.all_options().join(" "),
+        };
+        script += &format!(
+            "        {}) compadd -- {}; _files ;;\n",
+            command.name, options
+        );
+    }
+    script += &format!("    esac\n}}\n\n_{bin} \"$@\"\n", bin = BIN);
+    script
+}
+
+fn fish() -> String {
+    let mut script = format!("complete -c {} -f\n", BIN);
+    for command in &COMMANDS {
+        script += &format!(
+            "complete -c {} -n __fish_use_subcommand -a {} -d '{}'\n",
+            BIN, command.name, command.summary
+        );
+    }
+    for command in &COMMANDS {
+        let condition = format!("__fish_seen_subcommand_from {}", command.name);
+        if command.name == "completions" {
+            script += &format!(
+                "complete -c {} -n '{}' -a '{}'\n",
+                BIN,
+                condition,
+                SHELLS.join(" ")
+            );
+            continue;
+        }
+        for option in command.all_options() {
+            script += &format!(
+                "complete -c {} -n '{}' -r -l {}\n",
+                BIN,
+                condition,
+                &option[2..]
+            );
+        }
+    }
+    script
+}
+
+// Prints the completion script of a shell, e.g. for bash
+// `rummikub_bot completions bash > /etc/bash_completion.d/rummikub_bot`
+pub fn run(args: &Args) -> Result<(), Report> {
+    let script = match args.positional(0) {
+        Some("bash") => bash(),
+        Some("fish") => fish(),
+        Some("zsh") => zsh(),
+        Some(shell) => bail!(
+            "unknown shell {}, expected one of {}",
+            shell,
+            SHELLS.join(", ")
+        ),
+        None => bail!("missing the shell, one of {}", SHELLS.join(", ")),
+    };
+    print!("{}", script);
+    Ok(())
+}
+
+// Hyphens escaped, as roff would break lines at them
+fn roff(text: &str) -> String {
+    text.replace('-', "\\-")
+}
+
+// Prints the man page in roff, e.g. `rummikub_bot man > rummikub_bot.1`
+pub fn man() -> Result<(), Report> {
+    let mut page = format!(
+        ".TH {} 1 \"\" \"{}\"\n.SH NAME\n{} \\- Rummikub solver, bots and referee\n.SH SYNOPSIS\n.B {}\n.I command\n[\\fIoptions\\fR]\n",
+        BIN.to_uppercase(),
+        env!("CARGO_PKG_VERSION"),
+        roff(BIN),
+        roff(BIN)
+    );
+    page += ".SH COMMANDS\n";
+    for command in &COMMANDS {
+        page += &format!(".TP\n.B {}\n{}.\n", command.name, roff(command.summary));
+        let options = command
+            .options
+            .iter()
+            .map(|option| format!("\\fB{}\\fR", roff(option)))
+            .collect::<Vec<_>>();
+        if !options.is_empty() {
+            page += &format!("Options: {}\n", options.join(", "));
+        }
+    }
+    page += ".SH GAME OPTIONS\nThe commands that deal games also take ";
+    page += &GAME_OPTIONS
+        .iter()
+        .map(|option| format!("\\fB{}\\fR", roff(option)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    page += ".\n.SH GLOBAL OPTIONS\nEvery command takes ";
+    page += &GLOBAL_OPTIONS
+        .iter()
+        .map(|option| format!("\\fB{}\\fR", roff(option)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    page += ".\n";
+    page += ".SH ENVIRONMENT\n\\fBRUMMIKUB_\\fIOPTION\\fR sets an option the command line does not give, e.g. \\fBRUMMIKUB_MOVE_TIME\\fR for \\fB\\-\\-move\\-time\\fR.\n";
+    page += ".SH FILES\n.I $XDG_CONFIG_HOME/rummikubbot/config.toml\nOptions neither the command line nor the environment give, keys above the first header for every command and a table per command, e.g. \\fB[simulate]\\fR.\n";
+    print!("{}", page);
+    Ok(())
+}
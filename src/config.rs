@@ -0,0 +1,182 @@
+use color_eyre::eyre::{bail, eyre, Result};
+
+// The small part of TOML the configuration files need: `[table]` and
+// `[[array]]` headers with `key = value` lines of strings, numbers and booleans
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum Value {
+    String(String),
+    Number(f64),
+    Bool(bool),
+}
+
+pub struct Table {
+    // Header name, empty for keys above the first header
+    pub name: String,
+    pub line: usize,
+    entries: Vec<(String, Value, usize)>,
+}
+
+impl Table {
+    fn new(name: &str, line: usize) -> Table {
+        Table {
+            name: name.to_string(),
+            line,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|(name, _, _)| name == key)
+            .map(|(_, value, _)| value)
+    }
+
+    // Keys in file order
+    pub fn keys(&self) -> Vec<&str> {
+        self.entries
+            .iter()
+            .map(|(key, _, _)| key.as_str())
+            .collect()
+    }
+
+    pub fn string(&self, key: &str) -> Result<Option<&str>> {
+        match self.get(key) {
+            Some(Value::String(text)) => Ok(Some(text)),
+            Some(_) => bail!(
+                "[{}] on line {}: {} must be a string",
+                self.name,
+                self.line,
+                key
+            ),
+            None => Ok(None),
+        }
+    }
+
+    pub fn number(&self, key: &str) -> Result<Option<f64>> {
+        match self.get(key) {
+            Some(Value::Number(number)) => Ok(Some(*number)),
+            Some(_) => bail!(
+                "[{}] on line {}: {} must be a number",
+                self.name,
+                self.line,
+                key
+            ),
+            None => Ok(None),
+        }
+    }
+
+    pub fn bool(&self, key: &str) -> Result<Option<bool>> {
+        match self.get(key) {
+            Some(Value::Bool(value)) => Ok(Some(*value)),
+            Some(_) => bail!(
+                "[{}] on line {}: {} must be true or false",
+                self.name,
+                self.line,
+                key
+            ),
+            None => Ok(None),
+        }
+    }
+
+    // Catches typos in key names instead of silently ignoring them
+    pub fn check_keys(&self, known: &[&str]) -> Result<()> {
+        match self
+            .entries
+            .iter()
+            .find(|(key, _, _)| !known.contains(&key.as_str()))
+        {
+            Some((key, _, line)) => bail!("line {}: unknown key {} in [{}]", line, key, self.name),
+            None => Ok(()),
+        }
+    }
+}
+
+// Everything after a # that is not inside a string
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut chars = line.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(text: &str) -> Option<Value> {
+    if let Some(text) = text.strip_prefix('"') {
+        let text = text.strip_suffix('"')?;
+        let mut value = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            value.push(match c {
+                '\\' => match chars.next()? {
+                    'n' => '\n',
+                    't' => '\t',
+                    other => other,
+                },
+                '"' => return None,
+                c => c,
+            });
+        }
+        return Some(Value::String(value));
+    }
+
+    match text {
+        "true" => Some(Value::Bool(true)),
+        "false" => Some(Value::Bool(false)),
+        _ => text.replace('_', "").parse().ok().map(Value::Number),
+    }
+}
+
+// Quotes and backslashes escaped for a string value written back to a file
+pub fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Tables in file order. Keys above the first header end up in a table without name.
+pub fn parse(text: &str) -> Result<Vec<Table>> {
+    let mut tables = Vec::new();
+    let mut table = Table::new("", 0);
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let header = line
+            .strip_prefix("[[")
+            .and_then(|rest| rest.strip_suffix("]]"))
+            .or_else(|| {
+                line.strip_prefix('[')
+                    .and_then(|rest| rest.strip_suffix(']'))
+            });
+        if let Some(name) = header {
+            tables.push(table);
+            table = Table::new(name.trim(), number);
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| eyre!("line {}: expected `key = value`", number))?;
+        let key = key.trim().to_string();
+        let value = parse_value(value.trim())
+            .ok_or_else(|| eyre!("line {}: invalid value for {}", number, key))?;
+
+        if table.get(&key).is_some() {
+            bail!("line {}: {} is set twice in [{}]", number, key, table.name);
+        }
+        table.entries.push((key, value, number));
+    }
+    tables.push(table);
+    Ok(tables)
+}
@@ -0,0 +1,48 @@
+use crate::bag::Bag;
+use crate::rng::{uniform_index, SeededRng};
+use crate::solver::{Inventory, Tile};
+use rand::{Rng, SeedableRng};
+
+// Every tile of the pool in a uniformly random order (Fisher-Yates), jokers
+// included like any other tile
+pub fn shuffle(pool: &Inventory, rng: &mut impl Rng) -> Vec<Tile> {
+    let mut tiles = pool.tiles();
+    for last in (1..tiles.len()).rev() {
+        tiles.swap(last, uniform_index(rng, last + 1));
+    }
+    tiles
+}
+
+// Racks of a new game and the bag with its tiles in the order they are drawn
+pub struct Deal {
+    pub racks: Vec<Inventory>,
+    pub bag: Bag,
+}
+
+impl Deal {
+    // The full pool shuffled with the seed, each rack dealt off the top in
+    // turn and the rest left as the bag, so a seed fixes the whole game
+    pub fn new(players: usize, rack_size: u32, seed: u64) -> Deal {
+        let mut rng = SeededRng::seed_from_u64(seed);
+        let mut tiles = shuffle(&Inventory::new(2), &mut rng).into_iter();
+        let racks = (0..players)
+            .map(|_| {
+                let mut rack = Inventory::new(0);
+                tiles
+                    .by_ref()
+                    .take(rack_size as usize)
+                    .for_each(|tile| rack.add_tile(&tile));
+                rack
+            })
+            .collect();
+        Deal {
+            racks,
+            bag: Bag::in_order(tiles.collect()),
+        }
+    }
+}
+
+// Rack of the given size from a fresh bag
+pub fn random_rack(size: u32) -> Inventory {
+    Bag::full().draw_n(size, &mut rand::thread_rng())
+}
@@ -0,0 +1,88 @@
+use crate::json::{self, Json};
+use color_eyre::{eyre::bail, eyre::Report, eyre::Result};
+use std::env;
+use std::process::Command;
+
+// Checked when no command is given
+const DEFAULT: [&str; 5] = ["simulate", "--games", "20", "--seed", "1"];
+
+// Fields of JSON output that time the work, which never repeat
+const TIMINGS: [&str; 2] = ["seconds", "slowest_seconds"];
+
+// Standard output of this program run with the arguments
+fn output(arguments: &[String]) -> Result<String> {
+    let output = Command::new(env::current_exe()?).args(arguments).output()?;
+    if !output.status.success() {
+        bail!(
+            "{} failed: {}",
+            arguments.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+// A line as it is compared, objects of JSON without their timings
+fn comparable(line: &str) -> String {
+    match json::parse(line) {
+        Ok(Json::Object(entries)) => Json::Object(
+            entries
+                .into_iter()
+                .filter(|(key, _)| !TIMINGS.contains(&key.as_str()))
+                .collect(),
+        )
+        .to_string(),
+        _ => line.to_string(),
+    }
+}
+
+// The arguments on one thread, for analyze which spreads files over cores
+fn single_threaded(arguments: &[String]) -> Vec<String> {
+    let mut single = arguments.to_vec();
+    match single.iter().position(|argument| argument == "--jobs") {
+        Some(index) if index + 1 < single.len() => single[index + 1] = "1".to_string(),
+        _ => single.extend(["--jobs".to_string(), "1".to_string()]),
+    }
+    single
+}
+
+// Runs a command twice with the same seed, e.g. `verify-determinism
+// tournament --seed 3`, and fails at the first line the runs print
+// differently. analyze runs again on a single thread, so the result must not
+// depend on the number of threads either. Timings in JSON are left out.
+pub fn run(arguments: &[String]) -> Result<(), Report> {
+    let arguments = match arguments.is_empty() {
+        true => DEFAULT.map(str::to_string).to_vec(),
+        false => arguments.to_vec(),
+    };
+    if arguments[0] == "verify-determinism" {
+        bail!("verify-determinism cannot check itself");
+    }
+    let again = match arguments[0].as_str() {
+        "analyze" => single_threaded(&arguments),
+        _ => arguments.clone(),
+    };
+    let (first, second) = (output(&arguments)?, output(&again)?);
+    let (first, second) = (
+        first.lines().collect::<Vec<_>>(),
+        second.lines().collect::<Vec<_>>(),
+    );
+    for index in 0..first.len().max(second.len()) {
+        let (one, other) = (first.get(index).copied(), second.get(index).copied());
+        if one.map(comparable) != other.map(comparable) {
+            bail!(
+                "the runs of {} differ at line {}:\n  first:  {}\n  second: {}",
+                arguments.join(" "),
+                index + 1,
+                one.unwrap_or("(no line)"),
+                other.unwrap_or("(no line)")
+            );
+        }
+    }
+    println!(
+        "Both runs of {} printed the same {} lines",
+        arguments.join(" "),
+        first.len()
+    );
+    Ok(())
+}
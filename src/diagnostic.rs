@@ -0,0 +1,125 @@
+use crate::json::{self, Json};
+use color_eyre::eyre::{eyre, Report};
+use std::fmt;
+
+// A part of the text that was parsed, as byte offsets
+#[derive(Clone, Debug)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+impl Span {
+    // The place of `part` in `whole`, None when it is not a slice of it
+    pub fn of(whole: &str, part: &str) -> Option<Span> {
+        let start = (part.as_ptr() as usize).checked_sub(whole.as_ptr() as usize)?;
+        let end = start.checked_add(part.len())?;
+        if end > whole.len() {
+            return None;
+        }
+        Some(Span {
+            start,
+            end,
+            text: part.to_string(),
+        })
+    }
+
+    fn shifted(&self, offset: usize) -> Span {
+        Span {
+            start: self.start + offset,
+            end: self.end + offset,
+            text: self.text.clone(),
+        }
+    }
+}
+
+// An error with a code that stays the same when the wording changes and the
+// spans of the input it is about, so programs do not have to read the
+// message. It travels in a Report like any other error and shows as the
+// message alone.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub code: &'static str,
+    pub message: String,
+    pub spans: Vec<Span>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+pub fn report(code: &'static str, message: String, spans: Vec<Span>) -> Report {
+    Report::new(Diagnostic {
+        code,
+        message,
+        spans,
+    })
+}
+
+// An error about no particular part of the input
+pub fn coded(code: &'static str, message: String) -> Report {
+    report(code, message, Vec::new())
+}
+
+fn find(report: &Report) -> Option<&Diagnostic> {
+    report
+        .chain()
+        .find_map(|error| error.downcast_ref::<Diagnostic>())
+}
+
+// The error with `prefix: ` in front of the message, keeping its code, and
+// its spans moved by `offset` for text that was cut out of a longer one
+pub fn within(report: Report, prefix: &str, offset: usize) -> Report {
+    let message = format!("{}: {}", prefix, report);
+    match find(&report) {
+        Some(diagnostic) => self::report(
+            diagnostic.code,
+            message,
+            diagnostic
+                .spans
+                .iter()
+                .map(|span| span.shifted(offset))
+                .collect(),
+        ),
+        None => eyre!("{}", message),
+    }
+}
+
+// The spans of the error, none for an error without code
+pub fn spans(report: &Report) -> Vec<Span> {
+    find(report).map_or(Vec::new(), |diagnostic| diagnostic.spans.clone())
+}
+
+// The code, message and spans of the error, with code `error` for the errors
+// that have none
+pub fn fields(report: &Report) -> Vec<(String, Json)> {
+    let (code, spans) = match find(report) {
+        Some(diagnostic) => (diagnostic.code, diagnostic.spans.as_slice()),
+        None => ("error", &[][..]),
+    };
+    let spans = spans
+        .iter()
+        .map(|span| {
+            json::object([
+                ("start", Json::Number(span.start as f64)),
+                ("end", Json::Number(span.end as f64)),
+                ("text", json::string(span.text.as_str())),
+            ])
+        })
+        .collect();
+    vec![
+        ("code".to_string(), json::string(code)),
+        ("message".to_string(), json::string(report.to_string())),
+        ("spans".to_string(), Json::Array(spans)),
+    ]
+}
+
+// `{"code": ..., "message": ..., "spans": [{"start": ..., "end": ..., "text": ...}]}`
+pub fn to_json(report: &Report) -> Json {
+    Json::Object(fields(report))
+}
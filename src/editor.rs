@@ -0,0 +1,217 @@
+use crate::notation;
+use crate::solver::{Inventory, RackOrder, Tile};
+use crate::theme;
+use color_eyre::eyre::{bail, Result};
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+
+const HELP: &str = "arrows or hjkl move, + and - change the count, 0 clears, s sorts the rack, Enter accepts, q cancels";
+
+// Row of the joker counter below the 13 number rows
+const JOKER_ROW: usize = 13;
+
+// Switches the terminal to reading single key presses without echo for as long
+// as it lives, through `stty` so no terminal library is needed
+pub struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    pub fn enable() -> Result<RawMode> {
+        let saved = stty(&["-g"])?;
+        stty(&["-icanon", "-echo", "min", "1"])?;
+        Ok(RawMode {
+            saved: saved.trim().to_string(),
+        })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+fn stty(args: &[&str]) -> Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        bail!("this screen needs an interactive terminal");
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    More,
+    Fewer,
+    Clear,
+    Sort,
+    Accept,
+    Cancel,
+    Other,
+}
+
+fn read_key(input: &mut impl Read) -> Result<Key> {
+    let mut byte = [0u8];
+    input.read_exact(&mut byte)?;
+    Ok(match byte[0] {
+        b'\x1b' => {
+            // Arrow keys arrive as ESC [ A to D, a lone ESC cancels
+            let mut sequence = [0u8; 2];
+            if input.read_exact(&mut sequence).is_err() || sequence[0] != b'[' {
+                return Ok(Key::Cancel);
+            }
+            match sequence[1] {
+                b'A' => Key::Up,
+                b'B' => Key::Down,
+                b'C' => Key::Right,
+                b'D' => Key::Left,
+                _ => Key::Other,
+            }
+        }
+        b'k' => Key::Up,
+        b'j' => Key::Down,
+        b'l' => Key::Right,
+        b'h' => Key::Left,
+        b'+' | b'=' => Key::More,
+        b'-' | b'_' => Key::Fewer,
+        b'0' => Key::Clear,
+        b's' => Key::Sort,
+        b'\n' | b'\r' => Key::Accept,
+        b'q' => Key::Cancel,
+        _ => Key::Other,
+    })
+}
+
+struct Editor {
+    inventory: Inventory,
+    // Tiles that are still available, e.g. the pool minus the table
+    limit: Inventory,
+    row: usize,
+    color: usize,
+    // How the tiles picked so far are listed below the grid
+    order: RackOrder,
+    message: String,
+}
+
+impl Editor {
+    fn tile(&self) -> Tile {
+        if self.row == JOKER_ROW {
+            Tile {
+                color: 0,
+                number: 0,
+                is_joker: true,
+            }
+        } else {
+            Tile {
+                color: self.color as u8,
+                number: self.row as u8 + 1,
+                is_joker: false,
+            }
+        }
+    }
+
+    fn render(&self, title: &str) -> String {
+        let mut screen = format!("\x1b[2J\x1b[H{}\r\n\r\n     ", title);
+        for letter in notation::palette().letters {
+            screen.push_str(&format!("  {}  ", letter));
+        }
+        screen.push_str("\r\n");
+
+        let cell = |count: u8, selected: bool| {
+            if selected {
+                format!(" [{}] ", count)
+            } else {
+                format!("  {}  ", count)
+            }
+        };
+        for row in 0..JOKER_ROW {
+            screen.push_str(&format!("  {:2} ", row + 1));
+            for color in 0..4 {
+                let tile = Tile {
+                    color: color as u8,
+                    number: row as u8 + 1,
+                    is_joker: false,
+                };
+                let selected = self.row == row && self.color == color;
+                screen.push_str(&cell(self.inventory.count(&tile), selected));
+            }
+            screen.push_str("\r\n");
+        }
+        // Listed like the physical rack being copied, two spaces between groups
+        let rack = self
+            .inventory
+            .to_sorted_rack(self.order)
+            .iter()
+            .map(|group| theme::tiles(group))
+            .collect::<Vec<String>>();
+        screen.push_str(&format!(
+            "   J {}\r\n\r\n{} tiles {}: {}\r\n{}\r\n{}\r\n",
+            cell(self.inventory.jokers(), self.row == JOKER_ROW),
+            self.inventory.total_tile_count(),
+            self.order.name(),
+            rack.join("  "),
+            self.message,
+            HELP
+        ));
+        screen
+    }
+
+    fn change(&mut self, more: bool) {
+        let tile = self.tile();
+        let count = self.inventory.count(&tile);
+        self.message.clear();
+        if more && count >= self.limit.count(&tile) {
+            self.message = format!("no {} left in the pool", tile);
+        } else if more {
+            self.inventory.add_tile(&tile);
+        } else if count > 0 {
+            self.inventory.remove_tile(&tile);
+        }
+    }
+}
+
+// Lets the user fill an inventory by moving over the 13 by 4 grid of tiles and
+// changing counts, with a joker counter below. No count can exceed what
+// `limit` holds. Returns None when the user cancels.
+pub fn edit(title: &str, start: Inventory, limit: Inventory) -> Result<Option<Inventory>> {
+    let _raw = RawMode::enable()?;
+    let mut editor = Editor {
+        inventory: start,
+        limit,
+        row: 0,
+        color: 0,
+        order: RackOrder::ByColor,
+        message: String::new(),
+    };
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout();
+
+    loop {
+        write!(stdout, "{}", editor.render(title))?;
+        stdout.flush()?;
+        match read_key(&mut stdin)? {
+            Key::Up => editor.row = editor.row.saturating_sub(1),
+            Key::Down => editor.row = (editor.row + 1).min(JOKER_ROW),
+            Key::Left => editor.color = editor.color.saturating_sub(1),
+            Key::Right => editor.color = (editor.color + 1).min(3),
+            Key::More => editor.change(true),
+            Key::Fewer => editor.change(false),
+            Key::Clear => {
+                while editor.inventory.count(&editor.tile()) > 0 {
+                    editor.change(false);
+                }
+            }
+            Key::Sort => editor.order = editor.order.toggled(),
+            Key::Accept => return Ok(Some(editor.inventory)),
+            Key::Cancel => return Ok(None),
+            Key::Other => {}
+        }
+    }
+}
@@ -0,0 +1,123 @@
+// Numeric encoding of positions and moves shared by everything that feeds
+// models, so exported data and model inputs never drift apart. Any change to
+// the layout below has to bump ENCODING_VERSION.
+//
+// Tile counts use 53 columns: color * 13 + number - 1 for the numbered tiles
+// (colors red, blue, orange, black) and 52 for jokers. A position is, from the
+// view of the player to move:
+//
+//   rack       53  tiles on the rack
+//   table      53  tiles on the table, jokers as jokers
+//   unseen     53  tiles in the bag or on other racks
+//   opponents   3  rack sizes of the next players in turn order, 0 padded
+//   bag         1  tiles left in the bag
+//   opened      1  1 once the player has made the initial meld
+//
+// A move is the 53 tile counts it lays out from the rack, all 0 for drawing.
+
+use crate::game::GameState;
+use crate::solver::{self, Inventory, Set, Tile};
+pub use crate::solver::{tile_at, tile_index, TILE_TYPES};
+use crate::strategy::{Move, Observation};
+use color_eyre::eyre::{bail, Result};
+
+pub const ENCODING_VERSION: u32 = 1;
+
+pub const MAX_OPPONENTS: usize = 3;
+pub const POSITION_LEN: usize = 3 * TILE_TYPES + MAX_OPPONENTS + 2;
+
+pub type Position = [i32; POSITION_LEN];
+
+pub fn encode_tiles(tiles: &[Tile]) -> [i32; TILE_TYPES] {
+    let mut counts = [0; TILE_TYPES];
+    for index in tiles.iter().filter_map(tile_index) {
+        counts[index] += 1;
+    }
+    counts
+}
+
+// Rack tiles for the counts, jokers without a color or number
+pub fn decode_tiles(counts: &[i32]) -> Result<Inventory> {
+    if counts.len() != TILE_TYPES {
+        bail!("expected {} tile counts, got {}", TILE_TYPES, counts.len());
+    }
+    let mut inventory = Inventory::new(0);
+    for (index, &count) in counts.iter().enumerate() {
+        if !(0..=2).contains(&count) {
+            bail!("tile count {} in column {} is not 0, 1 or 2", count, index);
+        }
+        let tile = tile_at(index);
+        for _ in 0..count {
+            inventory.add_tile(&tile);
+        }
+    }
+    Ok(inventory)
+}
+
+pub fn encode_position(game: &GameState) -> Position {
+    encode_observation(&game.observation())
+}
+
+// The position as the player to move sees it, so strategies encode exactly
+// what the exporter wrote
+pub fn encode_observation(observation: &Observation) -> Position {
+    let seat = observation.seat;
+    let players = observation.tiles.len();
+    let rack = encode_tiles(&observation.rack.tiles());
+    let table = encode_tiles(&Inventory::from_sets(observation.table).tiles());
+    let pool = encode_tiles(&Inventory::new(2).tiles());
+
+    let mut position = [0; POSITION_LEN];
+    let (rack_part, rest) = position.split_at_mut(TILE_TYPES);
+    let (table_part, rest) = rest.split_at_mut(TILE_TYPES);
+    let (unseen_part, rest) = rest.split_at_mut(TILE_TYPES);
+    let (opponents_part, rest) = rest.split_at_mut(MAX_OPPONENTS);
+    rack_part.copy_from_slice(&rack);
+    table_part.copy_from_slice(&table);
+    for (index, unseen) in unseen_part.iter_mut().enumerate() {
+        *unseen = pool[index] - rack[index] - table[index];
+    }
+    for (offset, size) in opponents_part
+        .iter_mut()
+        .enumerate()
+        .take(players.saturating_sub(1))
+    {
+        *size = observation.tiles[(seat + offset + 1) % players] as i32;
+    }
+    rest[0] = observation.bag as i32;
+    rest[1] = observation.opened as i32;
+    position
+}
+
+pub fn encode_move(rack: &Inventory, chosen: Option<&Move>) -> [i32; TILE_TYPES] {
+    match chosen {
+        Some((_, rest)) => encode_tiles(&rest.missing(rack)),
+        None => [0; TILE_TYPES],
+    }
+}
+
+// A legal move laying out exactly the tiles of the counts, None for drawing.
+// Fails when the tiles are not on the rack or cannot be placed together.
+pub fn decode_move(
+    counts: &[i32],
+    table: &[Set],
+    rack: &Inventory,
+    opened: bool,
+) -> Result<Option<Move>> {
+    let played = decode_tiles(counts)?;
+    if played.total_tile_count() == 0 {
+        return Ok(None);
+    }
+    if !rack.missing(&played).is_empty() {
+        bail!("the move lays out tiles that are not on the rack");
+    }
+
+    match solver::best_move(table, &played, opened)? {
+        Some((new_table, rest)) if rest.total_tile_count() == 0 => {
+            let mut rest = *rack;
+            rest.subtract(&played);
+            Ok(Some((new_table, rest)))
+        }
+        _ => bail!("the tiles of the move cannot all be laid out"),
+    }
+}
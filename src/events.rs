@@ -0,0 +1,238 @@
+use crate::notation;
+use crate::solver::{Set, Tile};
+use color_eyre::eyre::{bail, eyre, Result};
+use std::fmt;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+// What happened in a game, as reported by the engine to its observers
+#[derive(Clone, Debug)]
+pub enum GameEvent {
+    TileDrawn {
+        seat: usize,
+        tile: Tile,
+    },
+    // New sets laid out next to the untouched table, all from the rack
+    SetsPlayed {
+        seat: usize,
+        sets: Vec<Set>,
+    },
+    // Sets of the table were changed, `table` is the complete new table and
+    // `tiles` are the ones that came from the rack
+    BoardRearranged {
+        seat: usize,
+        table: Vec<Set>,
+        tiles: Vec<Tile>,
+    },
+    // The move broke the rules, the player draws the penalty instead
+    IllegalMove {
+        seat: usize,
+        reason: String,
+    },
+    // The move took longer than the turn time, the player draws instead
+    TurnTimeout {
+        seat: usize,
+        elapsed: Duration,
+    },
+    // The end of a turn: how the points on the rack of the player changed,
+    // negative when tiles were laid out, and the points on every rack
+    TurnSummary {
+        seat: usize,
+        swing: i32,
+        points: Vec<u32>,
+    },
+    // None for a blocked game without a winner
+    GameWon {
+        winner: Option<usize>,
+        blocked: bool,
+    },
+}
+
+impl fmt::Display for GameEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sets = |sets: &[Set]| {
+            sets.iter()
+                .map(|set| format!("[{}]", set))
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        match self {
+            GameEvent::TileDrawn { seat, tile } => write!(f, "Player {} draws {}", seat + 1, tile),
+            GameEvent::SetsPlayed { seat, sets: played } => {
+                write!(f, "Player {} plays {}", seat + 1, sets(played))
+            }
+            GameEvent::BoardRearranged { seat, table, tiles } => {
+                let tiles = tiles.iter().map(Tile::to_string).collect::<Vec<String>>();
+                write!(
+                    f,
+                    "Player {} adds {} and rearranges the table to {}",
+                    seat + 1,
+                    tiles.join(" "),
+                    sets(table)
+                )
+            }
+            GameEvent::IllegalMove { seat, reason } => {
+                write!(f, "Player {} made an illegal move: {}", seat + 1, reason)
+            }
+            GameEvent::TurnTimeout { seat, elapsed } => {
+                write!(
+                    f,
+                    "Player {} ran out of time after {:.1?}",
+                    seat + 1,
+                    elapsed
+                )
+            }
+            GameEvent::TurnSummary {
+                seat,
+                swing,
+                points,
+            } => {
+                let points = points.iter().map(u32::to_string).collect::<Vec<String>>();
+                write!(
+                    f,
+                    "Player {} ends the turn with {:+} points, the racks hold {} points",
+                    seat + 1,
+                    swing,
+                    points.join(" ")
+                )
+            }
+            GameEvent::GameWon {
+                winner: Some(seat),
+                blocked,
+            } => write!(
+                f,
+                "Player {} wins{}",
+                seat + 1,
+                if *blocked { " the blocked game" } else { "" }
+            ),
+            GameEvent::GameWon { winner: None, .. } => write!(f, "The blocked game is a draw"),
+        }
+    }
+}
+
+// Sets shown as `[R1 R2 R3] [B5 J B7]`
+fn parse_sets(text: &str) -> Result<Vec<Set>> {
+    text.split(']')
+        .map(|set| set.trim().trim_start_matches('['))
+        .filter(|set| !set.is_empty())
+        .map(notation::parse_set)
+        .collect()
+}
+
+// Durations as printed with `{:.1?}`, e.g. `2.5s` or `850.0ms`
+fn parse_duration(text: &str) -> Result<Duration> {
+    let units = [("ms", 1e-3), ("µs", 1e-6), ("ns", 1e-9), ("s", 1.0)];
+    for (unit, seconds) in units {
+        if let Some(number) = text.strip_suffix(unit) {
+            let number: f64 = number
+                .parse()
+                .map_err(|_| eyre!("invalid duration {}", text))?;
+            return Ok(Duration::from_secs_f64(number * seconds));
+        }
+    }
+    bail!("invalid duration {}", text)
+}
+
+impl GameEvent {
+    // Reads back an event in the form it is displayed in, e.g. from a log
+    pub fn parse(text: &str) -> Result<GameEvent> {
+        let text = text.trim();
+        if text == "The blocked game is a draw" {
+            return Ok(GameEvent::GameWon {
+                winner: None,
+                blocked: true,
+            });
+        }
+        let Some((player, rest)) = text
+            .strip_prefix("Player ")
+            .and_then(|text| text.split_once(' '))
+        else {
+            bail!("unknown event: {}", text);
+        };
+        let seat = match player.parse::<usize>() {
+            Ok(number) if number > 0 => number - 1,
+            _ => bail!("invalid player {} in event: {}", player, text),
+        };
+
+        if let Some(tile) = rest.strip_prefix("draws ") {
+            let tile = notation::parse_tile(tile)?;
+            return Ok(GameEvent::TileDrawn { seat, tile });
+        }
+        if let Some(sets) = rest.strip_prefix("plays ") {
+            let sets = parse_sets(sets)?;
+            return Ok(GameEvent::SetsPlayed { seat, sets });
+        }
+        if let Some((tiles, table)) = rest
+            .strip_prefix("adds ")
+            .and_then(|rest| rest.split_once(" and rearranges the table to "))
+        {
+            return Ok(GameEvent::BoardRearranged {
+                seat,
+                table: parse_sets(table)?,
+                tiles: notation::parse_tiles(tiles)?,
+            });
+        }
+        if let Some((swing, points)) = rest
+            .strip_prefix("ends the turn with ")
+            .and_then(|rest| rest.strip_suffix(" points"))
+            .and_then(|rest| rest.split_once(" points, the racks hold "))
+        {
+            let swing = swing
+                .parse()
+                .map_err(|_| eyre!("invalid points {} in event: {}", swing, text))?;
+            let points = points
+                .split_whitespace()
+                .map(|points| {
+                    points
+                        .parse()
+                        .map_err(|_| eyre!("invalid points {} in event: {}", points, text))
+                })
+                .collect::<Result<Vec<u32>>>()?;
+            return Ok(GameEvent::TurnSummary {
+                seat,
+                swing,
+                points,
+            });
+        }
+        if let Some(reason) = rest.strip_prefix("made an illegal move: ") {
+            return Ok(GameEvent::IllegalMove {
+                seat,
+                reason: reason.to_string(),
+            });
+        }
+        if let Some(elapsed) = rest.strip_prefix("ran out of time after ") {
+            let elapsed = parse_duration(elapsed)?;
+            return Ok(GameEvent::TurnTimeout { seat, elapsed });
+        }
+        match rest {
+            "wins" => Ok(GameEvent::GameWon {
+                winner: Some(seat),
+                blocked: false,
+            }),
+            "wins the blocked game" => Ok(GameEvent::GameWon {
+                winner: Some(seat),
+                blocked: true,
+            }),
+            _ => bail!("unknown event: {}", text),
+        }
+    }
+}
+
+// Receives the events of a game. The engine only knows its observers through
+// this trait, so user interfaces, loggers and recorders can come and go.
+pub trait Observer {
+    fn notify(&mut self, event: &GameEvent);
+}
+
+impl<F: FnMut(&GameEvent)> Observer for F {
+    fn notify(&mut self, event: &GameEvent) {
+        self(event)
+    }
+}
+
+// Forwards events to another thread. A dropped receiver just stops the stream.
+impl Observer for Sender<GameEvent> {
+    fn notify(&mut self, event: &GameEvent) {
+        let _ = self.send(event.clone());
+    }
+}
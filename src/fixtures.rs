@@ -0,0 +1,170 @@
+use crate::config;
+use crate::notation;
+use crate::rules::Ruleset;
+use crate::solver::{self, Inventory, Set, SolverCache};
+use crate::validator;
+use color_eyre::eyre::{bail, Result};
+
+// Positions with the optimum worked out by hand, see the file
+pub const OPTIMA: &str = include_str!("../fixtures/optima.toml");
+
+// A position of a fixture file and the tiles its best move lays out
+struct Fixture {
+    name: String,
+    line: usize,
+    table: Vec<Set>,
+    rack: Inventory,
+    opened: bool,
+    played: u32,
+}
+
+fn read_fixtures(text: &str) -> Result<Vec<Fixture>> {
+    let mut fixtures = Vec::new();
+    for table in config::parse(text)? {
+        match table.name.as_str() {
+            "" => table.check_keys(&[])?,
+            "position" => {
+                table.check_keys(&["name", "table", "rack", "opened", "played"])?;
+                let (Some(name), Some(rack), Some(played)) = (
+                    table.string("name")?,
+                    table.string("rack")?,
+                    table.number("played")?,
+                ) else {
+                    bail!("line {}: a position needs a name, the rack and the tiles its best move plays", table.line);
+                };
+                if played < 0.0 || played.fract() != 0.0 {
+                    bail!(
+                        "line {}: played must be a whole number of tiles, got {}",
+                        table.line,
+                        played
+                    );
+                }
+                let fixture = Fixture {
+                    name: name.to_string(),
+                    line: table.line,
+                    table: notation::parse_table(table.string("table")?.unwrap_or(""))?,
+                    rack: notation::parse_rack(rack)?,
+                    opened: table.bool("opened")?.unwrap_or(false),
+                    played: played as u32,
+                };
+                validator::check_position(&fixture.table, &fixture.rack)?;
+                fixtures.push(fixture);
+            }
+            name => bail!("line {}: unknown table [{}]", table.line, name),
+        }
+    }
+    Ok(fixtures)
+}
+
+// Tiles the move lays out, or why it is not a legal move of the position
+fn played(fixture: &Fixture, chosen: &Option<(Vec<Set>, Inventory)>) -> Result<u32, String> {
+    let Some((new_table, rest)) = chosen else {
+        return Ok(0);
+    };
+    match validator::validate_move(
+        &fixture.table,
+        &fixture.rack,
+        new_table,
+        fixture.opened,
+        &Ruleset::default(),
+    ) {
+        Ok(left) if left == *rest => Ok(fixture.rack.total_tile_count() - rest.total_tile_count()),
+        Ok(_) => Err("returns a rack that does not match its table".to_string()),
+        Err(error) => Err(format!("returns an illegal move: {}", error)),
+    }
+}
+
+// Every way of finding the best move, with what it found for the fixture. The
+// memos of `cache` are shared by all fixtures, as over the turns of a game.
+fn backends(
+    fixture: &Fixture,
+    cache: &mut SolverCache,
+) -> Result<Vec<(&'static str, Result<u32, String>)>> {
+    let rules = Ruleset::default();
+    let (table, rack, opened) = (&fixture.table, &fixture.rack, fixture.opened);
+    let mut found = vec![
+        (
+            "best_move",
+            played(fixture, &solver::best_move(table, rack, opened)?),
+        ),
+        (
+            "best_move with warm memos",
+            played(
+                fixture,
+                &solver::best_move_cached(table, rack, opened, cache)?,
+            ),
+        ),
+    ];
+    let mut last = None;
+    solver::anytime_moves(table, rack, opened, &rules, |chosen| {
+        last = Some(chosen.clone());
+        true
+    })?;
+    found.push(("anytime_moves", played(fixture, &last)));
+    let candidates = solver::candidate_moves(table, rack, opened, &rules)?
+        .into_iter()
+        .map(|chosen| played(fixture, &Some(chosen)))
+        .collect::<Result<Vec<u32>, String>>()
+        .map(|played| played.into_iter().max().unwrap_or(0));
+    found.push(("candidate_moves", candidates));
+    if opened {
+        found.push((
+            "table_play",
+            played(fixture, &solver::table_play(table, rack)),
+        ));
+    }
+    Ok(found)
+}
+
+// Runs every backend on every position of the fixtures and fails when one
+// lays out other than the most tiles or returns an illegal move
+pub fn run(text: &str) -> Result<()> {
+    let fixtures = read_fixtures(text)?;
+    let mut cache = SolverCache::default();
+    let mut failed = 0;
+    for fixture in &fixtures {
+        let wrong = backends(fixture, &mut cache)?
+            .into_iter()
+            .filter_map(|(backend, found)| match found {
+                Ok(played) if played == fixture.played => None,
+                Ok(played) => Some(format!("{} plays {} tiles", backend, played)),
+                Err(reason) => Some(format!("{} {}", backend, reason)),
+            })
+            .collect::<Vec<String>>();
+        match wrong.is_empty() {
+            true => println!(
+                "line {}: {}: {} tiles ok",
+                fixture.line, fixture.name, fixture.played
+            ),
+            false => {
+                println!(
+                    "line {}: {}: expected {} tiles, MISMATCH",
+                    fixture.line, fixture.name, fixture.played
+                );
+                wrong.iter().for_each(|wrong| println!("  {}", wrong));
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("{} of {} fixtures not reproduced", failed, fixtures.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_backend_reproduces_the_optima() -> Result<()> {
+        run(OPTIMA)
+    }
+
+    // A fixture that claims more tiles than can be laid out is not reproduced
+    #[test]
+    fn a_wrong_optimum_fails() {
+        let fixtures = "[[position]]\nname = \"Nothing fits\"\ntable = \"R1 R2 R3\"\nrack = \"B10 K12\"\nopened = true\nplayed = 1\n";
+        assert!(run(fixtures).is_err());
+    }
+}
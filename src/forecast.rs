@@ -0,0 +1,60 @@
+// How likely the bag runs dry within `turns` turns of a player, with
+// `players` turns of the game for each and every turn drawing a tile with
+// chance `draw_rate`. Once the bag is empty the game ends as soon as nobody
+// can lay out any more, and every point still on a rack counts, so this is
+// when hoarding tiles stops paying.
+pub fn exhaustion_chance(bag: u32, players: usize, turns: u32, draw_rate: f64) -> f64 {
+    let n = turns as usize * players;
+    let needed = bag as usize;
+    if needed == 0 {
+        return 1.0;
+    }
+    if needed > n || draw_rate <= 0.0 {
+        return 0.0;
+    }
+    if draw_rate >= 1.0 {
+        return 1.0;
+    }
+    // The chance of fewer than `needed` draws among the n turns, term by
+    // term of the binomial distribution
+    let odds = draw_rate / (1.0 - draw_rate);
+    let mut term = (1.0 - draw_rate).powi(n as i32);
+    let mut fewer = 0.0;
+    for draws in 0..needed {
+        fewer += term;
+        term *= (n - draws) as f64 / (draws + 1) as f64 * odds;
+    }
+    (1.0 - fewer).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn chances_of_small_bags() {
+        // Both of two coin flips draw
+        assert!(close(exhaustion_chance(2, 2, 1, 0.5), 0.25));
+        // At least 3 draws in 4 coin flips: (4 + 1) / 16
+        assert!(close(exhaustion_chance(3, 2, 2, 0.5), 0.3125));
+        // At least 4 of 10 turns with draws at 0.3, from a binomial table
+        assert!(close(exhaustion_chance(4, 5, 2, 0.3), 0.3503893));
+    }
+
+    #[test]
+    fn certain_and_impossible_ends() {
+        assert_eq!(exhaustion_chance(0, 2, 0, 0.5), 1.0);
+        assert_eq!(exhaustion_chance(5, 2, 2, 0.9), 0.0);
+        assert_eq!(exhaustion_chance(3, 2, 5, 0.0), 0.0);
+        assert_eq!(exhaustion_chance(3, 2, 5, 1.0), 1.0);
+        // More turns can only make an empty bag likelier
+        let chances = (2..20)
+            .map(|turns| exhaustion_chance(20, 4, turns, 0.4))
+            .collect::<Vec<f64>>();
+        assert!(chances.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+}
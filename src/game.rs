@@ -0,0 +1,819 @@
+use crate::assist::{self, Change};
+use crate::bag::Bag;
+use crate::budget::{self, Phase, TimeManager};
+use crate::cli::Args;
+use crate::deal::Deal;
+use crate::events::{GameEvent, Observer};
+use crate::rules::{self, Ruleset};
+use crate::scenario::Scenario;
+use crate::solver::{self, Inventory, Set, Tile};
+use crate::strategy::{Move, Observation, Strategy};
+use crate::threat::Threat;
+use crate::validator;
+use color_eyre::eyre::{bail, eyre, Report, Result};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Two copies of every numbered tile plus two jokers
+const POOL_SIZE: u32 = 106;
+
+// Tiles the rulebook has a player draw after an illegal move. Bots draw them
+// when the rules set no penalty, trying again would only bring the same move.
+const RULEBOOK_PENALTY: u32 = 3;
+
+pub struct GameConfig {
+    pub players: usize,
+    pub rack_size: u32,
+    pub rules: Ruleset,
+    // Trust the strategies and skip events for the most games per second
+    pub headless: bool,
+    // Biases every deal toward a situation, see src/scenario.rs
+    pub scenario: Option<Scenario>,
+    // Search time of every move, unlimited without one
+    pub time_manager: Option<Arc<dyn TimeManager + Send + Sync>>,
+}
+
+fn penalty_draws(flag: &str, tiles: &str) -> Result<u32> {
+    match tiles.parse::<u32>() {
+        Ok(tiles) if tiles > 0 => Ok(tiles),
+        _ => bail!(
+            "{} takes a number of tiles, at least 1, got {}",
+            flag,
+            tiles
+        ),
+    }
+}
+
+impl GameConfig {
+    pub fn new(players: usize, rack_size: u32) -> Result<GameConfig> {
+        if !(2..=4).contains(&players) {
+            bail!("player count must be between 2 and 4, got {}", players);
+        }
+        if rack_size == 0 || players as u32 * rack_size > POOL_SIZE {
+            bail!(
+                "cannot deal {} racks of {} tiles from a pool of {}",
+                players,
+                rack_size,
+                POOL_SIZE
+            );
+        }
+
+        Ok(GameConfig {
+            players,
+            rack_size,
+            rules: Ruleset::default(),
+            headless: false,
+            scenario: None,
+            time_manager: None,
+        })
+    }
+
+    // `--players`, `--rack-size`, `--headless`, the rules file of `--rules` and
+    // the rule variant switches, `--turn-time` in seconds, the penalties of
+    // `--timeout-draws` and `--illegal-move-draws` in tiles, the scenario file
+    // of `--scenario` and the time manager of `--move-time`
+    pub fn from_args(args: &Args) -> Result<GameConfig> {
+        let defaults = GameConfig::default();
+        let mut config = GameConfig::new(
+            args.parse_or("--players", defaults.players)?,
+            args.parse_or("--rack-size", defaults.rack_size)?,
+        )?;
+        config.headless = args.flag("--headless");
+        if let Some(path) = args.value("--rules") {
+            config.rules = rules::load(path)?.0;
+        }
+        config.rules.must_play |= args.flag("--must-play");
+        config.rules.rearrange_on_opening |= args.flag("--rearrange-on-opening");
+        if let Some(seconds) = args.value("--turn-time") {
+            let seconds: f64 = seconds
+                .parse()
+                .map_err(|_| eyre!("invalid value for --turn-time: {}", seconds))?;
+            if seconds <= 0.0 {
+                bail!("--turn-time must be positive, got {}", seconds);
+            }
+            config.rules.turn_time = Some(Duration::from_secs_f64(seconds));
+        }
+        if let Some(tiles) = args.value("--timeout-draws") {
+            config.rules.timeout_draws = penalty_draws("--timeout-draws", tiles)?;
+        }
+        if let Some(tiles) = args.value("--illegal-move-draws") {
+            config.rules.illegal_move_draws = Some(penalty_draws("--illegal-move-draws", tiles)?);
+        }
+        if let Some(path) = args.value("--scenario") {
+            let scenario = Scenario::load(path)?;
+            scenario.check(config.players, config.rack_size)?;
+            config.scenario = Some(scenario);
+        }
+        config.time_manager = budget::from_args(args)?;
+        Ok(config)
+    }
+}
+
+impl Default for GameConfig {
+    fn default() -> GameConfig {
+        GameConfig {
+            players: 2,
+            rack_size: 14,
+            rules: Ruleset::default(),
+            headless: false,
+            scenario: None,
+            time_manager: None,
+        }
+    }
+}
+
+pub struct GameResult {
+    pub winner: Option<usize>,
+    // Whether the game ended because the bag ran out and nobody could play
+    pub blocked: bool,
+    pub turns: u32,
+    // Rack size of each player at the time of their initial meld
+    pub initial_melds: Vec<Option<u32>>,
+    // Points left on the rack of each player
+    pub rack_points: Vec<u32>,
+    // Illegal moves that were taken back and penalized
+    pub incidents: Vec<Incident>,
+}
+
+// An illegal move taken back before it touched the game, and the tiles drawn
+// for it instead
+#[derive(Clone, Debug)]
+pub struct Incident {
+    pub seat: usize,
+    // Turns played before it, counting from 0
+    pub turn: u32,
+    pub reason: String,
+    pub penalty: u32,
+}
+
+// What a turn changed, enough to play it again from the position before it.
+// Every move, draw and penalty of a game is committed by GameState::commit,
+// which hands one back.
+#[derive(Clone, Debug)]
+pub struct AppliedMove {
+    pub seat: usize,
+    // Turns played before it, so a client that saw the game at this turn
+    // knows its move was made against the current game
+    pub turn: u32,
+    // The table after the move, the same as before when the player drew
+    pub table: Vec<Set>,
+    // Tiles laid out from the rack
+    pub played: Vec<Tile>,
+    // Tiles the player had to draw, none after laying out and more than one
+    // as a penalty, and those the bag still held
+    pub draws: u32,
+    pub drawn: Vec<Tile>,
+    // Whether it was the initial meld of the player
+    pub opening: bool,
+}
+
+// The game after a turn, worked out next to the game before it. Nothing of
+// it is seen until it is committed.
+struct Transaction {
+    rack: Inventory,
+    table: Vec<Set>,
+    bag: Bag,
+    applied: AppliedMove,
+}
+
+// The deal and who moves first, every earlier position is found by playing
+// the recorded turns again from here
+#[derive(Clone)]
+struct Start {
+    racks: Vec<Inventory>,
+    bag: Bag,
+    current: usize,
+}
+
+pub struct GameState {
+    start: Start,
+    // Every turn so far, headless games keep none
+    history: Vec<AppliedMove>,
+    bag: Bag,
+    racks: Vec<Inventory>,
+    table: Vec<Set>,
+    initial_melds: Vec<Option<u32>>,
+    // Points on every rack at the end of the last turn
+    points: Vec<u32>,
+    // Turns every player drew, as everyone at the table sees
+    draws: Vec<u32>,
+    current: usize,
+    turns: u32,
+    // Consecutive turns in which nobody could play or draw
+    passes: usize,
+    incidents: Vec<Incident>,
+    rules: Ruleset,
+    headless: bool,
+    time_manager: Option<Arc<dyn TimeManager + Send + Sync>>,
+    observers: Vec<Box<dyn Observer + Send>>,
+    // Best moves of the default bot by position_key, so a position reached
+    // again after going back in time or in a branch is not searched twice
+    hints: HashMap<u64, Option<Move>>,
+}
+
+impl GameState {
+    pub fn new(config: &GameConfig) -> GameState {
+        GameState::seeded(config, rand::random())
+    }
+
+    // Games with the same seed and config deal the same racks, and the bag
+    // hands out its tiles in the same order no matter who draws them, so
+    // strategies can swap seats and play with the very same tiles.
+    pub fn seeded(config: &GameConfig, seed: u64) -> GameState {
+        let deal = match &config.scenario {
+            Some(scenario) => scenario.deal(config.players, config.rack_size, seed),
+            None => Deal::new(config.players, config.rack_size, seed),
+        };
+        let start = Start {
+            racks: deal.racks,
+            bag: deal.bag,
+            current: 0,
+        };
+        let mut game = GameState::from_start(start, config.rules, config.headless);
+        game.time_manager = config.time_manager.clone();
+        game
+    }
+
+    // The game before its first turn, with the settings of this one
+    fn restart(&self) -> GameState {
+        let mut game = GameState::from_start(self.start.clone(), self.rules, self.headless);
+        game.time_manager = self.time_manager.clone();
+        game
+    }
+
+    fn from_start(start: Start, rules: Ruleset, headless: bool) -> GameState {
+        let players = start.racks.len();
+        let points = start.racks.iter().map(Inventory::points).collect();
+
+        GameState {
+            bag: start.bag.clone(),
+            racks: start.racks.clone(),
+            current: start.current,
+            start,
+            history: Vec::new(),
+            table: Vec::new(),
+            initial_melds: vec![None; players],
+            points,
+            draws: vec![0; players],
+            turns: 0,
+            passes: 0,
+            incidents: Vec::new(),
+            rules,
+            headless,
+            time_manager: None,
+            observers: Vec::new(),
+            hints: HashMap::new(),
+        }
+    }
+
+    // Let another player make the first move, as in later rounds of a match
+    pub fn starting_with(mut self, seat: usize) -> GameState {
+        self.current = seat;
+        self.start.current = seat;
+        self
+    }
+
+    // The same game in the same position, without the observers, to try
+    // another line of play and keep this one
+    pub fn branch(&self) -> Result<GameState> {
+        let mut branch = self.restart();
+        for turn in &self.history {
+            branch.replay(turn)?;
+        }
+        branch.hints = self.hints.clone();
+        branch.incidents = self.incidents.clone();
+        Ok(branch)
+    }
+
+    // Takes back the last `turns` turns. The observers hear nothing about the
+    // turns that are played again to get there.
+    pub fn rewind(&mut self, turns: usize) -> Result<()> {
+        if turns > self.history.len() {
+            bail!(
+                "cannot go back {} turns, only {} were played",
+                turns,
+                self.history.len()
+            );
+        }
+        let kept = self.history.len() - turns;
+        let history = std::mem::take(&mut self.history);
+        let observers = std::mem::take(&mut self.observers);
+        let hints = std::mem::take(&mut self.hints);
+        let mut incidents = std::mem::take(&mut self.incidents);
+        *self = self.restart();
+        for turn in &history[..kept] {
+            self.replay(turn)?;
+        }
+        incidents.retain(|incident| incident.turn < self.turns);
+        self.observers = observers;
+        self.hints = hints;
+        self.incidents = incidents;
+        Ok(())
+    }
+
+    // Plays a recorded turn again, the bag hands out the same tiles in order
+    fn replay(&mut self, turn: &AppliedMove) -> Result<()> {
+        let laid = (turn.draws == 0).then(|| {
+            let mut rest = self.racks[self.current].combined(&Inventory::from_sets(&self.table));
+            rest.subtract(&Inventory::from_sets(&turn.table));
+            (turn.table.clone(), rest)
+        });
+        self.transact(laid, turn.draws)?;
+        Ok(())
+    }
+
+    // Report the events of this game to the observer from now on. Headless
+    // games have no observers.
+    pub fn subscribe(&mut self, observer: Box<dyn Observer + Send>) {
+        if !self.headless {
+            self.observers.push(observer);
+        }
+    }
+
+    fn emit(&mut self, event: GameEvent) {
+        for observer in self.observers.iter_mut() {
+            observer.notify(&event);
+        }
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn players(&self) -> usize {
+        self.racks.len()
+    }
+
+    pub fn rack(&self, seat: usize) -> &Inventory {
+        &self.racks[seat]
+    }
+
+    pub fn table(&self) -> &[Set] {
+        &self.table
+    }
+
+    // Turns played so far, which every committed turn moves on by one
+    pub fn turn(&self) -> u32 {
+        self.turns
+    }
+
+    pub fn bag_size(&self) -> u32 {
+        self.bag.remaining()
+    }
+
+    pub fn has_opened(&self, seat: usize) -> bool {
+        self.initial_melds[seat].is_some()
+    }
+
+    pub fn phase(&self) -> Phase {
+        let seat = self.current;
+        Phase {
+            bag: self.bag_size(),
+            opened: self.has_opened(seat),
+            fewest_opponent_tiles: (0..self.players())
+                .filter(|&other| other != seat)
+                .map(|other| self.racks[other].total_tile_count())
+                .min()
+                .unwrap_or(0),
+        }
+    }
+
+    // What the current player can see of the game, all a strategy is told
+    pub fn observation(&self) -> Observation<'_> {
+        Observation {
+            table: &self.table,
+            rack: self.racks[self.current],
+            opened: self.has_opened(self.current),
+            rules: self.rules,
+            seat: self.current,
+            tiles: self.racks.iter().map(Inventory::total_tile_count).collect(),
+            melded: (0..self.players())
+                .map(|seat| self.has_opened(seat))
+                .collect(),
+            draws: self.draws.clone(),
+            bag: self.bag.remaining(),
+            turn: self.turns,
+        }
+    }
+
+    // Opponents of the current player who might go out on their next turn, as
+    // far as the current player can tell
+    pub fn threats(&self) -> Result<Vec<Threat>> {
+        self.observation().threats()
+    }
+
+    // The move the strategy would make for the current player, within the
+    // time the time manager gives it
+    pub fn strategy_move(&self, strategy: &dyn Strategy) -> Result<Option<Move>> {
+        let deadline = self
+            .time_manager
+            .as_ref()
+            .map(|manager| Instant::now() + manager.budget(&self.phase()));
+        let limits = solver::Limits {
+            deadline,
+            ..solver::Limits::default()
+        };
+        let observation = self.observation();
+        let (chosen, _) = solver::with_limits(limits, || strategy.choose_move(&observation));
+        chosen
+    }
+
+    // Zobrist key of the position of the current player: the sets on the
+    // table, their rack and whether they have opened. Sets are keyed by their
+    // tiles turned apart from the rack keys, in whatever order they lie.
+    pub fn position_key(&self) -> u64 {
+        let sets = self
+            .table
+            .iter()
+            .map(|set| Inventory::from_sets(std::slice::from_ref(set)).key());
+        let table = sets.fold(0u64, |sum, key| sum.wrapping_add(key.rotate_left(21)));
+        table ^ self.racks[self.current].key() ^ u64::from(self.has_opened(self.current))
+    }
+
+    // Moves of the default bot for the current player, quick ones first and
+    // then better ones as they are found, see solver::anytime_moves. In a
+    // position seen before only the best one is repeated.
+    pub fn suggested_moves(&mut self, mut improved: impl FnMut(&Move) -> bool) -> Result<()> {
+        let key = self.position_key();
+        if let Some(known) = self.hints.get(&key) {
+            if let Some(known) = known {
+                improved(known);
+            }
+            return Ok(());
+        }
+        let seat = self.current;
+        let (mut best, mut complete) = (None, true);
+        solver::anytime_moves(
+            &self.table,
+            &self.racks[seat],
+            self.has_opened(seat),
+            &self.rules,
+            |chosen| {
+                best = Some(chosen.clone());
+                complete = improved(chosen);
+                complete
+            },
+        )?;
+        // A search stopped early may have missed the best move
+        if complete {
+            self.hints.insert(key, best);
+        }
+        Ok(())
+    }
+
+    // The turn of the current player that lays out the table of `laid` and
+    // keeps its rack, or draws `draws` tiles when there is none. Checks that
+    // no tile appears or vanishes: the rack keeps only tiles it held and
+    // with the table holds the same tiles as before.
+    fn begin(&self, laid: Option<Move>, draws: u32) -> Result<Transaction> {
+        let seat = self.current;
+        let rack = self.racks[seat];
+        let (table, rest) = laid.unwrap_or_else(|| (self.table.clone(), rack));
+        let before = rack.combined(&Inventory::from_sets(&self.table));
+        if !rack.missing(&rest).is_empty() || rest.combined(&Inventory::from_sets(&table)) != before
+        {
+            bail!("the tiles of the move do not add up to those of the rack and the table");
+        }
+        let mut bag = self.bag.clone();
+        let drawn = (0..draws)
+            .map_while(|_| bag.draw_next())
+            .collect::<Vec<Tile>>();
+        let mut new_rack = rest;
+        drawn.iter().for_each(|tile| new_rack.add_tile(tile));
+        Ok(Transaction {
+            rack: new_rack,
+            applied: AppliedMove {
+                seat,
+                turn: self.turns,
+                table: table.clone(),
+                played: rest.missing(&rack),
+                draws,
+                drawn,
+                opening: draws == 0 && !self.has_opened(seat),
+            },
+            table,
+            bag,
+        })
+    }
+
+    // Makes the turn the game, tells the observers and passes the turn on
+    fn commit(&mut self, transaction: Transaction) -> AppliedMove {
+        let Transaction {
+            rack,
+            table,
+            bag,
+            applied,
+        } = transaction;
+        let seat = applied.seat;
+        if applied.draws == 0 && !self.observers.is_empty() {
+            let changes = assist::classify(&self.table, &table);
+            let kept = changes
+                .iter()
+                .filter(|&&change| change == Change::Kept)
+                .count();
+            let event = if kept == self.table.len() {
+                GameEvent::SetsPlayed {
+                    seat,
+                    sets: table
+                        .iter()
+                        .zip(&changes)
+                        .filter(|&(_, &change)| change != Change::Kept)
+                        .map(|(set, _)| *set)
+                        .collect(),
+                }
+            } else {
+                GameEvent::BoardRearranged {
+                    seat,
+                    table: table.clone(),
+                    tiles: applied.played.clone(),
+                }
+            };
+            self.emit(event);
+        }
+        if applied.opening {
+            self.initial_melds[seat] = Some(self.racks[seat].total_tile_count());
+        }
+        self.racks[seat] = rack;
+        self.table = table;
+        self.bag = bag;
+        for tile in &applied.drawn {
+            self.emit(GameEvent::TileDrawn { seat, tile: *tile });
+        }
+
+        if applied.draws > 0 {
+            self.draws[seat] += 1;
+        }
+        // A draw from the empty bag is a pass
+        if applied.draws == 0 || !applied.drawn.is_empty() {
+            self.passes = 0;
+        } else {
+            self.passes += 1;
+        }
+        self.turns += 1;
+        if !self.headless {
+            self.history.push(applied.clone());
+        }
+        let points = self.racks[seat].points();
+        let swing = points as i32 - self.points[seat] as i32;
+        self.points[seat] = points;
+        if !self.observers.is_empty() {
+            self.emit(GameEvent::TurnSummary {
+                seat,
+                swing,
+                points: self.points.clone(),
+            });
+        }
+
+        if self.is_over() {
+            self.emit(GameEvent::GameWon {
+                winner: self.winner(),
+                blocked: self.is_blocked(),
+            });
+        } else {
+            self.current = (self.current + 1) % self.racks.len();
+        }
+        applied
+    }
+
+    fn transact(&mut self, laid: Option<Move>, draws: u32) -> Result<AppliedMove> {
+        let transaction = self.begin(laid, draws)?;
+        Ok(self.commit(transaction))
+    }
+
+    // Replace the table on behalf of the current player, checked against the
+    // rules, and end the turn
+    pub fn apply_move(&mut self, table: Vec<Set>) -> Result<AppliedMove> {
+        let rest = validator::validate_move(
+            &self.table,
+            &self.racks[self.current],
+            &table,
+            self.has_opened(self.current),
+            &self.rules,
+        )?;
+        self.transact(Some((table, rest)), 0)
+    }
+
+    // Draw a tile instead of playing and end the turn, if the rules allow it
+    pub fn draw(&mut self) -> Result<AppliedMove> {
+        if self.rules.must_play
+            && solver::best_move_by_rules(
+                &self.table,
+                &self.racks[self.current],
+                self.has_opened(self.current),
+                &self.rules,
+            )?
+            .is_some()
+        {
+            bail!("tiles can be laid out, the rules do not allow drawing instead");
+        }
+        self.transact(None, 1)
+    }
+
+    // The move or draw of the current player broke the rules. With a penalty
+    // in the rules they draw its tiles and the turn is over, otherwise nothing
+    // happens and the player has to try again. Returns the penalty turn, None
+    // without one.
+    pub fn penalize(&mut self, error: &Report) -> Result<Option<AppliedMove>> {
+        let Some(tiles) = self.rules.illegal_move_draws else {
+            return Ok(None);
+        };
+        self.roll_back(error, tiles).map(Some)
+    }
+
+    // Records the illegal move, which left the game as it was, and ends the
+    // turn with the penalty
+    fn roll_back(&mut self, error: &Report, penalty: u32) -> Result<AppliedMove> {
+        self.incidents.push(Incident {
+            seat: self.current,
+            turn: self.turns,
+            reason: error.to_string(),
+            penalty,
+        });
+        self.emit(GameEvent::IllegalMove {
+            seat: self.current,
+            reason: error.to_string(),
+        });
+        self.transact(None, penalty)
+    }
+
+    // The player who went out. In a blocked game the player with the fewest
+    // points left on the rack wins, ties go to the player holding fewer tiles
+    // and are a draw otherwise.
+    pub fn winner(&self) -> Option<usize> {
+        if let Some(seat) = self
+            .racks
+            .iter()
+            .position(|rack| rack.total_tile_count() == 0)
+        {
+            return Some(seat);
+        }
+        if !self.is_blocked() {
+            return None;
+        }
+
+        let standings = self.standings();
+        let standing = |seat: usize| {
+            (
+                self.racks[seat].points(),
+                self.racks[seat].total_tile_count(),
+            )
+        };
+        match standings[..] {
+            [first, second, ..] if standing(first) == standing(second) => None,
+            [first, ..] => Some(first),
+            [] => None,
+        }
+    }
+
+    // The seats from the leader down: the fewest points left on the rack,
+    // then the fewest tiles, as a blocked game is decided
+    pub fn standings(&self) -> Vec<usize> {
+        let mut seats = (0..self.racks.len()).collect::<Vec<usize>>();
+        seats.sort_by_key(|&seat| {
+            (
+                self.racks[seat].points(),
+                self.racks[seat].total_tile_count(),
+            )
+        });
+        seats
+    }
+
+    // Nobody can move any more once the bag is empty and a full round passed
+    pub fn is_blocked(&self) -> bool {
+        self.passes >= self.racks.len()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.is_blocked() || self.racks.iter().any(|rack| rack.total_tile_count() == 0)
+    }
+
+    // The turn of the current player as the strategy plays it. Past the turn
+    // time of the rules the player draws instead, and a move that breaks the
+    // rules is taken back and costs the penalty.
+    pub fn strategy_turn(&mut self, strategy: &dyn Strategy) -> Result<AppliedMove> {
+        let start = Instant::now();
+        let chosen = self.strategy_move(strategy)?;
+        let elapsed = start.elapsed();
+        if self.rules.turn_time.is_some_and(|limit| elapsed > limit) {
+            self.emit(GameEvent::TurnTimeout {
+                seat: self.current,
+                elapsed,
+            });
+            return self.transact(None, self.rules.timeout_draws);
+        }
+
+        // Headless games take the strategy's word that the sets are legal
+        let moved = match chosen {
+            Some(laid) if self.headless => self.transact(Some(laid), 0),
+            Some((table, _)) => self.apply_move(table),
+            None if self.headless => self.transact(None, 1),
+            None => self.draw(),
+        };
+        // Moves are checked before they change anything, so a strategy that
+        // breaks the rules only costs its player the penalty
+        moved.or_else(|error| {
+            let penalty = self.rules.illegal_move_draws.unwrap_or(RULEBOOK_PENALTY);
+            self.roll_back(
+                &eyre!("{} broke the rules: {}", strategy.name(), error),
+                penalty,
+            )
+        })
+    }
+
+    // Bot game with one strategy per seat
+    pub fn play(mut self, strategies: &[&dyn Strategy]) -> Result<GameResult> {
+        while !self.is_over() {
+            self.strategy_turn(strategies[self.current])?;
+        }
+
+        Ok(GameResult {
+            winner: self.winner(),
+            blocked: self.is_blocked(),
+            turns: self.turns,
+            initial_melds: self.initial_melds,
+            rack_points: self.racks.iter().map(Inventory::points).collect(),
+            incidents: self.incidents,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::Greedy;
+
+    fn outcome(result: &GameResult) -> (Option<usize>, bool, u32, Vec<Option<u32>>, Vec<u32>) {
+        (
+            result.winner,
+            result.blocked,
+            result.turns,
+            result.initial_melds.clone(),
+            result.rack_points.clone(),
+        )
+    }
+
+    // Headless games leave out the events and the checks of the moves, not
+    // a single decision, so they end as the normal game of the seed does
+    #[test]
+    fn headless_games_end_as_normal_ones() -> Result<()> {
+        let greedy = Greedy::default();
+        for (players, seed) in (2..=4).flat_map(|players| (0..3).map(move |seed| (players, seed))) {
+            let strategies = vec![&greedy as &dyn Strategy; players];
+            let mut config = GameConfig::new(players, 14)?;
+            let normal = GameState::seeded(&config, seed).play(&strategies)?;
+            config.headless = true;
+            let headless = GameState::seeded(&config, seed).play(&strategies)?;
+            assert!(normal.incidents.is_empty());
+            assert_eq!(
+                outcome(&normal),
+                outcome(&headless),
+                "{} players, seed {}",
+                players,
+                seed
+            );
+        }
+        Ok(())
+    }
+
+    // The key of the position worked out from nothing, tile by tile
+    fn fresh_key(state: &GameState) -> u64 {
+        let key = |tiles: Vec<Tile>| {
+            let mut inventory = Inventory::new(0);
+            for tile in &tiles {
+                inventory.add_tile(tile);
+            }
+            inventory.key()
+        };
+        let table = state
+            .table()
+            .iter()
+            .map(|set| key(set.tiles.iter().copied().collect()).rotate_left(21))
+            .fold(0u64, u64::wrapping_add);
+        table
+            ^ key(state.rack(state.current()).tiles())
+            ^ u64::from(state.has_opened(state.current()))
+    }
+
+    // Racks keep their keys up to date as moves are played and taken back
+    #[test]
+    fn position_keys_survive_moves_and_rewinds() -> Result<()> {
+        let greedy = Greedy::default();
+        let config = GameConfig::new(3, 14)?;
+        let mut state = GameState::seeded(&config, 11);
+        let mut keys = vec![state.position_key()];
+        while !state.is_over() && keys.len() < 40 {
+            state.strategy_turn(&greedy)?;
+            assert_eq!(state.position_key(), fresh_key(&state));
+            keys.push(state.position_key());
+        }
+        while let Some(key) = keys.pop() {
+            assert_eq!(state.position_key(), key, "turn {}", state.turn());
+            assert_eq!(state.position_key(), fresh_key(&state));
+            if !keys.is_empty() {
+                state.rewind(1)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+use crate::solution::Solution;
+use crate::solver::{self, Inventory, Set, SolverCache, Tile};
+use color_eyre::eyre::{bail, Result};
+
+// Every combination of the unknown tiles gets a search of its own, so only a
+// few can be unknown
+pub const MAX_GHOSTS: u32 = 2;
+
+// The best move for some of the tiles the unknown ones could be
+pub struct Outcome {
+    // Every pick of tiles the unknown ones may be that leads to the move
+    pub tiles: Vec<Vec<Tile>>,
+    pub chance: f64,
+    // The table after the move and the tiles it lays out, None to draw
+    pub play: Option<(Solution, u32)>,
+}
+
+// Ways to pick `left` more tiles from `kinds` on, at most as many of a kind
+// as are unseen, calling `visit` with every pick and the number of ways to
+// draw it
+fn each_pick(
+    kinds: &[(Tile, u8)],
+    left: u32,
+    pick: &mut Vec<Tile>,
+    ways: u64,
+    visit: &mut impl FnMut(&[Tile], u64),
+) {
+    let Some((&(tile, unseen), rest)) = kinds.split_first() else {
+        if left == 0 {
+            visit(pick, ways);
+        }
+        return;
+    };
+    each_pick(rest, left, pick, ways, visit);
+    for copies in 1..=left.min(unseen as u32) {
+        pick.push(tile);
+        // Ways to choose `copies` of the unseen copies, with at most two
+        let choose = if copies == 1 { unseen as u64 } else { 1 };
+        each_pick(rest, left - copies, pick, ways * choose, visit);
+    }
+    pick.truncate(pick.len() - left.min(unseen as u32) as usize);
+}
+
+// The best move for every set of tiles the `ghosts` unknown tiles on the rack
+// could be, e.g. a tile fell under the table, grouped by the move. The
+// unknown tiles are drawn from those not on the table or the rest of the
+// rack, so each outcome comes with its chance.
+pub fn outcomes(
+    table: &[Set],
+    rack: &Inventory,
+    ghosts: u32,
+    opened: bool,
+) -> Result<Vec<Outcome>> {
+    if ghosts > MAX_GHOSTS {
+        bail!(
+            "at most {} tiles can be unknown, got {}",
+            MAX_GHOSTS,
+            ghosts
+        );
+    }
+    let mut unseen = Inventory::new(2);
+    unseen.subtract(&Inventory::from_sets(table));
+    unseen.subtract(rack);
+    let mut kinds = unseen.tiles();
+    kinds.dedup();
+    let kinds = kinds
+        .into_iter()
+        .map(|tile| (tile, unseen.count(&tile)))
+        .collect::<Vec<_>>();
+
+    let mut picks = Vec::new();
+    each_pick(&kinds, ghosts, &mut Vec::new(), 1, &mut |pick, ways| {
+        picks.push((pick.to_vec(), ways))
+    });
+    // Picks of the first kinds come last, list them in the order of a rack
+    picks.reverse();
+    let total = picks.iter().map(|(_, ways)| ways).sum::<u64>().max(1);
+
+    let mut outcomes: Vec<Outcome> = Vec::new();
+    let mut cache = SolverCache::default();
+    for (pick, ways) in picks {
+        let mut full = *rack;
+        pick.iter().for_each(|tile| full.add_tile(tile));
+        let play =
+            solver::best_move_cached(table, &full, opened, &mut cache)?.map(|(new_table, rest)| {
+                let played = full.total_tile_count() - rest.total_tile_count();
+                (Solution::from(new_table).canonical(), played)
+            });
+        let chance = ways as f64 / total as f64;
+        match outcomes.iter_mut().find(|outcome| outcome.play == play) {
+            Some(outcome) => {
+                outcome.tiles.push(pick);
+                outcome.chance += chance;
+            }
+            None => outcomes.push(Outcome {
+                tiles: vec![pick],
+                chance,
+                play,
+            }),
+        }
+    }
+    outcomes.sort_by(|a, b| b.chance.total_cmp(&a.chance));
+    Ok(outcomes)
+}
@@ -0,0 +1,197 @@
+use color_eyre::eyre::{bail, Result};
+use std::fmt;
+use std::sync::OnceLock;
+
+// Translations of the words and sentences the bot prints. Commands, tile
+// notation and error messages stay English, so the same input works everywhere.
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Language {
+    English,
+    Dutch,
+    German,
+}
+
+static LANGUAGE: OnceLock<Language> = OnceLock::new();
+
+impl Language {
+    pub fn parse(text: &str) -> Result<Language> {
+        // Also accepts locales such as `nl_NL.UTF-8`
+        match text.get(..2).map(str::to_ascii_lowercase).as_deref() {
+            Some("en") => Ok(Language::English),
+            Some("nl") => Ok(Language::Dutch),
+            Some("de") => Ok(Language::German),
+            _ => bail!("unknown language {}, expected en, nl or de", text),
+        }
+    }
+}
+
+// Picks the language from `--lang`, else from the LANG environment variable.
+// Unknown locales fall back to English, an unknown `--lang` is an error.
+pub fn init(args: &[String]) -> Result<()> {
+    let requested = args
+        .iter()
+        .position(|arg| arg == "--lang")
+        .and_then(|index| args.get(index + 1));
+    let language = match requested {
+        Some(text) => Language::parse(text)?,
+        None => std::env::var("LANG")
+            .ok()
+            .and_then(|locale| Language::parse(&locale).ok())
+            .unwrap_or(Language::English),
+    };
+    let _ = LANGUAGE.set(language);
+    Ok(())
+}
+
+pub fn language() -> Language {
+    *LANGUAGE.get().unwrap_or(&Language::English)
+}
+
+// Full color name of the standard set, in color index order
+pub fn color_name(color: u8) -> &'static str {
+    let names = match language() {
+        Language::English => ["Red", "Blue", "Orange", "Black"],
+        Language::Dutch => ["Rood", "Blauw", "Oranje", "Zwart"],
+        Language::German => ["Rot", "Blau", "Orange", "Schwarz"],
+    };
+    names.get(color as usize).copied().unwrap_or("?")
+}
+
+pub enum Message {
+    Group,
+    Run,
+    Kept,
+    Rearranged,
+    New,
+    FromRack { tiles: String },
+    SuggestedTable,
+    Plays { played: u32, total: u32 },
+    NoPlay,
+    FinalTable,
+    ToMove { player: usize, bag: u32 },
+    Holds { player: usize, tiles: u32 },
+    MayGoOut { player: usize, percent: u32 },
+    Table,
+    Rack,
+    InitialMeldNeeded,
+    Wins { player: usize },
+    Drawn,
+    Joker,
+    JokerAs { tile: String },
+    SetNumber { number: usize },
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Language::*;
+        use Message::*;
+        match (language(), self) {
+            (English, Group) => write!(f, "Group"),
+            (Dutch, Group) => write!(f, "Groep"),
+            (German, Group) => write!(f, "Gruppe"),
+            (English, Run) => write!(f, "Run"),
+            (Dutch, Run) => write!(f, "Rij"),
+            (German, Run) => write!(f, "Reihe"),
+            (English, Kept) => write!(f, "kept"),
+            (Dutch, Kept) => write!(f, "behouden"),
+            (German, Kept) => write!(f, "behalten"),
+            (English, Rearranged) => write!(f, "rearranged"),
+            (Dutch, Rearranged) => write!(f, "verschoven"),
+            (German, Rearranged) => write!(f, "umgelegt"),
+            (English, New) => write!(f, "new"),
+            (Dutch, New) => write!(f, "nieuw"),
+            (German, New) => write!(f, "neu"),
+            (English, FromRack { tiles }) => write!(f, "from the rack: {}", tiles),
+            (Dutch, FromRack { tiles }) => write!(f, "van het plankje: {}", tiles),
+            (German, FromRack { tiles }) => write!(f, "von der Bank: {}", tiles),
+            (English, SuggestedTable) => write!(f, "Suggested table:"),
+            (Dutch, SuggestedTable) => write!(f, "Voorgestelde tafel:"),
+            (German, SuggestedTable) => write!(f, "Vorgeschlagener Tisch:"),
+            (English, Plays { played, total }) => write!(f, "Plays {} of {} tiles", played, total),
+            (Dutch, Plays { played, total }) => write!(f, "Legt {} van {} stenen", played, total),
+            (German, Plays { played, total }) => write!(f, "Legt {} von {} Steinen", played, total),
+            (English, NoPlay) => write!(f, "No play possible, draw a tile"),
+            (Dutch, NoPlay) => write!(f, "Geen zet mogelijk, pak een steen"),
+            (German, NoPlay) => write!(f, "Kein Zug möglich, zieh einen Stein"),
+            (English, FinalTable) => write!(f, "Final table:"),
+            (Dutch, FinalTable) => write!(f, "Eindstand van de tafel:"),
+            (German, FinalTable) => write!(f, "Tisch am Ende:"),
+            (English, ToMove { player, bag }) => {
+                write!(
+                    f,
+                    "Player {} to move, {} tiles left in the bag",
+                    player, bag
+                )
+            }
+            (Dutch, ToMove { player, bag }) => {
+                write!(
+                    f,
+                    "Speler {} is aan de beurt, nog {} stenen in de zak",
+                    player, bag
+                )
+            }
+            (German, ToMove { player, bag }) => {
+                write!(
+                    f,
+                    "Spieler {} ist am Zug, noch {} Steine im Beutel",
+                    player, bag
+                )
+            }
+            (English, Holds { player, tiles }) => {
+                write!(f, "Player {} holds {} tiles", player, tiles)
+            }
+            (Dutch, Holds { player, tiles }) => {
+                write!(f, "Speler {} heeft {} stenen", player, tiles)
+            }
+            (German, Holds { player, tiles }) => {
+                write!(f, "Spieler {} hat {} Steine", player, tiles)
+            }
+            (English, MayGoOut { player, percent }) => {
+                write!(
+                    f,
+                    "Watch out: Player {} may go out next turn ({}%)",
+                    player, percent
+                )
+            }
+            (Dutch, MayGoOut { player, percent }) => {
+                write!(
+                    f,
+                    "Let op: speler {} kan volgende beurt uitgaan ({}%)",
+                    player, percent
+                )
+            }
+            (German, MayGoOut { player, percent }) => {
+                write!(
+                    f,
+                    "Achtung: Spieler {} kann nächste Runde ausgehen ({}%)",
+                    player, percent
+                )
+            }
+            (English, Table) => write!(f, "Table:"),
+            (Dutch, Table) => write!(f, "Tafel:"),
+            (German, Table) => write!(f, "Tisch:"),
+            (English, Rack) => write!(f, "Rack:"),
+            (Dutch, Rack) => write!(f, "Plankje:"),
+            (German, Rack) => write!(f, "Bank:"),
+            (English, InitialMeldNeeded) => write!(f, "Initial meld still needed"),
+            (Dutch, InitialMeldNeeded) => write!(f, "Eerste uitleg nog nodig"),
+            (German, InitialMeldNeeded) => write!(f, "Erstes Auslegen steht noch aus"),
+            (English, Wins { player }) => write!(f, "Player {} wins!", player),
+            (Dutch, Wins { player }) => write!(f, "Speler {} wint!", player),
+            (German, Wins { player }) => write!(f, "Spieler {} gewinnt!", player),
+            (English, Drawn) => write!(f, "The game is drawn"),
+            (Dutch, Drawn) => write!(f, "Het spel eindigt gelijk"),
+            (German, Drawn) => write!(f, "Das Spiel endet unentschieden"),
+            (English, Joker) => write!(f, "joker"),
+            (Dutch, Joker) => write!(f, "joker"),
+            (German, Joker) => write!(f, "Joker"),
+            (English, JokerAs { tile }) => write!(f, "joker as {}", tile),
+            (Dutch, JokerAs { tile }) => write!(f, "joker als {}", tile),
+            (German, JokerAs { tile }) => write!(f, "Joker als {}", tile),
+            (English, SetNumber { number }) => write!(f, "Set {}", number),
+            (Dutch, SetNumber { number }) => write!(f, "Set {}", number),
+            (German, SetNumber { number }) => write!(f, "Satz {}", number),
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use crate::config;
+use color_eyre::eyre::{bail, eyre, Result};
+use std::fs;
+use std::path::Path;
+
+// Most players a game can have, one `seat<n>` key each in the file
+const MAX_SEATS: usize = 4;
+
+pub struct Rated {
+    pub name: String,
+    pub rating: f64,
+    pub games: u32,
+    pub wins: u32,
+}
+
+// A finished game, enough to play it again with the same deal
+pub struct ArchivedGame {
+    pub seed: u64,
+    pub turns: u32,
+    // Names in seat order
+    pub players: Vec<String>,
+    pub winner: Option<usize>,
+}
+
+impl ArchivedGame {
+    // The game of a `[[game]]` table, as the ladder and tournament state keep it
+    pub fn read(table: &config::Table) -> Result<ArchivedGame> {
+        let seat_keys = (1..=MAX_SEATS)
+            .map(|seat| format!("seat{}", seat))
+            .collect::<Vec<String>>();
+        let mut known = vec!["seed", "turns", "winner"];
+        known.extend(seat_keys.iter().map(String::as_str));
+        table.check_keys(&known)?;
+        let players = seat_keys
+            .iter()
+            .map_while(|key| table.string(key).transpose())
+            .map(|name| name.map(str::to_string))
+            .collect::<Result<Vec<String>>>()?;
+        let winner = table.number("winner")?.map(|seat| seat as usize);
+        if winner.is_some_and(|seat| seat == 0 || seat > players.len()) {
+            bail!(
+                "line {}: winner must be one of the {} seats",
+                table.line,
+                players.len()
+            );
+        }
+        let (Some(seed), Some(turns)) = (table.string("seed")?, table.number("turns")?) else {
+            bail!("line {}: a game needs its seed and turns", table.line);
+        };
+        Ok(ArchivedGame {
+            seed: seed
+                .parse()
+                .map_err(|_| eyre!("line {}: seed must be a whole number", table.line))?,
+            turns: turns as u32,
+            players,
+            winner: winner.map(|seat| seat - 1),
+        })
+    }
+
+    pub fn write(&self, text: &mut String) {
+        text.push_str(&format!(
+            "[[game]]\nseed = \"{}\"\nturns = {}\n",
+            self.seed, self.turns
+        ));
+        for (seat, name) in self.players.iter().enumerate() {
+            text.push_str(&format!(
+                "seat{} = \"{}\"\n",
+                seat + 1,
+                config::escape(name)
+            ));
+        }
+        if let Some(winner) = self.winner {
+            text.push_str(&format!("winner = {}\n", winner + 1));
+        }
+        text.push('\n');
+    }
+}
+
+// Ratings, head to head records and games of strategies over every tournament
+// run with the same `--ladder` file, kept as
+//
+//     [[strategy]]
+//     name = "greedy(joker_weight=1)"
+//     rating = 1532.4
+//     games = 120
+//     wins = 64
+//
+//     [[beaten]]
+//     winner = "greedy(joker_weight=1)"
+//     loser = "greedy(joker_weight=0)"
+//     games = 31
+//
+//     [[game]]
+//     seed = "8461025334021"
+//     turns = 41
+//     seat1 = "greedy(joker_weight=0)"
+//     seat2 = "greedy(joker_weight=1)"
+//     winner = 2
+#[derive(Default)]
+pub struct Ladder {
+    strategies: Vec<Rated>,
+    beaten: Vec<(String, String, u32)>,
+    games: Vec<ArchivedGame>,
+}
+
+impl Ladder {
+    // An empty ladder when the file does not exist yet
+    pub fn load(path: &Path) -> Result<Ladder> {
+        let mut ladder = Ladder::default();
+        if !path.exists() {
+            return Ok(ladder);
+        }
+        for table in config::parse(&fs::read_to_string(path)?)? {
+            let number = |key: &str| -> Result<f64> {
+                table
+                    .number(key)?
+                    .ok_or_else(|| eyre!("line {}: missing {}", table.line, key))
+            };
+            let string = |key: &str| -> Result<String> {
+                table
+                    .string(key)?
+                    .map(str::to_string)
+                    .ok_or_else(|| eyre!("line {}: missing {}", table.line, key))
+            };
+            match table.name.as_str() {
+                "" => table.check_keys(&[])?,
+                "strategy" => {
+                    table.check_keys(&["name", "rating", "games", "wins"])?;
+                    ladder.strategies.push(Rated {
+                        name: string("name")?,
+                        rating: number("rating")?,
+                        games: number("games")? as u32,
+                        wins: number("wins")? as u32,
+                    });
+                }
+                "beaten" => {
+                    table.check_keys(&["winner", "loser", "games"])?;
+                    ladder.beaten.push((
+                        string("winner")?,
+                        string("loser")?,
+                        number("games")? as u32,
+                    ));
+                }
+                "game" => ladder.games.push(ArchivedGame::read(&table)?),
+                other => bail!(
+                    "line {}: unknown table [{}] in {}",
+                    table.line,
+                    other,
+                    path.display()
+                ),
+            }
+        }
+        Ok(ladder)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut text = String::new();
+        for rated in &self.strategies {
+            text.push_str(&format!(
+                "[[strategy]]\nname = \"{}\"\nrating = {}\ngames = {}\nwins = {}\n\n",
+                config::escape(&rated.name),
+                rated.rating,
+                rated.games,
+                rated.wins
+            ));
+        }
+        for (winner, loser, games) in &self.beaten {
+            text.push_str(&format!(
+                "[[beaten]]\nwinner = \"{}\"\nloser = \"{}\"\ngames = {}\n\n",
+                config::escape(winner),
+                config::escape(loser),
+                games
+            ));
+        }
+        for game in &self.games {
+            game.write(&mut text);
+        }
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn rating(&self, name: &str) -> Option<f64> {
+        self.strategies
+            .iter()
+            .find(|rated| rated.name == name)
+            .map(|rated| rated.rating)
+    }
+
+    // Adds the results of a run to those of the strategy, the rating replaces
+    // the old one as it already started from there
+    pub fn record(&mut self, name: &str, rating: f64, games: u32, wins: u32) {
+        match self.strategies.iter_mut().find(|rated| rated.name == name) {
+            Some(rated) => {
+                rated.rating = rating;
+                rated.games += games;
+                rated.wins += wins;
+            }
+            None => self.strategies.push(Rated {
+                name: name.to_string(),
+                rating,
+                games,
+                wins,
+            }),
+        }
+    }
+
+    pub fn add_beaten(&mut self, winner: &str, loser: &str, games: u32) {
+        match self
+            .beaten
+            .iter_mut()
+            .find(|(w, l, _)| w == winner && l == loser)
+        {
+            Some((_, _, total)) => *total += games,
+            None => self
+                .beaten
+                .push((winner.to_string(), loser.to_string(), games)),
+        }
+    }
+
+    pub fn beaten(&self, winner: &str, loser: &str) -> u32 {
+        self.beaten
+            .iter()
+            .find(|(w, l, _)| w == winner && l == loser)
+            .map_or(0, |(_, _, games)| *games)
+    }
+
+    pub fn archive(&mut self, game: ArchivedGame) {
+        self.games.push(game);
+    }
+
+    pub fn games(&self) -> &[ArchivedGame] {
+        &self.games
+    }
+
+    // Every strategy, highest rated first
+    pub fn ranking(&self) -> Vec<&Rated> {
+        let mut ranking = self.strategies.iter().collect::<Vec<&Rated>>();
+        ranking.sort_by(|a, b| b.rating.total_cmp(&a.rating));
+        ranking
+    }
+}
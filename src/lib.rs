@@ -0,0 +1,47 @@
+// The solver as a library: tiles and sets, their notation, the rules, move
+// validation and the search for the best move. It needs none of the
+// dependencies of the program around it, build it alone with
+// `default-features = false, features = ["std"]`. Without `std` only the
+// `no_std` core of tiles is left, see tiles.rs.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+// No input may make the library panic, whatever tiles or text it is given:
+// slices are read with `get`, and failures are returned
+#![deny(
+    clippy::indexing_slicing,
+    clippy::unwrap_used,
+    clippy::expect_used,
+    clippy::panic
+)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod audit;
+#[cfg(feature = "std")]
+pub mod cli;
+#[cfg(feature = "std")]
+pub mod config;
+#[cfg(feature = "std")]
+pub mod diagnostic;
+#[cfg(feature = "std")]
+pub mod i18n;
+#[cfg(feature = "std")]
+pub mod json;
+#[cfg(feature = "std")]
+pub mod notation;
+#[cfg(feature = "std")]
+pub mod rules;
+#[cfg(feature = "std")]
+pub mod search_stats;
+#[cfg(feature = "std")]
+pub mod search_trace;
+#[cfg(feature = "std")]
+pub mod solution;
+#[cfg(feature = "std")]
+pub mod solver;
+#[cfg(feature = "std")]
+pub mod tablebase;
+pub mod tiles;
+#[cfg(feature = "std")]
+pub mod validator;
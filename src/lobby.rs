@@ -0,0 +1,346 @@
+use crate::budget::Fixed;
+use crate::game::{AppliedMove, GameConfig, GameState};
+use crate::json::{self, Json};
+use crate::replay;
+use crate::solver::{Set, Tile};
+use crate::strategy::Greedy;
+use color_eyre::eyre::{bail, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Lobbies nobody used for this long are dropped, with their game
+const IDLE: Duration = Duration::from_secs(30 * 60);
+// Letters of the join codes, without ones that are easily mixed up
+const CODE_LETTERS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
+const CODE_LENGTH: usize = 6;
+// Time a bot searches for a move, the lobby waits for it
+const BOT_MOVE_TIME: Duration = Duration::from_secs(1);
+
+enum Seat {
+    Bot,
+    // Waiting for a human to join
+    Open,
+    // The token the human proves their seat with, also after reconnecting
+    Human(String),
+}
+
+// A game of humans against bots hosted by the server. Humans join with the
+// code of the lobby and get a token for their seat. The game starts once every
+// seat is taken, the bots move as soon as it is their turn.
+pub struct Lobby {
+    game: GameState,
+    seats: Vec<Seat>,
+}
+
+fn new_token() -> String {
+    format!(
+        "{:016x}{:016x}",
+        rand::random::<u64>(),
+        rand::random::<u64>()
+    )
+}
+
+impl Lobby {
+    fn started(&self) -> bool {
+        !self.seats.iter().any(|seat| matches!(seat, Seat::Open))
+    }
+
+    // The seat of the human with the token
+    pub fn seat(&self, token: &str) -> Option<usize> {
+        self.seats
+            .iter()
+            .position(|seat| matches!(seat, Seat::Human(owner) if owner == token))
+    }
+
+    // Takes the first open seat and returns it with its token
+    pub fn join(&mut self) -> Result<(usize, String)> {
+        let Some(seat) = self
+            .seats
+            .iter()
+            .position(|seat| matches!(seat, Seat::Open))
+        else {
+            bail!("the lobby is full");
+        };
+        let token = new_token();
+        self.seats[seat] = Seat::Human(token.clone());
+        self.play_bots()?;
+        Ok((seat, token))
+    }
+
+    // `seen` is the turn the human last saw the game at, if they say. A move
+    // worked out on an older table, e.g. in a second window, is refused
+    // rather than made on a table it was not meant for.
+    fn check_turn(&self, seat: usize, seen: Option<u32>) -> Result<()> {
+        if !self.started() {
+            bail!("the game starts once every seat is taken");
+        }
+        if self.game.is_over() {
+            bail!("the game is over");
+        }
+        if self.game.current() != seat {
+            bail!("it is the turn of player {}", self.game.current() + 1);
+        }
+        if let Some(seen) = seen.filter(|&seen| seen != self.game.turn()) {
+            bail!(
+                "the move was made at turn {}, the game is at turn {} now",
+                seen,
+                self.game.turn()
+            );
+        }
+        Ok(())
+    }
+
+    // The move of the human in the seat, then those of the bots after them.
+    // Returns the move of the human.
+    pub fn play(&mut self, seat: usize, table: Vec<Set>, seen: Option<u32>) -> Result<AppliedMove> {
+        self.check_turn(seat, seen)?;
+        let applied = self.game.apply_move(table)?;
+        self.play_bots()?;
+        Ok(applied)
+    }
+
+    pub fn draw(&mut self, seat: usize, seen: Option<u32>) -> Result<AppliedMove> {
+        self.check_turn(seat, seen)?;
+        let applied = self.game.draw()?;
+        self.play_bots()?;
+        Ok(applied)
+    }
+
+    fn play_bots(&mut self) -> Result<()> {
+        let bot = Greedy::default();
+        while self.started()
+            && !self.game.is_over()
+            && matches!(self.seats[self.game.current()], Seat::Bot)
+        {
+            self.game.strategy_turn(&bot)?;
+        }
+        Ok(())
+    }
+
+    // The game as the human in the seat sees it, with only their own rack
+    pub fn to_json(&self, code: &str, seat: usize) -> Json {
+        let status = if !self.started() {
+            "waiting"
+        } else if self.game.is_over() {
+            "over"
+        } else {
+            "playing"
+        };
+        let players = self.seats.iter().enumerate().map(|(other, kind)| {
+            let kind = match kind {
+                Seat::Bot => "bot",
+                Seat::Open => "open",
+                Seat::Human(_) => "human",
+            };
+            json::object([
+                ("player", Json::Number(other as f64 + 1.0)),
+                ("kind", json::string(kind)),
+                (
+                    "tiles",
+                    Json::Number(self.game.rack(other).total_tile_count() as f64),
+                ),
+                ("opened", Json::Bool(self.game.has_opened(other))),
+            ])
+        });
+        let winner = match self.game.winner() {
+            Some(winner) if self.game.is_over() => Json::Number(winner as f64 + 1.0),
+            _ => Json::Null,
+        };
+        json::object([
+            ("code", json::string(code)),
+            ("status", json::string(status)),
+            ("player", Json::Number(seat as f64 + 1.0)),
+            ("current", Json::Number(self.game.current() as f64 + 1.0)),
+            ("turn", Json::Number(self.game.turn() as f64)),
+            (
+                "your_turn",
+                Json::Bool(status == "playing" && self.game.current() == seat),
+            ),
+            ("players", Json::Array(players.collect())),
+            ("bag", Json::Number(self.game.bag_size() as f64)),
+            (
+                "table",
+                Json::Array(
+                    self.game
+                        .table()
+                        .iter()
+                        .map(|set| json::string(set.to_string()))
+                        .collect(),
+                ),
+            ),
+            (
+                "rack",
+                json::string(replay::rack_text(self.game.rack(seat))),
+            ),
+            ("opened", Json::Bool(self.game.has_opened(seat))),
+            ("winner", winner),
+        ])
+    }
+}
+
+// The move of a human as the server reports it back to them, with the tiles
+// they drew
+pub fn applied_json(applied: &AppliedMove) -> Json {
+    let tiles = |tiles: &[Tile]| {
+        json::string(
+            tiles
+                .iter()
+                .map(Tile::to_string)
+                .collect::<Vec<String>>()
+                .join(" "),
+        )
+    };
+    json::object([
+        ("turn", Json::Number(applied.turn as f64)),
+        ("played", tiles(&applied.played)),
+        ("drawn", tiles(&applied.drawn)),
+        ("opening", Json::Bool(applied.opening)),
+    ])
+}
+
+type SharedLobby = Arc<Mutex<Lobby>>;
+
+// Lobbies by join code, each with the time it was last used
+pub struct Lobbies {
+    limit: usize,
+    lobbies: Mutex<HashMap<String, (Instant, SharedLobby)>>,
+}
+
+impl Lobbies {
+    pub fn new(limit: usize) -> Lobbies {
+        Lobbies {
+            limit,
+            lobbies: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Opens a lobby of `players` seats, `bots` of them for bots, with the
+    // creator in the first seat. Returns the code, the token of the creator
+    // and the lobby, None when there are too many lobbies.
+    pub fn create(
+        &self,
+        players: usize,
+        bots: usize,
+        seed: Option<u64>,
+    ) -> Result<Option<(String, String, SharedLobby)>> {
+        if bots >= players {
+            bail!(
+                "a lobby needs a seat for at least one human, got {} bots for {} players",
+                bots,
+                players
+            );
+        }
+        let mut config = GameConfig::new(players, GameConfig::default().rack_size)?;
+        config.time_manager = Some(Arc::new(Fixed(BOT_MOVE_TIME)));
+        let game = match seed {
+            Some(seed) => GameState::seeded(&config, seed),
+            None => GameState::new(&config),
+        };
+        let token = new_token();
+        let mut seats = vec![Seat::Human(token.clone())];
+        seats.extend((1..players).map(|seat| {
+            if seat < players - bots {
+                Seat::Open
+            } else {
+                Seat::Bot
+            }
+        }));
+        let mut lobby = Lobby { game, seats };
+        lobby.play_bots()?;
+
+        let mut lobbies = self.lobbies.lock().unwrap();
+        lobbies.retain(|_, (used, _)| used.elapsed() < IDLE);
+        if lobbies.len() >= self.limit {
+            return Ok(None);
+        }
+        let code = loop {
+            let code = (0..CODE_LENGTH)
+                .map(|_| CODE_LETTERS[rand::random::<usize>() % CODE_LETTERS.len()] as char)
+                .collect::<String>();
+            if !lobbies.contains_key(&code) {
+                break code;
+            }
+        };
+        let lobby = Arc::new(Mutex::new(lobby));
+        lobbies.insert(code.clone(), (Instant::now(), lobby.clone()));
+        Ok(Some((code, token, lobby)))
+    }
+
+    // Codes are read back from people, so any case will do
+    pub fn get(&self, code: &str) -> Option<(String, SharedLobby)> {
+        let code = code.to_ascii_uppercase();
+        let mut lobbies = self.lobbies.lock().unwrap();
+        let (used, lobby) = lobbies
+            .get_mut(&code)
+            .filter(|(used, _)| used.elapsed() < IDLE)?;
+        *used = Instant::now();
+        Some((code, lobby.clone()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.lobbies.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation;
+
+    // A lobby of two humans and a bot, with both humans seated
+    fn started() -> Result<(Lobbies, String, [String; 2])> {
+        let lobbies = Lobbies::new(10);
+        let Some((code, first, _)) = lobbies.create(3, 1, Some(5))? else {
+            bail!("no room for the lobby");
+        };
+        let Some((_, lobby)) = lobbies.get(&code.to_ascii_lowercase()) else {
+            bail!("the lobby is gone");
+        };
+        let (seat, second) = lobby.lock().unwrap().join()?;
+        assert_eq!(seat, 1);
+        Ok((lobbies, code, [first, second]))
+    }
+
+    #[test]
+    fn the_game_waits_for_every_seat() -> Result<()> {
+        let lobbies = Lobbies::new(10);
+        let Some((_, token, lobby)) = lobbies.create(3, 1, Some(5))? else {
+            bail!("no room for the lobby");
+        };
+        let mut lobby = lobby.lock().unwrap();
+        assert_eq!(lobby.seat(&token), Some(0));
+        assert!(lobby.draw(0, None).is_err());
+        lobby.join()?;
+        assert!(lobby.join().is_err());
+        assert!(lobby.draw(0, None).is_ok());
+        Ok(())
+    }
+
+    // Moves name the turn they were made at, the bot moves right after the
+    // humans and the game goes on from there
+    #[test]
+    fn humans_and_bots_take_turns() -> Result<()> {
+        let (lobbies, code, [first, second]) = started()?;
+        let Some((_, lobby)) = lobbies.get(&code) else {
+            bail!("the lobby is gone");
+        };
+        let mut lobby = lobby.lock().unwrap();
+        let seats = [lobby.seat(&first), lobby.seat(&second)];
+        assert_eq!(seats, [Some(0), Some(1)]);
+
+        assert!(lobby.draw(1, None).is_err());
+        assert!(lobby.draw(0, Some(3)).is_err());
+        let drawn = lobby.draw(0, Some(0))?;
+        assert_eq!((drawn.turn, drawn.drawn.len()), (0, 1));
+        assert!(lobby.draw(0, Some(1)).is_err());
+
+        // An illegal move changes nothing and the human can try again
+        let table = notation::parse_table("R1 R2 R3")?;
+        assert!(lobby.play(1, table, Some(1)).is_err());
+        assert_eq!(lobby.game.turn(), 1);
+        lobby.draw(1, Some(1))?;
+        assert_eq!((lobby.game.turn(), lobby.game.current()), (3, 0));
+        Ok(())
+    }
+}
@@ -1,17 +1,214 @@
-use color_eyre::{eyre::Report, eyre::Result};
+// Builds without some of the default features leave parts of the shared
+// modules unused, such as the themes without `tui`
+#![cfg_attr(
+    not(all(
+        feature = "server",
+        feature = "tui",
+        feature = "ml",
+        feature = "parallel"
+    )),
+    allow(dead_code)
+)]
+
+use color_eyre::{eyre::bail, eyre::Report, eyre::Result};
+use std::process;
 use std::time::Instant;
 
-mod solver;
+// The solver and everything it needs come from the library, see lib.rs
+#[cfg(feature = "tui")]
+use rummikub_bot::audit;
+use rummikub_bot::{
+    cli, config, diagnostic, i18n, json, notation, rules, search_trace, solution, solver,
+    tablebase, validator,
+};
+
+#[cfg(feature = "tui")]
+mod achievements;
+mod analysis;
+mod assist;
+mod bag;
+mod batch;
+mod bench;
+mod board;
+mod budget;
+mod check;
+mod compare;
+mod completions;
+mod deal;
+mod determinism;
+#[cfg(feature = "tui")]
+mod editor;
+#[cfg(feature = "ml")]
+mod encoding;
+mod events;
+mod fixtures;
+mod forecast;
+mod game;
+mod ghost;
+mod ladder;
+#[cfg(feature = "server")]
+mod lobby;
+mod memo_bench;
+#[cfg(feature = "server")]
+mod metrics;
+mod notify;
+#[cfg(any(feature = "ml", feature = "png"))]
+mod npz;
+#[cfg(feature = "onnx")]
+mod onnx;
+mod perft;
+#[cfg(feature = "tui")]
+mod play;
+#[cfg(feature = "plugins")]
+mod plugin;
+mod position;
+mod profile;
+mod provenance;
+#[cfg(feature = "tui")]
+mod puzzle;
+#[cfg(feature = "tui")]
+mod referee;
+mod render;
+#[cfg(feature = "tui")]
+mod repair;
+mod replay;
+mod retrograde;
+mod review;
+mod rng;
+mod scenario;
+mod scoring;
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "server")]
+mod session;
+mod settings;
+mod simulate;
+#[cfg(feature = "tui")]
+mod spectate;
+mod speech;
+mod sse;
+mod stats;
+#[cfg(feature = "server")]
+mod storage;
+mod strategy;
+mod strength;
+mod suspicion;
+mod theme;
+mod threat;
+#[cfg(feature = "tui")]
+mod timeline;
+mod tournament;
+#[cfg(feature = "ml")]
+mod training;
+mod tune;
+#[cfg(feature = "tui")]
+mod tutorial;
+mod verify;
+
+// Whether errors are shown as text for people or, with `--errors json`, as
+// JSON for programs
+fn json_errors(args: &[String]) -> Result<bool> {
+    match cli::Args::new(args).value("--errors") {
+        None | Some("text") => Ok(false),
+        Some("json") => Ok(true),
+        Some(other) => bail!("unknown --errors {}, expected text or json", other),
+    }
+}
 
 fn main() -> Result<(), Report> {
     color_eyre::install()?;
 
-    let start = Instant::now();
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let layered = settings::layered(args.clone());
+    let json_errors = json_errors(layered.as_deref().unwrap_or(&args))?;
+    match layered.and_then(|args| run(&args)) {
+        // One line on standard error with the code and spans of the error
+        Err(error) if json_errors => {
+            eprintln!("{}", json::object([("error", diagnostic::to_json(&error))]));
+            process::exit(1);
+        }
+        result => result,
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Report> {
+    i18n::init(args)?;
+    speech::init(args);
+    theme::init(args)?;
+    notify::init(args)?;
+    // The colors of a rules file apply to every tile, so they are set up first
+    if let Some(path) = cli::Args::new(args).value("--rules") {
+        if let Some(palette) = rules::load(path)?.1 {
+            notation::set_palette(palette);
+        }
+    }
+    if let Some(path) = cli::Args::new(args).value("--tablebase") {
+        tablebase::set_path(path);
+    }
+    if let Some(command) = args.first() {
+        completions::check_options(command, &args[1..])?;
+    }
+    match args.first().map(String::as_str) {
+        Some("analyze") => batch::run(&cli::Args::new(&args[1..]))?,
+        Some("assist") => assist::run(&cli::Args::new(&args[1..]))?,
+        Some("bench") => bench::run(&cli::Args::new(&args[1..]))?,
+        Some("check") => check::run(&cli::Args::new(&args[1..]))?,
+        Some("compare") => compare::run(&cli::Args::new(&args[1..]))?,
+        Some("completions") => completions::run(&cli::Args::new(&args[1..]))?,
+        Some("man") => completions::man()?,
+        Some("match") => scoring::run(&cli::Args::new(&args[1..]))?,
+        Some("perft") => perft::run(&cli::Args::new(&args[1..]))?,
+        #[cfg(feature = "tui")]
+        Some("play") => play::run(&cli::Args::new(&args[1..]))?,
+        Some("probability") => analysis::run(&cli::Args::new(&args[1..]))?,
+        #[cfg(feature = "tui")]
+        Some("puzzle") => puzzle::run(&cli::Args::new(&args[1..]))?,
+        Some("suspicion") => suspicion::run(&cli::Args::new(&args[1..]))?,
+        Some("tablebase") => tablebase::run(&cli::Args::new(&args[1..]))?,
+        Some("tournament") => tournament::run(&cli::Args::new(&args[1..]))?,
+        Some("retrograde") => retrograde::run(&cli::Args::new(&args[1..]))?,
+        Some("review") => review::run(&cli::Args::new(&args[1..]))?,
+        #[cfg(feature = "tui")]
+        Some("referee") => referee::run(&cli::Args::new(&args[1..]))?,
+        #[cfg(feature = "ml")]
+        Some("selfplay") => training::run(&cli::Args::new(&args[1..]))?,
+        #[cfg(feature = "server")]
+        Some("serve") => server::run(&cli::Args::new(&args[1..]))?,
+        Some("simulate") => simulate::run(&cli::Args::new(&args[1..]))?,
+        #[cfg(feature = "tui")]
+        Some("spectate") => spectate::run(&cli::Args::new(&args[1..]))?,
+        #[cfg(feature = "tui")]
+        Some("stats") => achievements::run(&cli::Args::new(&args[1..]))?,
+        Some("strength") => strength::run(&cli::Args::new(&args[1..]))?,
+        #[cfg(feature = "tui")]
+        Some("tutorial") => tutorial::run(&cli::Args::new(&args[1..]))?,
+        Some("tune") => tune::run(&cli::Args::new(&args[1..]))?,
+        Some("verify") => verify::run(&cli::Args::new(&args[1..]))?,
+        Some("verify-determinism") => determinism::run(&args[1..])?,
+        #[cfg(not(feature = "server"))]
+        Some("serve") => bail!("serve needs a build with `--features server`"),
+        #[cfg(not(feature = "tui"))]
+        Some(command @ ("play" | "puzzle" | "referee" | "spectate" | "stats" | "tutorial")) => {
+            bail!("{} needs a build with `--features tui`", command)
+        }
+        #[cfg(not(feature = "ml"))]
+        Some("selfplay") => bail!("selfplay needs a build with `--features ml`"),
+        Some(command) => bail!(
+            "unknown command {}, expected one of {}",
+            command,
+            completions::names().join(", ")
+        ),
+        None => {
+            let start = Instant::now();
 
-    solver::solve()?;
+            let mut bag = bag::Bag::full();
+            let mut rng = rand::thread_rng();
+            solver::solve(|| bag.draw(&mut rng))?;
 
-    let duration = start.elapsed();
-    println!("Time elapsed in solving is: {:?}", duration);
+            let duration = start.elapsed();
+            println!("Time elapsed in solving is: {:?}", duration);
+        }
+    }
 
     Ok(())
 }
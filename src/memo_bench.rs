@@ -0,0 +1,271 @@
+use crate::cli::Args;
+use crate::deal::Deal;
+use crate::solver::{self, Inventory, Tile};
+use color_eyre::eyre::{bail, Report, Result};
+use rummikub_bot::tiles::{self, Memo};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, BuildHasherDefault, Hasher};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+// The designs compared, in the order of the report
+const DESIGNS: [&str; 5] = ["none", "btree", "hash", "fx", "persistent"];
+
+// Questions of one position: the table laid out from a dealt hand, and a rack
+// whose tiles are added to it one after another and one at a time
+struct Position {
+    table: Inventory,
+    rack: Vec<Tile>,
+}
+
+impl Position {
+    fn new(seed: u64, hand: u32, rack_size: u32) -> Position {
+        let mut deal = Deal::new(1, hand, seed);
+        let (_, table) = solver::max_tiles_meld(&deal.racks[0]);
+        let rack = (0..rack_size)
+            .filter_map(|_| deal.bag.draw_next())
+            .collect();
+        Position {
+            table: Inventory::from_sets(&table),
+            rack,
+        }
+    }
+
+    fn questions(&self) -> Vec<Inventory> {
+        let mut questions = vec![self.table];
+        let mut all = self.table;
+        for tile in &self.rack {
+            let mut one = self.table;
+            one.add_tile(tile);
+            all.add_tile(tile);
+            questions.extend([one, all]);
+        }
+        questions
+    }
+}
+
+// The hasher of rustc: one rotate, xor and multiply for every word. The keys
+// of inventories are already well mixed, so nothing more is needed.
+#[derive(Default)]
+struct FxHasher(u64);
+
+impl FxHasher {
+    fn add(&mut self, word: u64) {
+        self.0 = (self.0.rotate_left(5) ^ word).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        bytes.iter().for_each(|&byte| self.add(byte as u64));
+    }
+
+    fn write_u64(&mut self, word: u64) {
+        self.add(word);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// A HashMap with the hasher S, the std one hashes with SipHash
+#[derive(Default)]
+struct Hashed<S>(HashMap<Inventory, bool, S>);
+
+impl<S: BuildHasher> Memo<Inventory, bool> for Hashed<S> {
+    fn recall(&mut self, inventory: &Inventory) -> Option<bool> {
+        self.0.get(inventory).copied()
+    }
+
+    fn remember(&mut self, inventory: Inventory, possible: bool) {
+        self.0.insert(inventory, possible);
+    }
+}
+
+// Remembers nothing, every question is searched from the start
+struct Forgetful;
+
+impl Memo<Inventory, bool> for Forgetful {
+    fn recall(&mut self, _: &Inventory) -> Option<bool> {
+        None
+    }
+
+    fn remember(&mut self, _: Inventory, _: bool) {}
+}
+
+// Any memo with its looks counted
+struct Looked<M> {
+    memo: M,
+    looks: u64,
+    hits: u64,
+}
+
+impl<M: Memo<Inventory, bool>> Memo<Inventory, bool> for Looked<M> {
+    fn recall(&mut self, inventory: &Inventory) -> Option<bool> {
+        let known = self.memo.recall(inventory);
+        self.looks += 1;
+        self.hits += known.is_some() as u64;
+        known
+    }
+
+    fn remember(&mut self, inventory: Inventory, possible: bool) {
+        self.memo.remember(inventory, possible);
+    }
+}
+
+// What one design did on the positions of one hand size
+struct Measure {
+    each: Duration,
+    looks: u64,
+    hits: u64,
+    // Entries of the memo at the end, the largest one for fresh memos
+    entries: usize,
+}
+
+// Answers every question of every position, a fresh memo for each position
+// or, when `persistent`, one memo for all of them
+fn measure<M: Memo<Inventory, bool>>(
+    positions: &[Position],
+    fresh: impl Fn() -> M,
+    len: impl Fn(&M) -> usize,
+    persistent: Option<&mut Looked<M>>,
+) -> Measure {
+    let mut kept = persistent;
+    let (mut looks, mut hits, mut entries) = (0, 0, 0);
+    let start = Instant::now();
+    for position in positions {
+        let mut own = Looked {
+            memo: fresh(),
+            looks: 0,
+            hits: 0,
+        };
+        let memo = match kept.as_deref_mut() {
+            Some(kept) => kept,
+            None => &mut own,
+        };
+        let (looks_before, hits_before) = (memo.looks, memo.hits);
+        for question in black_box(position).questions() {
+            black_box(tiles::can_lay_out(&question, memo));
+        }
+        looks += memo.looks - looks_before;
+        hits += memo.hits - hits_before;
+        entries = entries.max(len(&memo.memo));
+    }
+    Measure {
+        each: start.elapsed() / positions.len().max(1) as u32,
+        looks,
+        hits,
+        entries,
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    match duration.as_nanos() {
+        nanos if nanos < 10_000 => format!("{} ns", nanos),
+        nanos if nanos < 10_000_000 => format!("{} µs", nanos / 1000),
+        nanos => format!("{} ms", nanos / 1_000_000),
+    }
+}
+
+// Compares the designs of the layout memo on positions dealt from fixed
+// seeds, for every hand size of `--sizes`: no memo, a BTreeMap, a HashMap with
+// SipHash or with the hasher of rustc, each fresh for every position, and a
+// HashMap kept over every position like the memos of a long game. `--memos`
+// leaves some out, the search without one gets slow on large hands. Build
+// with `--release`.
+pub fn run(args: &Args) -> Result<(), Report> {
+    let positions: u64 = args.parse_or("--positions", 50)?;
+    let seed: u64 = args.parse_or("--seed", 0)?;
+    let sizes = args
+        .value("--sizes")
+        .unwrap_or("14,28,42")
+        .split(',')
+        .map(|size| {
+            size.trim()
+                .parse::<u32>()
+                .map_err(|_| Report::msg(format!("bad hand size {}", size)))
+        })
+        .collect::<Result<Vec<u32>>>()?;
+    let designs = match args.value("--memos") {
+        None => DESIGNS.to_vec(),
+        Some(list) => {
+            let chosen = list.split(',').map(str::trim).collect::<Vec<&str>>();
+            if let Some(unknown) = chosen.iter().find(|name| !DESIGNS.contains(name)) {
+                bail!(
+                    "unknown memo {}, expected one of {}",
+                    unknown,
+                    DESIGNS.join(", ")
+                );
+            }
+            DESIGNS
+                .into_iter()
+                .filter(|name| chosen.contains(name))
+                .collect()
+        }
+    };
+
+    let mut persistent = Looked {
+        memo: Hashed::<std::hash::RandomState>::default(),
+        looks: 0,
+        hits: 0,
+    };
+    println!(
+        "{:10} | {:>5} | {:>11} | {:>10} | {:>8} | {:>8} | {:>8}",
+        "Memo", "Hand", "Table tiles", "Each", "Looks", "Hit rate", "Entries"
+    );
+    let mut fastest = Vec::new();
+    for &hand in &sizes {
+        let dealt = (0..positions)
+            .map(|offset| Position::new(seed.wrapping_add(offset), hand, 8))
+            .collect::<Vec<_>>();
+        let table_tiles = dealt
+            .iter()
+            .map(|position| position.table.total_tile_count())
+            .sum::<u32>() as f64
+            / dealt.len().max(1) as f64;
+        let mut best: Option<(&str, Duration)> = None;
+        for &design in &designs {
+            let measure = match design {
+                "none" => measure(&dealt, || Forgetful, |_| 0, None),
+                "btree" => measure(&dealt, BTreeMap::new, BTreeMap::len, None),
+                "hash" => measure(
+                    &dealt,
+                    Hashed::<std::hash::RandomState>::default,
+                    |memo| memo.0.len(),
+                    None,
+                ),
+                "fx" => measure(
+                    &dealt,
+                    Hashed::<BuildHasherDefault<FxHasher>>::default,
+                    |memo| memo.0.len(),
+                    None,
+                ),
+                _ => measure(
+                    &dealt,
+                    Hashed::default,
+                    |memo| memo.0.len(),
+                    Some(&mut persistent),
+                ),
+            };
+            println!(
+                "{:10} | {:>5} | {:>11.1} | {:>10} | {:>8} | {:>7.1}% | {:>8}",
+                design,
+                hand,
+                table_tiles,
+                format_duration(measure.each),
+                measure.looks,
+                100.0 * measure.hits as f64 / measure.looks.max(1) as f64,
+                measure.entries
+            );
+            if best.is_none_or(|(_, each)| measure.each < each) {
+                best = Some((design, measure.each));
+            }
+        }
+        if let Some((design, _)) = best {
+            fastest.push(format!("{} with hands of {}", design, hand));
+        }
+    }
+    println!("Fastest: {}", fastest.join(", "));
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use crate::solver::SEARCH;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// Upper bounds of the solve time buckets in seconds, most solves take
+// milliseconds and the slow ones run into the timeout
+const SOLVE_BUCKETS: [f64; 8] = [0.001, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0];
+
+#[derive(Default)]
+struct Histogram {
+    // Observations up to each bound of SOLVE_BUCKETS, not cumulative
+    counts: [u64; SOLVE_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        if let Some(bucket) = SOLVE_BUCKETS.iter().position(|&bound| value <= bound) {
+            self.counts[bucket] += 1;
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+}
+
+#[derive(Default)]
+struct Counts {
+    // Responses by endpoint and status code
+    requests: BTreeMap<(&'static str, u16), u64>,
+    // Solve times by outcome, timed out solves are only counted
+    solves: BTreeMap<&'static str, Histogram>,
+    timeouts: u64,
+}
+
+// What the server has done since it started, in the text format of Prometheus
+#[derive(Default)]
+pub struct Metrics {
+    counts: Mutex<Counts>,
+}
+
+// Current values that are not counted but looked up when scraped
+pub struct Gauges {
+    pub queued: usize,
+    pub direct: usize,
+    pub sessions: usize,
+    pub lobbies: usize,
+    pub workers: usize,
+}
+
+impl Metrics {
+    pub fn request(&self, endpoint: &'static str, status: u16) {
+        *self
+            .counts
+            .lock()
+            .unwrap()
+            .requests
+            .entry((endpoint, status))
+            .or_insert(0) += 1;
+    }
+
+    // `outcome` is the status of the finished job, e.g. `done` or `draw`
+    pub fn solve(&self, outcome: &'static str, took: Duration) {
+        let mut counts = self.counts.lock().unwrap();
+        counts
+            .solves
+            .entry(outcome)
+            .or_default()
+            .observe(took.as_secs_f64());
+    }
+
+    pub fn timeout(&self) {
+        self.counts.lock().unwrap().timeouts += 1;
+    }
+
+    pub fn render(&self, gauges: &Gauges) -> String {
+        let counts = self.counts.lock().unwrap();
+        let mut text = String::new();
+        let header = |text: &mut String, name: &str, kind: &str, help: &str| {
+            let _ = writeln!(text, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+        };
+
+        header(
+            &mut text,
+            "rummikub_requests_total",
+            "counter",
+            "HTTP responses by endpoint and status code.",
+        );
+        for ((endpoint, status), count) in &counts.requests {
+            let _ = writeln!(
+                text,
+                "rummikub_requests_total{{endpoint=\"{}\",status=\"{}\"}} {}",
+                endpoint, status, count
+            );
+        }
+
+        header(
+            &mut text,
+            "rummikub_solve_seconds",
+            "histogram",
+            "Time taken by finished solves.",
+        );
+        for (outcome, histogram) in &counts.solves {
+            let mut cumulative = 0;
+            for (bound, count) in SOLVE_BUCKETS.iter().zip(histogram.counts) {
+                cumulative += count;
+                let _ = writeln!(
+                    text,
+                    "rummikub_solve_seconds_bucket{{outcome=\"{}\",le=\"{}\"}} {}",
+                    outcome, bound, cumulative
+                );
+            }
+            let _ = writeln!(
+                text,
+                "rummikub_solve_seconds_bucket{{outcome=\"{}\",le=\"+Inf\"}} {}",
+                outcome, histogram.count
+            );
+            let _ = writeln!(
+                text,
+                "rummikub_solve_seconds_sum{{outcome=\"{}\"}} {}",
+                outcome, histogram.sum
+            );
+            let _ = writeln!(
+                text,
+                "rummikub_solve_seconds_count{{outcome=\"{}\"}} {}",
+                outcome, histogram.count
+            );
+        }
+
+        let values = [
+            (
+                "rummikub_solve_timeouts_total",
+                "counter",
+                "Solves given up after the timeout.",
+                counts.timeouts,
+            ),
+            (
+                "rummikub_solver_nodes_total",
+                "counter",
+                "Positions visited by the solver.",
+                SEARCH.nodes.load(Ordering::Relaxed),
+            ),
+            (
+                "rummikub_solver_memo_hits_total",
+                "counter",
+                "Positions the solver found in its memo.",
+                SEARCH.memo_hits.load(Ordering::Relaxed),
+            ),
+            (
+                "rummikub_queue_length",
+                "gauge",
+                "Solves waiting for a worker.",
+                gauges.queued as u64,
+            ),
+            (
+                "rummikub_direct_solves_active",
+                "gauge",
+                "Streamed and session solves running.",
+                gauges.direct as u64,
+            ),
+            (
+                "rummikub_sessions",
+                "gauge",
+                "Sessions of API clients.",
+                gauges.sessions as u64,
+            ),
+            (
+                "rummikub_lobbies",
+                "gauge",
+                "Games of humans against bots.",
+                gauges.lobbies as u64,
+            ),
+            (
+                "rummikub_workers",
+                "gauge",
+                "Solves that can run at the same time.",
+                gauges.workers as u64,
+            ),
+        ];
+        for (name, kind, help, value) in values {
+            header(&mut text, name, kind, help);
+            let _ = writeln!(text, "{} {}", name, value);
+        }
+        text
+    }
+}
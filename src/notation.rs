@@ -0,0 +1,243 @@
+use crate::diagnostic::{self, Span};
+use crate::i18n;
+use crate::solver::{Inventory, Set, Tile};
+use crate::validator;
+use color_eyre::eyre::{bail, eyre, Report, Result};
+use std::fmt;
+use std::sync::OnceLock;
+
+// Letters and names of the four colors, in color index order. Standard sets
+// are red, blue, orange and black, other editions can be set up in the rules
+// file. Without names the translated standard names are used.
+#[derive(Clone, Debug)]
+pub struct Palette {
+    pub letters: [char; 4],
+    pub names: Option<[String; 4]>,
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette {
+            letters: ['R', 'B', 'O', 'K'],
+            names: None,
+        }
+    }
+}
+
+static PALETTE: OnceLock<Palette> = OnceLock::new();
+
+// Used by every tile that is parsed or printed afterwards, so it has to be set
+// before any tiles are
+pub fn set_palette(palette: Palette) {
+    let _ = PALETTE.set(palette);
+}
+
+pub fn palette() -> &'static Palette {
+    PALETTE.get_or_init(Palette::default)
+}
+
+pub fn color_letter(color: u8) -> char {
+    palette()
+        .letters
+        .get(color as usize)
+        .copied()
+        .unwrap_or('?')
+}
+
+pub fn color_name(color: u8) -> String {
+    match &palette().names {
+        Some(names) => names
+            .get(color as usize)
+            .cloned()
+            .unwrap_or_else(|| "?".to_string()),
+        None => i18n::color_name(color).to_string(),
+    }
+}
+
+impl fmt::Display for Tile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_joker {
+            write!(f, "J")
+        } else {
+            write!(f, "{}{}", color_letter(self.color), self.number)
+        }
+    }
+}
+
+impl fmt::Display for Set {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", tiles_text(&self.tiles))
+    }
+}
+
+// Tiles separated by spaces, like a set
+pub fn tiles_text(tiles: &[Tile]) -> String {
+    tiles
+        .iter()
+        .map(Tile::to_string)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// A single tile such as `R5`, `k13` or `J` for a joker. Jokers are returned
+// without a color or number, those depend on the set they end up in.
+pub fn parse_tile(text: &str) -> Result<Tile> {
+    let mut chars = text.chars();
+    let letter = chars
+        .next()
+        .ok_or_else(|| eyre!("empty tile"))?
+        .to_ascii_uppercase();
+
+    if letter == 'J' && chars.as_str().is_empty() {
+        return Ok(Tile {
+            color: 0,
+            number: 0,
+            is_joker: true,
+        });
+    }
+
+    let Some(color) = palette().letters.iter().position(|&c| c == letter) else {
+        bail!("unknown color in tile {}", text);
+    };
+    match chars.as_str().parse::<u8>() {
+        Ok(number) if (1..=13).contains(&number) => Ok(Tile {
+            color: color as u8,
+            number,
+            is_joker: false,
+        }),
+        _ => bail!("tile {} needs a number from 1 to 13", text),
+    }
+}
+
+// The tokens of tiles separated by spaces or commas, slices of `text`
+fn tokens(text: &str) -> impl Iterator<Item = &str> + '_ {
+    text.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|token| !token.is_empty())
+}
+
+// A token of `text`, with the span of the token in `text` for an error
+fn parse_token(text: &str, token: &str) -> Result<Tile> {
+    parse_tile(token).map_err(|error| {
+        diagnostic::report(
+            "unknown_tile",
+            error.to_string(),
+            Span::of(text, token).into_iter().collect(),
+        )
+    })
+}
+
+// Tiles separated by spaces or commas
+pub fn parse_tiles(text: &str) -> Result<Vec<Tile>> {
+    tokens(text).map(|token| parse_token(text, token)).collect()
+}
+
+// A set such as `R5 J R7`, with the jokers assigned to the tiles they replace.
+// Illegal sets are reported together with the closest legal ones.
+pub fn parse_set(text: &str) -> Result<Set> {
+    let tiles = parse_tiles(text)?;
+    validator::complete_set(&tiles).map_err(|error| {
+        let suggestions = validator::suggest_fixes(&tiles)
+            .iter()
+            .take(3)
+            .filter_map(|fix| {
+                let fixed = validator::complete_set(&fix.apply(&tiles)).ok()?;
+                Some(format!("{} ({})", fixed, fix.describe(&tiles)))
+            })
+            .collect::<Vec<String>>();
+        let message = if suggestions.is_empty() {
+            error.to_string()
+        } else {
+            format!("{}, did you mean {}", error, suggestions.join(" or "))
+        };
+        diagnostic::report(
+            "illegal_set",
+            message,
+            Span::of(text, text.trim()).into_iter().collect(),
+        )
+    })
+}
+
+pub fn parse_rack(text: &str) -> Result<Inventory> {
+    let mut rack = Inventory::new(0);
+    for tile in parse_tiles(text)? {
+        rack.add_tile(&tile);
+    }
+    Ok(rack)
+}
+
+// A rack with `?` for tiles that are there but cannot be read, returned with
+// the number of them
+pub fn parse_rack_with_ghosts(text: &str) -> Result<(Inventory, u32)> {
+    let mut rack = Inventory::new(0);
+    let mut ghosts = 0;
+    for token in tokens(text) {
+        match token {
+            "?" => ghosts += 1,
+            token => rack.add_tile(&parse_token(text, token)?),
+        }
+    }
+    Ok((rack, ghosts))
+}
+
+// Text around a pasted set that is not part of it: numbering such as `3:` or
+// `3.`, list bullets and the brackets sets are shown in
+fn strip_decoration(text: &str) -> &str {
+    let text = text.trim();
+    let text = match text.split_once([':', '.']) {
+        Some((number, rest)) if number.trim().parse::<u32>().is_ok() => rest.trim(),
+        _ => text,
+    };
+    let text = text.trim_start_matches(['-', '*', '•']).trim();
+    text.strip_prefix('[')
+        .and_then(|text| text.strip_suffix(']'))
+        .unwrap_or(text)
+        .trim()
+}
+
+// Sets separated by semicolons or line breaks, as typed or pasted. Every
+// malformed set is reported, not just the first.
+pub fn parse_table(text: &str) -> Result<Vec<Set>> {
+    let mut table = Vec::new();
+    let mut errors = Vec::new();
+    for (line_index, line) in text.lines().enumerate() {
+        let sets = line
+            .split(';')
+            .map(strip_decoration)
+            .filter(|set| !set.is_empty())
+            .collect::<Vec<&str>>();
+        for (set_index, set) in sets.iter().enumerate() {
+            let offset = Span::of(text, set).map_or(0, |span| span.start);
+            match parse_set(set) {
+                Ok(set) => table.push(set),
+                Err(error) if sets.len() > 1 => errors.push(diagnostic::within(
+                    error,
+                    &format!("line {}, set {}", line_index + 1, set_index + 1),
+                    offset,
+                )),
+                Err(error) => errors.push(diagnostic::within(
+                    error,
+                    &format!("line {}", line_index + 1),
+                    offset,
+                )),
+            }
+        }
+    }
+
+    match errors.len() {
+        0 => Ok(table),
+        1 => Err(errors.remove(0)),
+        count => Err(diagnostic::report(
+            "malformed_table",
+            format!(
+                "{} malformed sets in the table:\n  {}",
+                count,
+                errors
+                    .iter()
+                    .map(Report::to_string)
+                    .collect::<Vec<_>>()
+                    .join("\n  ")
+            ),
+            errors.iter().flat_map(diagnostic::spans).collect(),
+        )),
+    }
+}
@@ -0,0 +1,107 @@
+use crate::cli::Args;
+use color_eyre::eyre::{bail, Result};
+use std::io::{self, Write};
+use std::process::Command;
+use std::sync::OnceLock;
+
+// Things worth knowing about while the terminal is in the background. Only the
+// terminal modes send them: `serve` has nobody at its terminal, its page
+// notifies the browser when a solve is done and lobby clients get `your_turn`
+// and the tiles of every player with each answer.
+pub enum Event {
+    // A hot-seat game waits for the next player
+    YourTurn,
+    // A player is down to the last tile
+    OneTile,
+    // The assistant found its move
+    SolverFinished,
+}
+
+// Names of the events in `--notify`, in the order of Event
+const EVENTS: [&str; 3] = ["your-turn", "one-tile", "solver-finished"];
+
+#[derive(Clone, Copy)]
+enum Hook {
+    Off,
+    // The terminal bell, which most terminals turn into a sound or a flash
+    Bell,
+    // notify-send, or osascript on macOS, falling back to the bell
+    Desktop,
+}
+
+impl Hook {
+    fn parse(text: &str) -> Result<Hook> {
+        match text {
+            "off" => Ok(Hook::Off),
+            "bell" => Ok(Hook::Bell),
+            "desktop" => Ok(Hook::Desktop),
+            _ => bail!(
+                "unknown notification {}, expected off, bell or desktop",
+                text
+            ),
+        }
+    }
+}
+
+// The hook of every event, in the order of Event
+static HOOKS: OnceLock<[Hook; 3]> = OnceLock::new();
+
+// `--notify` lists hooks separated by commas, each for one event such as
+// `one-tile=desktop` or for every event such as `bell`, later ones winning:
+// `--notify bell,solver-finished=off`. Nothing is sent without it.
+pub fn init(args: &[String]) -> Result<()> {
+    let mut hooks = [Hook::Off; 3];
+    if let Some(list) = Args::new(args).value("--notify") {
+        for entry in list.split(',').map(str::trim) {
+            match entry.split_once('=') {
+                Some((name, hook)) => match EVENTS.iter().position(|event| *event == name) {
+                    Some(index) => hooks[index] = Hook::parse(hook)?,
+                    None => bail!(
+                        "unknown event {} in --notify, expected one of {}",
+                        name,
+                        EVENTS.join(", ")
+                    ),
+                },
+                None => hooks = [Hook::parse(entry)?; 3],
+            }
+        }
+    }
+    let _ = HOOKS.set(hooks);
+    Ok(())
+}
+
+fn bell() {
+    print!("\x07");
+    let _ = io::stdout().flush();
+}
+
+fn desktop(message: &str) -> bool {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        let script = format!(
+            "display notification \"{}\" with title \"RummikubBot\"",
+            message.replace('"', "'")
+        );
+        command.args(["-e", &script]);
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.args(["RummikubBot", message]);
+        command
+    };
+    command.output().is_ok_and(|output| output.status.success())
+}
+
+// Sends the notification the event is set up for, if any. Failures are not
+// errors, a missed notification should not end a game.
+pub fn send(event: Event, message: &str) {
+    match HOOKS.get().map_or(Hook::Off, |hooks| hooks[event as usize]) {
+        Hook::Off => {}
+        Hook::Bell => bell(),
+        Hook::Desktop => {
+            if !desktop(message) {
+                bell();
+            }
+        }
+    }
+}
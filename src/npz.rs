@@ -0,0 +1,186 @@
+#[cfg(feature = "ml")]
+use std::fs;
+#[cfg(feature = "ml")]
+use std::io;
+
+// Writer for NumPy .npz archives: a zip file of .npy arrays, stored without
+// compression. `numpy.load` reads them as they are. Only the CRC is needed
+// without the `ml` feature, for PNG pictures.
+
+// Element types the exporters use, as numpy type strings
+#[cfg(feature = "ml")]
+#[derive(Clone, Copy)]
+pub enum Dtype {
+    U8,
+    I8,
+    I32,
+}
+
+#[cfg(feature = "ml")]
+impl Dtype {
+    fn descr(&self) -> &str {
+        match self {
+            Dtype::U8 => "|u1",
+            Dtype::I8 => "|i1",
+            Dtype::I32 => "<i4",
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Dtype::U8 | Dtype::I8 => 1,
+            Dtype::I32 => 4,
+        }
+    }
+}
+
+// C-ordered array with the first dimension growing as rows are pushed
+#[cfg(feature = "ml")]
+pub struct Array {
+    name: String,
+    dtype: Dtype,
+    row_shape: Vec<usize>,
+    rows: usize,
+    data: Vec<u8>,
+}
+
+#[cfg(feature = "ml")]
+impl Array {
+    pub fn new(name: &str, dtype: Dtype, row_shape: &[usize]) -> Array {
+        Array {
+            name: name.to_string(),
+            dtype,
+            row_shape: row_shape.to_vec(),
+            rows: 0,
+            data: Vec::new(),
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    fn row_len(&self) -> usize {
+        self.row_shape.iter().product()
+    }
+
+    // Values are stored as the array's dtype, so they have to fit it
+    pub fn push(&mut self, row: &[i32]) {
+        assert_eq!(
+            row.len(),
+            self.row_len(),
+            "row of {} has the wrong length",
+            self.name
+        );
+        for &value in row {
+            match self.dtype {
+                Dtype::U8 | Dtype::I8 => self.data.push(value as u8),
+                Dtype::I32 => self.data.extend(value.to_le_bytes()),
+            }
+        }
+        self.rows += 1;
+    }
+
+    // Contents of the .npy file, format version 1.0
+    fn to_npy(&self) -> Vec<u8> {
+        let shape = std::iter::once(self.rows)
+            .chain(self.row_shape.iter().copied())
+            .map(|size| size.to_string())
+            .collect::<Vec<String>>();
+        let shape = match shape.len() {
+            1 => format!("({},)", shape[0]),
+            _ => format!("({})", shape.join(", ")),
+        };
+        let mut header = format!(
+            "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+            self.dtype.descr(),
+            shape
+        );
+        // Magic, version and header length take 10 bytes, the data starts
+        // 64 byte aligned after the newline ending the header
+        while (10 + header.len() + 1) % 64 != 0 {
+            header.push(' ');
+        }
+        header.push('\n');
+
+        let mut npy = b"\x93NUMPY\x01\x00".to_vec();
+        npy.extend((header.len() as u16).to_le_bytes());
+        npy.extend(header.as_bytes());
+        debug_assert_eq!(
+            self.data.len(),
+            self.rows * self.row_len() * self.dtype.size()
+        );
+        npy.extend(&self.data);
+        npy
+    }
+}
+
+// CRC-32 as used by zip and PNG
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(feature = "ml")]
+pub fn write(path: &str, arrays: &[Array]) -> io::Result<()> {
+    // 1980-01-01, the earliest date zip files can hold
+    const DOS_DATE: u16 = (1 << 5) | 1;
+
+    let mut zip = Vec::new();
+    let mut directory = Vec::new();
+    for array in arrays {
+        let name = format!("{}.npy", array.name);
+        let data = array.to_npy();
+        let crc = crc32(&data);
+        let offset = zip.len() as u32;
+
+        // Fields shared by the local header and the central directory entry:
+        // version needed, flags, stored method, time, date, crc and sizes
+        let mut common = Vec::new();
+        common.extend(20u16.to_le_bytes());
+        common.extend(0u16.to_le_bytes());
+        common.extend(0u16.to_le_bytes());
+        common.extend(0u16.to_le_bytes());
+        common.extend(DOS_DATE.to_le_bytes());
+        common.extend(crc.to_le_bytes());
+        common.extend((data.len() as u32).to_le_bytes());
+        common.extend((data.len() as u32).to_le_bytes());
+        common.extend((name.len() as u16).to_le_bytes());
+        common.extend(0u16.to_le_bytes());
+
+        zip.extend(0x0403_4b50u32.to_le_bytes());
+        zip.extend(&common);
+        zip.extend(name.as_bytes());
+        zip.extend(&data);
+
+        directory.extend(0x0201_4b50u32.to_le_bytes());
+        directory.extend(20u16.to_le_bytes());
+        directory.extend(&common);
+        // Comment length, disk, internal and external attributes
+        directory.extend([0; 10]);
+        directory.extend(offset.to_le_bytes());
+        directory.extend(name.as_bytes());
+    }
+
+    let directory_offset = zip.len() as u32;
+    zip.extend(&directory);
+    zip.extend(0x0605_4b50u32.to_le_bytes());
+    zip.extend([0; 4]);
+    zip.extend((arrays.len() as u16).to_le_bytes());
+    zip.extend((arrays.len() as u16).to_le_bytes());
+    zip.extend((directory.len() as u32).to_le_bytes());
+    zip.extend(directory_offset.to_le_bytes());
+    zip.extend(0u16.to_le_bytes());
+
+    fs::write(path, zip)
+}
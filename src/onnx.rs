@@ -0,0 +1,963 @@
+// Strategies guided by a model in the ONNX format, trained outside this crate
+// on the data of `selfplay`. Only what a small network needs is read and run
+// here, so the feature adds no dependencies: the protobuf of the file is
+// decoded by hand and the graph is run operator by operator on float tensors.
+//
+// The model scores moves. Its input is one row of INPUT_LEN numbers per move
+// worth considering: the position before the move as src/encoding.rs lays it
+// out and selfplay exports it (rack, table, unseen, opponents, bag, opened),
+// followed by the 53 tile counts the move lays out, all 0 for drawing, which
+// is the `played` array of the export. Its first output holds a score for
+// every row, such as the expected `outcome` of the game after the move, and
+// the move with the highest score is made. All rows go in one batch, so the
+// first dimension of the input should be dynamic.
+//
+// Supported operators: Gemm, MatMul, Add, Sub, Mul, Relu, LeakyRelu, Sigmoid,
+// Tanh, Softmax, Flatten, Reshape, Identity and Constant, on tensors of
+// floats, doubles and integers. That covers the multilayer perceptrons that
+// e.g. `torch.onnx.export` writes for a stack of Linear layers and
+// activations. A model with any other operator is refused when it is loaded.
+
+use crate::encoding::{self, ENCODING_VERSION, POSITION_LEN, TILE_TYPES};
+use crate::solver;
+use crate::strategy::{Move, Observation, Strategy};
+use color_eyre::eyre::{bail, eyre, Report, Result};
+use std::collections::HashMap;
+use std::fs;
+
+// Numbers of one input row, the position and the move
+pub const INPUT_LEN: usize = POSITION_LEN + TILE_TYPES;
+
+const OPERATORS: [&str; 14] = [
+    "Gemm",
+    "MatMul",
+    "Add",
+    "Sub",
+    "Mul",
+    "Relu",
+    "LeakyRelu",
+    "Sigmoid",
+    "Tanh",
+    "Softmax",
+    "Flatten",
+    "Reshape",
+    "Identity",
+    "Constant",
+];
+
+// Data types of TensorProto
+const FLOAT: u64 = 1;
+const INT32: u64 = 6;
+const INT64: u64 = 7;
+const DOUBLE: u64 = 11;
+
+// Row-major numbers with their shape, whatever type the file stores
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tensor {
+    pub shape: Vec<usize>,
+    pub data: Vec<f32>,
+}
+
+impl Tensor {
+    pub fn new(shape: Vec<usize>, data: Vec<f32>) -> Result<Tensor> {
+        if shape.iter().product::<usize>() != data.len() {
+            bail!(
+                "a tensor of shape {:?} cannot hold {} numbers",
+                shape,
+                data.len()
+            );
+        }
+        Ok(Tensor { shape, data })
+    }
+
+    fn map(&self, f: impl Fn(f32) -> f32) -> Tensor {
+        Tensor {
+            shape: self.shape.clone(),
+            data: self.data.iter().map(|&value| f(value)).collect(),
+        }
+    }
+
+    fn matrix(&self, op: &str) -> Result<(usize, usize)> {
+        match self.shape[..] {
+            [rows, columns] => Ok((rows, columns)),
+            _ => bail!("{} needs matrices, got shape {:?}", op, self.shape),
+        }
+    }
+}
+
+// One field of a protobuf message
+enum Field<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+fn malformed(what: &str) -> Report {
+    eyre!("the model is not a valid ONNX file, {}", what)
+}
+
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if len > bytes.len() {
+        return Err(malformed("it ends early"));
+    }
+    let (taken, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(taken)
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = take(bytes, 1)?[0];
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(malformed("a number is too long"))
+}
+
+// The fields of a message by number, in the order of the file
+fn fields(mut bytes: &[u8]) -> Result<Vec<(u64, Field<'_>)>> {
+    let mut fields = Vec::new();
+    while !bytes.is_empty() {
+        let key = read_varint(&mut bytes)?;
+        let field = match key & 7 {
+            0 => Field::Varint(read_varint(&mut bytes)?),
+            1 => Field::Fixed64(u64::from_le_bytes(take(&mut bytes, 8)?.try_into()?)),
+            2 => {
+                let len = usize::try_from(read_varint(&mut bytes)?)?;
+                Field::Bytes(take(&mut bytes, len)?)
+            }
+            5 => Field::Fixed32(u32::from_le_bytes(take(&mut bytes, 4)?.try_into()?)),
+            wire => {
+                return Err(malformed(&format!(
+                    "field {} has wire type {}",
+                    key >> 3,
+                    wire
+                )))
+            }
+        };
+        fields.push((key >> 3, field));
+    }
+    Ok(fields)
+}
+
+impl<'a> Field<'a> {
+    fn varint(&self) -> Result<u64> {
+        match self {
+            Field::Varint(value) => Ok(*value),
+            _ => Err(malformed("a number is stored as something else")),
+        }
+    }
+
+    fn bytes(&self) -> Result<&'a [u8]> {
+        match self {
+            Field::Bytes(bytes) => Ok(bytes),
+            _ => Err(malformed("a message is stored as something else")),
+        }
+    }
+
+    fn string(&self) -> Result<String> {
+        Ok(String::from_utf8(self.bytes()?.to_vec())?)
+    }
+
+    // Repeated integers, one per field or packed
+    fn varints(&self) -> Result<Vec<i64>> {
+        match self {
+            Field::Varint(value) => Ok(vec![*value as i64]),
+            Field::Bytes(mut bytes) => {
+                let mut values = Vec::new();
+                while !bytes.is_empty() {
+                    values.push(read_varint(&mut bytes)? as i64);
+                }
+                Ok(values)
+            }
+            _ => Err(malformed("integers are stored as something else")),
+        }
+    }
+
+    // Repeated floats, one per field or packed
+    fn floats(&self) -> Result<Vec<f32>> {
+        match self {
+            Field::Fixed32(bits) => Ok(vec![f32::from_bits(*bits)]),
+            Field::Bytes(bytes) => little_endian(bytes, f32::from_le_bytes),
+            _ => Err(malformed("floats are stored as something else")),
+        }
+    }
+
+    fn doubles(&self) -> Result<Vec<f32>> {
+        match self {
+            Field::Fixed64(bits) => Ok(vec![f64::from_bits(*bits) as f32]),
+            Field::Bytes(bytes) => {
+                little_endian(bytes, |chunk: [u8; 8]| f64::from_le_bytes(chunk) as f32)
+            }
+            _ => Err(malformed("doubles are stored as something else")),
+        }
+    }
+}
+
+fn little_endian<const N: usize>(
+    bytes: &[u8],
+    number: impl Fn([u8; N]) -> f32,
+) -> Result<Vec<f32>> {
+    if !bytes.len().is_multiple_of(N) {
+        return Err(malformed("numbers of a tensor are cut off"));
+    }
+    bytes
+        .chunks_exact(N)
+        .map(|chunk| Ok(number(chunk.try_into()?)))
+        .collect()
+}
+
+// A TensorProto with its name
+fn parse_tensor(bytes: &[u8]) -> Result<(String, Tensor)> {
+    let mut name = String::new();
+    let mut dims = Vec::new();
+    let mut data_type = 0;
+    let mut raw = None;
+    let mut numbers = Vec::new();
+    for (number, field) in fields(bytes)? {
+        match number {
+            1 => dims.extend(field.varints()?),
+            2 => data_type = field.varint()?,
+            4 => numbers.extend(field.floats()?),
+            5 | 7 => numbers.extend(field.varints()?.into_iter().map(|value| value as f32)),
+            8 => name = field.string()?,
+            9 => raw = Some(field.bytes()?),
+            10 => numbers.extend(field.doubles()?),
+            _ => {}
+        }
+    }
+    let data = match (data_type, raw) {
+        (FLOAT, Some(raw)) => little_endian(raw, f32::from_le_bytes)?,
+        (DOUBLE, Some(raw)) => little_endian(raw, |chunk| f64::from_le_bytes(chunk) as f32)?,
+        (INT32, Some(raw)) => little_endian(raw, |chunk| i32::from_le_bytes(chunk) as f32)?,
+        (INT64, Some(raw)) => little_endian(raw, |chunk| i64::from_le_bytes(chunk) as f32)?,
+        (FLOAT | DOUBLE | INT32 | INT64, None) => numbers,
+        (other, _) => bail!(
+            "tensor {} has data type {}, only floats, doubles and integers are supported",
+            name,
+            other
+        ),
+    };
+    let shape = dims
+        .into_iter()
+        .map(|dim| usize::try_from(dim).map_err(|_| malformed("a tensor has a negative size")))
+        .collect::<Result<Vec<usize>>>()?;
+    let tensor = Tensor::new(shape, data).map_err(|report| eyre!("tensor {}: {}", name, report))?;
+    Ok((name, tensor))
+}
+
+// The name of a ValueInfoProto and the sizes of its tensor, None for the
+// dynamic ones
+fn parse_value_info(bytes: &[u8]) -> Result<(String, Vec<Option<u64>>)> {
+    // Field of the message `bytes` with the number, if there is one
+    fn nested(bytes: &[u8], wanted: u64) -> Result<Option<&[u8]>> {
+        for (number, field) in fields(bytes)? {
+            if number == wanted {
+                return field.bytes().map(Some);
+            }
+        }
+        Ok(None)
+    }
+    let mut name = String::new();
+    let mut dims = Vec::new();
+    for (number, field) in fields(bytes)? {
+        match number {
+            1 => name = field.string()?,
+            // TypeProto, its tensor_type and that one's shape
+            2 => {
+                let shape = match nested(field.bytes()?, 1)? {
+                    Some(tensor_type) => nested(tensor_type, 2)?,
+                    None => None,
+                };
+                for (number, dim) in fields(shape.unwrap_or_default())? {
+                    if number == 1 {
+                        let value = fields(dim.bytes()?)?
+                            .into_iter()
+                            .find(|(number, _)| *number == 1)
+                            .map(|(_, value)| value.varint())
+                            .transpose()?;
+                        dims.push(value);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok((name, dims))
+}
+
+// The attributes of a node that the operators here read
+#[derive(Default)]
+struct Attribute {
+    float: Option<f32>,
+    int: Option<i64>,
+    tensor: Option<Tensor>,
+}
+
+fn parse_attribute(bytes: &[u8]) -> Result<(String, Attribute)> {
+    let mut name = String::new();
+    let mut attribute = Attribute::default();
+    for (number, field) in fields(bytes)? {
+        match number {
+            1 => name = field.string()?,
+            2 => attribute.float = field.floats()?.first().copied(),
+            3 => attribute.int = Some(field.varint()? as i64),
+            5 => attribute.tensor = Some(parse_tensor(field.bytes()?)?.1),
+            _ => {}
+        }
+    }
+    Ok((name, attribute))
+}
+
+struct Node {
+    op: String,
+    inputs: Vec<String>,
+    output: String,
+    attributes: HashMap<String, Attribute>,
+}
+
+fn parse_node(bytes: &[u8]) -> Result<Node> {
+    let mut op = String::new();
+    let mut inputs = Vec::new();
+    let mut outputs = Vec::new();
+    let mut attributes = HashMap::new();
+    for (number, field) in fields(bytes)? {
+        match number {
+            1 => inputs.push(field.string()?),
+            2 => outputs.push(field.string()?),
+            4 => op = field.string()?,
+            5 => {
+                let (name, attribute) = parse_attribute(field.bytes()?)?;
+                attributes.insert(name, attribute);
+            }
+            _ => {}
+        }
+    }
+    if !OPERATORS.contains(&op.as_str()) {
+        bail!(
+            "the model uses the operator {}, supported are {}",
+            op,
+            OPERATORS.join(", ")
+        );
+    }
+    let Some(output) = outputs.into_iter().next() else {
+        bail!("a {} node of the model has no output", op);
+    };
+    Ok(Node {
+        op,
+        inputs,
+        output,
+        attributes,
+    })
+}
+
+// The numbers of `a` and `b` combined element by element, with the smaller
+// one repeated along the axes it lacks or has a size of 1 in, as numpy does
+fn broadcast(a: &Tensor, b: &Tensor, op: impl Fn(f32, f32) -> f32) -> Result<Tensor> {
+    let rank = a.shape.len().max(b.shape.len());
+    let padded = |tensor: &Tensor| {
+        let mut dims = vec![1; rank - tensor.shape.len()];
+        dims.extend(&tensor.shape);
+        dims
+    };
+    let (dims_a, dims_b) = (padded(a), padded(b));
+    let mut shape = Vec::with_capacity(rank);
+    for (&x, &y) in dims_a.iter().zip(&dims_b) {
+        shape.push(match (x, y) {
+            (x, y) if x == y => x,
+            (1, y) => y,
+            (x, 1) => x,
+            _ => bail!("shapes {:?} and {:?} do not broadcast", a.shape, b.shape),
+        });
+    }
+    // Steps through the numbers for every axis, none along a repeated one
+    let strides = |dims: &[usize]| {
+        let mut strides = vec![0; rank];
+        let mut step = 1;
+        for axis in (0..rank).rev() {
+            strides[axis] = if dims[axis] == 1 { 0 } else { step };
+            step *= dims[axis];
+        }
+        strides
+    };
+    let (strides_a, strides_b) = (strides(&dims_a), strides(&dims_b));
+    let len = shape.iter().product();
+    let mut data = Vec::with_capacity(len);
+    for flat in 0..len {
+        let (mut rest, mut index_a, mut index_b) = (flat, 0, 0);
+        for axis in (0..rank).rev() {
+            let coordinate = rest % shape[axis];
+            rest /= shape[axis];
+            index_a += coordinate * strides_a[axis];
+            index_b += coordinate * strides_b[axis];
+        }
+        data.push(op(a.data[index_a], b.data[index_b]));
+    }
+    Ok(Tensor { shape, data })
+}
+
+// alpha * A * B + beta * C, with A and B transposed first if asked
+fn gemm(
+    a: &Tensor,
+    b: &Tensor,
+    c: Option<&Tensor>,
+    (alpha, beta): (f32, f32),
+    (transpose_a, transpose_b): (bool, bool),
+) -> Result<Tensor> {
+    let (rows_a, columns_a) = a.matrix("Gemm")?;
+    let (rows_b, columns_b) = b.matrix("Gemm")?;
+    let (m, k) = match transpose_a {
+        true => (columns_a, rows_a),
+        false => (rows_a, columns_a),
+    };
+    let (inner, n) = match transpose_b {
+        true => (columns_b, rows_b),
+        false => (rows_b, columns_b),
+    };
+    if k != inner {
+        bail!(
+            "cannot multiply matrices of shape {:?} and {:?}, the model expects rows of {} numbers",
+            a.shape,
+            b.shape,
+            if transpose_b { columns_b } else { rows_b }
+        );
+    }
+    let at = |row: usize, column: usize| match transpose_a {
+        true => a.data[column * columns_a + row],
+        false => a.data[row * columns_a + column],
+    };
+    let bt = |row: usize, column: usize| match transpose_b {
+        true => b.data[column * columns_b + row],
+        false => b.data[row * columns_b + column],
+    };
+    let mut data = Vec::with_capacity(m * n);
+    for row in 0..m {
+        for column in 0..n {
+            let sum = (0..k).map(|p| at(row, p) * bt(p, column)).sum::<f32>();
+            data.push(alpha * sum);
+        }
+    }
+    let product = Tensor {
+        shape: vec![m, n],
+        data,
+    };
+    match c {
+        Some(c) => {
+            let sum = broadcast(&product, &c.map(|value| beta * value), |x, y| x + y)?;
+            if sum.shape != product.shape {
+                bail!("Gemm cannot add {:?} to {:?}", c.shape, product.shape);
+            }
+            Ok(sum)
+        }
+        None => Ok(product),
+    }
+}
+
+// The axis of the attribute, counted from the end when negative
+fn axis(value: i64, rank: usize, op: &str) -> Result<usize> {
+    let axis = if value < 0 {
+        value + rank as i64
+    } else {
+        value
+    };
+    usize::try_from(axis)
+        .ok()
+        .filter(|&axis| axis <= rank)
+        .ok_or_else(|| eyre!("{} has axis {} for a tensor of rank {}", op, value, rank))
+}
+
+fn softmax(input: &Tensor, axis: usize) -> Result<Tensor> {
+    if axis >= input.shape.len() {
+        bail!("Softmax has axis {} for shape {:?}", axis, input.shape);
+    }
+    let len = input.shape[axis];
+    let inner = input.shape[axis + 1..].iter().product::<usize>();
+    let mut output = input.clone();
+    for start in (0..input.data.len()).step_by((len * inner).max(1)) {
+        for offset in 0..inner {
+            let indices = (0..len).map(|step| start + step * inner + offset);
+            let max = indices
+                .clone()
+                .map(|index| input.data[index])
+                .fold(f32::NEG_INFINITY, f32::max);
+            let sum = indices
+                .clone()
+                .map(|index| (input.data[index] - max).exp())
+                .sum::<f32>();
+            for index in indices {
+                output.data[index] = (input.data[index] - max).exp() / sum;
+            }
+        }
+    }
+    Ok(output)
+}
+
+// The shape of Reshape: 0 keeps the size of the input, -1 takes what is left
+fn reshape(input: &Tensor, shape: &Tensor) -> Result<Tensor> {
+    let mut dims = Vec::with_capacity(shape.data.len());
+    let mut inferred = None;
+    for (index, &value) in shape.data.iter().enumerate() {
+        match value as i64 {
+            0 => dims.push(*input.shape.get(index).unwrap_or(&0)),
+            -1 if inferred.is_none() => {
+                inferred = Some(index);
+                dims.push(1);
+            }
+            size if size > 0 => dims.push(size as usize),
+            _ => bail!("Reshape to {:?} is not a shape", shape.data),
+        }
+    }
+    if let Some(index) = inferred {
+        let known = dims.iter().product::<usize>().max(1);
+        dims[index] = input.data.len() / known;
+    }
+    Tensor::new(dims, input.data.clone())
+}
+
+impl Node {
+    fn float(&self, name: &str, default: f32) -> f32 {
+        self.attributes
+            .get(name)
+            .and_then(|attribute| attribute.float)
+            .unwrap_or(default)
+    }
+
+    fn int(&self, name: &str, default: i64) -> i64 {
+        self.attributes
+            .get(name)
+            .and_then(|attribute| attribute.int)
+            .unwrap_or(default)
+    }
+
+    fn apply(&self, inputs: &[Option<&Tensor>]) -> Result<Tensor> {
+        let input = |index: usize| {
+            inputs
+                .get(index)
+                .copied()
+                .flatten()
+                .ok_or_else(|| eyre!("{} is missing its input {}", self.op, index + 1))
+        };
+        match self.op.as_str() {
+            "Gemm" => gemm(
+                input(0)?,
+                input(1)?,
+                inputs.get(2).copied().flatten(),
+                (self.float("alpha", 1.0), self.float("beta", 1.0)),
+                (self.int("transA", 0) != 0, self.int("transB", 0) != 0),
+            ),
+            "MatMul" => gemm(input(0)?, input(1)?, None, (1.0, 1.0), (false, false)),
+            "Add" => broadcast(input(0)?, input(1)?, |x, y| x + y),
+            "Sub" => broadcast(input(0)?, input(1)?, |x, y| x - y),
+            "Mul" => broadcast(input(0)?, input(1)?, |x, y| x * y),
+            "Relu" => Ok(input(0)?.map(|x| x.max(0.0))),
+            "LeakyRelu" => {
+                let alpha = self.float("alpha", 0.01);
+                Ok(input(0)?.map(|x| if x < 0.0 { alpha * x } else { x }))
+            }
+            "Sigmoid" => Ok(input(0)?.map(|x| 1.0 / (1.0 + (-x).exp()))),
+            "Tanh" => Ok(input(0)?.map(f32::tanh)),
+            "Softmax" => {
+                let input = input(0)?;
+                softmax(
+                    input,
+                    axis(self.int("axis", -1), input.shape.len(), "Softmax")?,
+                )
+            }
+            "Flatten" => {
+                let input = input(0)?;
+                let axis = axis(self.int("axis", 1), input.shape.len(), "Flatten")?;
+                let outer = input.shape[..axis].iter().product();
+                let inner = input.shape[axis..].iter().product();
+                Tensor::new(vec![outer, inner], input.data.clone())
+            }
+            "Reshape" => reshape(input(0)?, input(1)?),
+            "Identity" => Ok(input(0)?.clone()),
+            "Constant" => self
+                .attributes
+                .get("value")
+                .and_then(|attribute| attribute.tensor.clone())
+                .ok_or_else(|| eyre!("Constant without a tensor value")),
+            op => bail!("the operator {} is not supported", op),
+        }
+    }
+}
+
+// The graph of a model with its weights
+pub struct Model {
+    nodes: Vec<Node>,
+    weights: HashMap<String, Tensor>,
+    input: String,
+    // Sizes of the input, None for the dynamic ones
+    input_dims: Vec<Option<u64>>,
+    output: String,
+}
+
+impl Model {
+    pub fn load(path: &str) -> Result<Model> {
+        let bytes = fs::read(path).map_err(|error| eyre!("{}: {}", path, error))?;
+        Model::parse(&bytes).map_err(|report| eyre!("{}: {}", path, report))
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Model> {
+        // The graph of the ModelProto
+        let Some(graph) = fields(bytes)?
+            .into_iter()
+            .find(|(number, _)| *number == 7)
+            .map(|(_, graph)| graph.bytes())
+            .transpose()?
+        else {
+            bail!("the model has no graph");
+        };
+        let mut nodes = Vec::new();
+        let mut weights = HashMap::new();
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        for (number, field) in fields(graph)? {
+            match number {
+                1 => nodes.push(parse_node(field.bytes()?)?),
+                5 => {
+                    let (name, tensor) = parse_tensor(field.bytes()?)?;
+                    weights.insert(name, tensor);
+                }
+                11 => inputs.push(parse_value_info(field.bytes()?)?),
+                12 => outputs.push(parse_value_info(field.bytes()?)?.0),
+                _ => {}
+            }
+        }
+        // Older exporters list the weights among the inputs too
+        let Some((input, input_dims)) = inputs
+            .into_iter()
+            .find(|(name, _)| !weights.contains_key(name))
+        else {
+            bail!("the model has no input");
+        };
+        let Some(output) = outputs.into_iter().next() else {
+            bail!("the model has no output");
+        };
+        Ok(Model {
+            nodes,
+            weights,
+            input,
+            input_dims,
+            output,
+        })
+    }
+
+    // Runs the nodes in the order of the file, which ONNX requires to be one
+    // where every node comes after those it takes its inputs from
+    pub fn run(&self, input: Tensor) -> Result<Tensor> {
+        let mut values: HashMap<&str, Tensor> = HashMap::new();
+        values.insert(&self.input, input);
+        for node in &self.nodes {
+            let output = {
+                let inputs = node
+                    .inputs
+                    .iter()
+                    .map(|name| match name.as_str() {
+                        "" => Ok(None),
+                        name => values
+                            .get(name)
+                            .or_else(|| self.weights.get(name))
+                            .map(Some)
+                            .ok_or_else(|| {
+                                eyre!("{} takes {}, which nothing computes", node.op, name)
+                            }),
+                    })
+                    .collect::<Result<Vec<Option<&Tensor>>>>()?;
+                node.apply(&inputs)?
+            };
+            values.insert(&node.output, output);
+        }
+        values
+            .remove(self.output.as_str())
+            .ok_or_else(|| eyre!("nothing computes the output {} of the model", self.output))
+    }
+}
+
+// Makes the move the model scores highest among the candidate moves of the
+// solver and drawing
+pub struct Learned {
+    model: Model,
+    path: String,
+}
+
+impl Learned {
+    pub fn load(path: &str) -> Result<Learned> {
+        Learned::new(Model::load(path)?, path)
+    }
+
+    fn new(model: Model, path: &str) -> Result<Learned> {
+        if let Some(Some(len)) = model.input_dims.last() {
+            if *len != INPUT_LEN as u64 {
+                bail!(
+                    "{} takes rows of {} numbers, encoding version {} has {}",
+                    path,
+                    len,
+                    ENCODING_VERSION,
+                    INPUT_LEN
+                );
+            }
+        }
+        Ok(Learned {
+            model,
+            path: path.to_string(),
+        })
+    }
+}
+
+impl Strategy for Learned {
+    fn name(&self) -> String {
+        format!("onnx({})", self.path)
+    }
+
+    fn choose_move(&self, observation: &Observation) -> Result<Option<Move>> {
+        let Observation {
+            table,
+            rack,
+            opened,
+            rules,
+            ..
+        } = observation;
+        let mut moves = solver::candidate_moves(table, rack, *opened, rules)?
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<Option<Move>>>();
+        if !rules.must_play || moves.is_empty() {
+            moves.push(None);
+        }
+        if moves.len() == 1 {
+            return Ok(moves.pop().flatten());
+        }
+
+        let position = encoding::encode_observation(observation);
+        let mut rows = Vec::with_capacity(moves.len() * INPUT_LEN);
+        for chosen in &moves {
+            rows.extend(position.iter().map(|&value| value as f32));
+            rows.extend(
+                encoding::encode_move(rack, chosen.as_ref())
+                    .iter()
+                    .map(|&value| value as f32),
+            );
+        }
+        let scores = self
+            .model
+            .run(Tensor::new(vec![moves.len(), INPUT_LEN], rows)?)?;
+        if scores.data.is_empty() || scores.data.len() % moves.len() != 0 {
+            bail!(
+                "{} scored {} moves with {} numbers",
+                self.path,
+                moves.len(),
+                scores.data.len()
+            );
+        }
+        let per_move = scores.data.len() / moves.len();
+        // The first of the best scores, so ties go to the solver's order
+        let best = scores
+            .data
+            .iter()
+            .step_by(per_move)
+            .enumerate()
+            .fold(
+                None,
+                |best: Option<(usize, f32)>, (index, &score)| match best {
+                    Some((_, top)) if top >= score || score.is_nan() => best,
+                    _ => Some((index, score)),
+                },
+            )
+            .map_or(0, |(index, _)| index);
+        Ok(moves.swap_remove(best))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation;
+    use crate::rules::Ruleset;
+
+    // A protobuf writer for the few messages the tests need
+    fn varint(mut value: u64, out: &mut Vec<u8>) {
+        while value >= 0x80 {
+            out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn bytes(number: u64, bytes: &[u8], out: &mut Vec<u8>) {
+        varint(number << 3 | 2, out);
+        varint(bytes.len() as u64, out);
+        out.extend(bytes);
+    }
+
+    fn number(number: u64, value: u64, out: &mut Vec<u8>) {
+        varint(number << 3, out);
+        varint(value, out);
+    }
+
+    fn tensor(name: &str, dims: &[u64], values: &[f32]) -> Vec<u8> {
+        let mut out = Vec::new();
+        dims.iter().for_each(|&dim| number(1, dim, &mut out));
+        number(2, FLOAT, &mut out);
+        let packed = values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect::<Vec<u8>>();
+        bytes(4, &packed, &mut out);
+        bytes(8, name.as_bytes(), &mut out);
+        out
+    }
+
+    fn int_attribute(name: &str, value: i64) -> Vec<u8> {
+        let mut out = Vec::new();
+        bytes(1, name.as_bytes(), &mut out);
+        number(3, value as u64, &mut out);
+        out
+    }
+
+    fn node(op: &str, inputs: &[&str], output: &str, attributes: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = Vec::new();
+        inputs
+            .iter()
+            .for_each(|input| bytes(1, input.as_bytes(), &mut out));
+        bytes(2, output.as_bytes(), &mut out);
+        bytes(4, op.as_bytes(), &mut out);
+        attributes
+            .iter()
+            .for_each(|attribute| bytes(5, attribute, &mut out));
+        out
+    }
+
+    // A value info of float tensors with the sizes, None for dynamic ones
+    fn value_info(name: &str, dims: &[Option<u64>]) -> Vec<u8> {
+        let mut shape = Vec::new();
+        for dim in dims {
+            let mut size = Vec::new();
+            match dim {
+                Some(dim) => number(1, *dim, &mut size),
+                None => bytes(2, b"N", &mut size),
+            }
+            bytes(1, &size, &mut shape);
+        }
+        let mut tensor_type = Vec::new();
+        number(1, FLOAT, &mut tensor_type);
+        bytes(2, &shape, &mut tensor_type);
+        let mut type_proto = Vec::new();
+        bytes(1, &tensor_type, &mut type_proto);
+        let mut out = Vec::new();
+        bytes(1, name.as_bytes(), &mut out);
+        bytes(2, &type_proto, &mut out);
+        out
+    }
+
+    fn model(nodes: &[Vec<u8>], weights: &[Vec<u8>], input: &[u8], output: &str) -> Vec<u8> {
+        let mut graph = Vec::new();
+        nodes.iter().for_each(|node| bytes(1, node, &mut graph));
+        weights
+            .iter()
+            .for_each(|weight| bytes(5, weight, &mut graph));
+        bytes(11, input, &mut graph);
+        bytes(12, &value_info(output, &[]), &mut graph);
+        let mut out = Vec::new();
+        number(1, 8, &mut out);
+        bytes(7, &graph, &mut out);
+        out
+    }
+
+    #[test]
+    fn a_small_network_runs() -> Result<()> {
+        let bytes = model(
+            &[
+                node("Gemm", &["x", "w", "b"], "h", &[int_attribute("transB", 1)]),
+                node("Relu", &["h"], "r", &[]),
+                node("Add", &["r", "c"], "y", &[]),
+            ],
+            &[
+                tensor("w", &[2, 3], &[1.0, 0.0, -1.0, 0.5, 0.5, 0.5]),
+                tensor("b", &[2], &[0.0, 1.0]),
+                tensor("c", &[1, 2], &[10.0, 20.0]),
+            ],
+            &value_info("x", &[None, Some(3)]),
+            "y",
+        );
+        let model = Model::parse(&bytes)?;
+        let output = model.run(Tensor::new(vec![2, 3], vec![1.0, 2.0, 3.0, 4.0, 2.0, 0.0])?)?;
+        assert_eq!(
+            output,
+            Tensor::new(vec![2, 2], vec![10.0, 24.0, 14.0, 24.0])?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn shapes_and_softmax() -> Result<()> {
+        let bytes = model(
+            &[
+                node("Reshape", &["x", "shape"], "flat", &[]),
+                node("Softmax", &["flat"], "y", &[]),
+            ],
+            &[tensor("shape", &[2], &[0.0, -1.0])],
+            &value_info("x", &[None, Some(2), Some(1)]),
+            "y",
+        );
+        let output = Model::parse(&bytes)?.run(Tensor::new(vec![1, 2, 1], vec![3.0, 3.0])?)?;
+        assert_eq!(output, Tensor::new(vec![1, 2], vec![0.5, 0.5])?);
+        Ok(())
+    }
+
+    #[test]
+    fn broken_models_are_refused() {
+        let input = value_info("x", &[None, Some(3)]);
+        let unknown = model(&[node("Conv", &["x"], "y", &[])], &[], &input, "y");
+        assert!(Model::parse(&unknown).is_err());
+        let relu = model(&[node("Relu", &["x"], "y", &[])], &[], &input, "y");
+        assert!(Model::parse(&relu).is_ok());
+        for len in 0..relu.len() {
+            let _ = Model::parse(&relu[..len]);
+        }
+        let too_small = Tensor::new(vec![2, 3], vec![0.0; 5]);
+        assert!(too_small.is_err());
+    }
+
+    // A model that scores the tiles a move lays out, minus what it scores
+    // the position, which is the same for every move
+    fn tile_counter(sign: f32) -> Learned {
+        let weights = (0..INPUT_LEN)
+            .map(|index| if index < POSITION_LEN { 0.0 } else { sign })
+            .collect::<Vec<f32>>();
+        let bytes = model(
+            &[node("MatMul", &["x", "w"], "y", &[])],
+            &[tensor("w", &[INPUT_LEN as u64, 1], &weights)],
+            &value_info("x", &[None, Some(INPUT_LEN as u64)]),
+            "y",
+        );
+        Learned::new(Model::parse(&bytes).unwrap(), "test").unwrap()
+    }
+
+    #[test]
+    fn the_best_scored_move_is_made() -> Result<()> {
+        let table = notation::parse_table("R1 R2 R3")?;
+        let rack = notation::parse_rack("R4 R5 B7 B8 B9 K13")?;
+        let observation = Observation::of(&table, rack, true, Ruleset::default());
+        let chosen = tile_counter(1.0).choose_move(&observation)?;
+        assert!(chosen.map(|(_, rest)| rest) == Some(notation::parse_rack("K13")?));
+        assert!(tile_counter(-1.0).choose_move(&observation)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn rows_that_do_not_fit_the_encoding_are_refused() {
+        let bytes = model(
+            &[node("Relu", &["x"], "y", &[])],
+            &[],
+            &value_info("x", &[None, Some(POSITION_LEN as u64)]),
+            "y",
+        );
+        let model = Model::parse(&bytes).unwrap();
+        assert!(Learned::new(model, "test").is_err());
+    }
+}
@@ -0,0 +1,454 @@
+use crate::bag::Bag;
+use crate::cli::Args;
+use crate::deal::Deal;
+use crate::game::GameConfig;
+use crate::solver::{self, Inventory, LayoutMemo};
+use color_eyre::{eyre::bail, eyre::Report, eyre::Result};
+use std::time::Instant;
+
+// Positions counted for dealt games when the move generator was trusted. A
+// refactor of the solver that changes one of these changed which moves are
+// legal. The counts are the output of `perft` itself when it was added, with
+// `verify` finding the solver backends in agreement. The generator is checked
+// on its own by the brute force count in the tests, which splits tiles into
+// sets by hand.
+struct Fixture {
+    seed: u64,
+    players: usize,
+    rack_size: u32,
+    depth: usize,
+    positions: u64,
+}
+
+const FIXTURES: [Fixture; 7] = [
+    Fixture {
+        seed: 2,
+        players: 2,
+        rack_size: 14,
+        depth: 5,
+        positions: 396,
+    },
+    Fixture {
+        seed: 5,
+        players: 2,
+        rack_size: 14,
+        depth: 6,
+        positions: 134,
+    },
+    Fixture {
+        seed: 7,
+        players: 2,
+        rack_size: 14,
+        depth: 5,
+        positions: 2329,
+    },
+    Fixture {
+        seed: 1,
+        players: 2,
+        rack_size: 20,
+        depth: 2,
+        positions: 267,
+    },
+    Fixture {
+        seed: 3,
+        players: 3,
+        rack_size: 14,
+        depth: 5,
+        positions: 1508,
+    },
+    Fixture {
+        seed: 1,
+        players: 4,
+        rack_size: 14,
+        depth: 5,
+        positions: 120,
+    },
+    Fixture {
+        seed: 5,
+        players: 4,
+        rack_size: 14,
+        depth: 5,
+        positions: 200,
+    },
+];
+
+// A position of the game tree, with the racks of everyone and the order of the
+// bag known
+#[derive(Clone)]
+struct Node {
+    table: Inventory,
+    racks: Vec<Inventory>,
+    opened: Vec<bool>,
+    bag: Bag,
+    current: usize,
+}
+
+impl Node {
+    fn dealt(players: usize, rack_size: u32, seed: u64) -> Node {
+        let deal = Deal::new(players, rack_size, seed);
+        Node {
+            table: Inventory::new(0),
+            opened: vec![false; players],
+            racks: deal.racks,
+            bag: deal.bag,
+            current: 0,
+        }
+    }
+
+    fn is_over(&self) -> bool {
+        self.racks.iter().any(|rack| rack.total_tile_count() == 0)
+    }
+
+    fn next(&self) -> Node {
+        let mut next = self.clone();
+        next.current = (self.current + 1) % self.racks.len();
+        next
+    }
+
+    // Draws the next tile, or passes when the bag is empty
+    fn draw(&self) -> Node {
+        let mut next = self.next();
+        if let Some(tile) = next.bag.draw_next() {
+            next.racks[self.current].add_tile(&tile);
+        }
+        next
+    }
+
+    fn play(&self, tiles: &Inventory) -> Node {
+        let mut next = self.next();
+        next.racks[self.current].subtract(tiles);
+        next.table = next.table.combined(tiles);
+        next.opened[self.current] = true;
+        next
+    }
+}
+
+// Moves and positions found at one ply below the start
+#[derive(Default, Clone, Copy)]
+struct Ply {
+    positions: u64,
+    plays: u64,
+    draws: u64,
+}
+
+struct Perft {
+    memo: LayoutMemo,
+    plies: Vec<Ply>,
+    nodes: u64,
+    max_nodes: u64,
+}
+
+impl Perft {
+    fn count(&mut self, node: &Node, ply: usize) -> Result<()> {
+        if ply == self.plies.len() || node.is_over() {
+            return Ok(());
+        }
+        self.nodes += 1;
+        if self.nodes > self.max_nodes {
+            bail!(
+                "gave up after {} positions, raise --max-nodes or lower --depth",
+                self.max_nodes
+            );
+        }
+
+        let (rack, opened) = (node.racks[node.current], node.opened[node.current]);
+        let plays = solver::legal_picks(&node.table, &rack, opened, &mut self.memo);
+        let counts = &mut self.plies[ply];
+        counts.plays += plays.len() as u64;
+        counts.draws += 1;
+        counts.positions += plays.len() as u64 + 1;
+        for tiles in plays {
+            self.count(&node.play(&tiles), ply + 1)?;
+        }
+        self.count(&node.draw(), ply + 1)
+    }
+}
+
+fn perft(
+    players: usize,
+    rack_size: u32,
+    seed: u64,
+    depth: usize,
+    max_nodes: u64,
+) -> Result<Vec<Ply>> {
+    let mut perft = Perft {
+        memo: LayoutMemo::new(),
+        plies: vec![Ply::default(); depth],
+        nodes: 0,
+        max_nodes,
+    };
+    perft.count(&Node::dealt(players, rack_size, seed), 0)?;
+    Ok(perft.plies)
+}
+
+// Counts the positions solver::legal_picks reaches from a dealt game, `--depth`
+// turns deep, like perft does for chess engines. Every turn the player draws,
+// or passes on an empty bag, or lays out any legal pick of their tiles. With
+// `--check` the counts of the fixtures are compared instead.
+pub fn run(args: &Args) -> Result<(), Report> {
+    let max_nodes: u64 = args.parse_or("--max-nodes", 1_000_000)?;
+    if args.flag("--check") {
+        let mut failed = 0;
+        for fixture in &FIXTURES {
+            let plies = perft(
+                fixture.players,
+                fixture.rack_size,
+                fixture.seed,
+                fixture.depth,
+                max_nodes,
+            )?;
+            let positions = plies.last().map_or(1, |ply| ply.positions);
+            let verdict = if positions == fixture.positions {
+                "ok"
+            } else {
+                "MISMATCH"
+            };
+            println!(
+                "seed {} with {} players of {} tiles, depth {}: {} positions, expected {} {}",
+                fixture.seed,
+                fixture.players,
+                fixture.rack_size,
+                fixture.depth,
+                positions,
+                fixture.positions,
+                verdict
+            );
+            failed += (positions != fixture.positions) as usize;
+        }
+        if failed > 0 {
+            bail!(
+                "{} of {} fixtures counted differently",
+                failed,
+                FIXTURES.len()
+            );
+        }
+        return Ok(());
+    }
+
+    let config = GameConfig::from_args(args)?;
+    let seed: u64 = args.parse_or("--seed", 0)?;
+    let depth: usize = args.parse_or("--depth", 3)?;
+    let start = Instant::now();
+    let plies = perft(config.players, config.rack_size, seed, depth, max_nodes)?;
+    println!("Depth |   Positions |       Plays |       Draws");
+    for (ply, counts) in plies.iter().enumerate() {
+        println!(
+            "{:5} | {:11} | {:11} | {:11}",
+            ply + 1,
+            counts.positions,
+            counts.plays,
+            counts.draws
+        );
+    }
+    println!("Counted in {:.1?}", start.elapsed());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_NODES: u64 = 10_000_000;
+
+    #[test]
+    fn the_fixtures_count_as_recorded() -> Result<()> {
+        for fixture in &FIXTURES {
+            let plies = perft(
+                fixture.players,
+                fixture.rack_size,
+                fixture.seed,
+                fixture.depth,
+                MAX_NODES,
+            )?;
+            assert_eq!(
+                plies.last().map(|ply| ply.positions),
+                Some(fixture.positions),
+                "seed {} with {} players",
+                fixture.seed,
+                fixture.players
+            );
+        }
+        Ok(())
+    }
+
+    // Tile counts of a game without jokers, color * 13 + number - 1
+    type Counts = [u8; 52];
+
+    fn counts(inventory: &Inventory) -> Counts {
+        let mut counts = [0; 52];
+        for tile in inventory.tiles() {
+            assert!(!tile.is_joker, "the brute force count has no jokers");
+            counts[tile.color as usize * 13 + tile.number as usize - 1] += 1;
+        }
+        counts
+    }
+
+    // Whether the tiles split into runs and groups. The first tile left can
+    // only be the start of a run or in a group with colors after its own.
+    fn lays_out(counts: &mut Counts) -> bool {
+        let Some(first) = counts.iter().position(|&count| count > 0) else {
+            return true;
+        };
+        let (color, number) = (first / 13, first % 13);
+        let mut end = first;
+        while end % 13 < 12 && counts[end + 1] > 0 {
+            end += 1;
+            if end - first >= 2 {
+                (first..=end).for_each(|index| counts[index] -= 1);
+                let found = lays_out(counts);
+                (first..=end).for_each(|index| counts[index] += 1);
+                if found {
+                    return true;
+                }
+            }
+        }
+        for others in 1..8_usize {
+            let colors = (color + 1..4)
+                .filter(|other| others >> (other - color - 1) & 1 == 1)
+                .collect::<Vec<usize>>();
+            if colors.len() != others.count_ones() as usize
+                || colors.len() < 2
+                || colors.iter().any(|other| counts[other * 13 + number] == 0)
+            {
+                continue;
+            }
+            let group = colors
+                .iter()
+                .map(|other| other * 13 + number)
+                .chain([first])
+                .collect::<Vec<usize>>();
+            group.iter().for_each(|&index| counts[index] -= 1);
+            let found = lays_out(counts);
+            group.iter().for_each(|&index| counts[index] += 1);
+            if found {
+                return true;
+            }
+        }
+        false
+    }
+
+    // Every different pick of the rack, the empty one included
+    fn subsets(rack: &Counts) -> Vec<Counts> {
+        let mut subsets = vec![[0; 52]];
+        for (index, &count) in rack.iter().enumerate() {
+            let mut grown = Vec::new();
+            for subset in &subsets {
+                for taken in 1..=count {
+                    let mut subset = *subset;
+                    subset[index] = taken;
+                    grown.push(subset);
+                }
+            }
+            subsets.extend(grown);
+        }
+        subsets
+    }
+
+    fn plus(a: &Counts, b: &Counts) -> Counts {
+        let mut sum = *a;
+        sum.iter_mut().zip(b).for_each(|(sum, b)| *sum += b);
+        sum
+    }
+
+    fn minus(a: &Counts, b: &Counts) -> Counts {
+        let mut rest = *a;
+        rest.iter_mut().zip(b).for_each(|(rest, b)| *rest -= b);
+        rest
+    }
+
+    fn points(counts: &Counts) -> usize {
+        counts
+            .iter()
+            .enumerate()
+            .map(|(index, &count)| (index % 13 + 1) * count as usize)
+            .sum()
+    }
+
+    #[derive(Clone)]
+    struct Brute {
+        table: Counts,
+        racks: Vec<Counts>,
+        opened: Vec<bool>,
+        // The tiles still to be drawn, the next one last
+        bag: Vec<Counts>,
+        current: usize,
+    }
+
+    impl Brute {
+        fn picks(&self) -> Vec<Counts> {
+            let rack = &self.racks[self.current];
+            subsets(rack)
+                .into_iter()
+                .filter(|pick| match self.opened[self.current] {
+                    true => points(pick) > 0 && lays_out(&mut plus(&self.table, pick)),
+                    false => points(pick) >= 30 && lays_out(&mut pick.clone()),
+                })
+                .collect()
+        }
+
+        fn count(&self, ply: usize, plies: &mut [u64]) {
+            if ply == plies.len() || self.racks.iter().any(|rack| points(rack) == 0) {
+                return;
+            }
+            let picks = self.picks();
+            plies[ply] += picks.len() as u64 + 1;
+            let players = self.racks.len();
+            for pick in picks {
+                let mut next = self.clone();
+                next.racks[self.current] = minus(&self.racks[self.current], &pick);
+                next.table = plus(&self.table, &pick);
+                next.opened[self.current] = true;
+                next.current = (self.current + 1) % players;
+                next.count(ply + 1, plies);
+            }
+            let mut next = self.clone();
+            if let Some(tile) = next.bag.pop() {
+                next.racks[self.current] = plus(&self.racks[self.current], &tile);
+            }
+            next.current = (self.current + 1) % players;
+            next.count(ply + 1, plies);
+        }
+    }
+
+    // Counts the tree again from the deal with nothing of the solver, only
+    // splitting tiles into sets by hand
+    fn brute_force(players: usize, rack_size: u32, seed: u64, depth: usize) -> Vec<u64> {
+        let mut deal = Deal::new(players, rack_size, seed);
+        let mut bag = Vec::new();
+        while let Some(tile) = deal.bag.draw_next() {
+            let mut drawn = Inventory::new(0);
+            drawn.add_tile(&tile);
+            bag.push(drawn);
+        }
+        let bag = bag
+            .into_iter()
+            .take(depth)
+            .rev()
+            .map(|tile| counts(&tile))
+            .collect();
+        let node = Brute {
+            table: [0; 52],
+            racks: deal.racks.iter().map(counts).collect(),
+            opened: vec![false; players],
+            bag,
+            current: 0,
+        };
+        let mut plies = vec![0; depth];
+        node.count(0, &mut plies);
+        plies
+    }
+
+    // Deals without jokers in the racks or the first draws, which reach
+    // positions after the initial meld within four turns
+    #[test]
+    fn a_brute_force_count_agrees() -> Result<()> {
+        for seed in [4, 19, 23] {
+            let counted = perft(2, 14, seed, 4, MAX_NODES)?
+                .iter()
+                .map(|ply| ply.positions)
+                .collect::<Vec<u64>>();
+            assert_eq!(counted, brute_force(2, 14, seed, 4), "seed {}", seed);
+        }
+        Ok(())
+    }
+}
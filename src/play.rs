@@ -0,0 +1,344 @@
+use crate::achievements::{self, Records};
+use crate::cli::Args;
+use crate::game::{GameConfig, GameState};
+use crate::i18n::Message;
+use crate::notation;
+use crate::notify::{self, Event};
+use crate::solver::{Inventory, Set};
+use crate::speech;
+use crate::strength;
+use crate::theme;
+use color_eyre::{eyre::bail, eyre::Report, eyre::Result};
+use std::io::{self, BufRead, Lines, StdinLock, Write};
+
+const HELP: &str = "Commands:
+  new <tiles>   lay out a new set, e.g. `new R5 R6 J`
+  table         enter the whole new table, one set per line, ending with an empty line
+  reset         start the move over from the current table
+  hint          show the move the bot would make
+  strength      compare the rack with random racks
+  done          finish the move
+  draw          draw a tile and end the turn
+  undo-turn [n] take back the last n turns, 1 by default
+  branch        keep the game as it is and go on playing a copy of it
+  branch <n>    go back to game n, the first one is game 1
+  quit          stop the game";
+
+// How a turn at the keyboard ended
+enum Step {
+    Ended,
+    Quit,
+    // Play on in a copy of the game
+    Branch,
+    // Switch to the game with this index
+    Switch(usize),
+}
+
+pub fn clear_screen() {
+    print!("\x1b[2J\x1b[H");
+}
+
+pub fn print_table(table: &[Set]) {
+    if table.is_empty() {
+        println!("  (empty)");
+    }
+    for (index, set) in table.iter().enumerate() {
+        if speech::enabled() {
+            println!(
+                "{}, {}.",
+                Message::SetNumber { number: index + 1 },
+                speech::set(set)
+            );
+        } else if theme::is_large() {
+            print!("  {:2}:\n{}", index + 1, theme::large(&set.tiles));
+        } else {
+            println!("  {:2}: {}", index + 1, theme::tiles(&set.tiles));
+        }
+    }
+}
+
+pub fn print_rack(rack: &Inventory) {
+    if speech::enabled() {
+        println!("{} {}.", Message::Rack, speech::rack(rack));
+    } else if theme::is_large() {
+        print!("{}\n{}", Message::Rack, theme::large(&rack.tiles()));
+    } else {
+        println!("{} {}", Message::Rack, theme::tiles(&rack.tiles()));
+    }
+}
+
+fn print_threats(game: &GameState) -> Result<()> {
+    for threat in game.threats()? {
+        let percent = (threat.chance * 100.0).round() as u32;
+        println!(
+            "{}",
+            Message::MayGoOut {
+                player: threat.seat + 1,
+                percent
+            }
+        );
+    }
+    Ok(())
+}
+
+fn print_state(game: &GameState, draft: &[Set]) -> Result<()> {
+    let seat = game.current();
+    println!(
+        "{}",
+        Message::ToMove {
+            player: seat + 1,
+            bag: game.bag_size(),
+        }
+    );
+    for other in (0..game.players()).filter(|&other| other != seat) {
+        println!(
+            "  {}",
+            Message::Holds {
+                player: other + 1,
+                tiles: game.rack(other).total_tile_count(),
+            }
+        );
+    }
+    println!("{}", Message::Table);
+    print_table(draft);
+
+    print_rack(game.rack(seat));
+    if !game.has_opened(seat) {
+        println!("{}", Message::InitialMeldNeeded);
+    }
+    print_threats(game)
+}
+
+pub fn prompt(lines: &mut Lines<StdinLock>, text: &str) -> Result<Option<String>> {
+    print!("{}", text);
+    io::stdout().flush()?;
+    Ok(lines.next().transpose()?)
+}
+
+// Sets entered one per line until an empty line
+pub fn read_table(lines: &mut Lines<StdinLock>) -> Result<Option<Vec<Set>>> {
+    let mut table = Vec::new();
+    while let Some(line) = prompt(lines, "| ")? {
+        if line.trim().is_empty() {
+            return Ok(Some(table));
+        }
+        match notation::parse_set(&line) {
+            Ok(set) => table.push(set),
+            Err(error) => println!("{}, enter the set again", error),
+        }
+    }
+    Ok(None)
+}
+
+// Reads commands until the current player ends their turn, the players go
+// back in time or want to stop. `games` is the number of games played side
+// by side.
+fn take_turn(
+    game: &mut GameState,
+    lines: &mut Lines<StdinLock>,
+    hints: bool,
+    games: usize,
+) -> Result<Step> {
+    let mut draft = game.table().to_vec();
+    print_state(game, &draft)?;
+
+    loop {
+        let Some(line) = prompt(lines, "> ")? else {
+            return Ok(Step::Quit);
+        };
+        let (command, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+
+        match command {
+            "new" => match notation::parse_set(rest) {
+                Ok(set) => {
+                    draft.push(set);
+                    print_table(&draft);
+                }
+                Err(error) => println!("{}", error),
+            },
+            "table" => match read_table(lines)? {
+                Some(table) => {
+                    draft = table;
+                    print_table(&draft);
+                }
+                None => return Ok(Step::Quit),
+            },
+            "reset" => {
+                draft = game.table().to_vec();
+                print_state(game, &draft)?;
+            }
+            "hint" if hints => {
+                // Quick hints show up at once, better ones follow as they are found
+                let mut found = false;
+                game.suggested_moves(|(table, _)| {
+                    if found {
+                        println!("Better:");
+                    } else {
+                        println!("{}", Message::SuggestedTable);
+                    }
+                    print_table(table);
+                    found = true;
+                    true
+                })?;
+                if !found {
+                    println!("Drawing a tile is the best option");
+                }
+                print_threats(game)?;
+            }
+            "hint" => println!("Hints are disabled, start the game with --hints"),
+            "strength" => {
+                // Fewer samples than the strength subcommand, to answer at once
+                for line in strength::report(game.rack(game.current()), 100, 10) {
+                    println!("{}", line);
+                }
+            }
+            "done" => match game.apply_move(draft.clone()) {
+                Ok(_) => return Ok(Step::Ended),
+                Err(error) => match game.penalize(&error)? {
+                    Some(_) => {
+                        println!(
+                            "Illegal move: {}, the penalty tiles are drawn instead",
+                            error
+                        );
+                        return Ok(Step::Ended);
+                    }
+                    None => println!("Illegal move: {}", error),
+                },
+            },
+            "draw" => match game.draw() {
+                Ok(_) => return Ok(Step::Ended),
+                Err(error) => match game.penalize(&error)? {
+                    Some(_) => {
+                        println!(
+                            "Cannot draw: {}, the penalty tiles are drawn instead",
+                            error
+                        );
+                        return Ok(Step::Ended);
+                    }
+                    None => println!("Cannot draw: {}", error),
+                },
+            },
+            "undo-turn" => {
+                let turns = if rest.is_empty() {
+                    Ok(1)
+                } else {
+                    rest.parse::<usize>()
+                };
+                match turns.map(|turns| game.rewind(turns)) {
+                    Ok(Ok(())) => return Ok(Step::Ended),
+                    Ok(Err(error)) => println!("{}", error),
+                    Err(_) => println!("undo-turn takes a number of turns, got {}", rest),
+                }
+            }
+            "branch" if rest.is_empty() => return Ok(Step::Branch),
+            "branch" => match rest.parse::<usize>() {
+                Ok(number) if (1..=games).contains(&number) => return Ok(Step::Switch(number - 1)),
+                _ => println!("There are games 1 to {}, got {}", games, rest),
+            },
+            "quit" => return Ok(Step::Quit),
+            _ => println!("{}", HELP),
+        }
+    }
+}
+
+// Hot-seat game for humans sharing one terminal. The screen is cleared between
+// turns so nobody sees the rack of another player.
+pub fn run(args: &Args) -> Result<(), Report> {
+    let config = GameConfig::from_args(args)?;
+    let hints = args.flag("--hints");
+    // Statistics are kept for the players named, seat by seat
+    let names = match args.value("--names") {
+        Some(names) if !args.flag("--no-stats") => names
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .collect(),
+        _ => Vec::new(),
+    };
+    if !names.is_empty() && names.len() != config.players {
+        bail!(
+            "expected {} names, one per seat, got {}",
+            config.players,
+            names.len()
+        );
+    }
+    // Points of the most every seat laid out in one turn
+    let mut best_melds = vec![0; config.players];
+
+    // Every game started with `branch`, the one being played is games[current]
+    let mut games = vec![GameState::new(&config)];
+    let mut current = 0;
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    while !games[current].is_over() {
+        clear_screen();
+        let mut text = format!(
+            "Pass the keyboard to Player {} and press Enter",
+            games[current].current() + 1
+        );
+        if games.len() > 1 {
+            text = format!("Game {} of {}. {}", current + 1, games.len(), text);
+        }
+        notify::send(
+            Event::YourTurn,
+            &format!("Player {} to move", games[current].current() + 1),
+        );
+        if prompt(&mut lines, &text)?.is_none() {
+            return Ok(());
+        }
+        clear_screen();
+        println!("{}", HELP);
+        let count = games.len();
+        let seat = games[current].current();
+        let table_points = |game: &GameState| game.table().iter().map(Set::points).sum::<u32>();
+        let before = table_points(&games[current]);
+        match take_turn(&mut games[current], &mut lines, hints, count)? {
+            Step::Ended => {
+                let laid_out = table_points(&games[current]).saturating_sub(before);
+                best_melds[seat] = best_melds[seat].max(laid_out);
+                if games[current].rack(seat).total_tile_count() == 1 {
+                    notify::send(
+                        Event::OneTile,
+                        &format!("Player {} has one tile left", seat + 1),
+                    );
+                }
+            }
+            Step::Quit => return Ok(()),
+            Step::Branch => {
+                games.push(games[current].branch()?);
+                current = games.len() - 1;
+            }
+            Step::Switch(index) => current = index,
+        }
+    }
+
+    let game = &games[current];
+    clear_screen();
+    println!("{}", Message::FinalTable);
+    print_table(game.table());
+    if game.is_blocked() {
+        println!("The bag is empty and nobody can move, points left on the racks:");
+        for seat in game.standings() {
+            println!("  Player {}: {}", seat + 1, game.rack(seat).points());
+        }
+    }
+    match game.winner() {
+        Some(seat) => println!("{}", Message::Wins { player: seat + 1 }),
+        None => println!("{}", Message::Drawn),
+    }
+
+    if !names.is_empty() {
+        let mut records = Records::load()?;
+        for (seat, name) in names.iter().enumerate() {
+            let earned = records.update(name, |record| {
+                record.games += 1;
+                record.wins += (game.winner() == Some(seat)) as u32;
+                record.points_left += game.rack(seat).points();
+                record.best_meld = record.best_meld.max(best_melds[seat]);
+            })?;
+            achievements::print_earned(name, &earned);
+        }
+    }
+
+    Ok(())
+}
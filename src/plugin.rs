@@ -0,0 +1,282 @@
+// Strategies from shared libraries, so bots can be written outside this crate.
+//
+// A plugin exports `rummikub_plugin_v1`, a function without arguments returning
+// a pointer to a `PluginVTable` that lives as long as the library. All data
+// crosses the boundary as plain C structs, so plugins can be written in any
+// language with a C ABI. Tiles use color 0-3 (red, blue, orange, black) and
+// numbers 1-13. Jokers on the rack have color and number 0, jokers on the
+// table carry the tile they stand in for, and so must jokers in the answer.
+//
+// `choose_move` writes the complete new table into the caller's buffers: the
+// tiles of all sets one after another into `tiles` and the size of each set
+// into `set_lengths`. It returns the number of sets, `PLUGIN_DRAW` to draw a
+// tile, or any other negative number on failure. Moves are checked against
+// the rules before the engine accepts them. examples/group_plugin.rs is a
+// small plugin in Rust.
+
+use crate::solver::{Set, Tile, MAX_SET_TILES};
+use crate::strategy::{Move, Observation, Strategy};
+use crate::validator;
+use color_eyre::eyre::{bail, eyre, Result};
+use std::ffi::{c_char, c_void, CStr, CString};
+
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+pub const PLUGIN_DRAW: i32 = -1;
+
+// Room for every tile of the pool, in sets of at least 3 tiles
+const MAX_TILES: usize = 106;
+const MAX_SETS: usize = MAX_TILES / 3;
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct CTile {
+    pub color: u8,
+    pub number: u8,
+    pub is_joker: u8,
+}
+
+#[repr(C)]
+pub struct CPosition {
+    pub rack: *const CTile,
+    pub rack_len: usize,
+    // Tiles of the table sets one after another
+    pub table: *const CTile,
+    pub table_set_lengths: *const u8,
+    pub table_sets: usize,
+    pub opened: u8,
+    pub must_play: u8,
+}
+
+#[repr(C)]
+pub struct PluginVTable {
+    pub abi_version: u32,
+    pub name: extern "C" fn() -> *const c_char,
+    pub choose_move:
+        extern "C" fn(position: *const CPosition, tiles: *mut CTile, set_lengths: *mut u8) -> i32,
+}
+
+impl From<&Tile> for CTile {
+    fn from(tile: &Tile) -> CTile {
+        CTile {
+            color: tile.color,
+            number: tile.number,
+            is_joker: tile.is_joker as u8,
+        }
+    }
+}
+
+impl From<&CTile> for Tile {
+    fn from(tile: &CTile) -> Tile {
+        Tile {
+            color: tile.color,
+            number: tile.number,
+            is_joker: tile.is_joker != 0,
+        }
+    }
+}
+
+pub struct Plugin {
+    handle: *mut c_void,
+    vtable: &'static PluginVTable,
+    name: String,
+}
+
+impl Plugin {
+    pub fn load(path: &str) -> Result<Plugin> {
+        let c_path = CString::new(path)?;
+        // SAFETY: dlopen only reads the path, a null handle is checked below
+        let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        if handle.is_null() {
+            bail!("cannot load plugin {}: {}", path, last_error());
+        }
+
+        let symbol = CString::new("rummikub_plugin_v1")?;
+        // SAFETY: the handle was just opened, the symbol name is nul terminated
+        let entry = unsafe { libc::dlsym(handle, symbol.as_ptr()) };
+        if entry.is_null() {
+            // SAFETY: the handle is valid and not used afterwards
+            unsafe { libc::dlclose(handle) };
+            bail!("{} does not export rummikub_plugin_v1", path);
+        }
+
+        // SAFETY: the plugin ABI defines the entry point as a function returning
+        // a vtable that stays valid while the library is loaded
+        let vtable = unsafe {
+            let entry: extern "C" fn() -> *const PluginVTable = std::mem::transmute(entry);
+            entry().as_ref()
+        };
+        let Some(vtable) = vtable.filter(|vtable| vtable.abi_version == PLUGIN_ABI_VERSION) else {
+            // SAFETY: the handle is valid and not used afterwards
+            unsafe { libc::dlclose(handle) };
+            bail!(
+                "{} does not implement plugin ABI version {}",
+                path,
+                PLUGIN_ABI_VERSION
+            );
+        };
+
+        let name = (vtable.name)();
+        let name = if name.is_null() {
+            path.to_string()
+        } else {
+            // SAFETY: the plugin returns a nul terminated string it owns
+            unsafe { CStr::from_ptr(name) }
+                .to_string_lossy()
+                .into_owned()
+        };
+
+        Ok(Plugin {
+            handle,
+            vtable,
+            name,
+        })
+    }
+}
+
+fn last_error() -> String {
+    // SAFETY: dlerror returns null or a nul terminated message
+    let error = unsafe { libc::dlerror() };
+    if error.is_null() {
+        "unknown error".to_string()
+    } else {
+        // SAFETY: checked for null above
+        unsafe { CStr::from_ptr(error) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        // SAFETY: the handle came from dlopen and the vtable is not used any more
+        unsafe { libc::dlclose(self.handle) };
+    }
+}
+
+impl Strategy for Plugin {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn choose_move(&self, observation: &Observation) -> Result<Option<Move>> {
+        let Observation {
+            table,
+            rack,
+            opened,
+            rules,
+            ..
+        } = observation;
+        let rack_tiles = rack.tiles().iter().map(CTile::from).collect::<Vec<CTile>>();
+        let table_tiles = table
+            .iter()
+            .flat_map(|set| set.tiles.iter().map(CTile::from))
+            .collect::<Vec<CTile>>();
+        let table_set_lengths = table
+            .iter()
+            .map(|set| set.tiles.len() as u8)
+            .collect::<Vec<u8>>();
+        let position = CPosition {
+            rack: rack_tiles.as_ptr(),
+            rack_len: rack_tiles.len(),
+            table: table_tiles.as_ptr(),
+            table_set_lengths: table_set_lengths.as_ptr(),
+            table_sets: table.len(),
+            opened: *opened as u8,
+            must_play: rules.must_play as u8,
+        };
+
+        let mut tiles = [CTile::default(); MAX_TILES];
+        let mut set_lengths = [0u8; MAX_SETS];
+        let sets =
+            (self.vtable.choose_move)(&position, tiles.as_mut_ptr(), set_lengths.as_mut_ptr());
+        if sets == PLUGIN_DRAW {
+            return Ok(None);
+        }
+        let sets = usize::try_from(sets)
+            .ok()
+            .filter(|&sets| sets <= MAX_SETS)
+            .ok_or_else(|| eyre!("plugin {} failed with code {}", self.name, sets))?;
+
+        let mut new_table = Vec::new();
+        let mut next = 0;
+        for &length in &set_lengths[..sets] {
+            let end = next + length as usize;
+            if end > MAX_TILES {
+                bail!(
+                    "plugin {} returned more tiles than the pool holds",
+                    self.name
+                );
+            }
+            if length as usize > MAX_SET_TILES {
+                bail!("plugin {} returned a set of {} tiles", self.name, length);
+            }
+            new_table.push(Set {
+                tiles: tiles[next..end].iter().map(Tile::from).collect(),
+            });
+            next = end;
+        }
+
+        let rest = validator::validate_move(table, rack, &new_table, *opened, rules)
+            .map_err(|error| eyre!("plugin {} made an illegal move: {}", self.name, error))?;
+        Ok(Some((new_table, rest)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameConfig, GameState};
+    use crate::notation;
+    use crate::rules::Ruleset;
+    use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+
+    // The plugin of examples/group_plugin.rs, built by cargo first
+    fn group_plugin() -> Result<Plugin> {
+        let built = std::process::Command::new(env!("CARGO"))
+            .args([
+                "build",
+                "--quiet",
+                "--example",
+                "group_plugin",
+                "--features",
+                "plugins",
+            ])
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .status()?;
+        if !built.success() {
+            bail!("cannot build the example plugin");
+        }
+        let target = std::env::var("CARGO_TARGET_DIR")
+            .unwrap_or_else(|_| concat!(env!("CARGO_MANIFEST_DIR"), "/target").to_string());
+        let path = std::path::Path::new(&target)
+            .join("debug/examples")
+            .join(format!("{}group_plugin{}", DLL_PREFIX, DLL_SUFFIX));
+        Plugin::load(&path.to_string_lossy())
+    }
+
+    #[test]
+    fn the_example_lays_out_its_groups() -> Result<()> {
+        let plugin = group_plugin()?;
+        assert_eq!(plugin.name(), "Groups");
+        let table = notation::parse_table("R1 R2 R3")?;
+        let rack = notation::parse_rack("R9 B9 K9 R5 B6")?;
+        let opened = Observation::of(&table, rack, true, Ruleset::default());
+        let (new_table, rest) = plugin.choose_move(&opened)?.ok_or_else(|| eyre!("drew"))?;
+        assert_eq!(new_table.len(), 2);
+        assert!(rest == notation::parse_rack("R5 B6")?);
+        // 27 points are not enough to open
+        let closed = Observation::of(&table, rack, false, Ruleset::default());
+        assert!(plugin.choose_move(&closed)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn the_example_plays_a_turn() -> Result<()> {
+        let plugin = group_plugin()?;
+        let mut state = GameState::seeded(&GameConfig::new(2, 14)?, 3);
+        state.strategy_move(&plugin)?;
+        let applied = state.strategy_turn(&plugin)?;
+        assert_eq!((applied.seat, state.turn()), (0, 1));
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use crate::json::{self, Json};
+use crate::notation;
+use crate::replay;
+use crate::solution::{self, Solution};
+use crate::solver::{Inventory, Set};
+use color_eyre::eyre::{bail, Result};
+
+// Positions and moves as JSON, the way the server and analyze read and write them
+
+// Parts of a position sent by a client, whatever is left out stays as it was
+pub struct Update {
+    pub table: Option<Vec<Set>>,
+    pub rack: Option<Inventory>,
+    pub opened: Option<bool>,
+}
+
+// `{"rack": "R5 R6 R7 J", "table": "R1 R2 R3; B9 O9 K9", "opened": true}`,
+// the table may also be an array of sets. An empty body changes nothing.
+pub fn update_from(body: &Json) -> Result<Update> {
+    let table = match body.get("table") {
+        None | Some(Json::Null) => None,
+        Some(Json::String(text)) => Some(notation::parse_table(text)?),
+        Some(sets @ Json::Array(_)) => Some(Solution::from_json(sets)?.into_sets()),
+        Some(_) => bail!("\"table\" has to be a string or an array of sets"),
+    };
+    let rack = match body.get("rack") {
+        None | Some(Json::Null) => None,
+        Some(rack) => match rack.as_str() {
+            Some(rack) => Some(notation::parse_rack(rack)?),
+            None => bail!("\"rack\" has to be a string, e.g. \"R5 R6 R7 J\""),
+        },
+    };
+    Ok(Update {
+        table,
+        rack,
+        opened: body.get("opened").and_then(Json::as_bool),
+    })
+}
+
+#[derive(Clone)]
+pub struct SolveRequest {
+    pub table: Vec<Set>,
+    pub rack: Inventory,
+    pub opened: bool,
+}
+
+// A position to solve, an update with at least the rack
+pub fn solve_request(body: &Json) -> Result<SolveRequest> {
+    let update = update_from(body)?;
+    let Some(rack) = update.rack else {
+        bail!("missing \"rack\", e.g. \"R5 R6 R7 J\"");
+    };
+    Ok(SolveRequest {
+        table: update.table.unwrap_or_default(),
+        rack,
+        opened: update.opened.unwrap_or(false),
+    })
+}
+
+// The table after a move, the tiles left on the rack and how many were played.
+// `origins` has for every tile of every set "rack" or the number of the set of
+// the old table it was taken from.
+pub fn move_json(rack: &Inventory, old_table: &[Set], table: &[Set], rest: &Inventory) -> Json {
+    let played = rack.total_tile_count() - rest.total_tile_count();
+    json::object([
+        (
+            "table",
+            Json::Array(
+                table
+                    .iter()
+                    .map(|set| json::string(set.to_string()))
+                    .collect(),
+            ),
+        ),
+        ("origins", solution::origins_json(old_table, table)),
+        ("rest", json::string(replay::rack_text(rest))),
+        ("played", Json::Number(played as f64)),
+    ])
+}
@@ -0,0 +1,220 @@
+use crate::cli::Args;
+use crate::config;
+use crate::strategy::{self, Defensive, Greedy, Move, Observation, Strategy};
+use color_eyre::eyre::{bail, Result};
+use std::cell::Cell;
+use std::fs;
+use std::time::{Duration, Instant};
+
+const PROFILE_KEYS: [&str; 7] = [
+    "name",
+    "strategy",
+    "joker_weight",
+    "dump_chance",
+    "caution",
+    "time_budget",
+    "path",
+];
+
+// Named participant of games and tournaments
+pub struct Profile {
+    pub name: String,
+    strategy: Box<dyn Strategy>,
+    // Time a move should take at most. Slower moves still count, but are tallied.
+    pub time_budget: Option<Duration>,
+    overruns: Cell<u32>,
+}
+
+impl Profile {
+    pub fn new(name: &str, strategy: Box<dyn Strategy>) -> Profile {
+        Profile {
+            name: name.to_string(),
+            strategy,
+            time_budget: None,
+            overruns: Cell::new(0),
+        }
+    }
+
+    // Moves that took longer than the time budget
+    pub fn overruns(&self) -> u32 {
+        self.overruns.get()
+    }
+}
+
+impl Strategy for Profile {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn choose_move(&self, observation: &Observation) -> Result<Option<Move>> {
+        let start = Instant::now();
+        let chosen = self.strategy.choose_move(observation)?;
+        if self
+            .time_budget
+            .is_some_and(|budget| start.elapsed() > budget)
+        {
+            self.overruns.set(self.overruns.get() + 1);
+        }
+        Ok(chosen)
+    }
+}
+
+// Strategy from the shared library at `path`, see src/plugin.rs
+#[cfg(feature = "plugins")]
+fn plugin_strategy(path: Option<&str>, line: usize, name: &str) -> Result<Box<dyn Strategy>> {
+    match path {
+        Some(path) => Ok(Box::new(crate::plugin::Plugin::load(path)?)),
+        None => bail!("line {}: plugin player {} needs a path", line, name),
+    }
+}
+
+#[cfg(not(feature = "plugins"))]
+fn plugin_strategy(_: Option<&str>, line: usize, name: &str) -> Result<Box<dyn Strategy>> {
+    bail!(
+        "line {}: {} is a plugin, but plugins need a build with `--features plugins`",
+        line,
+        name
+    )
+}
+
+// Strategy from the ONNX model at `path`, see src/onnx.rs
+#[cfg(feature = "onnx")]
+fn onnx_strategy(path: Option<&str>, line: usize, name: &str) -> Result<Box<dyn Strategy>> {
+    match path {
+        Some(path) => Ok(Box::new(crate::onnx::Learned::load(path)?)),
+        None => bail!(
+            "line {}: onnx player {} needs the path of the model",
+            line,
+            name
+        ),
+    }
+}
+
+#[cfg(not(feature = "onnx"))]
+fn onnx_strategy(_: Option<&str>, line: usize, name: &str) -> Result<Box<dyn Strategy>> {
+    bail!(
+        "line {}: {} plays by a model, but models need a build with `--features onnx`",
+        line,
+        name
+    )
+}
+
+// Players from a file of `[[player]]` tables such as
+//
+//     [[player]]
+//     name = "Hoarder"
+//     strategy = "greedy"
+//     joker_weight = 1.5
+//     dump_chance = 0.5    # plays the jokers once the bag may soon run dry
+//     time_budget = 2.0    # seconds per move
+//
+//     [[player]]
+//     name = "Blocker"
+//     strategy = "defensive"
+//     caution = 10         # tiles worth giving up to stop an opponent going out
+//
+//     [[player]]
+//     name = "External"
+//     strategy = "plugin"
+//     path = "./libmybot.so"
+//
+//     [[player]]
+//     name = "Learned"
+//     strategy = "onnx"
+//     path = "./value.onnx"
+pub fn load(path: &str) -> Result<Vec<Profile>> {
+    let mut profiles: Vec<Profile> = Vec::new();
+    for table in config::parse(&fs::read_to_string(path)?)? {
+        if table.name.is_empty() {
+            table.check_keys(&[])?;
+            continue;
+        }
+        if table.name != "player" {
+            bail!(
+                "line {}: unknown table [{}] in {}",
+                table.line,
+                table.name,
+                path
+            );
+        }
+        table.check_keys(&PROFILE_KEYS)?;
+
+        let Some(name) = table.string("name")? else {
+            bail!("line {}: player without a name in {}", table.line, path);
+        };
+        if profiles.iter().any(|profile| profile.name == name) {
+            bail!(
+                "line {}: there already is a player called {}",
+                table.line,
+                name
+            );
+        }
+
+        let dump_chance = table.number("dump_chance")?;
+        if dump_chance.is_some_and(|chance| !(0.0..=1.0).contains(&chance)) {
+            bail!(
+                "line {}: dump_chance of {} must be between 0 and 1",
+                table.line,
+                name
+            );
+        }
+        let greedy = Greedy {
+            joker_weight: table.number("joker_weight")?.unwrap_or(0.0),
+            dump_chance,
+        };
+        let strategy: Box<dyn Strategy> = match table.string("strategy")?.unwrap_or("greedy") {
+            "greedy" => Box::new(greedy),
+            "defensive" => Box::new(Defensive {
+                greedy,
+                caution: table
+                    .number("caution")?
+                    .unwrap_or(Defensive::default().caution),
+            }),
+            "plugin" => plugin_strategy(table.string("path")?, table.line, name)?,
+            "onnx" => onnx_strategy(table.string("path")?, table.line, name)?,
+            other => bail!(
+                "line {}: unknown strategy {} for {}",
+                table.line,
+                other,
+                name
+            ),
+        };
+
+        let mut profile = Profile::new(name, strategy);
+        if let Some(seconds) = table.number("time_budget")? {
+            if seconds <= 0.0 {
+                bail!(
+                    "line {}: time_budget of {} must be positive",
+                    table.line,
+                    name
+                );
+            }
+            profile.time_budget = Some(Duration::from_secs_f64(seconds));
+        }
+        profiles.push(profile);
+    }
+
+    if profiles.is_empty() {
+        bail!("{} does not define any [[player]]", path);
+    }
+    Ok(profiles)
+}
+
+// Players from `--profiles`, otherwise one greedy player per `--weights` entry
+pub fn from_args(args: &Args, default_weights: &str) -> Result<Vec<Profile>> {
+    if let Some(path) = args.value("--profiles") {
+        return load(path);
+    }
+
+    let weights = strategy::parse_weights(args.value("--weights").unwrap_or(default_weights))?;
+    Ok(weights
+        .into_iter()
+        .map(|joker_weight| {
+            let greedy = Greedy {
+                joker_weight,
+                dump_chance: None,
+            };
+            Profile::new(&greedy.name(), Box::new(greedy))
+        })
+        .collect())
+}
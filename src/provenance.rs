@@ -0,0 +1,236 @@
+use crate::events::GameEvent;
+use crate::solver::{self, Inventory, Origin, Set, Tile, TILE_TYPES};
+use color_eyre::eyre::{bail, Result};
+use std::fmt;
+
+// Every tile type has two physical copies, jokers included
+const COPIES: usize = 2;
+
+// One physical tile of the pool, the same for the whole game. Shown as the
+// tile and its copy, e.g. `R7#2`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TileId(u8);
+
+impl TileId {
+    fn new(tile: &Tile, copy: usize) -> TileId {
+        // Only tiles of the pool get an id, and those all exist
+        let index = solver::tile_index(tile).unwrap_or(TILE_TYPES - 1);
+        TileId((index * COPIES + copy) as u8)
+    }
+
+    pub fn tile(&self) -> Tile {
+        solver::tile_at(self.0 as usize / COPIES)
+    }
+
+    fn copy(&self) -> usize {
+        self.0 as usize % COPIES
+    }
+}
+
+impl fmt::Display for TileId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}#{}", self.tile(), self.copy() + 1)
+    }
+}
+
+// Where a tile is, sets numbered in the order of the table
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Place {
+    Bag,
+    Rack(usize),
+    Table(usize),
+}
+
+impl fmt::Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Place::Bag => write!(f, "the bag"),
+            Place::Rack(seat) => write!(f, "rack {}", seat + 1),
+            Place::Table(set) => write!(f, "set {}", set + 1),
+        }
+    }
+}
+
+// A tile that changed place with an event
+pub struct Movement {
+    pub id: TileId,
+    pub from: Place,
+    pub to: Place,
+}
+
+impl fmt::Display for Movement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} -> {}", self.id, self.from, self.to)
+    }
+}
+
+// The place of every physical tile through a game, following its events. A
+// tile can only leave the place it is in, so an event that takes a tile that
+// is not there, or a copy that does not exist, is an error rather than a
+// duplicated tile.
+pub struct Provenance {
+    places: Vec<Place>,
+}
+
+impl Provenance {
+    // The tiles of the racks dealt, the lower copy going to the first holder,
+    // and the rest in the bag
+    pub fn new(racks: &[Inventory]) -> Result<Provenance> {
+        let mut provenance = Provenance {
+            places: vec![Place::Bag; TILE_TYPES * COPIES],
+        };
+        for (seat, rack) in racks.iter().enumerate() {
+            for tile in rack.tiles() {
+                let Some(id) = provenance.find(&tile, Place::Bag) else {
+                    bail!(
+                        "player {} is dealt {}, which is not left in the pool",
+                        seat + 1,
+                        tile
+                    );
+                };
+                provenance.places[id.0 as usize] = Place::Rack(seat);
+            }
+        }
+        Ok(provenance)
+    }
+
+    fn place(&self, id: TileId) -> Place {
+        self.places[id.0 as usize]
+    }
+
+    // The tiles in the place, in the order of the pool
+    fn held(&self, place: Place) -> Vec<TileId> {
+        (0..self.places.len())
+            .map(|index| TileId(index as u8))
+            .filter(|id| self.place(*id) == place)
+            .collect()
+    }
+
+    // The lowest copy of the tile in the place
+    fn find(&self, tile: &Tile, place: Place) -> Option<TileId> {
+        (0..COPIES)
+            .map(|copy| TileId::new(tile, copy))
+            .find(|id| self.place(*id) == place)
+    }
+
+    fn table_len(&self) -> usize {
+        self.places
+            .iter()
+            .filter_map(|place| match place {
+                Place::Table(set) => Some(set + 1),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Moves the tiles the event moved and returns them. Rearranged tables
+    // take their tiles from where solver::tile_origins says.
+    pub fn apply(&mut self, event: &GameEvent) -> Result<Vec<Movement>> {
+        let before = self.places.clone();
+        match event {
+            GameEvent::TileDrawn { seat, tile } => {
+                let Some(id) = self.find(tile, Place::Bag) else {
+                    bail!(
+                        "player {} draws {}, which is not in the bag",
+                        seat + 1,
+                        tile
+                    );
+                };
+                self.places[id.0 as usize] = Place::Rack(*seat);
+            }
+            GameEvent::SetsPlayed { seat, sets } => {
+                let first = self.table_len();
+                for (index, set) in sets.iter().enumerate() {
+                    for tile in set.tiles.iter() {
+                        let Some(id) = self.find(tile, Place::Rack(*seat)) else {
+                            bail!(
+                                "player {} lays out {}, which is not on the rack",
+                                seat + 1,
+                                tile
+                            );
+                        };
+                        self.places[id.0 as usize] = Place::Table(first + index);
+                    }
+                }
+            }
+            GameEvent::BoardRearranged { seat, table, tiles } => {
+                self.rearrange(*seat, table, tiles)?
+            }
+            GameEvent::IllegalMove { .. }
+            | GameEvent::TurnTimeout { .. }
+            | GameEvent::TurnSummary { .. }
+            | GameEvent::GameWon { .. } => {}
+        }
+        Ok(before
+            .iter()
+            .zip(&self.places)
+            .enumerate()
+            .filter(|(_, (from, to))| from != to)
+            .map(|(index, (from, to))| Movement {
+                id: TileId(index as u8),
+                from: *from,
+                to: *to,
+            })
+            .collect())
+    }
+
+    fn rearrange(&mut self, seat: usize, table: &[Set], tiles: &[Tile]) -> Result<()> {
+        let old = self.places.clone();
+        let old_table = (0..self.table_len())
+            .map(|index| Set {
+                tiles: self
+                    .held(Place::Table(index))
+                    .iter()
+                    .map(TileId::tile)
+                    .collect(),
+            })
+            .collect::<Vec<Set>>();
+        let mut from_rack = Inventory::new(0);
+        tiles.iter().for_each(|tile| from_rack.add_tile(tile));
+        let mut taken = vec![false; self.places.len()];
+        for (index, (set, origins)) in table
+            .iter()
+            .zip(solver::tile_origins(&old_table, table))
+            .enumerate()
+        {
+            for (tile, origin) in set.tiles.iter().zip(origins) {
+                let place = match origin {
+                    Origin::Table(old_set) => Place::Table(old_set),
+                    Origin::Rack if from_rack.count(tile) > 0 => {
+                        from_rack.remove_tile(tile);
+                        Place::Rack(seat)
+                    }
+                    Origin::Rack => bail!(
+                        "the rearranged table holds a {} that was neither on the table nor added",
+                        tile
+                    ),
+                };
+                let Some(id) = (0..COPIES)
+                    .map(|copy| TileId::new(tile, copy))
+                    .find(|id| !taken[id.0 as usize] && old[id.0 as usize] == place)
+                else {
+                    bail!(
+                        "player {} adds {}, which is not on the rack",
+                        seat + 1,
+                        tile
+                    );
+                };
+                taken[id.0 as usize] = true;
+                self.places[id.0 as usize] = Place::Table(index);
+            }
+        }
+        if let Some(id) =
+            (0..old.len()).find(|&index| matches!(old[index], Place::Table(_)) && !taken[index])
+        {
+            bail!("{} left the table when it was rearranged", TileId(id as u8));
+        }
+        if from_rack.total_tile_count() > 0 {
+            bail!(
+                "player {} adds tiles that are not on the rearranged table",
+                seat + 1
+            );
+        }
+        Ok(())
+    }
+}
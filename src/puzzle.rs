@@ -0,0 +1,397 @@
+use crate::achievements::{self, Records};
+use crate::cli::Args;
+use crate::config;
+use crate::game::{GameConfig, GameState};
+use crate::notation;
+use crate::play::{self, print_table};
+use crate::replay;
+use crate::rules::Ruleset;
+use crate::solution::Solution;
+use crate::solver::{self, Inventory, Objective, Set};
+use crate::strategy::Greedy;
+use crate::validator;
+use color_eyre::{eyre::bail, eyre::eyre, eyre::Report, eyre::Result};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, BufRead};
+
+// Packs that come with the bot, by name
+const PACKS: [(&str, &str); 1] = [("starter", include_str!("../puzzles/starter.toml"))];
+
+// Turns a generated game is played for at most before giving up on a seed
+const MAX_TURNS: u32 = 60;
+// The first turn a position is taken from, before it the table is too bare
+const FIRST_TURN: u32 = 8;
+
+// What a puzzle asks for, the `objective` of a pack
+#[derive(Clone, Copy, PartialEq)]
+enum Goal {
+    // Lay out as many tiles as possible
+    MostTiles,
+    // Lay out every tile of the rack
+    GoOut,
+}
+
+impl Goal {
+    fn parse(text: &str) -> Result<Goal> {
+        match text {
+            "most-tiles" => Ok(Goal::MostTiles),
+            "go-out" => Ok(Goal::GoOut),
+            _ => bail!("unknown objective {}, expected most-tiles or go-out", text),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Goal::MostTiles => "most-tiles",
+            Goal::GoOut => "go-out",
+        }
+    }
+
+    fn task(self) -> &'static str {
+        match self {
+            Goal::MostTiles => "lay out as many tiles as you can",
+            Goal::GoOut => "lay out every tile of your rack",
+        }
+    }
+}
+
+// A position with the best answer the solver found. `tiles` is how many
+// tiles that answer lays out, answers are scored against it.
+struct Puzzle {
+    table: Vec<Set>,
+    rack: Inventory,
+    opened: bool,
+    goal: Goal,
+    answer: Vec<Set>,
+    tiles: u32,
+    difficulty: String,
+}
+
+fn table_text(table: &[Set]) -> String {
+    table
+        .iter()
+        .map(Set::to_string)
+        .collect::<Vec<String>>()
+        .join("; ")
+}
+
+// A pack is a `name` and `[[puzzle]]` tables:
+//
+//     name = "Starter"
+//
+//     [[puzzle]]
+//     table = "R1 R2 R3 R4 R5 R6"
+//     rack = "R4 B9"
+//     objective = "most-tiles"
+//     answer = "R1 R2 R3 R4; R4 R5 R6"
+//     tiles = 1
+//     difficulty = "easy"
+//
+// `opened = false` makes a puzzle about the initial meld. The answer has to
+// be a legal move laying out `tiles` tiles.
+fn read_pack(text: &str) -> Result<(String, Vec<Puzzle>)> {
+    let mut name = String::new();
+    let mut puzzles = Vec::new();
+    for table in config::parse(text)? {
+        match table.name.as_str() {
+            "" => {
+                table.check_keys(&["name"])?;
+                name = table.string("name")?.unwrap_or("").to_string();
+            }
+            "puzzle" => {
+                table.check_keys(&[
+                    "table",
+                    "rack",
+                    "opened",
+                    "objective",
+                    "answer",
+                    "tiles",
+                    "difficulty",
+                ])?;
+                let (Some(rack), Some(answer), Some(tiles)) = (
+                    table.string("rack")?,
+                    table.string("answer")?,
+                    table.number("tiles")?,
+                ) else {
+                    bail!(
+                        "line {}: a puzzle needs the rack, the answer and its tiles",
+                        table.line
+                    );
+                };
+                let puzzle = Puzzle {
+                    table: notation::parse_table(table.string("table")?.unwrap_or(""))?,
+                    rack: notation::parse_rack(rack)?,
+                    opened: table.bool("opened")?.unwrap_or(true),
+                    goal: Goal::parse(table.string("objective")?.unwrap_or("most-tiles"))?,
+                    answer: notation::parse_table(answer)?,
+                    tiles: tiles as u32,
+                    difficulty: table.string("difficulty")?.unwrap_or("unrated").to_string(),
+                };
+                validator::check_position(&puzzle.table, &puzzle.rack)?;
+                let rest = validator::validate_move(
+                    &puzzle.table,
+                    &puzzle.rack,
+                    &puzzle.answer,
+                    puzzle.opened,
+                    &Ruleset::default(),
+                )
+                .map_err(|error| eyre!("line {}: the answer is illegal: {}", table.line, error))?;
+                if puzzle.rack.total_tile_count() - rest.total_tile_count() != puzzle.tiles {
+                    bail!(
+                        "line {}: the answer does not lay out {} tiles",
+                        table.line,
+                        puzzle.tiles
+                    );
+                }
+                if puzzle.goal == Goal::GoOut && rest.total_tile_count() > 0 {
+                    bail!(
+                        "line {}: the answer of a go-out puzzle keeps {}",
+                        table.line,
+                        replay::rack_text(&rest)
+                    );
+                }
+                puzzles.push(puzzle);
+            }
+            name => bail!("line {}: unknown table [{}]", table.line, name),
+        }
+    }
+    Ok((name, puzzles))
+}
+
+fn write_pack(name: &str, puzzles: &[Puzzle]) -> String {
+    let mut text = format!("name = \"{}\"\n", config::escape(name));
+    for puzzle in puzzles {
+        let _ = write!(
+            text,
+            "\n[[puzzle]]\ntable = \"{}\"\nrack = \"{}\"\n",
+            table_text(&puzzle.table),
+            replay::rack_text(&puzzle.rack)
+        );
+        if !puzzle.opened {
+            text += "opened = false\n";
+        }
+        let _ = write!(
+            text,
+            "objective = \"{}\"\nanswer = \"{}\"\ntiles = {}\ndifficulty = \"{}\"\n",
+            puzzle.goal.name(),
+            table_text(&puzzle.answer),
+            puzzle.tiles,
+            puzzle.difficulty
+        );
+    }
+    text
+}
+
+// The same tiles in the nicest arrangement, longest sets first, to show
+// answers in
+fn tidy(table: &[Set]) -> Vec<Set> {
+    solver::arrange(&Inventory::from_sets(table), Objective::Aesthetic)
+        .map_or_else(|| table.to_vec(), Solution::into_sets)
+}
+
+// Sets of the table the answer splits or takes tiles from, rather than
+// keeping them whole in one of its sets
+fn broken_sets(table: &[Set], answer: &[Set]) -> usize {
+    let new_sets = answer
+        .iter()
+        .map(|set| Inventory::from_sets(std::slice::from_ref(set)))
+        .collect::<Vec<_>>();
+    table
+        .iter()
+        .map(|set| Inventory::from_sets(std::slice::from_ref(set)))
+        .filter(|old| !new_sets.iter().any(|new| new.missing(old).is_empty()))
+        .count()
+}
+
+// The more sets have to be taken apart and the more tiles go down, the harder
+fn difficulty(table: &[Set], answer: &[Set], tiles: u32) -> &'static str {
+    match (broken_sets(table, answer), tiles) {
+        (0..=1, 0..=2) => "easy",
+        (0..=2, 0..=4) => "medium",
+        _ => "hard",
+    }
+}
+
+// The first position of a greedy bot game of the seed where the best move
+// takes sets of the table apart
+fn generate(config: &GameConfig, seed: u64) -> Result<Option<Puzzle>> {
+    let mut game = GameState::seeded(config, seed);
+    let bot = Greedy {
+        joker_weight: 0.0,
+        dump_chance: None,
+    };
+    for turn in 0..MAX_TURNS {
+        if game.is_over() {
+            break;
+        }
+        let seat = game.current();
+        let rack = *game.rack(seat);
+        if turn >= FIRST_TURN && game.has_opened(seat) {
+            if let Some((answer, rest)) = solver::best_move(game.table(), &rack, true)? {
+                if broken_sets(game.table(), &answer) > 0 {
+                    let tiles = rack.total_tile_count() - rest.total_tile_count();
+                    return Ok(Some(Puzzle {
+                        table: game.table().to_vec(),
+                        rack,
+                        opened: true,
+                        goal: if rest.total_tile_count() == 0 {
+                            Goal::GoOut
+                        } else {
+                            Goal::MostTiles
+                        },
+                        difficulty: difficulty(game.table(), &answer, tiles).to_string(),
+                        answer,
+                        tiles,
+                    }));
+                }
+            }
+        }
+        match game.strategy_move(&bot)? {
+            Some((table, _)) => game.apply_move(table)?,
+            None => game.draw()?,
+        };
+    }
+    Ok(None)
+}
+
+fn load(pack: &str) -> Result<(String, Vec<Puzzle>)> {
+    match PACKS.iter().find(|(name, _)| *name == pack) {
+        Some((_, text)) => read_pack(text),
+        None => read_pack(&fs::read_to_string(pack)?),
+    }
+}
+
+// Asks every puzzle of the pack once. A legal answer scores the tiles it lays
+// out against the best answer, `hint` shows the best answer and scores
+// nothing.
+fn play_pack(pack: &str, user: Option<String>) -> Result<()> {
+    let (name, puzzles) = load(pack)?;
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let (mut laid, mut best, mut tried, mut solved) = (0, 0, 0, 0);
+    println!(
+        "{}: {} puzzles. Enter the new table, the sets separated by `;`, or hint, skip or quit.",
+        name,
+        puzzles.len()
+    );
+    'pack: for (number, puzzle) in puzzles.iter().enumerate() {
+        println!(
+            "\nPuzzle {} ({}): {}",
+            number + 1,
+            puzzle.difficulty,
+            puzzle.goal.task()
+        );
+        if !puzzle.opened {
+            println!("You have not made your initial meld yet");
+        }
+        print_table(&puzzle.table);
+        play::print_rack(&puzzle.rack);
+        loop {
+            let Some(line) = play::prompt(&mut lines, "> ")? else {
+                break 'pack;
+            };
+            let rest = match line.trim() {
+                "quit" => break 'pack,
+                "skip" => break,
+                "hint" => {
+                    print_table(&tidy(&puzzle.answer));
+                    break;
+                }
+                text => notation::parse_table(text).and_then(|table| {
+                    validator::validate_move(
+                        &puzzle.table,
+                        &puzzle.rack,
+                        &table,
+                        puzzle.opened,
+                        &Ruleset::default(),
+                    )
+                    .map(|rest| (table, rest))
+                }),
+            };
+            match rest {
+                Ok((_, rest)) if puzzle.goal == Goal::GoOut && rest.total_tile_count() > 0 => {
+                    println!(
+                        "Legal, but {} is still on your rack",
+                        replay::rack_text(&rest)
+                    );
+                }
+                Ok((table, rest)) => {
+                    let tiles = puzzle.rack.total_tile_count() - rest.total_tile_count();
+                    laid += tiles.min(puzzle.tiles);
+                    if tiles >= puzzle.tiles {
+                        solved += 1;
+                        println!("Solved!");
+                        // Graded on the tiles alone, but a nicer table is worth showing
+                        let nicest = tidy(&table);
+                        if Objective::Aesthetic.cost(&nicest) < Objective::Aesthetic.cost(&table) {
+                            println!("The same tiles make this tidier table:");
+                            print_table(&nicest);
+                        }
+                    } else {
+                        println!(
+                            "{} tiles, the best answer lays out {}:",
+                            tiles, puzzle.tiles
+                        );
+                        print_table(&tidy(&puzzle.answer));
+                    }
+                    break;
+                }
+                Err(error) => println!("Illegal: {}", error),
+            }
+        }
+        best += puzzle.tiles;
+        tried += 1;
+    }
+    if let Some(user) = user.filter(|_| tried > 0) {
+        let earned = Records::load()?.update(&user, |record| {
+            record.puzzles_tried += tried;
+            record.puzzles_solved += solved;
+        })?;
+        achievements::print_earned(&user, &earned);
+    }
+    println!(
+        "\nSolved {} of {} puzzles, {} of {} tiles laid out ({:.0}%)",
+        solved,
+        tried,
+        laid,
+        best,
+        100.0 * laid as f64 / best.max(1) as f64
+    );
+    Ok(())
+}
+
+// `puzzle play <pack>` asks the puzzles of a pack file, or of a pack that
+// comes with the bot such as `starter`. `puzzle generate` takes positions
+// from greedy bot games of `--count` seeds from `--seed` on and prints them
+// as a pack named `--name`.
+pub fn run(args: &Args) -> Result<(), Report> {
+    match args.positional(0) {
+        Some("play") => {
+            let Some(pack) = args.positional(1) else {
+                bail!(
+                    "missing the pack, a file or one of {}",
+                    PACKS.map(|(name, _)| name).join(", ")
+                );
+            };
+            play_pack(pack, achievements::user(args))
+        }
+        Some("generate") => {
+            let config = GameConfig::from_args(args)?;
+            let count: u64 = args.parse_or("--count", 10)?;
+            let first: u64 = args.parse_or("--seed", 0)?;
+            let mut puzzles = Vec::new();
+            for seed in first..first + count {
+                if let Some(puzzle) = generate(&config, seed)? {
+                    puzzles.push(puzzle);
+                }
+            }
+            print!(
+                "{}",
+                write_pack(args.value("--name").unwrap_or("Generated"), &puzzles)
+            );
+            Ok(())
+        }
+        _ => bail!("expected puzzle play <pack> or puzzle generate"),
+    }
+}
@@ -0,0 +1,389 @@
+use crate::audit::Audit;
+use crate::board;
+use crate::cli::Args;
+use crate::game::GameConfig;
+use crate::notation;
+use crate::notify::{self, Event};
+use crate::play::{print_table, prompt, read_table};
+use crate::repair;
+use crate::rules::Ruleset;
+use crate::scoring::{self, ScoreSheet};
+use crate::solver::{Inventory, Set, Tile};
+use color_eyre::{eyre::bail, eyre::Report, eyre::Result};
+use std::fs;
+use std::io::{self, BufRead, Lines, StdinLock};
+use std::time::Instant;
+
+const HELP: &str = "Commands:
+  new <tiles>   the player laid out a new set, e.g. `new R5 R6 J`
+  table         enter the whole new table, one set per line, ending with an empty line
+  reset         start the move over from the current table
+  correct       the table was entered wrong before: enter it as it really is,
+                then settle whatever does not fit the game
+  done [n]      the player finished the move, saying n tiles were laid out if given
+  draw          the player drew a tile
+  quit          stop refereeing";
+
+// A physical game followed move by move. The racks are hidden from the referee,
+// so it only knows how many tiles every player holds and checks every move
+// against the table, the pool and the initial meld.
+struct Round {
+    table: Vec<Set>,
+    tiles: Vec<u32>,
+    opened: Vec<bool>,
+    bag: u32,
+    current: usize,
+    turns: u32,
+    // Consecutive turns in which nobody could play or draw
+    passes: usize,
+    // Every tile laid out or counted on a rack, to catch entry mistakes and
+    // duplicated tiles at the entry they happened
+    audit: Audit,
+    rules: Ruleset,
+}
+
+impl Round {
+    fn new(config: &GameConfig, first: usize) -> Round {
+        Round {
+            table: Vec::new(),
+            tiles: vec![config.rack_size; config.players],
+            opened: vec![false; config.players],
+            bag: Inventory::new(2).total_tile_count() - config.players as u32 * config.rack_size,
+            current: first,
+            turns: 0,
+            passes: 0,
+            audit: Audit::new(),
+            rules: config.rules,
+        }
+    }
+
+    // `entry` names the move in the audit and `claimed` is how many tiles the
+    // player says were laid out, if they said. Returns what changed on the table.
+    fn apply_move(&mut self, table: Vec<Set>, entry: &str, claimed: Option<u32>) -> Result<String> {
+        let delta = board::diff(&self.table, &table);
+        // Tiles taken off the table are reported by the move check instead
+        if !delta.takes_tiles() {
+            self.audit.check(entry, &delta.added)?;
+        }
+
+        // The rack is hidden, so the move only has to be legal for some rack
+        let played = board::infer_move(
+            &self.table,
+            &table,
+            self.opened[self.current],
+            &self.rules,
+            claimed,
+        )?;
+        let holds = self.tiles[self.current];
+        if played.total_tile_count() > holds {
+            bail!(
+                "{} tiles were laid out, the player only holds {}",
+                played.total_tile_count(),
+                holds
+            );
+        }
+
+        self.audit.record(entry, &played)?;
+        self.tiles[self.current] -= played.total_tile_count();
+        self.opened[self.current] = true;
+        self.table = table;
+        self.passes = 0;
+        self.next_turn();
+        Ok(delta.to_string())
+    }
+
+    // Draws `tiles`, more than one as a penalty, as many as the bag holds
+    // Takes the table as it really is after an entry mistake, once repaired
+    // to fit the racks and the bag. The audit starts over from it, since the
+    // entries it holds led to the mistake. Returns false when the players
+    // gave up on the correction, which leaves the round as it was.
+    fn correct(
+        &mut self,
+        table: Vec<Vec<Tile>>,
+        names: &[String],
+        lines: &mut Lines<StdinLock>,
+    ) -> Result<bool> {
+        let Some(table) = repair::repair(table, names, &mut self.tiles, &mut self.bag, lines)?
+        else {
+            return Ok(false);
+        };
+        self.audit = Audit::new();
+        self.audit
+            .record("the corrected table", &Inventory::from_sets(&table))?;
+        self.table = table;
+        Ok(true)
+    }
+
+    fn draw(&mut self, tiles: u32) {
+        if self.bag > 0 {
+            let tiles = tiles.min(self.bag);
+            self.bag -= tiles;
+            self.tiles[self.current] += tiles;
+            self.passes = 0;
+        } else {
+            self.passes += 1;
+        }
+        self.next_turn();
+    }
+
+    fn next_turn(&mut self) {
+        self.turns += 1;
+        if !self.is_over() {
+            self.current = (self.current + 1) % self.tiles.len();
+        }
+    }
+
+    fn is_blocked(&self) -> bool {
+        self.passes >= self.tiles.len()
+    }
+
+    fn is_over(&self) -> bool {
+        self.is_blocked() || self.tiles.contains(&0)
+    }
+}
+
+fn print_state(round: &Round, names: &[String], draft: &[Set]) {
+    println!(
+        "{} to move, {} tiles left in the bag",
+        names[round.current], round.bag
+    );
+    for (seat, name) in names.iter().enumerate() {
+        let opened = if round.opened[seat] {
+            ""
+        } else {
+            ", initial meld still needed"
+        };
+        println!("  {} holds {} tiles{}", name, round.tiles[seat], opened);
+    }
+    println!("Table:");
+    print_table(draft);
+}
+
+fn tiles_text(tiles: u32) -> String {
+    if tiles == 1 {
+        "a tile".to_string()
+    } else {
+        format!("{} tiles", tiles)
+    }
+}
+
+// Reads the moves of one turn until the player is done or draws.
+// Returns false once the players want to stop.
+fn referee_turn(
+    round: &mut Round,
+    names: &[String],
+    rules: &Ruleset,
+    lines: &mut Lines<StdinLock>,
+) -> Result<bool> {
+    let mut draft = round.table.clone();
+    print_state(round, names, &draft);
+    let seat = round.current;
+    let start = Instant::now();
+    let entry = format!("turn {} ({})", round.turns + 1, names[round.current]);
+
+    loop {
+        let Some(line) = prompt(lines, "> ")? else {
+            return Ok(false);
+        };
+        let (command, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+
+        match command {
+            "new" => match notation::parse_set(rest) {
+                Ok(set) => {
+                    draft.push(set);
+                    print_table(&draft);
+                }
+                Err(error) => println!("Illegal set: {}", error),
+            },
+            "table" => match read_table(lines)? {
+                Some(table) => {
+                    draft = table;
+                    print_table(&draft);
+                }
+                None => return Ok(false),
+            },
+            "reset" => {
+                draft = round.table.clone();
+                print_table(&draft);
+            }
+            "correct" => match repair::read_entries(lines)? {
+                Some(table) => {
+                    match round.correct(table, names, lines)? {
+                        true => println!("Table corrected"),
+                        false => println!("Correction dropped, the table stays as it was"),
+                    }
+                    draft = round.table.clone();
+                    print_state(round, names, &draft);
+                }
+                None => return Ok(false),
+            },
+            "done" if rules.turn_time.is_some_and(|limit| start.elapsed() > limit) => {
+                println!(
+                    "{} took too long and draws {} instead",
+                    names[round.current],
+                    tiles_text(rules.timeout_draws)
+                );
+                round.draw(rules.timeout_draws);
+                return Ok(true);
+            }
+            "done" => match round.apply_move(draft.clone(), &entry, rest.trim().parse().ok()) {
+                Ok(summary) => {
+                    println!("Legal move: {}", summary);
+                    if round.tiles[seat] == 1 {
+                        notify::send(
+                            Event::OneTile,
+                            &format!("{} has one tile left", names[seat]),
+                        );
+                    }
+                    return Ok(true);
+                }
+                Err(error) => match rules.illegal_move_draws {
+                    Some(tiles) => {
+                        println!(
+                            "Illegal move: {}, take the tiles back, {} draws {} as the penalty",
+                            error,
+                            names[round.current],
+                            tiles_text(tiles)
+                        );
+                        round.draw(tiles);
+                        return Ok(true);
+                    }
+                    None => println!("Illegal move: {}, take the tiles back and try again", error),
+                },
+            },
+            "draw" => {
+                round.draw(1);
+                return Ok(true);
+            }
+            "quit" => return Ok(false),
+            _ => println!("{}", HELP),
+        }
+    }
+}
+
+// The points left on every rack, entered as the tiles so the count can be
+// checked. Returns None once the players want to stop.
+fn read_rack_points(
+    round: &mut Round,
+    names: &[String],
+    lines: &mut Lines<StdinLock>,
+) -> Result<Option<Vec<u32>>> {
+    let mut points = Vec::new();
+    for (seat, name) in names.iter().enumerate() {
+        if round.tiles[seat] == 0 {
+            points.push(0);
+            continue;
+        }
+        loop {
+            let text = format!("Tiles left on the rack of {}: ", name);
+            let Some(line) = prompt(lines, &text)? else {
+                return Ok(None);
+            };
+            match notation::parse_rack(&line) {
+                Ok(rack) if rack.total_tile_count() == round.tiles[seat] => {
+                    match round
+                        .audit
+                        .record(&format!("the final rack of {}", name), &rack)
+                    {
+                        Ok(()) => {
+                            points.push(rack.points());
+                            break;
+                        }
+                        Err(error) => println!("{}, enter the rack again", error),
+                    }
+                }
+                Ok(rack) => println!(
+                    "{} tiles entered, {} should hold {}",
+                    rack.total_tile_count(),
+                    name,
+                    round.tiles[seat]
+                ),
+                Err(error) => println!("{}, enter the rack again", error),
+            }
+        }
+    }
+    Ok(Some(points))
+}
+
+// In a blocked round the fewest points win, ties go to the player holding
+// fewer tiles and are a draw otherwise
+fn winner(round: &Round, points: &[u32]) -> Option<usize> {
+    if let Some(seat) = round.tiles.iter().position(|&tiles| tiles == 0) {
+        return Some(seat);
+    }
+    let standing = |seat: usize| (points[seat], round.tiles[seat]);
+    let best = (0..points.len()).map(standing).min()?;
+    let mut leaders = (0..points.len()).filter(|&seat| standing(seat) == best);
+    match (leaders.next(), leaders.next()) {
+        (Some(seat), None) => Some(seat),
+        _ => None,
+    }
+}
+
+// Referee for humans playing with real tiles: every move is entered as it
+// happens and checked against the rules, and the scores of every round end up
+// on a score sheet. No bot plays or hints.
+pub fn run(args: &Args) -> Result<(), Report> {
+    let config = GameConfig::from_args(args)?;
+    let rounds: usize = args.parse_or("--rounds", 1)?;
+    let names = match args.value("--names") {
+        Some(names) => names
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .collect(),
+        None => (1..=config.players)
+            .map(|seat| format!("Player {}", seat))
+            .collect::<Vec<String>>(),
+    };
+    if names.len() != config.players {
+        bail!(
+            "expected {} names, one per seat, got {}",
+            config.players,
+            names.len()
+        );
+    }
+    if config.rules.must_play {
+        println!("(!) The racks are hidden, so players have to check --must-play themselves");
+    }
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut sheet = ScoreSheet::new(names.clone());
+    println!("{}", HELP);
+
+    for number in 0..rounds {
+        let mut round = Round::new(&config, number % config.players);
+        println!("Round {}", number + 1);
+        while !round.is_over() {
+            if !referee_turn(&mut round, &names, &config.rules, &mut lines)? {
+                return Ok(());
+            }
+        }
+
+        println!("Final table:");
+        print_table(&round.table);
+        if round.is_blocked() {
+            println!("The bag is empty and nobody can move");
+        }
+        let Some(points) = read_rack_points(&mut round, &names, &mut lines)? else {
+            return Ok(());
+        };
+        let winner = winner(&round, &points);
+        match winner {
+            Some(seat) => println!("{} wins the round", names[seat]),
+            None => println!("The round is drawn"),
+        }
+        sheet.add_round(scoring::round_scores(&points, winner));
+        sheet.print();
+    }
+
+    match sheet.leader() {
+        Some(seat) => println!("{} wins the match", names[seat]),
+        None => println!("The match is tied"),
+    }
+    if let Some(path) = args.value("--csv") {
+        fs::write(path, sheet.to_csv())?;
+        println!("Score sheet written to {}", path);
+    }
+    Ok(())
+}
@@ -0,0 +1,242 @@
+use crate::solver::{Set, Tile};
+
+// Pictures of a table for sharing, as boxed Unicode text, HTML and, with the
+// `png` feature, as an image
+
+// Characters a tile takes in the Unicode layout, box included
+const TILE_WIDTH: usize = 5;
+// Characters between two sets on the same row
+const SET_GAP: usize = 2;
+
+// Rows of sets that fit the width, sets kept in table order and never split.
+// A set wider than the row gets a row of its own.
+pub fn layout(table: &[Set], width: usize) -> Vec<Vec<&Set>> {
+    let mut rows: Vec<Vec<&Set>> = Vec::new();
+    let mut used = 0;
+    for set in table {
+        let set_width = set.tiles.len() * TILE_WIDTH;
+        match rows.last_mut() {
+            Some(row) if used + SET_GAP + set_width <= width => {
+                row.push(set);
+                used += SET_GAP + set_width;
+            }
+            _ => {
+                rows.push(vec![set]);
+                used = set_width;
+            }
+        }
+    }
+    rows
+}
+
+// The table as boxed tiles, e.g.
+//
+//   ┌───┐┌───┐┌───┐  ┌───┐┌───┐┌───┐
+//   │R5 ││R6 ││ J │  │B9 ││Y9 ││K9 │
+//   └───┘└───┘└───┘  └───┘└───┘└───┘
+pub fn unicode(table: &[Set], width: usize) -> String {
+    let gap = " ".repeat(SET_GAP);
+    let mut text = String::new();
+    for row in layout(table, width) {
+        let line = |tile: &dyn Fn(&Tile) -> String| {
+            row.iter()
+                .map(|set| set.tiles.iter().map(tile).collect::<String>())
+                .collect::<Vec<String>>()
+                .join(&gap)
+        };
+        text.push_str(&line(&|_| "┌───┐".to_string()));
+        text.push('\n');
+        text.push_str(&line(&|tile| format!("│{:^3}│", tile.to_string())));
+        text.push('\n');
+        text.push_str(&line(&|_| "└───┘".to_string()));
+        text.push('\n');
+    }
+    text
+}
+
+// The table as HTML for a page that styles the tile classes: `tile` with `c0`
+// to `c3` for the colors or `joker`, in the rows of the Unicode layout. Tiles
+// show their number, the page colors them.
+pub fn html(table: &[Set], width: usize) -> String {
+    let mut html = String::from("<div class=\"table\">\n");
+    for row in layout(table, width) {
+        html.push_str("<div class=\"row\">");
+        for set in row {
+            html.push_str("<span class=\"set\">");
+            for tile in &set.tiles {
+                let (class, text) = if tile.is_joker {
+                    ("joker".to_string(), "J".to_string())
+                } else {
+                    (format!("c{}", tile.color), tile.number.to_string())
+                };
+                html.push_str(&format!("<span class=\"tile {}\">{}</span>", class, text));
+            }
+            html.push_str("</span>");
+        }
+        html.push_str("</div>\n");
+    }
+    html.push_str("</div>\n");
+    html
+}
+
+// 3 by 5 dot glyphs for the digits and J, one row per entry
+pub fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        _ => [0b001, 0b001, 0b001, 0b101, 0b111],
+    }
+}
+
+#[cfg(feature = "png")]
+pub use image::png;
+
+#[cfg(feature = "png")]
+mod image {
+    use super::{glyph, layout};
+    use crate::npz::crc32;
+    use crate::solver::{Set, Tile};
+
+    const TILE_PIXELS: (usize, usize) = (30, 40);
+    const MARGIN: usize = 8;
+    // Pixels per font dot
+    const SCALE: usize = 3;
+
+    const BACKGROUND: [u8; 3] = [30, 110, 60];
+    const TILE_FACE: [u8; 3] = [250, 245, 230];
+    const TILE_EDGE: [u8; 3] = [150, 140, 120];
+    const COLORS: [[u8; 3]; 4] = [[200, 30, 30], [30, 70, 200], [225, 150, 0], [20, 20, 20]];
+    const JOKER: [u8; 3] = [150, 30, 150];
+
+    struct Canvas {
+        width: usize,
+        height: usize,
+        pixels: Vec<u8>,
+    }
+
+    impl Canvas {
+        fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: [u8; 3]) {
+            for row in y..(y + height).min(self.height) {
+                for column in x..(x + width).min(self.width) {
+                    let index = (row * self.width + column) * 3;
+                    self.pixels[index..index + 3].copy_from_slice(&color);
+                }
+            }
+        }
+
+        fn tile(&mut self, x: usize, y: usize, tile: &Tile) {
+            let (width, height) = TILE_PIXELS;
+            self.fill(x, y, width, height, TILE_EDGE);
+            self.fill(x + 1, y + 1, width - 2, height - 2, TILE_FACE);
+
+            let (text, color) = if tile.is_joker {
+                ("J".to_string(), JOKER)
+            } else {
+                (tile.number.to_string(), COLORS[tile.color as usize])
+            };
+            let text_width = text.len() * 4 * SCALE - SCALE;
+            let left = x + (width - text_width) / 2;
+            let top = y + (height - 5 * SCALE) / 2;
+            for (index, c) in text.chars().enumerate() {
+                for (row, bits) in glyph(c).iter().enumerate() {
+                    for column in 0..3 {
+                        if bits & (0b100 >> column) != 0 {
+                            let dot_x = left + (index * 4 + column) * SCALE;
+                            self.fill(dot_x, top + row * SCALE, SCALE, SCALE, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        png.extend((data.len() as u32).to_be_bytes());
+        let start = png.len();
+        png.extend(kind);
+        png.extend(data);
+        let crc = crc32(&png[start..]);
+        png.extend(crc.to_be_bytes());
+    }
+
+    // zlib stream of uncompressed deflate blocks, tables are small enough
+    fn zlib_stored(data: &[u8]) -> Vec<u8> {
+        let mut stream = vec![0x78, 0x01];
+        let mut blocks = data.chunks(0xffff).peekable();
+        if blocks.peek().is_none() {
+            stream.extend([1, 0, 0, 0xff, 0xff]);
+        }
+        while let Some(block) = blocks.next() {
+            stream.push(blocks.peek().is_none() as u8);
+            stream.extend((block.len() as u16).to_le_bytes());
+            stream.extend((!(block.len() as u16)).to_le_bytes());
+            stream.extend(block);
+        }
+        let (mut a, mut b) = (1u32, 0u32);
+        for &byte in data {
+            a = (a + byte as u32) % 65521;
+            b = (b + a) % 65521;
+        }
+        stream.extend(((b << 16) | a).to_be_bytes());
+        stream
+    }
+
+    // PNG picture of the table, with as many tiles per row as `width` text
+    // columns would hold
+    pub fn png(table: &[Set], width: usize) -> Vec<u8> {
+        let rows = layout(table, width);
+        let (tile_width, tile_height) = TILE_PIXELS;
+        let row_pixels = |row: &Vec<&Set>| {
+            row.iter()
+                .map(|set| set.tiles.len() * tile_width)
+                .sum::<usize>()
+                + row.len().saturating_sub(1) * tile_width / 2
+        };
+        let mut canvas = Canvas {
+            width: rows.iter().map(row_pixels).max().unwrap_or(0) + 2 * MARGIN,
+            height: rows.len() * (tile_height + MARGIN) + MARGIN,
+            pixels: Vec::new(),
+        };
+        canvas.pixels = vec![0; canvas.width * canvas.height * 3];
+        canvas.fill(0, 0, canvas.width, canvas.height, BACKGROUND);
+
+        for (row_index, row) in rows.iter().enumerate() {
+            let y = MARGIN + row_index * (tile_height + MARGIN);
+            let mut x = MARGIN;
+            for set in row {
+                for tile in &set.tiles {
+                    canvas.tile(x, y, tile);
+                    x += tile_width;
+                }
+                x += tile_width / 2;
+            }
+        }
+
+        // Every scanline starts with filter type 0
+        let mut raw = Vec::with_capacity((canvas.width * 3 + 1) * canvas.height);
+        for line in canvas.pixels.chunks(canvas.width * 3) {
+            raw.push(0);
+            raw.extend(line);
+        }
+
+        let mut header = Vec::new();
+        header.extend((canvas.width as u32).to_be_bytes());
+        header.extend((canvas.height as u32).to_be_bytes());
+        // 8 bit RGB, default compression, filtering and no interlacing
+        header.extend([8, 2, 0, 0, 0]);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        chunk(&mut png, b"IHDR", &header);
+        chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+        chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
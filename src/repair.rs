@@ -0,0 +1,300 @@
+use crate::notation;
+use crate::play::prompt;
+use crate::solver::{Inventory, Set, Tile, MAX_SET_TILES};
+use crate::theme;
+use crate::validator::{self, SetFix};
+use color_eyre::eyre::{bail, Result};
+use std::io::{Lines, StdinLock};
+
+// Fixes offered for an illegal set at most
+const FIXES: usize = 5;
+
+// What keeps a corrected table from fitting the rest of the game
+enum Conflict {
+    IllegalSet { index: usize, reason: String },
+    // More copies of the tile than the pool holds, in the sets given
+    TooManyCopies { tile: Tile, sets: Vec<usize> },
+    // Tiles the table holds beyond what the racks and the bag leave for it,
+    // fewer when negative
+    Count { difference: i64 },
+}
+
+// The tiles held outside the table: the count of every rack and of the bag
+struct Holders<'a> {
+    names: &'a [String],
+    racks: Vec<u32>,
+    bag: u32,
+}
+
+impl Holders<'_> {
+    fn table_tiles(&self) -> i64 {
+        Inventory::new(2).total_tile_count() as i64
+            - self.racks.iter().sum::<u32>() as i64
+            - self.bag as i64
+    }
+}
+
+// The tiles of the sets as entered, jokers without what they stand in for
+fn tiles_of(table: &[Vec<Tile>]) -> Inventory {
+    let mut tiles = Inventory::new(0);
+    table.iter().flatten().for_each(|tile| tiles.add_tile(tile));
+    tiles
+}
+
+// Every conflict, illegal sets first since fixing one may settle the others
+fn conflicts(table: &[Vec<Tile>], holders: &Holders) -> Vec<Conflict> {
+    let mut found = table
+        .iter()
+        .enumerate()
+        .filter_map(|(index, tiles)| {
+            let reason = validator::complete_set(tiles).err()?;
+            Some(Conflict::IllegalSet {
+                index,
+                reason: reason.to_string(),
+            })
+        })
+        .collect::<Vec<Conflict>>();
+    for tile in Inventory::new(2).missing(&tiles_of(table)).iter().copied() {
+        if found.iter().any(|conflict| matches!(conflict, Conflict::TooManyCopies { tile: other, .. } if *other == tile)) {
+            continue;
+        }
+        let sets = (0..table.len())
+            .filter(|&index| table[index].contains(&tile))
+            .collect();
+        found.push(Conflict::TooManyCopies { tile, sets });
+    }
+    if found.is_empty() {
+        let difference = tiles_of(table).total_tile_count() as i64 - holders.table_tiles();
+        if difference != 0 {
+            found.push(Conflict::Count { difference });
+        }
+    }
+    found
+}
+
+// The tiles of a set as typed, legal or not, so a mistake can be entered as
+// it is on the table and repaired afterwards
+fn parse_entry(line: &str) -> Result<Vec<Tile>> {
+    let tiles = notation::parse_tiles(line)?;
+    if tiles.len() > MAX_SET_TILES {
+        bail!("a set holds at most {} tiles", MAX_SET_TILES);
+    }
+    Ok(tiles)
+}
+
+// The sets of the table one per line until an empty line, like
+// play::read_table but without checking them. None once the players want to
+// stop.
+pub fn read_entries(lines: &mut Lines<StdinLock>) -> Result<Option<Vec<Vec<Tile>>>> {
+    let mut table = Vec::new();
+    while let Some(line) = prompt(lines, "| ")? {
+        if line.trim().is_empty() {
+            return Ok(Some(table));
+        }
+        match parse_entry(&line) {
+            Ok(tiles) => table.push(tiles),
+            Err(error) => println!("{}, enter the set again", error),
+        }
+    }
+    Ok(None)
+}
+
+// A set as it really is, typed again. None once the players want to stop,
+// an empty line takes the set off the table.
+fn retype(lines: &mut Lines<StdinLock>) -> Result<Option<Option<Vec<Tile>>>> {
+    loop {
+        let Some(line) = prompt(lines, "set, or empty to take it off the table: ")? else {
+            return Ok(None);
+        };
+        if line.trim().is_empty() {
+            return Ok(Some(None));
+        }
+        match parse_entry(&line) {
+            Ok(tiles) => return Ok(Some(Some(tiles))),
+            Err(error) => println!("{}, enter the set again", error),
+        }
+    }
+}
+
+fn replace(table: &mut Vec<Vec<Tile>>, index: usize, set: Option<Vec<Tile>>) {
+    match set {
+        Some(set) => table[index] = set,
+        None => {
+            table.remove(index);
+        }
+    }
+}
+
+// Offers the likeliest fixes of an illegal set, or typing it again
+fn repair_set(
+    table: &mut Vec<Vec<Tile>>,
+    index: usize,
+    lines: &mut Lines<StdinLock>,
+) -> Result<bool> {
+    let tiles = table[index].clone();
+    let fixes = validator::suggest_fixes(&tiles)
+        .into_iter()
+        .take(FIXES)
+        .collect::<Vec<SetFix>>();
+    if fixes.is_empty() {
+        println!("No single change makes it legal, enter it as it is on the table");
+        let Some(set) = retype(lines)? else {
+            return Ok(false);
+        };
+        replace(table, index, set);
+        return Ok(true);
+    }
+    for (number, fix) in fixes.iter().enumerate() {
+        println!("  {}) {}", number + 1, fix.describe(&tiles));
+    }
+    loop {
+        let Some(line) = prompt(lines, "number of a fix, or `type` to enter the set again: ")?
+        else {
+            return Ok(false);
+        };
+        if line.trim() == "type" {
+            let Some(set) = retype(lines)? else {
+                return Ok(false);
+            };
+            replace(table, index, set);
+            return Ok(true);
+        }
+        match line
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .and_then(|number| fixes.get(number.checked_sub(1)?))
+        {
+            Some(fix) => {
+                table[index] = fix.apply(&tiles);
+                return Ok(true);
+            }
+            None => println!("Expected a number from 1 to {} or `type`", fixes.len()),
+        }
+    }
+}
+
+// Asks whose count was off by the tiles the table holds too many or too few
+fn repair_count(
+    difference: i64,
+    holders: &mut Holders,
+    lines: &mut Lines<StdinLock>,
+) -> Result<bool> {
+    let (more, tiles) = (difference > 0, difference.unsigned_abs() as u32);
+    for (seat, name) in holders.names.iter().enumerate() {
+        println!(
+            "  {}) {} holds {} tiles",
+            seat + 1,
+            name,
+            holders.racks[seat]
+        );
+    }
+    println!("  b) the bag holds {} tiles", holders.bag);
+    loop {
+        let question = match more {
+            true => format!("where did the {} extra tiles come from? ", tiles),
+            false => format!("where did the {} missing tiles go? ", tiles),
+        };
+        let Some(line) = prompt(lines, &question)? else {
+            return Ok(false);
+        };
+        let count = match line.trim() {
+            "b" => Some(&mut holders.bag),
+            seat => seat
+                .parse::<usize>()
+                .ok()
+                .and_then(|seat| holders.racks.get_mut(seat.checked_sub(1)?)),
+        };
+        match (count, more) {
+            (Some(count), true) if *count >= tiles => {
+                *count -= tiles;
+                return Ok(true);
+            }
+            (Some(count), true) => println!("There are only {} tiles there", count),
+            (Some(count), false) => {
+                *count += tiles;
+                return Ok(true);
+            }
+            (None, _) => println!("Expected a seat from 1 to {} or b", holders.names.len()),
+        }
+    }
+}
+
+// Walks through everything that keeps a table entered as a correction from
+// fitting the game: illegal sets, more copies of a tile than the pool holds,
+// and tiles that the counts of the racks and the bag do not account for. The
+// racks and the bag only change once every conflict is settled. Returns the
+// repaired table, or None when the players stop and the correction is dropped.
+pub fn repair(
+    mut table: Vec<Vec<Tile>>,
+    names: &[String],
+    racks: &mut [u32],
+    bag: &mut u32,
+    lines: &mut Lines<StdinLock>,
+) -> Result<Option<Vec<Set>>> {
+    let mut holders = Holders {
+        names,
+        racks: racks.to_vec(),
+        bag: *bag,
+    };
+    while let Some(conflict) = conflicts(&table, &holders).into_iter().next() {
+        for (index, tiles) in table.iter().enumerate() {
+            println!("  {:2}: {}", index + 1, theme::tiles(tiles));
+        }
+        let settled = match conflict {
+            Conflict::IllegalSet { index, reason } => {
+                println!("Set {} is not legal: {}", index + 1, reason);
+                repair_set(&mut table, index, lines)?
+            }
+            Conflict::TooManyCopies { tile, sets } => {
+                let numbers = sets
+                    .iter()
+                    .map(|index| (index + 1).to_string())
+                    .collect::<Vec<String>>();
+                println!(
+                    "The pool does not hold enough copies of {} for sets {}",
+                    tile,
+                    numbers.join(", ")
+                );
+                loop {
+                    let Some(line) = prompt(lines, "number of the set to enter again: ")? else {
+                        break false;
+                    };
+                    match line
+                        .trim()
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|number| sets.contains(&number.wrapping_sub(1)))
+                    {
+                        Some(number) => match retype(lines)? {
+                            Some(set) => {
+                                replace(&mut table, number - 1, set);
+                                break true;
+                            }
+                            None => break false,
+                        },
+                        None => println!("Expected one of {}", numbers.join(", ")),
+                    }
+                }
+            }
+            Conflict::Count { difference } => {
+                println!(
+                    "The table holds {} tiles, the racks and the bag leave {} for it",
+                    tiles_of(&table).total_tile_count(),
+                    holders.table_tiles()
+                );
+                repair_count(difference, &mut holders, lines)?
+            }
+        };
+        if !settled {
+            return Ok(None);
+        }
+    }
+    let table = table
+        .iter()
+        .map(|tiles| validator::complete_set(tiles))
+        .collect::<Result<Vec<Set>>>()?;
+    racks.copy_from_slice(&holders.racks);
+    *bag = holders.bag;
+    Ok(Some(table))
+}
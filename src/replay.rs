@@ -0,0 +1,182 @@
+use crate::bag::Bag;
+use crate::events::GameEvent;
+use crate::notation;
+use crate::solver::{Inventory, Set};
+use color_eyre::eyre::{bail, eyre, Result};
+
+// Games as logged by `simulate --log` or `--replay`, one block per game:
+//
+//   Game 1
+//     Player 1 is dealt R1 R5 B7 J ...
+//     Player 2 is dealt ...
+//     Player 1 plays [R10 R11 R12] [B4 O4 K4]
+//     Player 2 draws K9
+//     ...
+//
+// Games from elsewhere, e.g. exported from an online table, can be converted
+// to this form to be analysed the same way.
+pub struct Replay {
+    pub racks: Vec<Inventory>,
+    pub events: Vec<GameEvent>,
+}
+
+// A turn of a replayed game and the position the player was in before it
+pub struct Turn<'a> {
+    pub seat: usize,
+    pub event: &'a GameEvent,
+    // Tiles the turn took from the rack
+    pub played: Inventory,
+    pub table: Vec<Set>,
+    pub rack: Inventory,
+    // The racks of every player, the one of `seat` being `rack`
+    pub racks: Vec<Inventory>,
+    pub bag: Bag,
+    pub opened: bool,
+}
+
+impl Replay {
+    // Every draw and play of the game with the position before it. Fails
+    // where the racks, draws and plays of the log do not add up.
+    pub fn turns(&self) -> Result<Vec<Turn<'_>>> {
+        let players = self.racks.len();
+        let mut racks = self.racks.clone();
+        let mut bag = Bag::full();
+        for tile in racks.iter().flat_map(Inventory::tiles) {
+            if !bag.take(&tile) {
+                bail!("the racks hold more tiles than the pool");
+            }
+        }
+        let mut table: Vec<Set> = Vec::new();
+        let mut opened = vec![false; players];
+        let mut turns = Vec::new();
+        // The seat that just drew, further draws of it are a penalty of the
+        // same turn
+        let mut drawing = None;
+
+        for event in &self.events {
+            let penalty =
+                matches!(event, GameEvent::TileDrawn { seat, .. } if drawing == Some(*seat));
+            drawing = match event {
+                GameEvent::TileDrawn { seat, .. } => Some(*seat),
+                _ => None,
+            };
+            let (seat, played) = match event {
+                GameEvent::TileDrawn { seat, .. } => (*seat, Inventory::new(0)),
+                GameEvent::SetsPlayed { seat, sets } => (*seat, Inventory::from_sets(sets)),
+                GameEvent::BoardRearranged { seat, tiles, .. } => {
+                    let mut played = Inventory::new(0);
+                    tiles.iter().for_each(|tile| played.add_tile(tile));
+                    (*seat, played)
+                }
+                GameEvent::IllegalMove { .. }
+                | GameEvent::TurnTimeout { .. }
+                | GameEvent::TurnSummary { .. }
+                | GameEvent::GameWon { .. } => continue,
+            };
+            if seat >= players {
+                bail!("player {} was not dealt a rack", seat + 1);
+            }
+            if !racks[seat].missing(&played).is_empty() {
+                bail!(
+                    "player {} lays out tiles that are not on the rack",
+                    seat + 1
+                )
+            }
+            if !penalty {
+                turns.push(Turn {
+                    seat,
+                    event,
+                    played,
+                    table: table.clone(),
+                    rack: racks[seat],
+                    racks: racks.clone(),
+                    bag: bag.clone(),
+                    opened: opened[seat],
+                });
+            }
+
+            match event {
+                GameEvent::TileDrawn { tile, .. } => {
+                    if !bag.take(tile) {
+                        bail!(
+                            "player {} draws {}, which is not in the bag",
+                            seat + 1,
+                            tile
+                        )
+                    }
+                    racks[seat].add_tile(tile);
+                }
+                GameEvent::SetsPlayed { sets, .. } => table.extend(sets.iter().cloned()),
+                GameEvent::BoardRearranged {
+                    table: new_table, ..
+                } => table = new_table.clone(),
+                _ => {}
+            }
+            if played.total_tile_count() > 0 {
+                racks[seat].subtract(&played);
+                opened[seat] = true;
+            }
+        }
+        Ok(turns)
+    }
+}
+
+pub fn rack_text(rack: &Inventory) -> String {
+    rack.tiles()
+        .iter()
+        .map(|tile| tile.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+pub fn parse(text: &str) -> Result<Vec<Replay>> {
+    let mut replays: Vec<Replay> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |error| eyre!("line {}: {}", index + 1, error);
+        if line
+            .strip_prefix("Game ")
+            .is_some_and(|number| number.parse::<usize>().is_ok())
+        {
+            replays.push(Replay {
+                racks: Vec::new(),
+                events: Vec::new(),
+            });
+            continue;
+        }
+        let Some(replay) = replays.last_mut() else {
+            bail!(
+                "line {}: expected `Game 1` before the first event",
+                index + 1
+            );
+        };
+
+        let deal = line
+            .strip_prefix("Player ")
+            .and_then(|rest| rest.split_once(" is dealt"));
+        if let Some((player, tiles)) = deal {
+            if !replay.events.is_empty() || player != (replay.racks.len() + 1).to_string() {
+                bail!(
+                    "line {}: racks have to be dealt in seat order before the first event",
+                    index + 1
+                );
+            }
+            replay
+                .racks
+                .push(notation::parse_rack(tiles).map_err(error)?);
+        } else {
+            let event = GameEvent::parse(line).map_err(error)?;
+            replay.events.push(event);
+        }
+    }
+    if let Some(number) = replays.iter().position(|replay| replay.racks.len() < 2) {
+        bail!(
+            "game {} of the replay does not deal at least 2 racks",
+            number + 1
+        );
+    }
+    Ok(replays)
+}
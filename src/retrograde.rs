@@ -0,0 +1,217 @@
+use crate::cli::Args;
+use crate::notation;
+use crate::profile;
+use crate::replay;
+use crate::rules::Ruleset;
+use crate::solver::{self, Inventory, LayoutMemo};
+use crate::strategy::{Observation, Strategy};
+use crate::validator;
+use color_eyre::{eyre::bail, eyre::Report, eyre::Result};
+use std::collections::HashMap;
+use std::time::Instant;
+
+// Every pick of both racks can come up, so the racks have to stay small
+const MAX_TILES: u32 = 12;
+
+// The result of a position for the player to move
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+enum Outcome {
+    Loss,
+    Draw,
+    Win,
+}
+
+impl Outcome {
+    // The same result for the other player
+    fn flipped(self) -> Outcome {
+        match self {
+            Outcome::Loss => Outcome::Win,
+            Outcome::Draw => Outcome::Draw,
+            Outcome::Win => Outcome::Loss,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Outcome::Loss => "loss",
+            Outcome::Draw => "draw",
+            Outcome::Win => "win",
+        }
+    }
+}
+
+// A two player position with the bag empty, the player to move first
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+struct Position {
+    table: Inventory,
+    racks: [Inventory; 2],
+    opened: [bool; 2],
+    // Whether the other player passed, another pass blocks the game
+    passed: bool,
+}
+
+impl Position {
+    // The position after the player to move lays out the pick, or passes
+    // with an empty one
+    fn after(&self, pick: &Inventory) -> Position {
+        let mut rack = self.racks[0];
+        rack.subtract(pick);
+        let played = pick.total_tile_count() > 0;
+        Position {
+            table: self.table.combined(pick),
+            racks: [self.racks[1], rack],
+            opened: [self.opened[1], self.opened[0] || played],
+            passed: !played,
+        }
+    }
+
+    // A blocked game goes to the fewest points left, then the fewest tiles,
+    // as in GameState::winner
+    fn blocked(&self) -> Outcome {
+        let standing = |rack: &Inventory| (rack.points(), rack.total_tile_count());
+        match standing(&self.racks[0]).cmp(&standing(&self.racks[1])) {
+            std::cmp::Ordering::Less => Outcome::Win,
+            std::cmp::Ordering::Equal => Outcome::Draw,
+            std::cmp::Ordering::Greater => Outcome::Loss,
+        }
+    }
+}
+
+// Outcomes with perfect play of every position reached, worked out back from
+// the end of the game
+#[derive(Default)]
+struct Solver {
+    outcomes: HashMap<Position, Outcome>,
+    layouts: LayoutMemo,
+}
+
+impl Solver {
+    // Every move of the player to move with the outcome it leads to, passing
+    // last as an empty pick
+    fn moves(&mut self, position: &Position) -> Vec<(Inventory, Outcome)> {
+        let rack = position.racks[0];
+        let mut picks = solver::legal_picks(
+            &position.table,
+            &rack,
+            position.opened[0],
+            &mut self.layouts,
+        );
+        picks.push(Inventory::new(0));
+        picks
+            .into_iter()
+            .map(|pick| {
+                let outcome = if pick.total_tile_count() == rack.total_tile_count() {
+                    Outcome::Win
+                } else if pick.total_tile_count() == 0 && position.passed {
+                    position.blocked()
+                } else {
+                    self.outcome(&position.after(&pick)).flipped()
+                };
+                (pick, outcome)
+            })
+            .collect()
+    }
+
+    fn outcome(&mut self, position: &Position) -> Outcome {
+        if let Some(&outcome) = self.outcomes.get(position) {
+            return outcome;
+        }
+        let best = self
+            .moves(position)
+            .into_iter()
+            .map(|(_, outcome)| outcome)
+            .max()
+            .unwrap_or(Outcome::Loss);
+        self.outcomes.insert(*position, best);
+        best
+    }
+}
+
+fn pick_text(pick: &Inventory) -> String {
+    if pick.total_tile_count() == 0 {
+        "pass".to_string()
+    } else {
+        format!("lay out {}", replay::rack_text(pick))
+    }
+}
+
+// Solves a small two player endgame with the bag empty: the result with
+// perfect play of both, and of every move the player to move has. The
+// position is `--table`, `--rack` of the player to move and `--opponent`,
+// with `--opened` and `--opponent-opened`. With `--profiles` or `--weights`
+// the moves of those strategies are judged against perfect play.
+pub fn run(args: &Args) -> Result<(), Report> {
+    let table = notation::parse_table(args.value("--table").unwrap_or(""))?;
+    let (Some(rack), Some(opponent)) = (args.value("--rack"), args.value("--opponent")) else {
+        bail!(
+            "missing --rack of the player to move or --opponent with the rack of the other player"
+        );
+    };
+    let racks = [notation::parse_rack(rack)?, notation::parse_rack(opponent)?];
+    validator::check_position(&table, &racks[0].combined(&racks[1]))?;
+    let tiles = racks[0].total_tile_count() + racks[1].total_tile_count();
+    if tiles > MAX_TILES {
+        bail!(
+            "the racks hold {} tiles, positions with at most {} can be solved",
+            tiles,
+            MAX_TILES
+        );
+    }
+    if racks.iter().any(|rack| rack.total_tile_count() == 0) {
+        bail!("the game is already over");
+    }
+    let position = Position {
+        table: Inventory::from_sets(&table),
+        racks,
+        opened: [args.flag("--opened"), args.flag("--opponent-opened")],
+        passed: false,
+    };
+
+    let start = Instant::now();
+    let mut solver = Solver::default();
+    let mut moves = solver.moves(&position);
+    moves.sort_by(|(_, a), (_, b)| b.cmp(a));
+    let best = moves.first().map_or(Outcome::Loss, |(_, outcome)| *outcome);
+    println!(
+        "With perfect play the player to move has a {} ({} positions solved in {:.1?})",
+        best.name(),
+        solver.outcomes.len() + 1,
+        start.elapsed()
+    );
+    for (pick, outcome) in &moves {
+        println!("  {:5} {}", outcome.name(), pick_text(pick));
+    }
+
+    if args.value("--profiles").is_some() || args.value("--weights").is_some() {
+        println!();
+        for player in profile::from_args(args, "0")? {
+            let chosen = player.choose_move(&Observation::of(
+                &table,
+                racks[0],
+                position.opened[0],
+                Ruleset::default(),
+            ))?;
+            let pick = match &chosen {
+                Some((_, rest)) => {
+                    let mut pick = racks[0];
+                    pick.subtract(rest);
+                    pick
+                }
+                None => Inventory::new(0),
+            };
+            let outcome = moves
+                .iter()
+                .find(|(legal, _)| *legal == pick)
+                .map(|(_, outcome)| *outcome);
+            let verdict = match outcome {
+                Some(outcome) if outcome == best => "perfect".to_string(),
+                Some(outcome) => {
+                    format!("a {} where a {} was possible", outcome.name(), best.name())
+                }
+                None => "not a move the solver knows".to_string(),
+            };
+            println!("{}: {}, {}", player.name, pick_text(&pick), verdict);
+        }
+    }
+    Ok(())
+}
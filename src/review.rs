@@ -0,0 +1,213 @@
+use crate::cli::Args;
+use crate::events::GameEvent;
+use crate::provenance::Provenance;
+use crate::render;
+use crate::replay::{self, Turn};
+use crate::solution::Solution;
+use crate::solver::{self, Set};
+use color_eyre::{eyre::bail, eyre::eyre, eyre::Report, eyre::Result};
+use std::fs;
+
+const PAGE: &str = include_str!("web/replay.html");
+
+#[cfg(feature = "tui")]
+fn timeline(replay: &replay::Replay, min_loss: u32, width: usize) -> Result<()> {
+    crate::timeline::show(replay, min_loss, width)
+}
+
+#[cfg(not(feature = "tui"))]
+fn timeline(_: &replay::Replay, _: u32, _: usize) -> Result<()> {
+    bail!("the timeline needs a build with `--features tui`")
+}
+
+#[derive(Default)]
+struct PlayerReview {
+    turns: u32,
+    // Turns that laid out as many tiles as the best move
+    best: u32,
+    blunders: u32,
+    tiles_lost: u32,
+    points_lost: i64,
+}
+
+// A turn that laid out fewer tiles than the best move
+pub struct Blunder {
+    pub tiles_lost: u32,
+    pub points_lost: i64,
+    pub best: Vec<Set>,
+}
+
+// Compares the turn with the best move and counts it for the player
+fn review(turn: &Turn, player: &mut PlayerReview) -> Result<Option<Blunder>> {
+    let mut rest = turn.rack;
+    rest.subtract(&turn.played);
+    let (best, best_rest) = solver::best_move(&turn.table, &turn.rack, turn.opened)?
+        .unwrap_or((turn.table.clone(), turn.rack));
+    let possible = turn.rack.total_tile_count() - best_rest.total_tile_count();
+    let tiles_lost = possible.saturating_sub(turn.played.total_tile_count());
+
+    player.turns += 1;
+    if tiles_lost == 0 {
+        player.best += 1;
+        return Ok(None);
+    }
+    let points_lost = rest.points() as i64 - best_rest.points() as i64;
+    player.tiles_lost += tiles_lost;
+    player.points_lost += points_lost;
+    Ok(Some(Blunder {
+        tiles_lost,
+        points_lost,
+        best,
+    }))
+}
+
+// The blunder of every turn that lost at least `min_loss` tiles, for the
+// timeline
+pub fn blunders(turns: &[Turn], min_loss: u32) -> Result<Vec<Option<Blunder>>> {
+    let mut players = Vec::new();
+    turns
+        .iter()
+        .map(|turn| {
+            players.resize_with(players.len().max(turn.seat + 1), PlayerReview::default);
+            let blunder = review(turn, &mut players[turn.seat])?;
+            Ok(blunder.filter(|blunder| blunder.tiles_lost >= min_loss))
+        })
+        .collect()
+}
+
+pub fn table_after(turn: &Turn) -> Vec<Set> {
+    match turn.event {
+        GameEvent::SetsPlayed { sets, .. } => turn.table.iter().chain(sets).cloned().collect(),
+        GameEvent::BoardRearranged { table, .. } => table.clone(),
+        _ => turn.table.clone(),
+    }
+}
+
+// Replays every turn of a game log, finds the best move of the solver for the
+// position the player was in and reports the turns where the player laid out
+// fewer tiles, like the blunder check of a chess engine. The points are those
+// left on the rack, which count against the player when someone goes out.
+// `--html` writes the annotated game as a page to share. `--provenance` lists
+// where every turn moved each physical tile, e.g. `R7#2 rack 1 -> set 3`.
+pub fn run(args: &Args) -> Result<(), Report> {
+    let Some(path) = args.value("--replay") else {
+        bail!("missing --replay with a game log, e.g. from `simulate --replay`");
+    };
+    let replays = replay::parse(&fs::read_to_string(path)?)?;
+    // Only the turns of this player, all players without it
+    let player: Option<usize> = args.value("--player").map(str::parse).transpose()?;
+    if player == Some(0) {
+        bail!("players are numbered from 1");
+    }
+    // Turns that lay out fewer tiles than this below the best are not reported
+    let min_loss: u32 = args.parse_or("--min-loss", 1)?;
+    if args.flag("--timeline") {
+        let game: usize = args.parse_or("--game", 1)?;
+        let Some(replay) = game.checked_sub(1).and_then(|index| replays.get(index)) else {
+            bail!(
+                "the log holds {} games, there is no game {}",
+                replays.len(),
+                game
+            );
+        };
+        return timeline(replay, min_loss, args.parse_or("--width", 80)?);
+    }
+
+    // The whole game with its blunders and the table after every turn
+    let html_path = args.value("--html");
+    let width: usize = args.parse_or("--width", 80)?;
+    let mut html = String::new();
+    let show_provenance = args.flag("--provenance");
+
+    let mut reviews: Vec<PlayerReview> = Vec::new();
+    for (game, replay) in replays.iter().enumerate() {
+        reviews.resize_with(reviews.len().max(replay.racks.len()), PlayerReview::default);
+        let turns = replay
+            .turns()
+            .map_err(|error| eyre!("game {}: {}", game + 1, error))?;
+        html.push_str(&format!("<h2>Game {}</h2>\n", game + 1));
+        let mut provenance = Provenance::new(&replay.racks)
+            .map_err(|error| eyre!("game {}: {}", game + 1, error))?;
+        for (seat, rack) in replay.racks.iter().enumerate() {
+            html.push_str(&format!(
+                "<p>Player {} is dealt {}</p>\n",
+                seat + 1,
+                replay::rack_text(rack)
+            ));
+        }
+        for (number, turn) in turns.iter().enumerate() {
+            html.push_str(&format!(
+                "<div class=\"turn\">\n<h3>Turn {}</h3>\n<p>{}</p>\n",
+                number + 1,
+                turn.event
+            ));
+            let blunder = if player.is_none_or(|player| player == turn.seat + 1) {
+                review(turn, &mut reviews[turn.seat])?
+            } else {
+                None
+            };
+            if let Some(blunder) = blunder.filter(|blunder| blunder.tiles_lost >= min_loss) {
+                reviews[turn.seat].blunders += 1;
+                let made = match turn.event {
+                    GameEvent::TileDrawn { .. } => "drew".to_string(),
+                    _ => format!("laid out {} tiles", turn.played.total_tile_count()),
+                };
+                let text = format!(
+                    "Player {} {}, {} more could have been laid out for {} points less on the rack",
+                    turn.seat + 1,
+                    made,
+                    blunder.tiles_lost,
+                    blunder.points_lost
+                );
+                println!("Game {}, turn {}: {}", game + 1, number + 1, text);
+                println!("  best: {}", Solution::from(blunder.best.clone()));
+                html.push_str(&format!(
+                    "<p class=\"blunder\">{}. The best move:</p>\n",
+                    text
+                ));
+                html.push_str(&render::html(&blunder.best, width));
+            }
+            let movements = provenance
+                .apply(turn.event)
+                .map_err(|error| eyre!("game {}, turn {}: {}", game + 1, number + 1, error))?;
+            if show_provenance {
+                let movements = movements
+                    .iter()
+                    .map(|movement| movement.to_string())
+                    .collect::<Vec<String>>();
+                println!(
+                    "Game {}, turn {}: {}",
+                    game + 1,
+                    number + 1,
+                    movements.join(", ")
+                );
+            }
+            html.push_str(&render::html(&table_after(turn), width));
+            html.push_str("</div>\n");
+        }
+        if let Some(end @ GameEvent::GameWon { .. }) = replay.events.last() {
+            html.push_str(&format!("<p class=\"end\">{}</p>\n", end));
+        }
+    }
+    if let Some(path) = html_path {
+        fs::write(path, PAGE.replace("__GAMES__", &html))?;
+        println!("Annotated replay written to {}", path);
+    }
+
+    println!("Player | Turns | Best moves | Blunders | Tiles lost | Points lost");
+    for (seat, review) in reviews.iter().enumerate() {
+        if review.turns == 0 {
+            continue;
+        }
+        println!(
+            "{:6} | {:5} | {:9.0}% | {:8} | {:10} | {:11}",
+            seat + 1,
+            review.turns,
+            100.0 * review.best as f64 / review.turns as f64,
+            review.blunders,
+            review.tiles_lost,
+            review.points_lost
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,12 @@
+use rand::Rng;
+
+// The generator of every seeded deal, game and run. ChaCha12 by name rather
+// than StdRng, whose algorithm rand may change, so a seed deals the same tiles
+// on every platform and with every version of rand.
+pub type SeededRng = rand_chacha::ChaCha12Rng;
+
+// A number below `bound`, every one equally likely. gen_range over usize draws
+// 32 bits on some platforms and 64 on others, this always draws 64.
+pub fn uniform_index(rng: &mut impl Rng, bound: usize) -> usize {
+    rng.gen_range(0..bound as u64) as usize
+}
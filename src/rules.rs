@@ -0,0 +1,153 @@
+use crate::config;
+use crate::notation::Palette;
+use color_eyre::eyre::{bail, Result};
+use std::fs;
+use std::time::Duration;
+
+// Rule variants that differ between rulebooks and groups of players
+#[derive(Clone, Copy, Debug)]
+pub struct Ruleset {
+    // A player who can lay out tiles has to, instead of drawing to keep them
+    pub must_play: bool,
+    // The turn of the initial meld goes on with rearranging the table once
+    // the meld is laid out. Sets holding a joker stay as they are until the
+    // next turn.
+    pub rearrange_on_opening: bool,
+    // Longest a move may take, a player who is too slow draws a tile instead
+    pub turn_time: Option<Duration>,
+    // Tiles drawn by a player who ran out of time
+    pub timeout_draws: u32,
+    // Tiles drawn by a player whose move broke the rules, their turn is over.
+    // Without it the player has to take the tiles back and try again, bots
+    // draw the three tiles of the rulebook instead.
+    pub illegal_move_draws: Option<u32>,
+}
+
+impl Default for Ruleset {
+    fn default() -> Ruleset {
+        Ruleset {
+            must_play: false,
+            rearrange_on_opening: false,
+            turn_time: None,
+            timeout_draws: 1,
+            illegal_move_draws: None,
+        }
+    }
+}
+
+// The rules file given with `--rules`, for example
+//
+//     [rules]
+//     must_play = true
+//     rearrange_on_opening = true
+//     turn_time = 30       # seconds
+//     timeout_draws = 3    # tiles, 1 without it
+//     illegal_move_draws = 3
+//
+//     [colors]
+//     letters = "RBYK"
+//     names = "Red, Blue, Yellow, Black"
+//
+// Both tables are optional. The colors apply to every tile that is read or
+// printed, in color index order.
+pub fn load(path: &str) -> Result<(Ruleset, Option<Palette>)> {
+    let mut rules = Ruleset::default();
+    let mut palette = None;
+    for table in config::parse(&fs::read_to_string(path)?)? {
+        match table.name.as_str() {
+            "" => table.check_keys(&[])?,
+            "rules" => {
+                table.check_keys(&[
+                    "must_play",
+                    "rearrange_on_opening",
+                    "turn_time",
+                    "timeout_draws",
+                    "illegal_move_draws",
+                ])?;
+                rules.must_play = table.bool("must_play")?.unwrap_or(false);
+                rules.rearrange_on_opening = table.bool("rearrange_on_opening")?.unwrap_or(false);
+                if let Some(seconds) = table.number("turn_time")? {
+                    if seconds <= 0.0 {
+                        bail!(
+                            "line {}: turn_time must be positive, got {}",
+                            table.line,
+                            seconds
+                        );
+                    }
+                    rules.turn_time = Some(Duration::from_secs_f64(seconds));
+                }
+                let draws = |key: &str| -> Result<Option<u32>> {
+                    match table.number(key)? {
+                        Some(tiles) if tiles < 1.0 || tiles.fract() != 0.0 => {
+                            bail!(
+                                "line {}: {} must be a whole number of tiles, at least 1, got {}",
+                                table.line,
+                                key,
+                                tiles
+                            )
+                        }
+                        tiles => Ok(tiles.map(|tiles| tiles as u32)),
+                    }
+                };
+                rules.timeout_draws = draws("timeout_draws")?.unwrap_or(1);
+                rules.illegal_move_draws = draws("illegal_move_draws")?;
+            }
+            "colors" => {
+                table.check_keys(&["letters", "names"])?;
+                palette = Some(parse_palette(&table)?);
+            }
+            other => bail!("line {}: unknown table [{}] in {}", table.line, other, path),
+        }
+    }
+    Ok((rules, palette))
+}
+
+fn parse_palette(table: &config::Table) -> Result<Palette> {
+    let mut palette = Palette::default();
+    if let Some(letters) = table.string("letters")? {
+        let letters = letters
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != ',')
+            .map(|c| c.to_ascii_uppercase())
+            .collect::<Vec<char>>();
+        let Ok(letters) = <[char; 4]>::try_from(letters) else {
+            bail!(
+                "line {}: letters needs one letter for each of the 4 colors",
+                table.line
+            );
+        };
+        if let Some(letter) = letters
+            .iter()
+            .find(|c| !c.is_ascii_alphabetic() || **c == 'J')
+        {
+            bail!(
+                "line {}: {} cannot be a color letter, J is the joker",
+                table.line,
+                letter
+            );
+        }
+        if letters
+            .iter()
+            .enumerate()
+            .any(|(index, c)| letters.iter().skip(index + 1).any(|other| other == c))
+        {
+            bail!("line {}: every color needs its own letter", table.line);
+        }
+        palette.letters = letters;
+    }
+    if let Some(names) = table.string("names")? {
+        let names = names
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<String>>();
+        let Ok(names) = <[String; 4]>::try_from(names) else {
+            bail!(
+                "line {}: names needs a name for each of the 4 colors",
+                table.line
+            );
+        };
+        palette.names = Some(names);
+    }
+    Ok(palette)
+}
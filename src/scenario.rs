@@ -0,0 +1,270 @@
+use crate::bag::Bag;
+use crate::config;
+use crate::deal::{self, Deal};
+use crate::notation;
+use crate::rng::{uniform_index, SeededRng};
+use crate::solver::{Inventory, Tile};
+use color_eyre::eyre::{bail, eyre, Result};
+use rand::{Rng, SeedableRng};
+use std::fs;
+
+// Tiles a rule of the scenario applies to, every tile if it names none
+#[derive(Default)]
+struct Filter {
+    color: Option<u8>,
+    number: Option<u8>,
+    jokers: bool,
+}
+
+impl Filter {
+    fn parse(table: &config::Table) -> Result<Filter> {
+        let mut filter = Filter {
+            jokers: table.bool("jokers")?.unwrap_or(false),
+            ..Filter::default()
+        };
+        if let Some(letter) = table.string("color")? {
+            let letter = letter.trim().to_ascii_uppercase();
+            let color = notation::palette()
+                .letters
+                .iter()
+                .position(|c| letter == c.to_string())
+                .ok_or_else(|| eyre!("line {}: unknown color {}", table.line, letter))?;
+            filter.color = Some(color as u8);
+        }
+        if let Some(number) = table.number("number")? {
+            if !(1.0..=13.0).contains(&number) || number.fract() != 0.0 {
+                bail!(
+                    "line {}: number must be from 1 to 13, got {}",
+                    table.line,
+                    number
+                );
+            }
+            filter.number = Some(number as u8);
+        }
+        if filter.jokers && (filter.color.is_some() || filter.number.is_some()) {
+            bail!(
+                "line {}: jokers have no color or number to match",
+                table.line
+            );
+        }
+        Ok(filter)
+    }
+
+    fn matches(&self, tile: &Tile) -> bool {
+        if self.jokers {
+            return tile.is_joker;
+        }
+        if self.color.is_none() && self.number.is_none() {
+            return true;
+        }
+        !tile.is_joker
+            && self.color.is_none_or(|color| color == tile.color)
+            && self.number.is_none_or(|number| number == tile.number)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Place {
+    Top,
+    Anywhere,
+    Bottom,
+}
+
+// A deal that is random but leans toward a situation worth testing
+pub struct Scenario {
+    // Tiles a seat is dealt whatever the seed
+    fixed: Vec<(usize, Vec<Tile>)>,
+    // How much more likely matching tiles are dealt to a seat, or to every
+    // seat without one. The weights of all matching rules multiply.
+    weights: Vec<(Option<usize>, Filter, f64)>,
+    // Where matching tiles end up in the bag, the last matching rule wins
+    bag: Vec<(Filter, Place)>,
+}
+
+fn seat(table: &config::Table) -> Result<Option<usize>> {
+    match table.number("player")? {
+        Some(player) if player < 1.0 || player.fract() != 0.0 => {
+            bail!(
+                "line {}: player must be 1 or more, got {}",
+                table.line,
+                player
+            )
+        }
+        Some(player) => Ok(Some(player as usize - 1)),
+        None => Ok(None),
+    }
+}
+
+impl Scenario {
+    // The scenario file given with `--scenario`, for example
+    //
+    //     # Player 1 is dealt both jokers
+    //     [[rack]]
+    //     player = 1
+    //     tiles = "J J"
+    //
+    //     # Player 2 gets red tiles three times as often as others
+    //     [[weight]]
+    //     player = 2
+    //     color = "R"
+    //     weight = 3
+    //
+    //     # Red tiles are drawn last
+    //     [[bag]]
+    //     color = "R"
+    //     place = "bottom"
+    //
+    // `color`, `number` and `jokers = true` pick the tiles of a weight or bag
+    // rule. Racks are filled up and the bag is shuffled at random as usual.
+    pub fn load(path: &str) -> Result<Scenario> {
+        let mut scenario = Scenario {
+            fixed: Vec::new(),
+            weights: Vec::new(),
+            bag: Vec::new(),
+        };
+        let mut dealt = Inventory::new(0);
+        for table in config::parse(&fs::read_to_string(path)?)? {
+            match table.name.as_str() {
+                "" => table.check_keys(&[])?,
+                "rack" => {
+                    table.check_keys(&["player", "tiles"])?;
+                    let seat = seat(&table)?
+                        .ok_or_else(|| eyre!("line {}: [[rack]] needs a player", table.line))?;
+                    let tiles = notation::parse_tiles(table.string("tiles")?.unwrap_or_default())?;
+                    tiles.iter().for_each(|tile| dealt.add_tile(tile));
+                    scenario.fixed.push((seat, tiles));
+                }
+                "weight" => {
+                    table.check_keys(&["player", "color", "number", "jokers", "weight"])?;
+                    let Some(weight) = table.number("weight")? else {
+                        bail!("line {}: [[weight]] needs a weight", table.line);
+                    };
+                    if !(weight >= 0.0 && weight.is_finite()) {
+                        bail!(
+                            "line {}: weight cannot be negative, got {}",
+                            table.line,
+                            weight
+                        );
+                    }
+                    scenario
+                        .weights
+                        .push((seat(&table)?, Filter::parse(&table)?, weight));
+                }
+                "bag" => {
+                    table.check_keys(&["color", "number", "jokers", "place"])?;
+                    let place = match table.string("place")? {
+                        Some("top") => Place::Top,
+                        Some("bottom") => Place::Bottom,
+                        _ => bail!("line {}: place must be \"top\" or \"bottom\"", table.line),
+                    };
+                    scenario.bag.push((Filter::parse(&table)?, place));
+                }
+                other => bail!("line {}: unknown table [{}] in {}", table.line, other, path),
+            }
+        }
+        if let Some(tile) = Inventory::new(2).missing(&dealt).first() {
+            bail!("{} deals more copies of {} than the pool holds", path, tile);
+        }
+        Ok(scenario)
+    }
+
+    // Whether the scenario fits a game of `players` racks of `rack_size` tiles
+    pub fn check(&self, players: usize, rack_size: u32) -> Result<()> {
+        let seats = self.fixed.iter().map(|(seat, _)| *seat);
+        let seats = seats.chain(self.weights.iter().filter_map(|(seat, _, _)| *seat));
+        if let Some(seat) = seats.filter(|&seat| seat >= players).min() {
+            bail!(
+                "the scenario deals to player {}, the game has {} players",
+                seat + 1,
+                players
+            );
+        }
+        for seat in 0..players {
+            let fixed = self.fixed(seat).total_tile_count();
+            if fixed > rack_size {
+                bail!(
+                    "the scenario deals {} tiles to player {}, racks hold {}",
+                    fixed,
+                    seat + 1,
+                    rack_size
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn fixed(&self, seat: usize) -> Inventory {
+        let mut rack = Inventory::new(0);
+        for (_, tiles) in self.fixed.iter().filter(|(other, _)| *other == seat) {
+            tiles.iter().for_each(|tile| rack.add_tile(tile));
+        }
+        rack
+    }
+
+    fn weight(&self, seat: usize, tile: &Tile) -> f64 {
+        self.weights
+            .iter()
+            .filter(|(other, filter, _)| {
+                other.is_none_or(|other| other == seat) && filter.matches(tile)
+            })
+            .map(|(_, _, weight)| weight)
+            .product()
+    }
+
+    fn place(&self, tile: &Tile) -> Place {
+        self.bag
+            .iter()
+            .rev()
+            .find(|(filter, _)| filter.matches(tile))
+            .map_or(Place::Anywhere, |&(_, place)| place)
+    }
+
+    // Like `Deal::new`, and just as reproducible for a seed, after `check`
+    // has accepted the player count and rack size
+    pub fn deal(&self, players: usize, rack_size: u32, seed: u64) -> Deal {
+        let mut rng = SeededRng::seed_from_u64(seed);
+        let mut racks = (0..players)
+            .map(|seat| self.fixed(seat))
+            .collect::<Vec<Inventory>>();
+        let mut pool = Inventory::new(2);
+        racks.iter().for_each(|rack| pool.subtract(rack));
+        let mut tiles = deal::shuffle(&pool, &mut rng);
+
+        for (seat, rack) in racks.iter_mut().enumerate() {
+            while rack.total_tile_count() < rack_size {
+                let weights = tiles
+                    .iter()
+                    .map(|tile| self.weight(seat, tile))
+                    .collect::<Vec<f64>>();
+                rack.add_tile(&tiles.remove(pick(&weights, &mut rng)));
+            }
+        }
+        // Stable, so the tiles of every place stay shuffled
+        tiles.sort_by_key(|tile| self.place(tile));
+        Deal {
+            racks,
+            bag: Bag::in_order(tiles),
+        }
+    }
+}
+
+// Index chosen with a chance proportional to its weight, any index alike if
+// every weight is zero
+fn pick(weights: &[f64], rng: &mut impl Rng) -> usize {
+    let total = weights.iter().sum::<f64>();
+    if total <= 0.0 {
+        return uniform_index(rng, weights.len());
+    }
+    let mut left = rng.gen_range(0.0..total);
+    for (index, weight) in weights.iter().enumerate() {
+        if left < *weight {
+            return index;
+        }
+        left -= weight;
+    }
+    // Rounding can leave a little over, which belongs to the last tile that can be picked
+    weights
+        .iter()
+        .rposition(|&weight| weight > 0.0)
+        .unwrap_or(0)
+}
@@ -0,0 +1,157 @@
+use crate::cli::Args;
+use crate::game::{GameConfig, GameState};
+use crate::profile;
+use crate::strategy::Strategy;
+use color_eyre::{eyre::bail, eyre::Report, eyre::Result};
+use std::fs;
+
+// Scores of one round by the official rules: every loser loses the points left
+// on their rack and the winner gains what the losers hold more than they do.
+// Without a winner everybody just loses their rack points.
+pub fn round_scores(rack_points: &[u32], winner: Option<usize>) -> Vec<i32> {
+    let mut scores = rack_points
+        .iter()
+        .map(|&points| -(points as i32))
+        .collect::<Vec<i32>>();
+    if let Some(winner) = winner {
+        let own = rack_points[winner] as i32;
+        scores[winner] = rack_points
+            .iter()
+            .enumerate()
+            .filter(|&(seat, _)| seat != winner)
+            .map(|(_, &points)| points as i32 - own)
+            .sum();
+    }
+    scores
+}
+
+pub struct ScoreSheet {
+    names: Vec<String>,
+    rounds: Vec<Vec<i32>>,
+}
+
+impl ScoreSheet {
+    pub fn new(names: Vec<String>) -> ScoreSheet {
+        ScoreSheet {
+            names,
+            rounds: Vec::new(),
+        }
+    }
+
+    pub fn add_round(&mut self, scores: Vec<i32>) {
+        self.rounds.push(scores);
+    }
+
+    pub fn totals(&self) -> Vec<i32> {
+        (0..self.names.len())
+            .map(|seat| self.rounds.iter().map(|round| round[seat]).sum())
+            .collect()
+    }
+
+    // Player with the highest total, None while tied
+    pub fn leader(&self) -> Option<usize> {
+        let totals = self.totals();
+        let best = *totals.iter().max()?;
+        let mut leaders = (0..self.names.len()).filter(|&seat| totals[seat] == best);
+        match (leaders.next(), leaders.next()) {
+            (Some(seat), None) => Some(seat),
+            _ => None,
+        }
+    }
+
+    pub fn print(&self) {
+        let widths = self
+            .names
+            .iter()
+            .map(|name| name.len().max(6))
+            .collect::<Vec<usize>>();
+        print!("Round");
+        for (name, width) in self.names.iter().zip(&widths) {
+            print!(" | {:>width$}", name, width = width);
+        }
+        println!();
+
+        for (index, round) in self.rounds.iter().enumerate() {
+            print!("{:5}", index + 1);
+            for (score, width) in round.iter().zip(&widths) {
+                print!(" | {:width$}", score, width = width);
+            }
+            println!();
+        }
+
+        print!("Total");
+        for (total, width) in self.totals().iter().zip(&widths) {
+            print!(" | {:width$}", total, width = width);
+        }
+        println!();
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("round");
+        for name in &self.names {
+            csv.push_str(&format!(",{}", name));
+        }
+        csv.push('\n');
+
+        for (index, round) in self.rounds.iter().enumerate() {
+            csv.push_str(&(index + 1).to_string());
+            for score in round {
+                csv.push_str(&format!(",{}", score));
+            }
+            csv.push('\n');
+        }
+
+        csv.push_str("total");
+        for total in self.totals() {
+            csv.push_str(&format!(",{}", total));
+        }
+        csv.push('\n');
+        csv
+    }
+}
+
+// Plays rounds until a player reaches the target score or the round limit is
+// hit. The first move passes to the next player every round.
+pub fn run(args: &Args) -> Result<(), Report> {
+    let config = GameConfig::from_args(args)?;
+    let rounds: usize = args.parse_or("--rounds", 10)?;
+    let target: Option<i32> = args.value("--target").map(str::parse).transpose()?;
+
+    let players = profile::from_args(args, &vec!["0"; config.players].join(","))?;
+    if players.len() != config.players {
+        bail!(
+            "expected {} players, one per seat, got {}",
+            config.players,
+            players.len()
+        );
+    }
+    let strategies = players
+        .iter()
+        .map(|player| player as &dyn Strategy)
+        .collect::<Vec<&dyn Strategy>>();
+
+    let mut sheet = ScoreSheet::new(players.iter().map(|player| player.name.clone()).collect());
+    for round in 0..rounds {
+        let result = GameState::new(&config)
+            .starting_with(round % config.players)
+            .play(&strategies)?;
+        sheet.add_round(round_scores(&result.rack_points, result.winner));
+
+        if target.is_some_and(|target| sheet.totals().iter().any(|&total| total >= target)) {
+            break;
+        }
+    }
+
+    sheet.print();
+    match sheet.leader() {
+        Some(seat) => println!("{} wins the match", players[seat].name),
+        None => println!("The match is tied"),
+    }
+
+    if let Some(path) = args.value("--csv") {
+        fs::write(path, sheet.to_csv())?;
+        println!("Score sheet written to {}", path);
+    }
+
+    Ok(())
+}
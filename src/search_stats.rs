@@ -0,0 +1,156 @@
+use crate::solver::Set;
+use std::time::{Duration, Instant};
+
+// What a search tried for the lowest tile of a position
+#[derive(Clone, Copy)]
+pub enum Candidate {
+    Group,
+    Run,
+    // A group or run with a joker in it
+    Joker,
+    // The tile stays on the rack
+    Skip,
+}
+
+impl Candidate {
+    const ALL: [Candidate; 4] = [
+        Candidate::Group,
+        Candidate::Run,
+        Candidate::Joker,
+        Candidate::Skip,
+    ];
+
+    pub fn of(set: &Set) -> Candidate {
+        if set.tiles.iter().any(|tile| tile.is_joker) {
+            Candidate::Joker
+        } else if set.is_group() {
+            Candidate::Group
+        } else {
+            Candidate::Run
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Candidate::Group => "group",
+            Candidate::Run => "run",
+            Candidate::Joker => "with joker",
+            Candidate::Skip => "tile left out",
+        }
+    }
+}
+
+// Where the memoized searches spend their work, by depth below the position
+// searched and by the kind of candidate, see solver::with_search_stats
+#[derive(Default)]
+pub struct SearchStats {
+    nodes: Vec<u64>,
+    memo_hits: Vec<u64>,
+    // Positions searched rather than found in the memo, and the candidates
+    // tried in them
+    expanded: Vec<u64>,
+    children: Vec<u64>,
+    // Candidates tried of every kind and the time spent in the positions they
+    // lead to, without the time of the candidates tried there in turn
+    tried: [u64; 4],
+    time: [Duration; 4],
+    // Time of the candidates below every candidate being searched
+    below: Vec<Duration>,
+    started: Option<Instant>,
+    total: Duration,
+}
+
+impl SearchStats {
+    pub fn start(&mut self) {
+        self.started = Some(Instant::now());
+    }
+
+    pub fn stop(&mut self) {
+        self.total += self
+            .started
+            .take()
+            .map_or(Duration::ZERO, |started| started.elapsed());
+    }
+
+    fn depth(&self) -> usize {
+        self.below.len()
+    }
+
+    fn add_at_depth(counts: &mut Vec<u64>, depth: usize, amount: u64) {
+        if counts.len() <= depth {
+            counts.resize(depth + 1, 0);
+        }
+        if let Some(count) = counts.get_mut(depth) {
+            *count += amount;
+        }
+    }
+
+    pub fn visit(&mut self, memo_hit: bool) {
+        let depth = self.depth();
+        Self::add_at_depth(&mut self.nodes, depth, 1);
+        if memo_hit {
+            Self::add_at_depth(&mut self.memo_hits, depth, 1);
+        }
+    }
+
+    pub fn expand(&mut self, children: usize) {
+        let depth = self.depth();
+        Self::add_at_depth(&mut self.expanded, depth, 1);
+        Self::add_at_depth(&mut self.children, depth, children as u64);
+    }
+
+    pub fn enter(&mut self) {
+        self.below.push(Duration::ZERO);
+    }
+
+    pub fn leave(&mut self, candidate: Candidate, elapsed: Duration) {
+        let below = self.below.pop().unwrap_or_default();
+        if let Some(tried) = self.tried.get_mut(candidate as usize) {
+            *tried += 1;
+        }
+        if let Some(time) = self.time.get_mut(candidate as usize) {
+            *time += elapsed.saturating_sub(below);
+        }
+        if let Some(parent) = self.below.last_mut() {
+            *parent += elapsed;
+        }
+    }
+
+    // The breakdown as lines of text
+    pub fn report(&self) -> Vec<String> {
+        let nodes = self.nodes.iter().sum::<u64>();
+        let hits = self.memo_hits.iter().sum::<u64>();
+        let percent = |part: u64, whole: u64| 100.0 * part as f64 / whole.max(1) as f64;
+        let mut lines = vec![
+            format!(
+                "Searched {} positions in {:.1?}, {:.0}% answered from the memo",
+                nodes,
+                self.total,
+                percent(hits, nodes)
+            ),
+            "Depth |      Nodes | Memo hits | Branching".to_string(),
+        ];
+        for (depth, &count) in self.nodes.iter().enumerate() {
+            let hits = self.memo_hits.get(depth).copied().unwrap_or(0);
+            let expanded = self.expanded.get(depth).copied().unwrap_or(0);
+            let children = self.children.get(depth).copied().unwrap_or(0);
+            lines.push(format!(
+                "{:5} | {:10} | {:8.0}% | {:9.1}",
+                depth,
+                count,
+                percent(hits, count),
+                children as f64 / expanded.max(1) as f64
+            ));
+        }
+        lines.push("Candidate     |      Tried |       Time".to_string());
+        for ((candidate, tried), time) in Candidate::ALL.iter().zip(self.tried).zip(self.time) {
+            lines.push(format!(
+                "{:13} | {:10} | {:>10.1?}",
+                candidate.name(),
+                tried,
+                time
+            ));
+        }
+        lines
+    }
+}
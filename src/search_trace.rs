@@ -0,0 +1,204 @@
+use crate::json::{self, Json};
+use crate::search_stats::Candidate;
+use std::time::{Duration, Instant};
+
+// One position of a traced search
+struct Node {
+    parent: Option<usize>,
+    // The step that led here from the parent: a set, or a tile kept on the rack
+    step: String,
+    candidate: Option<Candidate>,
+    // Whether the position was found in the memo when first visited, later
+    // visits walk the memo to put the sets together
+    visited: bool,
+    memo_hit: bool,
+    // Why nothing below was searched, or why it came to nothing
+    pruned: Option<String>,
+    // What the search of the position returned
+    value: Option<String>,
+    start: Duration,
+    end: Duration,
+}
+
+// The first positions of a search as a tree, see solver::with_search_trace.
+// Past `max_nodes` positions are only counted, so a trace of a search that
+// runs for minutes stays small enough to look at.
+pub struct SearchTrace {
+    nodes: Vec<Node>,
+    // The node being searched at every depth, None past `max_nodes`
+    path: Vec<Option<usize>>,
+    max_nodes: usize,
+    dropped: u64,
+    started: Instant,
+}
+
+impl SearchTrace {
+    pub fn new(max_nodes: usize) -> SearchTrace {
+        let root = Node {
+            parent: None,
+            step: "position".to_string(),
+            candidate: None,
+            visited: false,
+            memo_hit: false,
+            pruned: None,
+            value: None,
+            start: Duration::ZERO,
+            end: Duration::ZERO,
+        };
+        SearchTrace {
+            nodes: vec![root],
+            path: vec![Some(0)],
+            max_nodes: max_nodes.max(1),
+            dropped: 0,
+            started: Instant::now(),
+        }
+    }
+
+    fn current(&mut self) -> Option<&mut Node> {
+        let index = self.path.last().copied().flatten()?;
+        self.nodes.get_mut(index)
+    }
+
+    pub fn enter(&mut self, candidate: Candidate, step: impl FnOnce() -> String) {
+        let parent = self.path.last().copied().flatten();
+        if parent.is_none() || self.nodes.len() >= self.max_nodes {
+            self.dropped += 1;
+            self.path.push(None);
+            return;
+        }
+        self.nodes.push(Node {
+            parent,
+            step: step(),
+            candidate: Some(candidate),
+            visited: false,
+            memo_hit: false,
+            pruned: None,
+            value: None,
+            start: self.started.elapsed(),
+            end: Duration::ZERO,
+        });
+        self.path.push(Some(self.nodes.len() - 1));
+    }
+
+    pub fn leave(&mut self, value: String) {
+        let end = self.started.elapsed();
+        if let Some(node) = self.current() {
+            node.value = Some(value);
+            node.end = end;
+        }
+        self.path.pop();
+    }
+
+    pub fn visit(&mut self, memo_hit: bool) {
+        if let Some(node) = self.current().filter(|node| !node.visited) {
+            node.visited = true;
+            node.memo_hit = memo_hit;
+        }
+    }
+
+    pub fn prune(&mut self, reason: impl FnOnce() -> String) {
+        if let Some(node) = self.current() {
+            node.pruned = Some(reason());
+        }
+    }
+
+    // Ends the root once the search is done
+    pub fn finish(&mut self) {
+        let end = self.started.elapsed();
+        if let Some(root) = self.nodes.first_mut() {
+            root.end = end;
+        }
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    fn label(node: &Node) -> String {
+        let mut lines = vec![node.step.clone()];
+        if let Some(value) = &node.value {
+            lines.push(format!("= {}", value));
+        }
+        if node.memo_hit {
+            lines.push("memo hit".to_string());
+        }
+        if let Some(reason) = &node.pruned {
+            lines.push(format!("pruned: {}", reason));
+        }
+        lines.join("\\n")
+    }
+
+    // Graphviz: a box for every position with the step that led to it, gray
+    // when answered from the memo and red when pruned
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph search {\n    node [shape=box, fontname=monospace];\n");
+        for (index, node) in self.nodes.iter().enumerate() {
+            let style = if node.pruned.is_some() {
+                ", color=red"
+            } else if node.memo_hit {
+                ", style=filled, fillcolor=lightgray"
+            } else {
+                ""
+            };
+            dot += &format!(
+                "    n{} [label=\"{}\"{}];\n",
+                index,
+                Self::label(node).replace('"', "'"),
+                style
+            );
+            if let Some(parent) = node.parent {
+                dot += &format!("    n{} -> n{};\n", parent, index);
+            }
+        }
+        if self.dropped > 0 {
+            dot += &format!(
+                "    dropped [label=\"{} more positions not traced\", shape=plaintext];\n",
+                self.dropped
+            );
+        }
+        dot + "}\n"
+    }
+
+    // The Trace Event Format of chrome://tracing and Perfetto: a complete
+    // event for every position, nested by time like the calls of the search
+    pub fn to_chrome_trace(&self) -> Json {
+        let micros = |duration: Duration| Json::Number(duration.as_secs_f64() * 1e6);
+        let events = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let mut args = vec![("memo_hit".to_string(), Json::Bool(node.memo_hit))];
+                if let Some(value) = &node.value {
+                    args.push(("value".to_string(), json::string(value.as_str())));
+                }
+                if let Some(reason) = &node.pruned {
+                    args.push(("pruned".to_string(), json::string(reason.as_str())));
+                }
+                json::object([
+                    ("name", json::string(node.step.as_str())),
+                    (
+                        "cat",
+                        json::string(node.candidate.map_or("root", Candidate::name)),
+                    ),
+                    ("ph", json::string("X")),
+                    ("ts", micros(node.start)),
+                    ("dur", micros(node.end.saturating_sub(node.start))),
+                    ("pid", Json::Number(1.0)),
+                    ("tid", Json::Number(1.0)),
+                    ("args", Json::Object(args)),
+                ])
+            })
+            .collect();
+        json::object([
+            ("traceEvents", Json::Array(events)),
+            (
+                "otherData",
+                json::object([("dropped", Json::Number(self.dropped as f64))]),
+            ),
+        ])
+    }
+}
@@ -0,0 +1,1007 @@
+use crate::budget;
+use crate::cli::Args;
+use crate::diagnostic;
+use crate::json::{self, Json};
+use crate::lobby::{self, Lobbies};
+use crate::metrics::{Gauges, Metrics};
+use crate::notation;
+use crate::position::{self, SolveRequest, Update};
+use crate::replay;
+use crate::rules::Ruleset;
+use crate::session::{Session, Sessions};
+use crate::solver::{self, Inventory, LimitExceeded, Set};
+use crate::sse;
+use crate::storage::{GameStore, SavedGame, Users};
+use color_eyre::{eyre::bail, eyre::eyre, eyre::Report, eyre::Result};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::{BufRead, BufReader, Read, Take, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// The web frontend, a single page calling the JSON API
+const PAGE: &str = include_str!("web/index.html");
+
+// Largest request body accepted, a full table is well below this
+const MAX_BODY: usize = 64 * 1024;
+// Largest request line and headers accepted, and most header lines
+const MAX_HEADER_BYTES: u64 = 16 * 1024;
+const MAX_HEADERS: usize = 100;
+// How long finished jobs can still be fetched
+const KEEP_FINISHED: Duration = Duration::from_secs(600);
+// A bucket untouched this long has refilled, so forgetting it changes nothing
+const KEEP_BUCKET: Duration = Duration::from_secs(60);
+// Time past the deadline for the fallback to sets from the rack alone
+const FALLBACK: Duration = Duration::from_secs(1);
+
+struct Limits {
+    // Solves running at the same time
+    workers: usize,
+    // Connections handled at the same time, each on its own thread
+    connections: usize,
+    // Jobs waiting for a worker before new ones are turned away
+    queue: usize,
+    // Solve requests per client and minute
+    rate: f64,
+    timeout: Duration,
+    // Nodes and memory of every solve, see solver::with_limits. The deadline
+    // is set when the solve starts.
+    search: solver::Limits,
+}
+
+enum Status {
+    Queued,
+    Running,
+    Done {
+        table: Vec<Set>,
+        rest: Inventory,
+        // Set when the solve fell back to sets from the rack alone
+        limit: Option<LimitExceeded>,
+    },
+    NoPlay,
+    Failed(String),
+    TimedOut,
+}
+
+struct Job {
+    request: SolveRequest,
+    // The user who asked for the solve, the only one who may see the result
+    owner: Option<String>,
+    status: Status,
+    finished: Option<Instant>,
+}
+
+impl Job {
+    fn to_json(&self, id: u64) -> Json {
+        let status = |name: &str| ("status".to_string(), json::string(name));
+        let mut fields = vec![("id".to_string(), Json::Number(id as f64))];
+        match &self.status {
+            Status::Queued => fields.push(status("queued")),
+            Status::Running => fields.push(status("running")),
+            Status::Done { table, rest, limit } => {
+                fields.push(status("done"));
+                if let Json::Object(entries) =
+                    position::move_json(&self.request.rack, &self.request.table, table, rest)
+                {
+                    fields.extend(entries);
+                }
+                if let Some(limit) = limit {
+                    fields.push(("limit".to_string(), json::string(limit.name())));
+                }
+            }
+            Status::NoPlay => fields.push(status("draw")),
+            Status::Failed(error) => {
+                fields.push(status("failed"));
+                fields.push(("error".to_string(), json::string(error.as_str())));
+            }
+            Status::TimedOut => fields.push(status("timed out")),
+        }
+        Json::Object(fields)
+    }
+}
+
+#[derive(Default)]
+struct Jobs {
+    next_id: u64,
+    jobs: HashMap<u64, Job>,
+    queue: VecDeque<u64>,
+}
+
+// Token bucket of one client, refilled continuously up to a minute's worth
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+struct Server {
+    limits: Limits,
+    jobs: Mutex<Jobs>,
+    queued: Condvar,
+    clients: Mutex<HashMap<IpAddr, Bucket>>,
+    // Streamed and session solves run on the thread of their connection, as
+    // many as there are workers
+    direct: AtomicUsize,
+    connections: AtomicUsize,
+    sessions: Sessions,
+    lobbies: Lobbies,
+    // Without users, everybody may use the server and nobody can save games
+    users: Option<Users>,
+    games: Option<GameStore>,
+    metrics: Metrics,
+}
+
+// A slot for one connection or one solve on its thread, given back when it
+// ends
+struct Slot<'a>(&'a AtomicUsize);
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Server {
+    fn allow(&self, client: IpAddr) -> bool {
+        let mut clients = self.clients.lock().unwrap();
+        let now = Instant::now();
+        clients.retain(|_, bucket| now.duration_since(bucket.updated) < KEEP_BUCKET);
+        let bucket = clients.entry(client).or_insert(Bucket {
+            tokens: self.limits.rate,
+            updated: now,
+        });
+        let refill = now.duration_since(bucket.updated).as_secs_f64() * self.limits.rate / 60.0;
+        bucket.tokens = (bucket.tokens + refill).min(self.limits.rate);
+        bucket.updated = now;
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+
+    // Queues the request and returns its job id, None when the queue is full
+    fn submit(&self, request: SolveRequest, owner: Option<&str>) -> Option<u64> {
+        let mut jobs = self.jobs.lock().unwrap();
+        if jobs.queue.len() >= self.limits.queue {
+            return None;
+        }
+        jobs.jobs.retain(|_, job| {
+            job.finished
+                .is_none_or(|finished| finished.elapsed() < KEEP_FINISHED)
+        });
+        jobs.next_id += 1;
+        let id = jobs.next_id;
+        jobs.jobs.insert(
+            id,
+            Job {
+                request,
+                owner: owner.map(str::to_string),
+                status: Status::Queued,
+                finished: None,
+            },
+        );
+        jobs.queue.push_back(id);
+        self.queued.notify_one();
+        Some(id)
+    }
+
+    fn next_job(&self) -> (u64, SolveRequest) {
+        let mut jobs = self.jobs.lock().unwrap();
+        loop {
+            if let Some(id) = jobs.queue.pop_front() {
+                if let Some(job) = jobs.jobs.get_mut(&id) {
+                    job.status = Status::Running;
+                    return (id, job.request.clone());
+                }
+            }
+            jobs = self.queued.wait(jobs).unwrap();
+        }
+    }
+
+    fn direct_slot(&self) -> Option<Slot<'_>> {
+        let slot = Slot(&self.direct);
+        (self.direct.fetch_add(1, Ordering::SeqCst) < self.limits.workers).then_some(slot)
+    }
+
+    // The limits of a solve starting now, which stops searching the table at
+    // the timeout
+    fn search_limits(&self) -> solver::Limits {
+        solver::Limits {
+            deadline: Some(Instant::now() + self.limits.timeout),
+            ..self.limits.search
+        }
+    }
+
+    fn finish(&self, id: u64, status: Status) {
+        if let Some(job) = self.jobs.lock().unwrap().jobs.get_mut(&id) {
+            job.status = status;
+            job.finished = Some(Instant::now());
+        }
+    }
+}
+
+// Runs queued solves one at a time. The table search stops at the timeout and
+// the move falls back to sets from the rack alone; a solve that does not
+// finish even then is left to run on its own thread while the worker moves on
+// to the next job.
+fn work(server: Arc<Server>) {
+    // The solve of a job that timed out. The worker waits for it before the
+    // next job, so no more than one per worker is ever left running.
+    let mut stray: Option<thread::JoinHandle<()>> = None;
+    loop {
+        if let Some(solve) = stray.take() {
+            let _ = solve.join();
+        }
+        let (id, request) = server.next_job();
+        let (sender, receiver) = mpsc::channel();
+        let start = Instant::now();
+        let limits = server.search_limits();
+        let solve = thread::spawn(move || {
+            let _ = sender.send(solver::with_limits(limits, || {
+                solver::best_move(&request.table, &request.rack, request.opened)
+            }));
+        });
+        let status = match receiver.recv_timeout(server.limits.timeout + FALLBACK) {
+            Ok((Ok(Some((table, rest))), limit)) => Status::Done { table, rest, limit },
+            Ok((Ok(None), _)) => Status::NoPlay,
+            Ok((Err(error), _)) => Status::Failed(error.to_string()),
+            Err(_) => {
+                stray = Some(solve);
+                Status::TimedOut
+            }
+        };
+        match status {
+            Status::Done { .. } => server.metrics.solve("done", start.elapsed()),
+            Status::NoPlay => server.metrics.solve("draw", start.elapsed()),
+            Status::Failed(_) => server.metrics.solve("failed", start.elapsed()),
+            _ => server.metrics.timeout(),
+        }
+        server.finish(id, status);
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    body: String,
+}
+
+// A request head over the limits, answered with 431 instead of 400
+#[derive(Debug)]
+struct HeadersTooLarge;
+
+impl fmt::Display for HeadersTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "request headers are over {} bytes or {} lines",
+            MAX_HEADER_BYTES, MAX_HEADERS
+        )
+    }
+}
+
+impl std::error::Error for HeadersTooLarge {}
+
+// A line of the request head, which cannot run on past MAX_HEADER_BYTES
+fn read_head_line(reader: &mut BufReader<Take<&TcpStream>>, line: &mut String) -> Result<usize> {
+    let read = reader.read_line(line)?;
+    if !line.ends_with('\n') && reader.get_ref().limit() == 0 {
+        return Err(Report::new(HeadersTooLarge));
+    }
+    Ok(read)
+}
+
+fn read_request(stream: &TcpStream) -> Result<Request> {
+    let mut reader = BufReader::new(stream.take(MAX_HEADER_BYTES));
+    let mut line = String::new();
+    read_head_line(&mut reader, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        bail!("malformed request line");
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut length = 0;
+    let mut authorization = None;
+    for headers in 0.. {
+        line.clear();
+        if read_head_line(&mut reader, &mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if headers == MAX_HEADERS {
+            return Err(Report::new(HeadersTooLarge));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| eyre!("invalid Content-Length"))?;
+            } else if name.trim().eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.trim().to_string());
+            }
+        }
+    }
+    if length > MAX_BODY {
+        bail!("request body of {} bytes is too large", length);
+    }
+    reader.get_mut().set_limit(length as u64);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Request {
+        method,
+        path,
+        authorization,
+        body: String::from_utf8(body)?,
+    })
+}
+
+// Returns the status, so the caller can count the response
+fn send(mut stream: &TcpStream, status: u16, content_type: &str, body: &str) -> Result<u16> {
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        409 => "Conflict",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        content_type,
+        body.len(),
+        body
+    )?;
+    Ok(status)
+}
+
+fn respond(stream: &TcpStream, status: u16, body: &Json) -> Result<u16> {
+    send(stream, status, "application/json", &body.to_string())
+}
+
+fn error(message: impl Into<String>) -> Json {
+    json::object([("error", json::string(message))])
+}
+
+// The message of the error with its code and the spans of the request it is
+// about, for clients that show their own messages
+fn report_error(report: &Report) -> Json {
+    let mut fields = vec![("error".to_string(), json::string(report.to_string()))];
+    fields.extend(
+        diagnostic::fields(report)
+            .into_iter()
+            .filter(|(key, _)| key != "message"),
+    );
+    Json::Object(fields)
+}
+
+// An empty body is an empty object
+fn body_json(body: &str) -> Result<Json> {
+    if body.trim().is_empty() {
+        return Ok(Json::Object(Vec::new()));
+    }
+    json::parse(body)
+}
+
+fn parse_update(body: &str) -> Result<Update> {
+    position::update_from(&body_json(body)?)
+}
+
+fn parse_solve(body: &str) -> Result<SolveRequest> {
+    position::solve_request(&body_json(body)?)
+}
+
+// Server-sent events: an `improvement` event for every better move as the
+// solver finds it, then `done` with the final status. A client that hangs up
+// stops the stream.
+fn stream_solve(server: &Server, mut stream: &TcpStream, request: SolveRequest) -> Result<u16> {
+    let Some(_slot) = server.direct_slot() else {
+        return respond(stream, 503, &error("all workers are busy, try again later"));
+    };
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n"
+    )?;
+
+    let start = Instant::now();
+    let mut found = false;
+    let mut connected = true;
+    let (solved, _) = solver::with_limits(server.search_limits(), || {
+        solver::anytime_moves(
+            &request.table,
+            &request.rack,
+            request.opened,
+            &Ruleset::default(),
+            |(table, rest)| {
+                found = true;
+                connected = sse::send_event(
+                    stream,
+                    "improvement",
+                    &position::move_json(&request.rack, &request.table, table, rest),
+                )
+                .is_ok();
+                connected
+            },
+        )
+    });
+    let outcome = match &solved {
+        Ok(()) if found => "done",
+        Ok(()) => "draw",
+        Err(_) => "failed",
+    };
+    server.metrics.solve(outcome, start.elapsed());
+    if connected {
+        let mut status = vec![("status".to_string(), json::string(outcome))];
+        if let Err(report) = solved {
+            status.push(("error".to_string(), json::string(report.to_string())));
+        }
+        let _ = sse::send_event(stream, "done", &Json::Object(status));
+    }
+    Ok(200)
+}
+
+// `POST /sessions/{id}/solve`: the best move from the position of the session,
+// after the changes in the body. With `"play": true` the session moves on to
+// the position after the move.
+fn session_solve(
+    server: &Server,
+    stream: &TcpStream,
+    session: &mut Session,
+    body: &str,
+) -> Result<u16> {
+    let update = match parse_update(body) {
+        Ok(update) => update,
+        Err(report) => return respond(stream, 400, &report_error(&report)),
+    };
+    let Some(_slot) = server.direct_slot() else {
+        return respond(stream, 503, &error("all workers are busy, try again later"));
+    };
+    session.update(update);
+
+    let start = Instant::now();
+    let (solved, limit) = solver::with_limits(server.search_limits(), || session.solve());
+    let play = json::parse(body)
+        .ok()
+        .and_then(|body| body.get("play")?.as_bool())
+        .unwrap_or(false);
+    let mut fields = vec![];
+    let outcome = match solved {
+        Ok(Some((table, rest))) => {
+            if let Json::Object(entries) =
+                position::move_json(&session.rack, &session.table, &table, &rest)
+            {
+                fields.extend(entries);
+            }
+            if let Some(limit) = limit {
+                fields.push(("limit".to_string(), json::string(limit.name())));
+            }
+            if play {
+                session.play(table, rest);
+            }
+            "done"
+        }
+        Ok(None) => "draw",
+        Err(report) => {
+            fields.push(("error".to_string(), json::string(report.to_string())));
+            "failed"
+        }
+    };
+    server.metrics.solve(outcome, start.elapsed());
+    fields.insert(0, ("status".to_string(), json::string(outcome)));
+    fields.push(("cached".to_string(), Json::Number(session.cached() as f64)));
+    respond(
+        stream,
+        if outcome == "failed" { 400 } else { 200 },
+        &Json::Object(fields),
+    )
+}
+
+// `/sessions` and everything below it
+fn route_session(
+    server: &Server,
+    stream: &TcpStream,
+    request: &Request,
+    client: IpAddr,
+    user: Option<&str>,
+) -> Result<u16> {
+    let rest = request.path["/sessions".len()..].trim_start_matches('/');
+    let (id, action) = rest.split_once('/').unwrap_or((rest, ""));
+    let method = request.method.as_str();
+
+    if id.is_empty() {
+        if method != "POST" {
+            return respond(stream, 404, &error("start a session with POST /sessions"));
+        }
+        let update = match parse_update(&request.body) {
+            Ok(update) => update,
+            Err(report) => return respond(stream, 400, &report_error(&report)),
+        };
+        // A saved game to continue, changed by whatever else the body holds
+        let game = json::parse(&request.body)
+            .ok()
+            .and_then(|body| Some(body.get("game")?.as_str()?.to_string()));
+        let saved = match (game, &server.games, user) {
+            (None, _, _) => None,
+            (Some(game), Some(games), Some(user)) => match games.load(user, &game) {
+                Ok(Some(saved)) => Some(saved),
+                Ok(None) => {
+                    return respond(
+                        stream,
+                        404,
+                        &error(format!("you have no saved game {}", game)),
+                    )
+                }
+                Err(report) => return respond(stream, 400, &report_error(&report)),
+            },
+            (Some(_), _, _) => {
+                return respond(
+                    stream,
+                    404,
+                    &error("saved games need a server started with --users"),
+                )
+            }
+        };
+        let (table, rack, opened) = match saved {
+            Some(saved) => (saved.table, saved.rack, saved.opened),
+            None => (Vec::new(), Inventory::new(0), false),
+        };
+        let table = update.table.unwrap_or(table);
+        let rack = update.rack.unwrap_or(rack);
+        let opened = update.opened.unwrap_or(opened);
+        let Some((id, session)) = server.sessions.create(user, table, rack, opened) else {
+            return respond(stream, 503, &error("too many sessions, try again later"));
+        };
+        let mut body = session.lock().unwrap().to_json(&id);
+        if let Json::Object(fields) = &mut body {
+            fields.push((
+                "location".to_string(),
+                json::string(format!("/sessions/{}", id)),
+            ));
+        }
+        return respond(stream, 201, &body);
+    }
+
+    let Some(session) = server.sessions.get(id, user) else {
+        return respond(
+            stream,
+            404,
+            &error("no such session, idle sessions end after 30 minutes"),
+        );
+    };
+    let mut session = session.lock().unwrap();
+    match (method, action) {
+        ("GET", "") => respond(stream, 200, &session.to_json(id)),
+        ("POST", "") => match parse_update(&request.body) {
+            Ok(update) => {
+                session.update(update);
+                respond(stream, 200, &session.to_json(id))
+            }
+            Err(report) => respond(stream, 400, &report_error(&report)),
+        },
+        ("DELETE", "") => {
+            drop(session);
+            server.sessions.remove(id);
+            respond(stream, 200, &json::object([("id", json::string(id)), ("status", json::string("ended"))]))
+        }
+        ("POST", "solve") if !server.allow(client) => {
+            respond(stream, 429, &error("too many requests, try again in a minute"))
+        }
+        ("POST", "solve") => session_solve(server, stream, &mut session, &request.body),
+        _ => respond(stream, 404, &error("unknown session endpoint, use GET, POST or DELETE /sessions/{id} or POST /sessions/{id}/solve")),
+    }
+}
+
+fn game_json(name: &str, game: &SavedGame) -> Json {
+    json::object([
+        ("name", json::string(name)),
+        (
+            "table",
+            Json::Array(
+                game.table
+                    .iter()
+                    .map(|set| json::string(set.to_string()))
+                    .collect(),
+            ),
+        ),
+        ("rack", json::string(replay::rack_text(&game.rack))),
+        ("opened", Json::Bool(game.opened)),
+    ])
+}
+
+// `/games` and everything below it: the saved games of the user
+fn route_games(
+    server: &Server,
+    stream: &TcpStream,
+    request: &Request,
+    user: Option<&str>,
+) -> Result<u16> {
+    let (Some(games), Some(user)) = (&server.games, user) else {
+        return respond(
+            stream,
+            404,
+            &error("saved games need a server started with --users"),
+        );
+    };
+    let name = request.path["/games".len()..].trim_start_matches('/');
+    let result = match (request.method.as_str(), name) {
+        ("GET", "") => games.list(user).map(|names| {
+            let names = names.into_iter().map(json::string).collect();
+            (200, json::object([("games", Json::Array(names))]))
+        }),
+        ("GET", name) => games.load(user, name).map(|game| match game {
+            Some(game) => (200, game_json(name, &game)),
+            None => (404, error(format!("you have no saved game {}", name))),
+        }),
+        ("PUT", name) if !name.is_empty() => parse_update(&request.body).and_then(|update| {
+            let mut game = games.load(user, name)?.unwrap_or(SavedGame {
+                table: Vec::new(),
+                rack: Inventory::new(0),
+                opened: false,
+            });
+            game.table = update.table.unwrap_or(game.table);
+            game.rack = update.rack.unwrap_or(game.rack);
+            game.opened = update.opened.unwrap_or(game.opened);
+            games.save(user, name, &game)?;
+            Ok((200, game_json(name, &game)))
+        }),
+        ("DELETE", name) if !name.is_empty() => {
+            games.delete(user, name).map(|deleted| match deleted {
+                true => (
+                    200,
+                    json::object([
+                        ("name", json::string(name)),
+                        ("status", json::string("deleted")),
+                    ]),
+                ),
+                false => (404, error(format!("you have no saved game {}", name))),
+            })
+        }
+        _ => Ok((
+            404,
+            error("unknown games endpoint, use GET /games or GET, PUT or DELETE /games/{name}"),
+        )),
+    };
+    match result {
+        Ok((status, body)) => respond(stream, status, &body),
+        Err(report) => respond(stream, 400, &report_error(&report)),
+    }
+}
+
+// `/lobbies` and everything below it: games of humans against bots, see
+// src/lobby.rs. `POST /lobbies` with `{"players": 3, "bots": 1}` opens a
+// lobby, `POST /lobbies/{code}/join` takes a seat. Every seat has a token,
+// `GET /lobbies/{code}?token=...` shows the game to its player, also after
+// reconnecting, and `POST /lobbies/{code}/move` with the token and the new
+// table or `POST /lobbies/{code}/draw` with the token make their move. A move
+// with the `"turn"` of the game it was made on is refused once the game moved
+// on, so two windows of a player cannot both make it.
+fn route_lobby(server: &Server, stream: &TcpStream, request: &Request) -> Result<u16> {
+    let (path, query) = request.path.split_once('?').unwrap_or((&request.path, ""));
+    let rest = path["/lobbies".len()..].trim_start_matches('/');
+    let (code, action) = rest.split_once('/').unwrap_or((rest, ""));
+    let method = request.method.as_str();
+    let body = match request.body.trim() {
+        "" => Json::Object(Vec::new()),
+        text => match json::parse(text) {
+            Ok(body) => body,
+            Err(report) => return respond(stream, 400, &report_error(&report)),
+        },
+    };
+
+    if code.is_empty() {
+        if method != "POST" {
+            return respond(stream, 404, &error("open a lobby with POST /lobbies"));
+        }
+        let field = |key: &str, default: u64| match body.get(key) {
+            None => Ok(default),
+            Some(Json::Number(value)) if *value >= 0.0 && value.fract() == 0.0 => Ok(*value as u64),
+            Some(_) => Err(format!("\"{}\" has to be a whole number", key)),
+        };
+        // The same seed deals the same tiles, e.g. to replay a game
+        let seed = body.get("seed").map(|_| field("seed", 0)).transpose();
+        let (players, bots, seed) = match (field("players", 2), field("bots", 1), seed) {
+            (Ok(players), Ok(bots), Ok(seed)) => (players as usize, bots as usize, seed),
+            (Err(message), _, _) | (_, Err(message), _) | (_, _, Err(message)) => {
+                return respond(stream, 400, &error(message))
+            }
+        };
+        return match server.lobbies.create(players, bots, seed) {
+            Ok(Some((code, token, lobby))) => {
+                let mut body = lobby.lock().unwrap().to_json(&code, 0);
+                if let Json::Object(fields) = &mut body {
+                    fields.push(("token".to_string(), json::string(token)));
+                    fields.push((
+                        "location".to_string(),
+                        json::string(format!("/lobbies/{}", code)),
+                    ));
+                }
+                respond(stream, 201, &body)
+            }
+            Ok(None) => respond(stream, 503, &error("too many lobbies, try again later")),
+            Err(report) => respond(stream, 400, &report_error(&report)),
+        };
+    }
+
+    let Some((code, lobby)) = server.lobbies.get(code) else {
+        return respond(
+            stream,
+            404,
+            &error("no such lobby, idle lobbies end after 30 minutes"),
+        );
+    };
+    let mut lobby = lobby.lock().unwrap();
+    if (method, action) == ("POST", "join") {
+        return match lobby.join() {
+            Ok((seat, token)) => {
+                let mut body = lobby.to_json(&code, seat);
+                if let Json::Object(fields) = &mut body {
+                    fields.push(("token".to_string(), json::string(token)));
+                }
+                respond(stream, 200, &body)
+            }
+            Err(report) => respond(stream, 409, &report_error(&report)),
+        };
+    }
+
+    let token = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("token="))
+        .or_else(|| body.get("token").and_then(Json::as_str));
+    let Some(seat) = token.and_then(|token| lobby.seat(token)) else {
+        return respond(
+            stream,
+            401,
+            &error("missing or unknown seat token, send the token from joining the lobby"),
+        );
+    };
+    let seen = match body.get("turn") {
+        None => None,
+        Some(Json::Number(turn)) if *turn >= 0.0 && turn.fract() == 0.0 => Some(*turn as u32),
+        Some(_) => return respond(stream, 400, &error("\"turn\" has to be a whole number")),
+    };
+    let played = match (method, action) {
+        ("GET", "") => return respond(stream, 200, &lobby.to_json(&code, seat)),
+        ("POST", "move") => match parse_update(&request.body) {
+            Ok(Update { table: Some(table), .. }) => lobby.play(seat, table, seen),
+            Ok(_) => Err(eyre!("missing \"table\" with the table after the move")),
+            Err(report) => Err(report),
+        },
+        ("POST", "draw") => lobby.draw(seat, seen),
+        _ => {
+            return respond(
+                stream,
+                404,
+                &error("unknown lobby endpoint, use GET /lobbies/{code} or POST /lobbies/{code}/join, move or draw"),
+            )
+        }
+    };
+    match played {
+        Ok(applied) => {
+            let mut body = lobby.to_json(&code, seat);
+            if let Json::Object(fields) = &mut body {
+                fields.push(("move".to_string(), lobby::applied_json(&applied)));
+            }
+            respond(stream, 200, &body)
+        }
+        Err(report) => respond(stream, 409, &report_error(&report)),
+    }
+}
+
+// Name of the endpoint in the metrics, job ids are left out so every job
+// counts towards the same endpoint
+fn endpoint(path: &str) -> &'static str {
+    match path {
+        "/solve" => "/solve",
+        "/solve/stream" => "/solve/stream",
+        "/" => "/",
+        "/metrics" => "/metrics",
+        path if path.starts_with("/jobs/") => "/jobs",
+        path if path == "/sessions" || path.starts_with("/sessions/") => "/sessions",
+        path if path == "/games" || path.starts_with("/games/") => "/games",
+        path if path == "/lobbies"
+            || path.starts_with("/lobbies/")
+            || path.starts_with("/lobbies?") =>
+        {
+            "/lobbies"
+        }
+        _ => "other",
+    }
+}
+
+fn handle(server: &Server, stream: &TcpStream) -> Result<()> {
+    let (endpoint, status) = match read_request(stream) {
+        Ok(request) => (endpoint(&request.path), route(server, stream, &request)?),
+        Err(report) => {
+            let status = match report.downcast_ref::<HeadersTooLarge>() {
+                Some(_) => 431,
+                None => 400,
+            };
+            ("other", respond(stream, status, &report_error(&report))?)
+        }
+    };
+    server.metrics.request(endpoint, status);
+    Ok(())
+}
+
+fn route(server: &Server, stream: &TcpStream, request: &Request) -> Result<u16> {
+    let client = stream.peer_addr()?.ip();
+    // The page and the metrics are public, everything else needs a known user
+    let user = match &server.users {
+        Some(_) if request.path == "/" || request.path == "/metrics" => None,
+        Some(users) => match users.authenticate(request.authorization.as_deref()) {
+            Some(user) => Some(user),
+            None => {
+                return respond(
+                    stream,
+                    401,
+                    &error("missing or unknown token, send Authorization: Bearer <token>"),
+                )
+            }
+        },
+        None => None,
+    };
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/solve") => {
+            if !server.allow(client) {
+                return respond(stream, 429, &error("too many requests, try again in a minute"));
+            }
+            let solve = match parse_solve(&request.body) {
+                Ok(solve) => solve,
+                Err(report) => return respond(stream, 400, &report_error(&report)),
+            };
+            match server.submit(solve, user) {
+                Some(id) => respond(
+                    stream,
+                    202,
+                    &json::object([
+                        ("id", Json::Number(id as f64)),
+                        ("status", json::string("queued")),
+                        ("location", json::string(format!("/jobs/{}", id))),
+                    ]),
+                ),
+                None => respond(stream, 503, &error("the queue is full, try again later")),
+            }
+        }
+        ("POST", "/solve/stream") => {
+            if !server.allow(client) {
+                return respond(stream, 429, &error("too many requests, try again in a minute"));
+            }
+            match parse_solve(&request.body) {
+                Ok(solve) => stream_solve(server, stream, solve),
+                Err(report) => respond(stream, 400, &report_error(&report)),
+            }
+        }
+        ("GET", path) if path.starts_with("/jobs/") => {
+            let id = path["/jobs/".len()..].parse::<u64>().ok();
+            let jobs = server.jobs.lock().unwrap();
+            let job = id.and_then(|id| Some((id, jobs.jobs.get(&id)?)));
+            match job.filter(|(_, job)| job.owner.as_deref() == user) {
+                Some((id, job)) => respond(stream, 200, &job.to_json(id)),
+                None => respond(stream, 404, &error("no such job, finished jobs are kept for 10 minutes")),
+            }
+        }
+        (_, path) if path == "/sessions" || path.starts_with("/sessions/") => {
+            route_session(server, stream, request, client, user)
+        }
+        (_, path) if path == "/games" || path.starts_with("/games/") => route_games(server, stream, request, user),
+        (_, path) if endpoint(path) == "/lobbies" => route_lobby(server, stream, request),
+        ("GET", "/") => {
+            let letters = notation::palette().letters.iter().collect::<String>();
+            send(stream, 200, "text/html; charset=utf-8", &PAGE.replace("__LETTERS__", &letters))
+        }
+        ("GET", "/metrics") => {
+            let gauges = Gauges {
+                queued: server.jobs.lock().unwrap().queue.len(),
+                direct: server.direct.load(Ordering::SeqCst).min(server.limits.workers),
+                sessions: server.sessions.len(),
+                lobbies: server.lobbies.len(),
+                workers: server.limits.workers,
+            };
+            send(stream, 200, "text/plain; version=0.0.4", &server.metrics.render(&gauges))
+        }
+        _ => respond(
+            stream,
+            404,
+            &error("unknown endpoint, use POST /solve, POST /solve/stream, GET /jobs/{id}, /sessions, /games or /lobbies"),
+        ),
+    }
+}
+
+// Solver as a web service: `POST /solve` queues a solve and returns its job id,
+// `GET /jobs/{id}` reports the result, `POST /solve/stream` sends every better
+// move as it is found over server-sent events and `GET /metrics` reports
+// request counts and solver work for Prometheus. `/sessions` follows a game
+// over many turns and keeps the solver memos in between. Every solve stops
+// searching the table at `--timeout`, `--max-nodes` and `--max-memory` keep it
+// from taking all the memory, and at most `--workers` solves and
+// `--connections` connections run at the same time.
+// `/lobbies` hosts games of humans against bots, joined by code. With
+// `--users` every request needs the token of a user, and `/games` keeps
+// the games of every user in `--data`. `GET /` serves a page to use the solver
+// from a browser.
+pub fn run(args: &Args) -> Result<(), Report> {
+    let address = format!(
+        "{}:{}",
+        args.value("--host").unwrap_or("127.0.0.1"),
+        args.parse_or("--port", 8080u16)?
+    );
+    let cores = thread::available_parallelism().map_or(2, |cores| cores.get());
+    let limits = Limits {
+        workers: args.parse_or("--workers", cores)?,
+        connections: args.parse_or("--connections", 256)?,
+        queue: args.parse_or("--queue", 100)?,
+        rate: args.parse_or("--rate", 30.0)?,
+        timeout: Duration::from_secs_f64(args.parse_or("--timeout", 10.0)?),
+        search: budget::search_limits(args)?,
+    };
+    if limits.workers == 0 || limits.connections == 0 || limits.rate <= 0.0 {
+        bail!("--workers, --connections and --rate must be positive");
+    }
+    let users = args.value("--users").map(Users::load).transpose()?;
+    let games = match (&users, args.value("--data")) {
+        (Some(_), data) => Some(GameStore::new(data.unwrap_or("saved-games"))?),
+        (None, Some(_)) => bail!("--data needs --users, saved games belong to a user"),
+        (None, None) => None,
+    };
+
+    let listener = TcpListener::bind(&address)?;
+    let server = Arc::new(Server {
+        limits,
+        jobs: Mutex::new(Jobs::default()),
+        queued: Condvar::new(),
+        clients: Mutex::new(HashMap::new()),
+        direct: AtomicUsize::new(0),
+        connections: AtomicUsize::new(0),
+        sessions: Sessions::new(args.parse_or("--sessions", 1000)?),
+        lobbies: Lobbies::new(args.parse_or("--lobbies", 100)?),
+        users,
+        games,
+        metrics: Metrics::default(),
+    });
+    for _ in 0..server.limits.workers {
+        let server = server.clone();
+        thread::spawn(move || work(server));
+    }
+    println!(
+        "Listening on http://{} with {} workers",
+        address, server.limits.workers
+    );
+
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        if server.connections.fetch_add(1, Ordering::SeqCst) >= server.limits.connections {
+            server.connections.fetch_sub(1, Ordering::SeqCst);
+            let _ = respond(
+                &stream,
+                503,
+                &error("too many connections, try again later"),
+            );
+            continue;
+        }
+        let server = server.clone();
+        thread::spawn(move || {
+            // Gives back the connection counted above
+            let _slot = Slot(&server.connections);
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(10)));
+            if let Err(report) = handle(&server, &stream) {
+                eprintln!("request failed: {}", report);
+            }
+        });
+    }
+    Ok(())
+}
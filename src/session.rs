@@ -0,0 +1,131 @@
+use crate::json::{self, Json};
+use crate::position::Update;
+use crate::replay;
+use crate::solver::{self, Inventory, Set, SolverCache};
+use color_eyre::eyre::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Sessions nobody used for this long are dropped
+const IDLE: Duration = Duration::from_secs(30 * 60);
+
+// A game followed by an API client over many turns. The solver memos stay with
+// the session, so every turn reuses the work of the turns before it.
+pub struct Session {
+    pub table: Vec<Set>,
+    pub rack: Inventory,
+    pub opened: bool,
+    cache: SolverCache,
+    // The user who started the session, the only one who may use it
+    owner: Option<String>,
+}
+
+impl Session {
+    pub fn update(&mut self, update: Update) {
+        if let Some(table) = update.table {
+            self.table = table;
+        }
+        if let Some(rack) = update.rack {
+            self.rack = rack;
+        }
+        if let Some(opened) = update.opened {
+            self.opened = opened;
+        }
+    }
+
+    // Entries in the solver memos, a measure of the work there is to reuse
+    pub fn cached(&self) -> usize {
+        self.cache.entries()
+    }
+
+    pub fn solve(&mut self) -> Result<Option<(Vec<Set>, Inventory)>> {
+        solver::best_move_cached(&self.table, &self.rack, self.opened, &mut self.cache)
+    }
+
+    // The move was made, the session continues from the position after it
+    pub fn play(&mut self, table: Vec<Set>, rest: Inventory) {
+        self.table = table;
+        self.rack = rest;
+        self.opened = true;
+    }
+
+    pub fn to_json(&self, id: &str) -> Json {
+        json::object([
+            ("id", json::string(id)),
+            (
+                "table",
+                Json::Array(
+                    self.table
+                        .iter()
+                        .map(|set| json::string(set.to_string()))
+                        .collect(),
+                ),
+            ),
+            ("rack", json::string(replay::rack_text(&self.rack))),
+            ("opened", Json::Bool(self.opened)),
+            ("cached", Json::Number(self.cached() as f64)),
+        ])
+    }
+}
+
+type SharedSession = Arc<Mutex<Session>>;
+
+// Sessions by id, each with the time it was last used
+pub struct Sessions {
+    limit: usize,
+    sessions: Mutex<HashMap<String, (Instant, SharedSession)>>,
+}
+
+impl Sessions {
+    pub fn new(limit: usize) -> Sessions {
+        Sessions {
+            limit,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Starts a session and returns its id with the session, None when there
+    // are too many
+    pub fn create(
+        &self,
+        owner: Option<&str>,
+        table: Vec<Set>,
+        rack: Inventory,
+        opened: bool,
+    ) -> Option<(String, SharedSession)> {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, (used, _)| used.elapsed() < IDLE);
+        if sessions.len() >= self.limit {
+            return None;
+        }
+        let id = format!("{:016x}", rand::random::<u64>());
+        let session = Arc::new(Mutex::new(Session {
+            table,
+            rack,
+            opened,
+            cache: SolverCache::default(),
+            owner: owner.map(str::to_string),
+        }));
+        sessions.insert(id.clone(), (Instant::now(), session.clone()));
+        Some((id, session))
+    }
+
+    // The session, if it belongs to the user
+    pub fn get(&self, id: &str, user: Option<&str>) -> Option<SharedSession> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let (used, session) = sessions.get_mut(id).filter(|(used, session)| {
+            used.elapsed() < IDLE && session.lock().unwrap().owner.as_deref() == user
+        })?;
+        *used = Instant::now();
+        Some(session.clone())
+    }
+
+    pub fn remove(&self, id: &str) -> bool {
+        self.sessions.lock().unwrap().remove(id).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+}
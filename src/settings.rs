@@ -0,0 +1,134 @@
+use crate::completions;
+use crate::config::{self, Value};
+use color_eyre::eyre::{bail, Result};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// Environment variables named like the options, `--move-time` is read from
+// RUMMIKUB_MOVE_TIME
+const ENV_PREFIX: &str = "RUMMIKUB_";
+
+// $XDG_CONFIG_HOME/rummikubbot/config.toml, ~/.config when it is not set
+fn config_path() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("rummikubbot").join("config.toml"))
+}
+
+// `move_time` for `--move-time`
+pub fn key_of(option: &str) -> String {
+    option.trim_start_matches("--").replace('-', "_")
+}
+
+// The arguments a default adds: true adds the flag, false leaves it out
+pub fn option_args(option: &str, value: &Value) -> Vec<String> {
+    match value {
+        Value::Bool(true) => vec![option.to_string()],
+        Value::Bool(false) => Vec::new(),
+        Value::String(text) => vec![option.to_string(), text.clone()],
+        Value::Number(number) => vec![option.to_string(), number.to_string()],
+    }
+}
+
+// Defaults of the config file, keys above the first header for every
+// subcommand and a table per subcommand for that one only, e.g.
+//
+//     lang = "nl"
+//     rules = "/home/me/house-rules.toml"
+//     move_time = 2.5
+//
+//     [simulate]
+//     games = 500
+fn file_defaults(
+    path: &PathBuf,
+    command: Option<&str>,
+    defaults: &mut Vec<(String, Value)>,
+) -> Result<()> {
+    let Ok(text) = fs::read_to_string(path) else {
+        return Ok(());
+    };
+    let every = completions::every_option();
+    for table in config::parse(&text)? {
+        let options = match table.name.as_str() {
+            "" => every.clone(),
+            name => match completions::options_of(name) {
+                Some(options) => options,
+                None => bail!(
+                    "{}: line {}: unknown subcommand [{}]",
+                    path.display(),
+                    table.line,
+                    name
+                ),
+            },
+        };
+        let keys = options
+            .iter()
+            .map(|option| key_of(option))
+            .collect::<Vec<_>>();
+        table.check_keys(&keys.iter().map(String::as_str).collect::<Vec<_>>())?;
+        if !table.name.is_empty() && Some(table.name.as_str()) != command {
+            continue;
+        }
+        for key in table.keys() {
+            if let Some(value) = table.get(key) {
+                defaults.push((format!("--{}", key.replace('_', "-")), value.clone()));
+            }
+        }
+    }
+    Ok(())
+}
+
+// The command line with the defaults of the config file and the environment
+// added for the options it does not give. Flags win over the environment,
+// which wins over the file. Keys above the first header of the file and the
+// environment apply to every subcommand that takes the option. Unlike keys of
+// the file, variables that name no option are ignored, the environment is
+// shared with other programs.
+pub fn layered(mut args: Vec<String>) -> Result<Vec<String>> {
+    let command = args
+        .first()
+        .map(String::as_str)
+        .filter(|arg| !arg.starts_with("--"));
+    let mut defaults = Vec::new();
+    if let Some(path) = config_path() {
+        file_defaults(&path, command, &mut defaults)?;
+    }
+
+    let mut variables = env::vars()
+        .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+        .collect::<Vec<_>>();
+    variables.sort();
+    for (name, text) in variables {
+        let option = format!(
+            "--{}",
+            name[ENV_PREFIX.len()..].to_lowercase().replace('_', "-")
+        );
+        let value = match text.as_str() {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => Value::String(text),
+        };
+        defaults.push((option, value));
+    }
+
+    let taken = command
+        .and_then(completions::options_of)
+        .unwrap_or_else(completions::every_option);
+    defaults.retain(|(option, _)| taken.contains(&option.as_str()));
+
+    // The last default of an option is the one that counts
+    let mut added = Vec::new();
+    for (index, (option, value)) in defaults.iter().enumerate() {
+        let overridden = defaults[index + 1..]
+            .iter()
+            .any(|(later, _)| later == option);
+        if !overridden && !args.contains(option) {
+            added.extend(option_args(option, value));
+        }
+    }
+    args.extend(added);
+    Ok(args)
+}
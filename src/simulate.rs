@@ -0,0 +1,237 @@
+use crate::cli::Args;
+use crate::events::GameEvent;
+use crate::game::{GameConfig, GameResult, GameState};
+use crate::profile;
+use crate::replay;
+use crate::strategy::Strategy;
+use color_eyre::{eyre::bail, eyre::Report, eyre::Result};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::mpsc;
+
+struct Summary {
+    count: usize,
+    mean: f64,
+    std_dev: f64,
+    median: u32,
+    min: u32,
+    max: u32,
+}
+
+impl Summary {
+    fn new(values: &[u32]) -> Option<Summary> {
+        if values.is_empty() {
+            return None;
+        }
+
+        let mut sorted = values.to_vec();
+        sorted.sort_unstable();
+        let count = sorted.len();
+        let mean = sorted.iter().map(|&value| value as f64).sum::<f64>() / count as f64;
+        let variance = sorted
+            .iter()
+            .map(|&value| (value as f64 - mean).powi(2))
+            .sum::<f64>()
+            / count as f64;
+
+        Some(Summary {
+            count,
+            mean,
+            std_dev: variance.sqrt(),
+            median: sorted[count / 2],
+            min: sorted[0],
+            max: sorted[count - 1],
+        })
+    }
+
+    fn print(&self, title: &str) {
+        println!(
+            "{}: n={} mean={:.2} sd={:.2} median={} min={} max={}",
+            title, self.count, self.mean, self.std_dev, self.median, self.min, self.max
+        );
+    }
+}
+
+// Count how often each value occurs, indexed by value
+fn histogram(values: &[u32]) -> Vec<usize> {
+    let max = values.iter().copied().max().unwrap_or(0) as usize;
+    let mut counts = vec![0; max + 1];
+    for &value in values {
+        counts[value as usize] += 1;
+    }
+    counts
+}
+
+fn print_histogram(title: &str, values: &[u32]) {
+    let counts = histogram(values);
+    let widest = counts.iter().copied().max().unwrap_or(0).max(1);
+
+    println!("{}", title);
+    for (value, &count) in counts.iter().enumerate().filter(|(_, &count)| count > 0) {
+        println!(
+            "{:4} | {:<50} {}",
+            value,
+            "#".repeat(count * 50 / widest),
+            count
+        );
+    }
+}
+
+fn write_histogram(path: &Path, values: &[u32]) -> Result<(), Report> {
+    let data = histogram(values)
+        .iter()
+        .enumerate()
+        .map(|(value, count)| format!("{} {}\n", value, count))
+        .collect::<String>();
+    fs::write(path, data)?;
+    Ok(())
+}
+
+// Write gnuplot data files and a script that renders them to PNG charts
+fn write_plots(dir: &Path, game_lengths: &[u32], initial_melds: &[u32]) -> Result<(), Report> {
+    fs::create_dir_all(dir)?;
+    write_histogram(&dir.join("game_length.dat"), game_lengths)?;
+    write_histogram(&dir.join("initial_meld.dat"), initial_melds)?;
+
+    let script = "set terminal png size 800,600\n\
+                  set style fill solid\n\
+                  set ylabel 'Count'\n\
+                  set output 'game_length.png'\n\
+                  set title 'Game length'\n\
+                  set xlabel 'Turns'\n\
+                  plot 'game_length.dat' using 1:2 with boxes notitle\n\
+                  set output 'initial_meld.png'\n\
+                  set title 'Tiles on rack at the initial meld'\n\
+                  set xlabel 'Tiles on rack'\n\
+                  plot 'initial_meld.dat' using 1:2 with boxes notitle\n";
+    fs::write(dir.join("plots.gp"), script)?;
+
+    match Command::new("gnuplot")
+        .arg("plots.gp")
+        .current_dir(dir)
+        .status()
+    {
+        Ok(status) if status.success() => println!("Charts written to {}", dir.display()),
+        _ => println!(
+            "gnuplot not available, run `gnuplot plots.gp` in {} to render the charts",
+            dir.display()
+        ),
+    }
+    Ok(())
+}
+
+pub fn run(args: &Args) -> Result<(), Report> {
+    let games: usize = args.parse_or("--games", 100)?;
+    let config = GameConfig::from_args(args)?;
+
+    // Greedy players without joker weights unless told otherwise
+    let players = profile::from_args(args, &vec!["0"; config.players].join(","))?;
+    if players.len() != config.players {
+        bail!(
+            "expected {} players, one per seat, got {}",
+            config.players,
+            players.len()
+        );
+    }
+    let strategies = players
+        .iter()
+        .map(|player| player as &dyn Strategy)
+        .collect::<Vec<&dyn Strategy>>();
+    let log = args.flag("--log");
+    // Game n of the run is dealt with seed + n, wrapping around
+    let seed: Option<u64> = args.value("--seed").map(str::parse).transpose()?;
+    // The same log written to a file, to be analysed later
+    let replay_path = args.value("--replay");
+    if (log || replay_path.is_some()) && config.headless {
+        bail!("headless games do not report events, --log and --replay need a normal game");
+    }
+    let mut replay_text = String::new();
+    let results = (0..games)
+        .map(|game| {
+            let mut state = match seed {
+                Some(seed) => GameState::seeded(&config, seed.wrapping_add(game as u64)),
+                None => GameState::new(&config),
+            };
+            // The deal makes the log a full replay, see src/replay.rs
+            let mut header = format!("Game {}\n", game + 1);
+            for seat in 0..state.players() {
+                let rack = replay::rack_text(state.rack(seat));
+                header.push_str(&format!("  Player {} is dealt {}\n", seat + 1, rack));
+            }
+            if log {
+                print!("{}", header);
+                state.subscribe(Box::new(|event: &GameEvent| println!("  {}", event)));
+            }
+            let (sender, receiver) = mpsc::channel();
+            if replay_path.is_some() {
+                state.subscribe(Box::new(sender));
+            }
+
+            let result = state.play(&strategies)?;
+            if replay_path.is_some() {
+                replay_text.push_str(&header);
+                for event in receiver.try_iter() {
+                    replay_text.push_str(&format!("  {}\n", event));
+                }
+            }
+            Ok(result)
+        })
+        .collect::<Result<Vec<GameResult>>>()?;
+    if let Some(path) = replay_path {
+        fs::write(path, replay_text)?;
+        println!("Replay of {} games written to {}", games, path);
+    }
+
+    let game_lengths = results
+        .iter()
+        .map(|result| result.turns)
+        .collect::<Vec<u32>>();
+    let initial_melds = results
+        .iter()
+        .flat_map(|result| result.initial_melds.iter().flatten().copied())
+        .collect::<Vec<u32>>();
+
+    if let Some(summary) = Summary::new(&game_lengths) {
+        summary.print("Game length in turns");
+    }
+    if let Some(summary) = Summary::new(&initial_melds) {
+        summary.print("Tiles on rack at initial meld");
+    }
+
+    let mut wins = vec![0; config.players];
+    for winner in results.iter().filter_map(|result| result.winner) {
+        wins[winner] += 1;
+    }
+    for (seat, count) in wins.iter().enumerate() {
+        println!("Player {} won {} games", seat + 1, count);
+    }
+    println!(
+        "{} games were blocked with an empty bag, {} of them drawn",
+        results.iter().filter(|result| result.blocked).count(),
+        results
+            .iter()
+            .filter(|result| result.winner.is_none())
+            .count()
+    );
+    for (game, result) in results.iter().enumerate() {
+        for incident in &result.incidents {
+            println!(
+                "Game {}, turn {}: player {} drew {} tiles after an illegal move: {}",
+                game + 1,
+                incident.turn + 1,
+                incident.seat + 1,
+                incident.penalty,
+                incident.reason
+            );
+        }
+    }
+
+    print_histogram("Game length in turns", &game_lengths);
+
+    if let Some(dir) = args.value("--plot") {
+        write_plots(Path::new(dir), &game_lengths, &initial_melds)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use crate::json::{self, Json};
+use crate::notation;
+use crate::solver::{self, Inventory, Origin, Set, Tile};
+use color_eyre::eyre::{bail, Result};
+use std::fmt;
+use std::ops::Deref;
+
+// Tiles laid out in legal sets: a complete arrangement found by the solver, or
+// the table after a move. Derefs to the sets.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct Solution {
+    sets: Vec<Set>,
+}
+
+fn tile_order(tile: &Tile) -> (u8, u8, bool) {
+    (tile.number, tile.color, tile.is_joker)
+}
+
+impl Solution {
+    pub fn into_sets(self) -> Vec<Set> {
+        self.sets
+    }
+
+    pub fn tiles(&self) -> Inventory {
+        Inventory::from_sets(&self.sets)
+    }
+
+    pub fn total_tiles(&self) -> u32 {
+        self.sets.iter().map(|set| set.tiles.len() as u32).sum()
+    }
+
+    // Jokers count as the tile they stand in for, as for the initial meld
+    pub fn total_points(&self) -> u32 {
+        self.sets.iter().map(Set::points).sum()
+    }
+
+    pub fn jokers_used(&self) -> u32 {
+        self.sets
+            .iter()
+            .flat_map(|set| set.tiles.iter())
+            .filter(|tile| tile.is_joker)
+            .count() as u32
+    }
+
+    // The same sets with their tiles in ascending order and the sets ordered
+    // by their tiles, so arrangements of the same sets compare equal
+    pub fn canonical(&self) -> Solution {
+        let mut sets = self.sets.clone();
+        for set in &mut sets {
+            set.tiles.sort_by_key(tile_order);
+        }
+        sets.sort_by(|a, b| {
+            a.tiles
+                .iter()
+                .map(tile_order)
+                .cmp(b.tiles.iter().map(tile_order))
+        });
+        Solution { sets }
+    }
+
+    // `{"sets": ["R1 R2 R3", "B9 O9 K9"], "tiles": 6, "points": 33, "jokers": 0}`
+    pub fn to_json(&self) -> Json {
+        json::object([
+            (
+                "sets",
+                Json::Array(
+                    self.sets
+                        .iter()
+                        .map(|set| json::string(set.to_string()))
+                        .collect(),
+                ),
+            ),
+            ("tiles", Json::Number(self.total_tiles() as f64)),
+            ("points", Json::Number(self.total_points() as f64)),
+            ("jokers", Json::Number(self.jokers_used() as f64)),
+        ])
+    }
+
+    // The sets of `to_json`, or just the array of sets. The totals are worked
+    // out again from the sets.
+    pub fn from_json(value: &Json) -> Result<Solution> {
+        let Json::Array(sets) = value.get("sets").unwrap_or(value) else {
+            bail!("a solution has to be an array of sets");
+        };
+        sets.iter()
+            .map(|set| match set.as_str() {
+                Some(set) => notation::parse_set(set),
+                None => bail!("every set has to be a string, e.g. \"R5 R6 R7\""),
+            })
+            .collect()
+    }
+}
+
+// For every tile of every set of the new table "rack", or the number of the
+// set of the old table it comes from: `[["rack", "rack", 1], [2, 2, 2]]`
+pub fn origins_json(table: &[Set], new_table: &[Set]) -> Json {
+    let origin = |origin: Origin| match origin {
+        Origin::Rack => json::string("rack"),
+        Origin::Table(index) => Json::Number((index + 1) as f64),
+    };
+    Json::Array(
+        solver::tile_origins(table, new_table)
+            .into_iter()
+            .map(|set| Json::Array(set.into_iter().map(origin).collect()))
+            .collect(),
+    )
+}
+
+impl From<Vec<Set>> for Solution {
+    fn from(sets: Vec<Set>) -> Solution {
+        Solution { sets }
+    }
+}
+
+impl FromIterator<Set> for Solution {
+    fn from_iter<I: IntoIterator<Item = Set>>(sets: I) -> Solution {
+        Solution {
+            sets: sets.into_iter().collect(),
+        }
+    }
+}
+
+impl Deref for Solution {
+    type Target = [Set];
+
+    fn deref(&self) -> &[Set] {
+        &self.sets
+    }
+}
+
+impl IntoIterator for Solution {
+    type Item = Set;
+    type IntoIter = std::vec::IntoIter<Set>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.sets.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Solution {
+    type Item = &'a Set;
+    type IntoIter = std::slice::Iter<'a, Set>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.sets.iter()
+    }
+}
+
+// Every set in brackets, e.g. `[R1 R2 R3] [B9 O9 K9]`
+impl fmt::Display for Solution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sets = self
+            .sets
+            .iter()
+            .map(|set| format!("[{}]", set))
+            .collect::<Vec<String>>();
+        write!(f, "{}", sets.join(" "))
+    }
+}
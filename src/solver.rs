@@ -1,50 +1,254 @@
-use color_eyre::{eyre::Report, eyre::Result};
-use rand::prelude::SliceRandom;
-use rand::Rng;
-use std::collections::HashMap;
+use crate::i18n::Message;
+use crate::notation;
+use crate::rules::Ruleset;
+use crate::search_stats::{Candidate, SearchStats};
+use crate::search_trace::SearchTrace;
+use crate::solution::Solution;
+use crate::tablebase;
+use crate::tiles::{self, next_sets, removed, sets_starting_at};
+use crate::validator;
+use color_eyre::{eyre::eyre, eyre::Report, eyre::Result};
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
 
-type Memo = HashMap<u64, Option<Vec<Set>>>;
+pub use crate::tiles::{
+    tile_at, tile_index, Inventory, Set, Tile, Tiles, JOKER_PENALTY, MAX_SET_TILES, TILE_TYPES,
+};
 
-#[derive(PartialEq, Clone, Copy, Eq, Hash, Debug)]
+// Whether the inventory with the hash can be laid out, and the index of its
+// first set among first_sets if so
+type Memo = HashMap<u64, Option<u16>>;
 
-struct Tile {
-    color: u8,
-    number: u8,
-    is_joker: bool,
+// Work of every search since the program started, for monitoring a server
+pub struct SearchCounters {
+    // Positions visited by the memoized searches
+    pub nodes: AtomicU64,
+    // Visits answered from the memo
+    pub memo_hits: AtomicU64,
 }
 
-#[derive(PartialEq, Clone, Eq, Hash, Debug)]
-struct Set {
-    // Group of 3 or 4 tiles with same number and different colors
-    // Or run of 3 or more tiles with same color and consecutive numbers
-    tiles: Vec<Tile>,
+pub static SEARCH: SearchCounters = SearchCounters {
+    nodes: AtomicU64::new(0),
+    memo_hits: AtomicU64::new(0),
+};
+
+fn visit(memo_hit: bool) {
+    SEARCH.nodes.fetch_add(1, Ordering::Relaxed);
+    if memo_hit {
+        SEARCH.memo_hits.fetch_add(1, Ordering::Relaxed);
+    }
+    record(|stats| stats.visit(memo_hit));
+    trace(|trace| trace.visit(memo_hit));
+}
+
+thread_local! {
+    // Collected while with_search_stats runs
+    static STATS: RefCell<Option<SearchStats>> = const { RefCell::new(None) };
+    // Collected while with_search_trace runs
+    static TRACE: RefCell<Option<SearchTrace>> = const { RefCell::new(None) };
+}
+
+// Runs `search` and, if enabled, returns where the table and meld searches of
+// this thread spent their work, for `--analyze-search`
+pub fn with_search_stats<T>(enabled: bool, search: impl FnOnce() -> T) -> (T, Option<SearchStats>) {
+    if !enabled {
+        return (search(), None);
+    }
+    let mut stats = SearchStats::default();
+    stats.start();
+    let previous = STATS.replace(Some(stats));
+    let result = search();
+    let mut stats = STATS.replace(previous).unwrap_or_default();
+    stats.stop();
+    (result, Some(stats))
+}
+
+// Runs `search` and, if `max_nodes` is given, returns the first positions the
+// table and meld searches of this thread visited as a tree, for `--trace`
+pub fn with_search_trace<T>(
+    max_nodes: Option<usize>,
+    search: impl FnOnce() -> T,
+) -> (T, Option<SearchTrace>) {
+    let Some(max_nodes) = max_nodes else {
+        return (search(), None);
+    };
+    let previous = TRACE.replace(Some(SearchTrace::new(max_nodes)));
+    let result = search();
+    let mut trace = TRACE
+        .replace(previous)
+        .unwrap_or_else(|| SearchTrace::new(max_nodes));
+    trace.finish();
+    (result, Some(trace))
+}
+
+fn trace(update: impl FnOnce(&mut SearchTrace)) {
+    TRACE.with_borrow_mut(|trace| {
+        if let Some(trace) = trace {
+            update(trace);
+        }
+    });
+}
+
+fn record(update: impl FnOnce(&mut SearchStats)) {
+    STATS.with_borrow_mut(|stats| {
+        if let Some(stats) = stats {
+            update(stats);
+        }
+    });
+}
+
+// Searches the position a candidate leads to, timed while collecting
+// statistics, and written down with its `step` while tracing
+fn explore<T: Debug>(
+    candidate: Candidate,
+    step: impl FnOnce() -> String,
+    search: impl FnOnce() -> T,
+) -> T {
+    if STATS.with_borrow(Option::is_none) && TRACE.with_borrow(Option::is_none) {
+        return search();
+    }
+    record(SearchStats::enter);
+    trace(|trace| trace.enter(candidate, step));
+    let started = Instant::now();
+    let result = search();
+    let elapsed = started.elapsed();
+    record(|stats| stats.leave(candidate, elapsed));
+    trace(|trace| trace.leave(format!("{:?}", result)));
+    result
+}
+
+// The step of the tile of a cell kept on the rack
+fn kept(number: usize, color: usize) -> String {
+    format!("keep {}", cell_tile(number, color))
+}
+
+fn cell_tile(number: usize, color: usize) -> Tile {
+    Tile {
+        color: color as u8,
+        number: number as u8 + 1,
+        is_joker: false,
+    }
+}
+
+// Nodes searched between two looks at the clock
+const CLOCK_INTERVAL: u32 = 1024;
+// Rough size of an entry of the table memo with the free room of the map and
+// its control byte
+const TABLE_ENTRY_BYTES: usize =
+    std::mem::size_of::<((Inventory, Inventory), Option<u32>)>() * 8 / 7 + 1;
+
+// When the table searches of a move give up, see with_limits
+#[derive(Clone, Copy, Default)]
+pub struct Limits {
+    pub deadline: Option<Instant>,
+    // Positions one search may visit
+    pub nodes: Option<u64>,
+    // Bytes the table memo may grow to
+    pub memory: Option<usize>,
+}
+
+// Which limit cut a search short
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimitExceeded {
+    Time,
+    Nodes,
+    Memory,
+}
+
+impl LimitExceeded {
+    pub fn name(self) -> &'static str {
+        match self {
+            LimitExceeded::Time => "time",
+            LimitExceeded::Nodes => "nodes",
+            LimitExceeded::Memory => "memory",
+        }
+    }
+}
+
+thread_local! {
+    static LIMITS: Cell<Limits> = const {
+        Cell::new(Limits {
+            deadline: None,
+            nodes: None,
+            memory: None,
+        })
+    };
+    static NODES: Cell<u64> = const { Cell::new(0) };
+    static UNTIL_CLOCK: Cell<u32> = const { Cell::new(CLOCK_INTERVAL) };
+    static EXCEEDED: Cell<Option<LimitExceeded>> = const { Cell::new(None) };
+}
+
+// Runs `search` with the table searches of this thread cut short once a limit
+// is reached. A search that hits one falls back to laying out sets from the
+// rack alone, see table_play_cached, and the limit is returned with the result.
+pub fn with_limits<T>(limits: Limits, search: impl FnOnce() -> T) -> (T, Option<LimitExceeded>) {
+    let previous = LIMITS.replace(limits);
+    NODES.set(0);
+    EXCEEDED.set(None);
+    let result = search();
+    LIMITS.set(previous);
+    (result, EXCEEDED.take())
 }
 
+// Whether a limit has been reached with a memo of `memo_entries`, which stays
+// so until the next search
+fn limit_reached(memo_entries: usize) -> bool {
+    if EXCEEDED.get().is_some() {
+        return true;
+    }
+    let limits = LIMITS.get();
+    let nodes = NODES.get() + 1;
+    NODES.set(nodes);
+    if limits.nodes.is_some_and(|max| nodes > max) {
+        EXCEEDED.set(Some(LimitExceeded::Nodes));
+    } else if limits
+        .memory
+        .is_some_and(|max| memo_entries * TABLE_ENTRY_BYTES > max)
+    {
+        EXCEEDED.set(Some(LimitExceeded::Memory));
+    } else if let Some(deadline) = limits.deadline {
+        let left = UNTIL_CLOCK.get() - 1;
+        UNTIL_CLOCK.set(if left == 0 { CLOCK_INTERVAL } else { left });
+        if left == 0 && Instant::now() > deadline {
+            EXCEEDED.set(Some(LimitExceeded::Time));
+        }
+    }
+    EXCEEDED.get().is_some()
+}
+
+// Minimum number of points the first meld of a player has to be worth
+pub const INITIAL_MELD_POINTS: u32 = 30;
+
 impl Set {
     fn print(&self) {
         // If all tiles have same color, print "Group"
-        let colors = ["Red", "Blue", "Yellow", "Black"];
         if self
             .tiles
             .iter()
-            .all(|&tile| tile.color == self.tiles[0].color)
+            .all(|&tile| Some(tile.color) == self.tiles.first().map(|first| first.color))
         {
             //println!("Group: {:?}", self.tiles);
             println!(
-                "Group: {:?}",
+                "{}: {:?}",
+                Message::Group,
                 self.tiles
                     .iter()
-                    .map(|&tile| format!("{} {}", colors[tile.color as usize], tile.number))
+                    .map(|&tile| format!("{} {}", notation::color_name(tile.color), tile.number))
                     .collect::<Vec<String>>()
                     .join(", ")
             );
         } else {
             //println!("Run: {:?}", self.tiles);
             println!(
-                "Run: {:?}",
+                "{}: {:?}",
+                Message::Run,
                 self.tiles
                     .iter()
-                    .map(|&tile| format!("{} {}", colors[tile.color as usize], tile.number))
+                    .map(|&tile| format!("{} {}", notation::color_name(tile.color), tile.number))
                     .collect::<Vec<String>>()
                     .join(", ")
             );
@@ -52,269 +256,1018 @@ impl Set {
     }
 }
 
-#[derive(PartialEq, Clone, Copy, Eq, Hash)]
+impl Inventory {
+    // The tiles in the groups players keep on a physical rack, jokers in a
+    // group of their own at the end. Empty groups are left out.
+    pub fn to_sorted_rack(self, order: RackOrder) -> Vec<Vec<Tile>> {
+        let mut groups = match order {
+            RackOrder::ByColor => (0..4)
+                .map(|color| {
+                    (0..13)
+                        .flat_map(|number| self.copies(number, color))
+                        .collect::<Vec<Tile>>()
+                })
+                .collect::<Vec<Vec<Tile>>>(),
+            RackOrder::ByNumber => (0..13)
+                .map(|number| {
+                    (0..4)
+                        .flat_map(|color| self.copies(number, color))
+                        .collect()
+                })
+                .collect(),
+        };
+        groups.push(
+            (0..self.jokers)
+                .map(|_| Tile {
+                    color: 0,
+                    number: 0,
+                    is_joker: true,
+                })
+                .collect(),
+        );
+        groups.retain(|group| !group.is_empty());
+        groups
+    }
+
+    // Every copy of the tile with this number, counted from 0, and color
+    fn copies(&self, number: usize, color: usize) -> impl Iterator<Item = Tile> {
+        let tile = Tile {
+            color: color as u8,
+            number: number as u8 + 1,
+            is_joker: false,
+        };
+        std::iter::repeat_n(tile, self.cell(number, color) as usize)
+    }
+
+    fn print(&self) {
+        // Print colors Red, Blue, Orange, Black
+        println!("Jokers: {}", self.jokers);
+        println!(
+            "   | {:>5}| {:>5}| {:>7}| {:>5}",
+            notation::color_name(0),
+            notation::color_name(1),
+            notation::color_name(2),
+            notation::color_name(3)
+        );
+
+        for (index, row) in self.grid.iter().enumerate() {
+            println!(
+                "{:2}    {:2}  | {:2}   | {:2}     | {:2}",
+                index + 1,
+                row[0],
+                row[1],
+                row[2],
+                row[3]
+            );
+        }
+    }
+}
 
-struct Inventory {
-    grid: [[u8; 4]; 13],
-    jokers: u8,
+// Groups of the number holding the tile of the color, one for every choice of
+// the other colors
+fn try_form_sets(inventory: &Inventory, number: u8, color: u8) -> Vec<Set> {
+    let others = (0..4)
+        .filter(|&other| other != color && inventory.cell(number as usize - 1, other as usize) > 0)
+        .collect::<Vec<u8>>();
+    (1u32..1 << others.len())
+        .filter(|chosen| chosen.count_ones() >= 2)
+        .map(|chosen| {
+            let mut colors = (0..others.len())
+                .filter(|&index| chosen & (1 << index) != 0)
+                .filter_map(|index| others.get(index).copied())
+                .collect::<Vec<u8>>();
+            colors.push(color);
+            colors.sort();
+            Set {
+                tiles: colors
+                    .into_iter()
+                    .map(|color| Tile {
+                        color,
+                        number,
+                        is_joker: false,
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
 }
 
-impl Inventory {
-    fn new(num: u8) -> Inventory {
-        Inventory {
-            grid: [[num; 4]; 13],
-            jokers: num,
+// Runs of the color starting at the number, one for every length from three
+fn try_form_runs(inventory: &Inventory, start_number: u8, color: u8) -> Vec<Set> {
+    let length = (start_number..=13)
+        .take_while(|&number| inventory.cell(number as usize - 1, color as usize) > 0)
+        .count() as u8;
+    (3..=length)
+        .map(|length| Set {
+            tiles: (start_number..start_number + length)
+                .map(|number| Tile {
+                    color,
+                    number,
+                    is_joker: false,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+// Value of the best meld of every inventory searched and its first step: the
+// index of the set holding the lowest tile among sets_starting_at, or None if
+// that tile stays on the rack. The sets are put together once at the end
+// instead of copied into every entry.
+type MeldMemo = HashMap<Inventory, (u32, Option<u16>)>;
+
+// Highest total value of sets that can be laid out from the inventory,
+// leaving any tiles on the rack that do not fit
+fn best_meld_value(inventory: &Inventory, value: fn(&Set) -> u32, memo: &mut MeldMemo) -> u32 {
+    let best = memo.get(inventory);
+    visit(best.is_some());
+    if let Some(&(best, _)) = best {
+        return best;
+    }
+
+    let Some(&(number, color)) = inventory.available_tiles().first() else {
+        return 0;
+    };
+
+    // Either the lowest tile stays on the rack, or it is part of one of the sets
+    let mut rest = *inventory;
+    rest.take(number, color);
+    let skip = || kept(number, color);
+    let mut best = (
+        explore(Candidate::Skip, skip, || {
+            best_meld_value(&rest, value, memo)
+        }),
+        None,
+    );
+
+    let sets = sets_starting_at(inventory, number as u8 + 1, color as u8);
+    record(|stats| stats.expand(sets.len() + 1));
+    for (index, set) in sets.iter().enumerate() {
+        let mut rest = *inventory;
+        rest.remove_tiles(set);
+        let total = value(set)
+            + explore(
+                Candidate::of(set),
+                || set.to_string(),
+                || best_meld_value(&rest, value, memo),
+            );
+        if total > best.0 {
+            best = (total, Some(index as u16));
         }
     }
 
-    fn is_empty(&self) -> bool {
-        self.grid.iter().flatten().all(|&tile| tile == 0)
+    memo.insert(*inventory, best);
+    best.0
+}
+
+// The sets of the best meld, following the steps in the memo
+fn best_meld(
+    inventory: &Inventory,
+    value: fn(&Set) -> u32,
+    memo: &mut MeldMemo,
+) -> (u32, Vec<Set>) {
+    let total = best_meld_value(inventory, value, memo);
+    let mut rest = *inventory;
+    let mut sets = Vec::new();
+    while let Some(&(number, color)) = rest.available_tiles().first() {
+        let Some(&(_, step)) = memo.get(&rest) else {
+            break;
+        };
+        match step {
+            Some(index) => {
+                let Some(&set) =
+                    sets_starting_at(&rest, number as u8 + 1, color as u8).get(index as usize)
+                else {
+                    break;
+                };
+                rest.remove_tiles(&set);
+                sets.push(set);
+            }
+            None => rest.take(number, color),
+        }
     }
+    // Sets of the highest tiles first, as they always came
+    sets.reverse();
+    (total, sets)
+}
 
-    fn total_tile_count(&self) -> u32 {
-        self.grid
-            .iter()
-            .flat_map(|row| row.iter())
-            .map(|&x| x as u32)
-            .sum::<u32>()
-            + self.jokers as u32
+// Meld worth the most points, as needed for the initial meld
+pub fn max_points_meld(rack: &Inventory) -> (u32, Vec<Set>) {
+    best_meld(rack, Set::points, &mut MeldMemo::new())
+}
+
+// Meld laying out as many tiles as possible, whatever their points
+pub fn max_tiles_meld(rack: &Inventory) -> (u32, Vec<Set>) {
+    best_meld(rack, |set| set.tiles.len() as u32, &mut MeldMemo::new())
+}
+
+struct Frame {
+    rest: Inventory,
+    candidates: Vec<Set>,
+    next: usize,
+    // Whether any solution was found below this frame
+    solved: bool,
+}
+
+// Depth first search over the sets holding the lowest tile left. Each step
+// only does the work needed for the next solution.
+pub struct Solutions {
+    // Set for an empty inventory, which has exactly one arrangement: no sets
+    empty: bool,
+    stack: Vec<Frame>,
+    chosen: Vec<Set>,
+    // Remaining tiles, with the set before them, known to have no solution
+    dead: HashSet<(Inventory, Option<Set>)>,
+}
+
+impl Solutions {
+    fn frame(&self, rest: Inventory) -> Frame {
+        let (candidates, _) = next_sets(&rest, self.chosen.last());
+        let candidates = candidates
+            .into_iter()
+            .filter(|set| !self.dead.contains(&(removed(&rest, set), Some(*set))))
+            .collect();
+        Frame {
+            rest,
+            candidates,
+            next: 0,
+            solved: false,
+        }
     }
+}
 
-    fn available_tiles(&self) -> Vec<(usize, usize)> {
-        self.grid
-            .iter()
-            .enumerate()
-            .flat_map(|(number, row)| {
-                row.iter().enumerate().filter_map(move |(color, &count)| {
-                    if count > 0 {
-                        Some((number, color))
-                    } else {
-                        None
+impl Iterator for Solutions {
+    type Item = Solution;
+
+    fn next(&mut self) -> Option<Solution> {
+        if self.empty {
+            self.empty = false;
+            return Some(Solution::default());
+        }
+        while let Some(frame) = self.stack.last_mut() {
+            if let Some(&set) = frame.candidates.get(frame.next) {
+                frame.next += 1;
+                let rest = removed(&frame.rest, &set);
+                self.chosen.push(set);
+
+                if rest.total_tile_count() == 0 {
+                    for frame in self.stack.iter_mut() {
+                        frame.solved = true;
                     }
-                })
-            })
-            .collect()
+                    let solution = Solution::from(self.chosen.clone());
+                    self.chosen.pop();
+                    return Some(solution);
+                }
+                let frame = self.frame(rest);
+                self.stack.push(frame);
+                continue;
+            }
+
+            let frame = self.stack.pop()?;
+            let last = self.chosen.pop();
+            if !frame.solved {
+                self.dead.insert((frame.rest, last));
+            }
+        }
+        None
+    }
+}
+
+// All ways to lay out every tile of the inventory, each arrangement once and
+// with its sets ordered by their lowest tile. Solutions are found as they are
+// taken, so `.take(k)` stays cheap even when there are millions of them.
+pub fn solve_all(inventory: &Inventory) -> Solutions {
+    let mut solutions = Solutions {
+        empty: inventory.total_tile_count() == 0,
+        stack: Vec::new(),
+        chosen: Vec::new(),
+        dead: HashSet::new(),
+    };
+    if !solutions.empty {
+        let frame = solutions.frame(*inventory);
+        solutions.stack.push(frame);
+    }
+    solutions
+}
+
+type CountMemo = HashMap<(Inventory, Option<Set>), u64>;
+
+fn count_from(rest: &Inventory, previous: Option<&Set>, memo: &mut CountMemo) -> u64 {
+    if rest.total_tile_count() == 0 {
+        return 1;
     }
+    let (sets, previous) = next_sets(rest, previous);
+    let key = (*rest, previous);
+    let count = memo.get(&key);
+    visit(count.is_some());
+    if let Some(&count) = count {
+        return count;
+    }
+
+    let count = sets.iter().fold(0u64, |count, set| {
+        count.saturating_add(count_from(&removed(rest, set), Some(set), memo))
+    });
+    memo.insert(key, count);
+    count
+}
+
+// Number of arrangements solve_all would yield, without building them.
+// Saturates at u64::MAX.
+pub fn count_solutions(inventory: &Inventory) -> u64 {
+    count_from(inventory, None, &mut CountMemo::new())
+}
 
-    fn remove_tiles(&mut self, set: &Set) {
-        for tile in &set.tiles {
-            self.grid[tile.number as usize - 1][tile.color as usize] -= 1;
+// How the tiles on a rack are grouped, see Inventory::to_sorted_rack
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RackOrder {
+    // A group for every color, in ascending numbers
+    ByColor,
+    // A group for every number, in the order of the colors
+    ByNumber,
+}
+
+impl RackOrder {
+    pub fn toggled(self) -> RackOrder {
+        match self {
+            RackOrder::ByColor => RackOrder::ByNumber,
+            RackOrder::ByNumber => RackOrder::ByColor,
         }
     }
 
-    fn hash(&self) -> u64 {
-        let mut hash: u64 = 0; // Explicit type declaration for hash
-        self.grid.iter().enumerate().for_each(|(i, row)| {
-            row.iter().enumerate().for_each(|(j, &tile)| {
-                hash = hash
-                    .wrapping_mul(31_u64) // Specify type for 31 as u64
-                    .wrapping_add(tile as u64)
-                    .wrapping_add(i as u64)
-                    .wrapping_add(j as u64);
-            });
-        });
+    pub fn name(self) -> &'static str {
+        match self {
+            RackOrder::ByColor => "by color",
+            RackOrder::ByNumber => "by number",
+        }
+    }
+}
 
-        // Incorporate jokers into the hash
-        hash.wrapping_mul(31_u64).wrapping_add(self.jokers as u64) // Specify type for 31 as u64
+// What makes one complete arrangement of tiles better than another
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Objective {
+    // As few sets as possible, so long runs beat several short ones
+    MinSets,
+    // The nicest looking table: as few sets as possible, then the longest
+    // runs and groups of 4 rather than 3
+    Aesthetic,
+}
+
+impl Objective {
+    pub fn parse(text: &str) -> Result<Objective> {
+        match text {
+            "min-sets" => Ok(Objective::MinSets),
+            "aesthetic" => Ok(Objective::Aesthetic),
+            other => Err(eyre!(
+                "unknown objective {}, use min-sets or aesthetic",
+                other
+            )),
+        }
     }
 
-    fn print(&self) {
-        // Print colors Red, Blue, Yellow, Black
-        println!("Jokers: {}", self.jokers);
-        println!("   |  Red | Blue | Yellow | Black");
+    // Arrangements with the lowest total cost win
+    fn set_cost(&self, set: &Set) -> u64 {
+        match self {
+            Objective::MinSets => 1,
+            // Every set costs more than the squared lengths of all tiles of the
+            // pool can make up, so the number of sets always decides first.
+            // Squares favor one long set over two shorter ones.
+            Objective::Aesthetic => (1 << 20) - (set.tiles.len() as u64).pow(2),
+        }
+    }
 
-        for (index, row) in self.grid.iter().enumerate() {
-            println!(
-                "{:2}    {:2}  | {:2}   | {:2}     | {:2}",
-                index + 1,
-                row[0],
-                row[1],
-                row[2],
-                row[3]
-            );
+    // Cost of a whole arrangement, of two tables with the same tiles the
+    // cheaper one suits the objective better
+    pub fn cost(self, sets: &[Set]) -> u64 {
+        sets.iter().map(|set| self.set_cost(set)).sum()
+    }
+}
+
+// Lowest cost of every inventory searched and the index of its first set
+// among next_sets, like MeldMemo
+type ArrangeMemo = HashMap<Inventory, Option<(u64, u16)>>;
+
+fn cheapest_arrangement(
+    rest: &Inventory,
+    objective: Objective,
+    memo: &mut ArrangeMemo,
+) -> Option<u64> {
+    if rest.total_tile_count() == 0 {
+        return Some(0);
+    }
+    let best = memo.get(rest);
+    visit(best.is_some());
+    if let Some(&best) = best {
+        return best.map(|(cost, _)| cost);
+    }
+
+    let (sets, _) = next_sets(rest, None);
+    let mut best: Option<(u64, u16)> = None;
+    for (index, set) in sets.iter().enumerate() {
+        let Some(cost) = cheapest_arrangement(&removed(rest, set), objective, memo) else {
+            continue;
+        };
+        let cost = cost + objective.set_cost(set);
+        if best.is_none_or(|(best, _)| cost < best) {
+            best = Some((cost, index as u16));
         }
     }
+
+    memo.insert(*rest, best);
+    best.map(|(cost, _)| cost)
+}
+
+// The complete arrangement of the inventory that suits the objective best, or
+// None if its tiles cannot all be laid out
+pub fn arrange(inventory: &Inventory, objective: Objective) -> Option<Solution> {
+    let mut memo = ArrangeMemo::new();
+    cheapest_arrangement(inventory, objective, &mut memo)?;
+    let mut rest = *inventory;
+    let mut solution = Vec::new();
+    while rest.total_tile_count() > 0 {
+        let (_, index) = (*memo.get(&rest)?)?;
+        let set = *next_sets(&rest, None).0.get(index as usize)?;
+        rest = removed(&rest, &set);
+        solution.push(set);
+    }
+    // The nicest table shows its longest sets first
+    if objective == Objective::Aesthetic {
+        solution.sort_by_key(|set| Reverse(set.tiles.len()));
+    }
+    Some(Solution::from(solution))
 }
 
-fn try_form_set_incl_jokers(inventory: &Inventory, number: u8) -> Option<Set> {
-    let mut set_tiles: Vec<Tile> = Vec::new();
-    let mut jokers_used = 0;
+type TableMemo = HashMap<(Inventory, Inventory), Option<u32>>;
 
-    // Iterate over each color
-    for color in 0..4 {
-        if inventory.grid[number as usize - 1][color] > 0 {
-            set_tiles.push(Tile {
-                color: color as u8,
-                number,
-                is_joker: false,
-            });
-        } else if jokers_used < inventory.jokers {
-            // Use a joker if a tile of the required color is not available
-            set_tiles.push(Tile {
-                color: color as u8,
-                number,
-                is_joker: true,
-            }); // Mark the tile as a joker
-            jokers_used += 1;
+pub type LayoutMemo = HashMap<Inventory, bool>;
+
+// The layout memo with every look at it counted by the search statistics
+struct Counted<'a>(&'a mut LayoutMemo);
+
+impl tiles::Memo<Inventory, bool> for Counted<'_> {
+    fn recall(&mut self, inventory: &Inventory) -> Option<bool> {
+        let known = self.0.get(inventory).copied();
+        visit(known.is_some());
+        known
+    }
+
+    fn remember(&mut self, inventory: Inventory, possible: bool) {
+        self.0.insert(inventory, possible);
+    }
+}
+
+// Whether every tile of the inventory can be laid out, see tiles::can_lay_out.
+// The memo keeps the answers for the tiles left along the way, so questions
+// about inventories that share most of their tiles, such as one table with
+// different racks, get quicker as they are asked.
+pub fn can_lay_out(inventory: &Inventory, memo: &mut LayoutMemo) -> bool {
+    tiles::can_lay_out(inventory, &mut Counted(memo))
+}
+
+// The move generator of perft and the game tree searches: every different
+// pick of rack tiles the player could lay out. Once opened a pick has to fit
+// with every tile of the table, before that it is an initial meld of 30
+// points on its own. Arrangements do not count, the table can always be
+// rearranged into any of them.
+pub fn legal_picks(
+    table: &Inventory,
+    rack: &Inventory,
+    opened: bool,
+    memo: &mut LayoutMemo,
+) -> Vec<Inventory> {
+    if !opened && !has_initial_meld(rack) {
+        return Vec::new();
+    }
+    let mut picks = Vec::new();
+    rack.each_subset(|pick| {
+        let legal = if opened {
+            pick.total_tile_count() > 0 && can_lay_out(&table.combined(pick), memo)
+        } else {
+            pick.total_tile_count() >= 3
+                && can_lay_out(pick, memo)
+                && max_points_meld(pick).0 >= INITIAL_MELD_POINTS
+        };
+        if legal {
+            picks.push(*pick);
+        }
+    });
+    picks
+}
+
+// Remove the tiles of a set, using table copies before rack copies.
+// Returns how many of the tiles came from the rack.
+pub fn take_table_first(table: &mut Inventory, rack: &mut Inventory, set: &Set) -> u32 {
+    let mut from_rack = 0;
+    for tile in &set.tiles {
+        if table.count(tile) > 0 {
+            table.remove_tile(tile);
+        } else {
+            rack.remove_tile(tile);
+            from_rack += 1;
+        }
+    }
+    from_rack
+}
+
+// Where a tile of the table after a move comes from
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Origin {
+    Rack,
+    // The index of the set of the old table
+    Table(usize),
+}
+
+// The origin of every tile of every set of the new table. Table copies are
+// used before rack copies, as in take_table_first, each from the old set that
+// shares most tiles with the new one, so a kept set keeps its own tiles.
+pub fn tile_origins(table: &[Set], new_table: &[Set]) -> Vec<Vec<Origin>> {
+    let mut left = table
+        .iter()
+        .map(|set| Inventory::from_sets(std::slice::from_ref(set)))
+        .collect::<Vec<_>>();
+    new_table
+        .iter()
+        .map(|set| {
+            let overlap = |old: &Set| {
+                set.tiles
+                    .iter()
+                    .filter(|tile| old.tiles.contains(tile))
+                    .count()
+            };
+            set.tiles
+                .iter()
+                .map(|tile| {
+                    let source = left
+                        .iter_mut()
+                        .zip(table)
+                        .enumerate()
+                        .filter(|(_, (copies, _))| copies.count(tile) > 0)
+                        .max_by_key(|(index, (_, old))| (overlap(old), std::cmp::Reverse(*index)));
+                    match source {
+                        Some((index, (copies, _))) => {
+                            copies.remove_tile(tile);
+                            Origin::Table(index)
+                        }
+                        None => Origin::Rack,
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Most rack tiles that can be added while every table tile stays in a set, or
+// None if the table tiles cannot all be placed. Rack tiles may stay on the rack.
+fn rearrangement_value(table: &Inventory, rack: &Inventory, memo: &mut TableMemo) -> Option<u32> {
+    // Values found past a limit are wrong, the caller throws the memo away
+    if limit_reached(memo.len()) {
+        trace(|trace| trace.prune(|| "search limit reached".to_string()));
+        return None;
+    }
+    let best = memo.get(&(*table, *rack));
+    visit(best.is_some());
+    if let Some(&best) = best {
+        return best;
+    }
+
+    let combined = table.combined(rack);
+    let Some(&(number, color)) = combined.available_tiles().first() else {
+        // Leftover jokers are attached to the finished arrangement afterwards
+        return Some(0);
+    };
+
+    let mut best = None;
+    let skip = table.cell(number, color) == 0;
+    if skip {
+        let mut rest = *rack;
+        rest.take(number, color);
+        best = explore(
+            Candidate::Skip,
+            || kept(number, color),
+            || rearrangement_value(table, &rest, memo),
+        );
+    }
+
+    let sets = sets_starting_at(&combined, number as u8 + 1, color as u8);
+    record(|stats| stats.expand(sets.len() + usize::from(skip)));
+    for set in sets {
+        let (mut table_rest, mut rack_rest) = (*table, *rack);
+        let from_rack = take_table_first(&mut table_rest, &mut rack_rest, &set);
+        if let Some(played) = explore(
+            Candidate::of(&set),
+            || set.to_string(),
+            || rearrangement_value(&table_rest, &rack_rest, memo),
+        ) {
+            best = best.max(Some(played + from_rack));
         }
+    }
+    if best.is_none() {
+        trace(|trace| {
+            trace.prune(|| {
+                format!(
+                    "no set with {} leaves tiles that can be laid out",
+                    cell_tile(number, color)
+                )
+            })
+        });
+    }
+
+    memo.insert((*table, *rack), best);
+    best
+}
+
+// Walk the memoized values again to recover the sets of the best arrangement
+fn best_rearrangement(
+    table: &Inventory,
+    rack: &Inventory,
+    memo: &mut TableMemo,
+) -> Option<Vec<Set>> {
+    let mut target = rearrangement_value(table, rack, memo)?;
+    let (mut table, mut rack) = (*table, *rack);
+    let mut sets = Vec::new();
 
-        // Check if we have a valid set with 3 tiles
-        if set_tiles.len() == 3 {
-            return Some(Set { tiles: set_tiles });
+    while let Some(&(number, color)) = table.combined(&rack).available_tiles().first() {
+        if table.cell(number, color) == 0 {
+            let mut rest = rack;
+            rest.take(number, color);
+            if rearrangement_value(&table, &rest, memo) == Some(target) {
+                rack = rest;
+                continue;
+            }
         }
+
+        let set = sets_starting_at(&table.combined(&rack), number as u8 + 1, color as u8)
+            .into_iter()
+            .find_map(|set| {
+                let (mut table_rest, mut rack_rest) = (table, rack);
+                let from_rack = take_table_first(&mut table_rest, &mut rack_rest, &set);
+                let played = rearrangement_value(&table_rest, &rack_rest, memo)?;
+                (played + from_rack == target).then_some((set, table_rest, rack_rest, from_rack))
+            })?;
+        target -= set.3;
+        (table, rack) = (set.1, set.2);
+        sets.push(set.0);
     }
 
-    // Return None if we don't have enough tiles (including jokers) for a valid set
-    None
+    Some(sets)
 }
-fn try_form_run_incl_jokers(inventory: &Inventory, start_number: u8, color: u8) -> Option<Set> {
-    let mut run_tiles: Vec<Tile> = Vec::new();
-    let mut jokers_used = 0;
 
-    // Iterate to check for consecutive numbers with the same color
-    for number in start_number..=13 {
-        if inventory.grid[number as usize - 1][color as usize] > 0 {
-            run_tiles.push(Tile {
-                color,
-                number,
-                is_joker: false,
-            });
-        } else if jokers_used < inventory.jokers {
-            // Use a joker if available
-            run_tiles.push(Tile {
-                color,
-                number,
+// Put a joker on the end of any set that still has room for one
+fn attach_joker(sets: &mut [Set]) -> bool {
+    for set in sets.iter_mut() {
+        let (Some(&first), Some(&last)) = (set.tiles.first(), set.tiles.last()) else {
+            continue;
+        };
+        let joker = if set.is_group() {
+            (0..4)
+                .find(|&color| set.tiles.iter().all(|tile| tile.color != color))
+                .map(|color| Tile {
+                    color,
+                    number: first.number,
+                    is_joker: true,
+                })
+        } else if last.number < 13 {
+            Some(Tile {
+                number: last.number + 1,
+                is_joker: true,
+                ..last
+            })
+        } else if first.number > 1 {
+            Some(Tile {
+                number: first.number - 1,
                 is_joker: true,
-            }); // Representing the joker
-            jokers_used += 1;
+                ..first
+            })
         } else {
-            break; // Stop if a consecutive number and joker are missing
+            None
+        };
+
+        if let Some(joker) = joker {
+            set.tiles.push(joker);
+            set.tiles.sort_by_key(|tile| (tile.number, tile.color));
+            return true;
+        }
+    }
+    false
+}
+
+// Memos behind best_move, kept from one turn to the next. Every entry only
+// depends on the tiles it is keyed by, so it stays valid as the game goes on.
+#[derive(Default)]
+pub struct SolverCache {
+    table: TableMemo,
+    meld: MeldMemo,
+}
+
+impl SolverCache {
+    // Entries kept at most, a long session starts over rather than grow forever
+    const MAX_ENTRIES: usize = 2_000_000;
+
+    pub fn entries(&self) -> usize {
+        self.table.len() + self.meld.len()
+    }
+
+    fn trim(&mut self) {
+        if self.entries() > Self::MAX_ENTRIES {
+            *self = SolverCache::default();
+        }
+    }
+}
+
+// Best play for a player that has already opened: rearranges the table to lay
+// out as many rack tiles as possible. Returns the new table and remaining rack.
+pub fn table_play(table: &[Set], rack: &Inventory) -> Option<(Vec<Set>, Inventory)> {
+    table_play_cached(table, rack, &mut SolverCache::default())
+}
+
+fn table_play_cached(
+    table: &[Set],
+    rack: &Inventory,
+    cache: &mut SolverCache,
+) -> Option<(Vec<Set>, Inventory)> {
+    let table_tiles = Inventory::from_sets(table);
+    let found = best_rearrangement(&table_tiles, rack, &mut cache.table);
+    if EXCEEDED.get().is_some() {
+        cache.table.clear();
+        return rack_play(table, rack);
+    }
+    let mut sets = found?;
+
+    // Jokers left over from the table must be placed, rack jokers are played if possible
+    let placed = Inventory::from_sets(&sets).jokers;
+    let table_jokers = table_tiles.jokers.saturating_sub(placed);
+    let rack_jokers = rack.jokers - placed.saturating_sub(table_tiles.jokers);
+    for _ in 0..table_jokers {
+        if !attach_joker(&mut sets) {
+            return None;
+        }
+    }
+    for _ in 0..rack_jokers {
+        if !attach_joker(&mut sets) {
+            break;
         }
     }
 
-    // Check if the run has at least 3 tiles
-    if run_tiles.len() >= 3 {
-        Some(Set { tiles: run_tiles })
+    let mut played = Inventory::from_sets(&sets);
+    played.subtract(&table_tiles);
+    let mut rest = *rack;
+    rest.subtract(&played);
+
+    if rest.total_tile_count() < rack.total_tile_count() {
+        Some((sets, rest))
     } else {
         None
     }
 }
 
-fn grab_tile(source: &mut Inventory, destination: &mut Inventory) {
-    let mut rng = rand::thread_rng();
-    let total_tiles = source.total_tile_count();
-    let grab_joker = source.jokers > 0 && rng.gen_bool(source.jokers as f64 / total_tiles as f64);
+// New sets from the rack alone next to the table, as many tiles as possible
+fn rack_play(table: &[Set], rack: &Inventory) -> Option<(Vec<Set>, Inventory)> {
+    let (played, sets) = max_tiles_meld(rack);
+    if played == 0 {
+        return None;
+    }
+    let mut rest = *rack;
+    for set in &sets {
+        rest.remove_tiles(set);
+    }
+    let mut new_table = table.to_vec();
+    new_table.extend(sets);
+    Some((new_table, rest))
+}
 
-    if grab_joker {
-        source.jokers -= 1;
-        destination.jokers += 1;
-    } else {
-        if let Some(&(number, color)) = source.available_tiles().choose(&mut rng) {
-            source.grid[number as usize][color as usize] -= 1;
-            destination.grid[number as usize][color as usize] += 1;
+// Greedy move: open with the highest scoring meld once it is worth enough,
+// afterwards rearrange the table to lay out as many tiles as possible.
+// Returns the new table and the rack left over, or None if drawing is best.
+// Illegal positions are rejected up front instead of producing nonsense plays.
+pub fn best_move(
+    table: &[Set],
+    rack: &Inventory,
+    opened: bool,
+) -> Result<Option<(Vec<Set>, Inventory)>> {
+    best_move_cached(table, rack, opened, &mut SolverCache::default())
+}
+
+// best_move reusing the memos of earlier turns. Positions small enough for
+// the tablebase of `--tablebase` are looked up instead.
+pub fn best_move_cached(
+    table: &[Set],
+    rack: &Inventory,
+    opened: bool,
+    cache: &mut SolverCache,
+) -> Result<Option<(Vec<Set>, Inventory)>> {
+    validator::check_position(table, rack)?;
+    cache.trim();
+
+    if opened {
+        let table_tiles = Inventory::from_sets(table);
+        if let Some(pick) = tablebase::best_move(&table_tiles, rack)? {
+            if pick.total_tile_count() == 0 {
+                return Ok(None);
+            }
+            if let Some(new_table) = arrange(&table_tiles.combined(&pick), Objective::MinSets) {
+                let mut rest = *rack;
+                rest.subtract(&pick);
+                return Ok(Some((new_table.into_sets(), rest)));
+            }
         }
+        return Ok(table_play_cached(table, rack, cache));
     }
-}
 
-fn try_form_set(inventory: &Inventory, number: u8) -> Option<Set> {
-    let set_tiles = (0..4)
-        .filter_map(|color| {
-            if inventory.grid[number as usize - 1][color] > 0 {
-                Some(Tile {
-                    color: color as u8,
-                    number,
-                    is_joker: false,
-                })
-            } else {
-                None
+    match best_meld(rack, Set::points, &mut cache.meld) {
+        (points, sets) if points >= INITIAL_MELD_POINTS => {
+            let mut rest = *rack;
+            for set in &sets {
+                rest.remove_tiles(set);
             }
-        })
-        .collect::<Vec<_>>();
+            let mut new_table = table.to_vec();
+            new_table.extend(sets);
+            Ok(Some((new_table, rest)))
+        }
+        _ => Ok(None),
+    }
+}
 
-    if set_tiles.len() >= 3 {
-        Some(Set { tiles: set_tiles })
-    } else {
-        None
+// The turn of the initial meld when the rules allow rearranging the table
+// after it: the meld with the most points, then as many of the other tiles as
+// the table can take. Sets holding a joker are left alone.
+pub fn opening_move(table: &[Set], rack: &Inventory) -> Result<Option<(Vec<Set>, Inventory)>> {
+    validator::check_position(table, rack)?;
+    let (points, meld) = max_points_meld(rack);
+    if points < INITIAL_MELD_POINTS {
+        return Ok(None);
     }
+    let mut rest = *rack;
+    meld.iter().for_each(|set| rest.remove_tiles(set));
+    let (kept, mut free): (Vec<Set>, Vec<Set>) = table
+        .iter()
+        .partition(|set| set.tiles.iter().any(|tile| tile.is_joker));
+    free.extend(meld);
+    let (mut new_table, rest) = table_play(&free, &rest).unwrap_or((free, rest));
+    new_table.extend(kept);
+    Ok(Some((new_table, rest)))
 }
 
-fn try_form_run(inventory: &Inventory, start_number: u8, color: u8) -> Option<Set> {
-    let run_tiles = (start_number..=13)
-        .take_while(|&number| inventory.grid[number as usize - 1][color as usize] > 0)
-        .map(|number| Tile {
-            color,
-            number,
-            is_joker: false,
-        })
-        .collect::<Vec<_>>();
+// best_move under the rule variants of the game
+pub fn best_move_by_rules(
+    table: &[Set],
+    rack: &Inventory,
+    opened: bool,
+    rules: &Ruleset,
+) -> Result<Option<(Vec<Set>, Inventory)>> {
+    if !opened && rules.rearrange_on_opening {
+        return opening_move(table, rack);
+    }
+    best_move(table, rack, opened)
+}
 
-    if run_tiles.len() >= 3 {
-        Some(Set { tiles: run_tiles })
-    } else {
-        None
+// Moves from quick to best, each laying out more tiles than the one before and
+// the last one matching best_move. The quick move only adds sets made from the
+// rack, so it is ready at once even when rearranging a big table takes long.
+// `improved` gets every move and returns false to skip the slower search.
+pub fn anytime_moves(
+    table: &[Set],
+    rack: &Inventory,
+    opened: bool,
+    rules: &Ruleset,
+    mut improved: impl FnMut(&(Vec<Set>, Inventory)) -> bool,
+) -> Result<()> {
+    if !opened {
+        // The initial meld only uses rack tiles, so there is nothing quicker
+        if let Some(chosen) = best_move_by_rules(table, rack, opened, rules)? {
+            improved(&chosen);
+        }
+        return Ok(());
     }
+    validator::check_position(table, rack)?;
+
+    let mut quick = 0;
+    if let Some(chosen) = rack_play(table, rack) {
+        quick = rack.total_tile_count() - chosen.1.total_tile_count();
+        if !improved(&chosen) {
+            return Ok(());
+        }
+    }
+
+    if let Some((new_table, rest)) = table_play(table, rack) {
+        if rack.total_tile_count() - rest.total_tile_count() > quick {
+            improved(&(new_table, rest));
+        }
+    }
+    Ok(())
 }
 
-fn solve_rummikub(inventory: &Inventory, memo: &mut Memo) -> Option<Vec<Set>> {
-    let hash = inventory.hash();
-    if let Some(solution) = memo.get(&hash) {
-        return solution.clone();
+// Moves worth comparing when more than the tiles laid out counts: the best
+// move, the sets of the rack alone, and every one of those sets on its own.
+// Drawing is not among them. Until the player has opened only the initial
+// meld is possible.
+pub fn candidate_moves(
+    table: &[Set],
+    rack: &Inventory,
+    opened: bool,
+    rules: &Ruleset,
+) -> Result<Vec<(Vec<Set>, Inventory)>> {
+    let mut candidates = Vec::new();
+    candidates.extend(best_move_by_rules(table, rack, opened, rules)?);
+    if !opened {
+        return Ok(candidates);
     }
+    let (_, sets) = max_tiles_meld(rack);
+    let alone = sets.iter().map(|set| vec![*set]);
+    for sets in std::iter::once(sets.clone()).chain(alone) {
+        if sets.is_empty() {
+            continue;
+        }
+        let mut rest = *rack;
+        sets.iter().for_each(|set| rest.remove_tiles(set));
+        let mut new_table = table.to_vec();
+        new_table.extend(sets);
+        if !candidates.iter().any(|(other, _)| *other == new_table) {
+            candidates.push((new_table, rest));
+        }
+    }
+    Ok(candidates)
+}
 
-    if inventory.is_empty() {
-        return Some(Vec::new());
+// First arrangement of every tile the search of the interactive solver finds.
+// It only tries sets of real tiles, so racks with jokers are never solved.
+pub fn first_solution(inventory: &Inventory) -> Option<Solution> {
+    solve_rummikub(inventory, &mut Memo::new()).map(Solution::from)
+}
+
+fn solve_rummikub(inventory: &Inventory, memo: &mut Memo) -> Option<Vec<Set>> {
+    if !solvable(inventory, memo) {
+        return None;
+    }
+    // Follow the first sets in the memo to the empty inventory
+    let mut rest = *inventory;
+    let mut solution = Vec::new();
+    while !rest.is_empty() {
+        let index = (*memo.get(&rest.key())?)?;
+        let set = *first_sets(&rest).get(index as usize)?;
+        rest.remove_tiles(&set);
+        solution.push(set);
     }
+    // Sets of the highest tiles first, as they always came
+    solution.reverse();
+    Some(solution)
+}
 
+// Groups and runs holding the lowest real tile of the inventory
+fn first_sets(inventory: &Inventory) -> Vec<Set> {
     for number in 1..=13 {
         for color in 0..4 {
-            if inventory.grid[number - 1][color] > 0 {
-                if let Some(new_set) = try_form_set(inventory, number as u8) {
-                    let mut new_inventory = inventory.clone();
-                    new_inventory.remove_tiles(&new_set);
-
-                    if let Some(mut solution) = solve_rummikub(&new_inventory, memo) {
-                        solution.push(new_set);
-                        memo.insert(hash, Some(solution.clone()));
-                        return Some(solution);
-                    }
-                }
-
-                if let Some(new_run) = try_form_run(inventory, number as u8, color as u8) {
-                    let mut new_inventory = inventory.clone();
-                    new_inventory.remove_tiles(&new_run);
-
-                    if let Some(mut solution) = solve_rummikub(&new_inventory, memo) {
-                        solution.push(new_run);
-                        memo.insert(hash, Some(solution.clone()));
-                        return Some(solution);
-                    }
-                }
+            if inventory.cell(number - 1, color) > 0 {
+                let mut sets = try_form_sets(inventory, number as u8, color as u8);
+                sets.extend(try_form_runs(inventory, number as u8, color as u8));
+                return sets;
             }
         }
     }
+    Vec::new()
+}
+
+fn solvable(inventory: &Inventory, memo: &mut Memo) -> bool {
+    let hash = inventory.key();
+    let known = memo.get(&hash);
+    visit(known.is_some());
+    if let Some(known) = known {
+        return known.is_some();
+    }
 
-    memo.insert(hash, None);
-    None
+    if inventory.is_empty() {
+        return true;
+    }
+
+    // Every set with the lowest tile left is tried, without any the inventory
+    // cannot be laid out
+    let first = first_sets(inventory).iter().position(|set| {
+        let mut new_inventory = *inventory;
+        new_inventory.remove_tiles(set);
+        solvable(&new_inventory, memo)
+    });
+    memo.insert(hash, first.map(|index| index as u16));
+    first.is_some()
 }
 
-pub fn solve() -> Result<(), Report> {
+pub fn has_initial_meld(rack: &Inventory) -> bool {
+    max_points_meld(rack).0 >= INITIAL_MELD_POINTS
+}
+
+// Adds the tiles of `draw` to an empty rack until they can all be laid out
+pub fn solve(mut draw: impl FnMut() -> Option<Tile>) -> Result<(), Report> {
     let mut memo = Memo::new();
     let mut player = Inventory::new(0);
-    let mut bag = Inventory::new(2);
 
     solve_rummikub(&player, &mut memo);
 
     loop {
-        grab_tile(&mut bag, &mut player);
+        if let Some(tile) = draw() {
+            player.add_tile(&tile);
+        }
         //player.print();
         let solution = solve_rummikub(&player, &mut memo);
         match solution {
             Some(sets) => {
-                let num_tiles = player
-                    .grid
-                    .iter()
-                    .flatten()
-                    .fold(0, |acc, &tile| acc + tile);
+                let num_tiles = player.grid.iter().flatten().sum::<u8>();
                 println!("Solution found after {} tiles", num_tiles);
                 for set in sets {
                     set.print();
@@ -325,11 +1278,7 @@ pub fn solve() -> Result<(), Report> {
             }
             None => {
                 // Print number of tiles
-                let num_tiles = player
-                    .grid
-                    .iter()
-                    .flatten()
-                    .fold(0, |acc, &tile| acc + tile);
+                let num_tiles = player.grid.iter().flatten().sum::<u8>();
                 println!("No solution found after {} tiles", num_tiles);
             }
         }
@@ -0,0 +1,244 @@
+use crate::cli::Args;
+use crate::events::GameEvent;
+use crate::game::{GameConfig, GameState};
+use crate::json::{self, Json};
+use crate::play;
+use crate::profile;
+use crate::replay;
+use crate::solver::{Inventory, Set};
+use crate::sse;
+use crate::strategy::Strategy;
+use color_eyre::{eyre::bail, eyre::Report, eyre::Result};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// What a spectator sees: the table and how many tiles everyone holds, worked
+// out from the events alone, so the racks stay hidden
+struct View {
+    names: Vec<String>,
+    tiles: Vec<u32>,
+    // Every rack, only with `--open-hand` to debug the strategies. The
+    // strategies themselves only ever get their own rack from the engine.
+    racks: Option<Vec<Inventory>>,
+    bag: u32,
+    table: Vec<Set>,
+    turn: u32,
+    last: Option<String>,
+}
+
+impl View {
+    fn new(game: &GameState, names: Vec<String>, open_hand: bool) -> View {
+        View {
+            tiles: (0..game.players())
+                .map(|seat| game.rack(seat).total_tile_count())
+                .collect(),
+            racks: open_hand.then(|| (0..game.players()).map(|seat| *game.rack(seat)).collect()),
+            names,
+            bag: game.bag_size(),
+            table: game.table().to_vec(),
+            turn: 0,
+            last: None,
+        }
+    }
+
+    // Whether the event ends a turn, a timeout is followed by the drawn tile
+    fn update(&mut self, event: &GameEvent) -> bool {
+        // The points on the racks would give away what they hold
+        if !matches!(event, GameEvent::TurnSummary { .. }) {
+            self.last = Some(event.to_string());
+        }
+        match event {
+            GameEvent::TileDrawn { seat, tile } => {
+                self.tiles[*seat] += 1;
+                self.bag = self.bag.saturating_sub(1);
+                if let Some(racks) = &mut self.racks {
+                    racks[*seat].add_tile(tile);
+                }
+            }
+            GameEvent::SetsPlayed { seat, sets } => {
+                self.tiles[*seat] -= sets.iter().map(|set| set.tiles.len() as u32).sum::<u32>();
+                self.table.extend(sets);
+                if let Some(racks) = &mut self.racks {
+                    racks[*seat].subtract(&Inventory::from_sets(sets));
+                }
+            }
+            GameEvent::BoardRearranged { seat, table, tiles } => {
+                self.tiles[*seat] -= tiles.len() as u32;
+                self.table = table.clone();
+                if let Some(racks) = &mut self.racks {
+                    tiles.iter().for_each(|tile| racks[*seat].remove_tile(tile));
+                }
+            }
+            GameEvent::IllegalMove { .. }
+            | GameEvent::TurnTimeout { .. }
+            | GameEvent::TurnSummary { .. }
+            | GameEvent::GameWon { .. } => return false,
+        }
+        self.turn += 1;
+        true
+    }
+
+    fn print(&self) {
+        play::clear_screen();
+        println!("Turn {}, {} tiles in the bag", self.turn, self.bag);
+        for (seat, (name, tiles)) in self.names.iter().zip(&self.tiles).enumerate() {
+            match &self.racks {
+                Some(racks) => println!(
+                    "  {} holds {} tiles: {}",
+                    name,
+                    tiles,
+                    replay::rack_text(&racks[seat])
+                ),
+                None => println!("  {} holds {} tiles", name, tiles),
+            }
+        }
+        println!("Table:");
+        play::print_table(&self.table);
+        if let Some(last) = &self.last {
+            println!("{}", last);
+        }
+    }
+
+    fn to_json(&self) -> Json {
+        let players =
+            self.names
+                .iter()
+                .zip(&self.tiles)
+                .enumerate()
+                .map(|(seat, (name, &tiles))| {
+                    let mut player = vec![
+                        ("name".to_string(), json::string(name)),
+                        ("tiles".to_string(), Json::Number(tiles as f64)),
+                    ];
+                    if let Some(racks) = &self.racks {
+                        player.push((
+                            "rack".to_string(),
+                            json::string(replay::rack_text(&racks[seat])),
+                        ));
+                    }
+                    Json::Object(player)
+                });
+        json::object([
+            ("turn", Json::Number(self.turn as f64)),
+            ("bag", Json::Number(self.bag as f64)),
+            ("players", Json::Array(players.collect())),
+            (
+                "table",
+                Json::Array(
+                    self.table
+                        .iter()
+                        .map(|set| json::string(set.to_string()))
+                        .collect(),
+                ),
+            ),
+            ("event", self.last.as_ref().map_or(Json::Null, json::string)),
+        ])
+    }
+}
+
+// Spectators connected with `--serve`, each sent a `turn` event after every
+// turn. Those who hang up are dropped.
+type Spectators = Arc<Mutex<Vec<TcpStream>>>;
+
+fn broadcast(spectators: &Spectators, event: &str, data: &Json) {
+    let mut spectators = spectators.lock().unwrap();
+    spectators.retain(|stream| sse::send_event(stream, event, data).is_ok());
+}
+
+// Answers every request with a stream of server-sent events that starts with
+// the position so far
+fn accept_spectators(listener: TcpListener, spectators: Spectators, view: Arc<Mutex<View>>) {
+    for stream in listener.incoming().flatten() {
+        // The request itself does not matter, only its end
+        let mut reader = BufReader::new(&stream);
+        let mut line = String::new();
+        while reader.read_line(&mut line).is_ok_and(|read| read > 0) && line.trim() != "" {
+            line.clear();
+        }
+        let header = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n";
+        if (&stream).write_all(header.as_bytes()).is_err() {
+            continue;
+        }
+        let position = view.lock().unwrap().to_json();
+        if sse::send_event(&stream, "turn", &position).is_ok() {
+            spectators.lock().unwrap().push(stream);
+        }
+    }
+}
+
+// A bot game played slowly enough to follow, `--delay` seconds per turn. It is
+// shown in the terminal, or with `--serve <address>` streamed to everyone who
+// connects as server-sent events, e.g. to a browser's EventSource. The
+// players come from `--profiles` or `--weights` as for tournaments.
+// `--open-hand` shows every rack, to see why the strategies move as they do.
+pub fn run(args: &Args) -> Result<(), Report> {
+    let config = GameConfig::from_args(args)?;
+    if config.headless {
+        bail!("headless games do not report events, spectating needs a normal game");
+    }
+    let delay: f64 = args.parse_or("--delay", 1.0)?;
+    if delay < 0.0 || !delay.is_finite() {
+        bail!("--delay takes the seconds between turns, got {}", delay);
+    }
+    let delay = Duration::from_secs_f64(delay);
+    let players = profile::from_args(args, &vec!["0"; config.players].join(","))?;
+    if players.len() != config.players {
+        bail!(
+            "expected {} players, one per seat, got {}",
+            config.players,
+            players.len()
+        );
+    }
+    let strategies = players
+        .iter()
+        .map(|player| player as &dyn Strategy)
+        .collect::<Vec<&dyn Strategy>>();
+
+    let seed: Option<u64> = args.value("--seed").map(str::parse).transpose()?;
+    let mut game = match seed {
+        Some(seed) => GameState::seeded(&config, seed),
+        None => GameState::new(&config),
+    };
+    let view = Arc::new(Mutex::new(View::new(
+        &game,
+        players.iter().map(|player| player.name.clone()).collect(),
+        args.flag("--open-hand"),
+    )));
+    let spectators = Spectators::default();
+
+    match args.value("--serve") {
+        Some(address) => {
+            let listener = TcpListener::bind(address)?;
+            println!("Streaming the game to http://{}/", listener.local_addr()?);
+            let (spectators, view) = (spectators.clone(), view.clone());
+            thread::spawn(move || accept_spectators(listener, spectators, view));
+        }
+        None => view.lock().unwrap().print(),
+    }
+    let serving = args.value("--serve").is_some();
+    let (watched, streamed) = (view.clone(), spectators.clone());
+    game.subscribe(Box::new(move |event: &GameEvent| {
+        let mut view = watched.lock().unwrap();
+        let ended_turn = view.update(event);
+        if serving {
+            broadcast(&streamed, "turn", &view.to_json());
+            println!("{}", event);
+        } else {
+            view.print();
+        }
+        drop(view);
+        if ended_turn {
+            thread::sleep(delay);
+        }
+    }));
+
+    let result = game.play(&strategies)?;
+    let winner = result
+        .winner
+        .map_or(Json::Null, |seat| json::string(&players[seat].name));
+    broadcast(&spectators, "done", &json::object([("winner", winner)]));
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use crate::i18n::Message;
+use crate::notation;
+use crate::solver::{Inventory, Set, Tile};
+use std::sync::OnceLock;
+
+// Output for screen readers: tiles and sets told in words, e.g. `run: red 5,
+// red 6, joker as red 7`, always in the same order, instead of letters that
+// only make sense to someone who sees the table
+static SCREEN_READER: OnceLock<bool> = OnceLock::new();
+
+pub fn init(args: &[String]) {
+    let _ = SCREEN_READER.set(args.iter().any(|arg| arg == "--screen-reader"));
+}
+
+pub fn enabled() -> bool {
+    *SCREEN_READER.get().unwrap_or(&false)
+}
+
+// `red 5`, `joker` on a rack and `joker as red 7` in a set
+pub fn tile(tile: &Tile) -> String {
+    let name = |tile: &Tile| {
+        format!(
+            "{} {}",
+            notation::color_name(tile.color).to_lowercase(),
+            tile.number
+        )
+    };
+    match tile.is_joker {
+        true if tile.number == 0 => Message::Joker.to_string(),
+        true => Message::JokerAs { tile: name(tile) }.to_string(),
+        false => name(tile),
+    }
+}
+
+fn tiles(tiles: &[Tile]) -> String {
+    tiles.iter().map(tile).collect::<Vec<String>>().join(", ")
+}
+
+// Runs from low to high, groups in the order of the colors
+pub fn set(set: &Set) -> String {
+    let mut sorted = set.tiles.to_vec();
+    sorted.sort_by_key(|tile| (tile.number, tile.color));
+    let kind = if set.is_group() {
+        Message::Group
+    } else {
+        Message::Run
+    };
+    format!("{}: {}", kind.to_string().to_lowercase(), tiles(&sorted))
+}
+
+// By color, then by number, the jokers last
+pub fn rack(rack: &Inventory) -> String {
+    tiles(&rack.tiles())
+}
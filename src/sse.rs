@@ -0,0 +1,10 @@
+use crate::json::Json;
+use std::io::{self, Write};
+use std::net::TcpStream;
+
+// Server-sent events, as streamed by the server and by `spectate --serve`
+
+pub fn send_event(mut stream: &TcpStream, event: &str, data: &Json) -> io::Result<()> {
+    write!(stream, "event: {}\ndata: {}\n\n", event, data)?;
+    stream.flush()
+}
@@ -0,0 +1,76 @@
+// Statistics for telling real differences between strategies from noise
+
+// z score of a two-sided 95% confidence level
+pub const Z_95: f64 = 1.959964;
+
+// Wilson score interval of a success rate. Unlike the normal approximation it
+// stays within 0 to 1 and works for few games or rates close to 0 or 1.
+pub fn wilson_interval(successes: u32, trials: u32, z: f64) -> (f64, f64) {
+    if trials == 0 {
+        return (0.0, 1.0);
+    }
+    let n = trials as f64;
+    let p = successes as f64 / n;
+    let z2 = z * z;
+    let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let margin = z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+    ((center - margin).max(0.0), (center + margin).min(1.0))
+}
+
+// Two-sided p-value of an exact binomial test against a fair coin: how likely
+// a split at least this uneven is when both sides are equally strong
+pub fn binomial_test(successes: u32, trials: u32) -> f64 {
+    if trials == 0 {
+        return 1.0;
+    }
+    // Probabilities in log space, 0.5^n underflows for long tournaments
+    let n = trials as f64;
+    let mut log_pmf = n * 0.5f64.ln();
+    let mut log_pmfs = Vec::with_capacity(trials as usize + 1);
+    for k in 0..=trials {
+        log_pmfs.push(log_pmf);
+        log_pmf += ((n - k as f64) / (k as f64 + 1.0)).ln();
+    }
+
+    let observed = log_pmfs[successes as usize];
+    let p: f64 = log_pmfs
+        .iter()
+        .filter(|&&log_pmf| log_pmf <= observed + 1e-9)
+        .map(|log_pmf| log_pmf.exp())
+        .sum();
+    p.min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    // Values as statistics tables list them
+    #[test]
+    fn wilson_intervals_of_known_rates() {
+        let (low, high) = wilson_interval(5, 10, Z_95);
+        assert!(close(low, 0.2366) && close(high, 0.7634));
+        let (low, high) = wilson_interval(0, 10, Z_95);
+        assert!(low == 0.0 && close(high, 0.2775));
+        let (low, high) = wilson_interval(10, 10, Z_95);
+        assert!(close(low, 0.7225) && close(high, 1.0));
+        assert_eq!(wilson_interval(0, 0, Z_95), (0.0, 1.0));
+    }
+
+    #[test]
+    fn binomial_tests_of_known_splits() {
+        // Both tails: 2 * (1 + 10 + 45) / 1024 for 8 of 10
+        assert!(close(binomial_test(8, 10), 112.0 / 1024.0));
+        assert!(close(binomial_test(2, 10), 112.0 / 1024.0));
+        assert!(close(binomial_test(0, 10), 2.0 / 1024.0));
+        assert!(close(binomial_test(5, 10), 1.0));
+        assert_eq!(binomial_test(0, 0), 1.0);
+        // Long tournaments do not underflow to nonsense
+        assert!(close(binomial_test(1000, 2000), 1.0));
+        assert!(binomial_test(900, 2000) < 1e-5);
+    }
+}
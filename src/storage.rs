@@ -0,0 +1,191 @@
+use crate::config;
+use crate::notation;
+use crate::replay;
+use crate::solver::{Inventory, Set};
+use color_eyre::eyre::{bail, Result};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+// Users of the server and their tokens, read from a file like
+//
+//     [[user]]
+//     name = "alice"
+//     token = "a long random string"
+pub struct Users {
+    users: Vec<(String, String)>,
+}
+
+// Names end up as file names, so only plain ones are allowed
+fn check_name(name: &str, what: &str) -> Result<()> {
+    let plain = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if name.is_empty() || name.len() > 64 || !plain {
+        bail!(
+            "{} name {:?} must be 1 to 64 letters, digits, - or _",
+            what,
+            name
+        );
+    }
+    Ok(())
+}
+
+// Compares every byte, so the time taken does not give away how much of a
+// token was right
+fn same_token(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+impl Users {
+    pub fn load(path: &str) -> Result<Users> {
+        let mut users = Vec::new();
+        for table in config::parse(&fs::read_to_string(path)?)? {
+            match table.name.as_str() {
+                "" => table.check_keys(&[])?,
+                "user" => {
+                    table.check_keys(&["name", "token"])?;
+                    let (Some(name), Some(token)) = (table.string("name")?, table.string("token")?)
+                    else {
+                        bail!(
+                            "line {}: every [[user]] needs a name and a token",
+                            table.line
+                        );
+                    };
+                    check_name(name, "user")?;
+                    if token.len() < 16 {
+                        bail!(
+                            "line {}: the token of {} is too short, use at least 16 characters",
+                            table.line,
+                            name
+                        );
+                    }
+                    users.push((name.to_string(), token.to_string()));
+                }
+                other => bail!("line {}: unknown table [{}] in {}", table.line, other, path),
+            }
+        }
+        if users.is_empty() {
+            bail!("{} has no [[user]]", path);
+        }
+        Ok(Users { users })
+    }
+
+    // The user sending an `Authorization: Bearer <token>` header
+    pub fn authenticate(&self, authorization: Option<&str>) -> Option<&str> {
+        let token = authorization?.strip_prefix("Bearer ")?.trim();
+        self.users
+            .iter()
+            .find(|(_, known)| same_token(known, token))
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+pub struct SavedGame {
+    pub table: Vec<Set>,
+    pub rack: Inventory,
+    pub opened: bool,
+}
+
+impl SavedGame {
+    fn to_text(&self) -> String {
+        let table = self
+            .table
+            .iter()
+            .map(|set| set.to_string())
+            .collect::<Vec<String>>();
+        format!(
+            "table = \"{}\"\nrack = \"{}\"\nopened = {}\n",
+            table.join("; "),
+            replay::rack_text(&self.rack),
+            self.opened
+        )
+    }
+
+    fn parse(text: &str) -> Result<SavedGame> {
+        let mut game = SavedGame {
+            table: Vec::new(),
+            rack: Inventory::new(0),
+            opened: false,
+        };
+        for table in config::parse(text)? {
+            table.check_keys(&["table", "rack", "opened"])?;
+            if let Some(text) = table.string("table")? {
+                game.table = notation::parse_table(text)?;
+            }
+            if let Some(text) = table.string("rack")? {
+                game.rack = notation::parse_rack(text)?;
+            }
+            game.opened = table.bool("opened")?.unwrap_or(false);
+        }
+        Ok(game)
+    }
+}
+
+// Saved games of every user, one directory per user and one file per game
+pub struct GameStore {
+    directory: PathBuf,
+}
+
+impl GameStore {
+    pub fn new(directory: &str) -> Result<GameStore> {
+        fs::create_dir_all(directory)?;
+        Ok(GameStore {
+            directory: PathBuf::from(directory),
+        })
+    }
+
+    fn path(&self, user: &str, game: &str) -> Result<PathBuf> {
+        check_name(game, "game")?;
+        Ok(self.directory.join(user).join(format!("{}.game", game)))
+    }
+
+    pub fn list(&self, user: &str) -> Result<Vec<String>> {
+        let entries = match fs::read_dir(self.directory.join(user)) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.into()),
+        };
+        let mut games = Vec::new();
+        for entry in entries {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if let Some(game) = name.strip_suffix(".game") {
+                games.push(game.to_string());
+            }
+        }
+        games.sort();
+        Ok(games)
+    }
+
+    // None when the user has no game of that name
+    pub fn load(&self, user: &str, game: &str) -> Result<Option<SavedGame>> {
+        match fs::read_to_string(self.path(user, game)?) {
+            Ok(text) => Ok(Some(SavedGame::parse(&text)?)),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    // Written to a temporary file first, so a crash never leaves half a game
+    pub fn save(&self, user: &str, game: &str, saved: &SavedGame) -> Result<()> {
+        let path = self.path(user, game)?;
+        fs::create_dir_all(self.directory.join(user))?;
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, saved.to_text())?;
+        fs::rename(temporary, path)?;
+        Ok(())
+    }
+
+    // False when there was no such game
+    pub fn delete(&self, user: &str, game: &str) -> Result<bool> {
+        match fs::remove_file(self.path(user, game)?) {
+            Ok(()) => Ok(true),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
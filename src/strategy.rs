@@ -0,0 +1,265 @@
+use crate::forecast;
+use crate::rng::SeededRng;
+use crate::rules::Ruleset;
+use crate::solver::{self, Inventory, LayoutMemo, Set};
+use crate::threat::{self, Threat};
+use color_eyre::eyre::{eyre, Result};
+use rand::SeedableRng;
+
+// New table and the rack left over after a move
+pub type Move = (Vec<Set>, Inventory);
+
+// Everything a player may know on their turn: their own rack and what the
+// whole table sees, never the bag or the racks of the others. This is all the
+// engine hands a strategy, so no strategy can look at hidden tiles.
+pub struct Observation<'a> {
+    pub table: &'a [Set],
+    pub rack: Inventory,
+    pub opened: bool,
+    pub rules: Ruleset,
+    pub seat: usize,
+    // Tiles on every rack, who made their initial meld and how many turns
+    // everyone drew, by seat
+    pub tiles: Vec<u32>,
+    pub melded: Vec<bool>,
+    pub draws: Vec<u32>,
+    pub bag: u32,
+    pub turn: u32,
+}
+
+impl Observation<'_> {
+    // A position on its own, without other players
+    pub fn of(table: &[Set], rack: Inventory, opened: bool, rules: Ruleset) -> Observation<'_> {
+        Observation {
+            table,
+            rack,
+            opened,
+            rules,
+            seat: 0,
+            tiles: vec![rack.total_tile_count()],
+            melded: vec![opened],
+            draws: vec![0],
+            bag: 0,
+            turn: 0,
+        }
+    }
+
+    // How likely the bag runs dry within the next `turns` turns of this
+    // player, with turns drawing as often as they did so far
+    pub fn exhaustion_chance(&self, turns: u32) -> f64 {
+        let draws = self.draws.iter().sum::<u32>();
+        // One imagined draw and play, so the first turns do not count as sure
+        let draw_rate = (draws + 1) as f64 / (self.turn + 2) as f64;
+        forecast::exhaustion_chance(self.bag, self.tiles.len(), turns, draw_rate)
+    }
+
+    // Opponents who might go out on their next turn, with their racks drawn
+    // from the tiles this player cannot see. The same observation always
+    // gives the same estimate.
+    pub fn threats(&self) -> Result<Vec<Threat>> {
+        let mut unseen = Inventory::new(2);
+        unseen.subtract(&Inventory::from_sets(self.table));
+        unseen.subtract(&self.rack);
+        let mut rng = SeededRng::seed_from_u64(self.turn as u64);
+        let mut memo = LayoutMemo::new();
+
+        let mut threats = Vec::new();
+        for other in (0..self.tiles.len()).filter(|&other| other != self.seat) {
+            let tiles = self.tiles[other];
+            if tiles > threat::THREAT_TILES {
+                continue;
+            }
+            let opened = self.melded[other];
+            let chance =
+                threat::going_out_chance(self.table, &unseen, tiles, opened, &mut rng, &mut memo)?;
+            if chance > 0.0 {
+                threats.push(Threat {
+                    seat: other,
+                    tiles,
+                    opened,
+                    chance,
+                });
+            }
+        }
+        Ok(threats)
+    }
+}
+
+pub trait Strategy {
+    fn name(&self) -> String;
+
+    // Move for the player to make, or None to draw a tile
+    fn choose_move(&self, observation: &Observation) -> Result<Option<Move>>;
+}
+
+// Turns ahead the end of the bag is looked for by `dump_chance`
+const DUMP_TURNS: u32 = 2;
+
+// Lays out as many tiles as possible each turn, but values jokers kept on the
+// rack for later turns
+#[derive(Clone, Copy, Default)]
+pub struct Greedy {
+    // How many tiles a joker kept on the rack is worth, 0 plays jokers whenever possible
+    pub joker_weight: f64,
+    // Once the bag runs dry within the next turns with this chance the
+    // jokers are played like any tile, before their points count against
+    // the player
+    pub dump_chance: Option<f64>,
+}
+
+impl Greedy {
+    // Tiles laid out minus the weighted jokers given up for them. Drawing scores 0.
+    pub fn score(&self, rack: &Inventory, rest: &Inventory) -> f64 {
+        let played = rack.total_tile_count() - rest.total_tile_count();
+        let jokers = rack.jokers() - rest.jokers();
+        played as f64 - self.joker_weight * jokers as f64
+    }
+}
+
+impl Strategy for Greedy {
+    fn name(&self) -> String {
+        match self.dump_chance {
+            Some(chance) => format!(
+                "greedy(joker_weight={}, dump_chance={})",
+                self.joker_weight, chance
+            ),
+            None => format!("greedy(joker_weight={})", self.joker_weight),
+        }
+    }
+
+    // Best move while holding back each possible number of jokers. Drawing is
+    // only chosen over a poor move when the rules allow it.
+    fn choose_move(&self, observation: &Observation) -> Result<Option<Move>> {
+        let Observation {
+            table,
+            rack,
+            opened,
+            rules,
+            ..
+        } = observation;
+        let dumping = self
+            .dump_chance
+            .is_some_and(|chance| observation.exhaustion_chance(DUMP_TURNS) >= chance);
+        let greedy = if dumping { Greedy::default() } else { *self };
+        let mut best: Option<(f64, Move)> = None;
+        let max_held = if greedy.joker_weight > 0.0 {
+            rack.jokers()
+        } else {
+            0
+        };
+        for held in 0..=max_held {
+            let mut reduced = *rack;
+            reduced.set_jokers(rack.jokers() - held);
+            let Some((new_table, mut rest)) =
+                solver::best_move_by_rules(table, &reduced, *opened, rules)?
+            else {
+                continue;
+            };
+            // Holding back more jokers cannot help once the move does not use any
+            let uses_jokers = rest.jokers() < reduced.jokers();
+            rest.set_jokers(rest.jokers() + held);
+
+            let score = greedy.score(rack, &rest);
+            if best.as_ref().is_none_or(|(best, _)| score > *best) {
+                best = Some((score, (new_table, rest)));
+            }
+            if !uses_jokers {
+                break;
+            }
+        }
+        Ok(best
+            .filter(|(score, _)| *score > 0.0 || rules.must_play)
+            .map(|(_, chosen)| chosen))
+    }
+}
+
+// Plays like greedy until an opponent might go out, then picks the move that
+// leaves them the least to work with. A move scores the greedy score minus
+// `caution` times the chance that each threatening opponent goes out on the
+// table it leaves, e.g. when it completes a group of four they could split.
+#[derive(Clone, Copy)]
+pub struct Defensive {
+    pub greedy: Greedy,
+    // Tiles worth giving up to stop an opponent who would surely go out
+    pub caution: f64,
+}
+
+impl Default for Defensive {
+    fn default() -> Defensive {
+        Defensive {
+            greedy: Greedy::default(),
+            caution: 10.0,
+        }
+    }
+}
+
+impl Strategy for Defensive {
+    fn name(&self) -> String {
+        format!(
+            "defensive(joker_weight={}, caution={})",
+            self.greedy.joker_weight, self.caution
+        )
+    }
+
+    fn choose_move(&self, observation: &Observation) -> Result<Option<Move>> {
+        let Observation {
+            table,
+            rack,
+            opened,
+            rules,
+            ..
+        } = observation;
+        let threats = observation.threats()?;
+        if threats.is_empty() {
+            return self.greedy.choose_move(observation);
+        }
+
+        // Whatever the move, the tiles the opponents might hold stay the same
+        let mut unseen = Inventory::new(2);
+        unseen.subtract(&Inventory::from_sets(table));
+        unseen.subtract(rack);
+        let mut memo = LayoutMemo::new();
+        let mut danger = |table: &[Set]| -> Result<f64> {
+            let mut total = 0.0;
+            for threat in &threats {
+                // The same racks for every move, so only the tables differ
+                let mut rng = SeededRng::seed_from_u64(threat.seat as u64);
+                total += threat::going_out_chance(
+                    table,
+                    &unseen,
+                    threat.tiles,
+                    threat.opened,
+                    &mut rng,
+                    &mut memo,
+                )?;
+            }
+            Ok(total)
+        };
+
+        // Drawing leaves the table as it is
+        let mut best: (f64, Option<Move>) = if rules.must_play {
+            (f64::NEG_INFINITY, None)
+        } else {
+            (-self.caution * danger(table)?, None)
+        };
+        for (new_table, rest) in solver::candidate_moves(table, rack, *opened, rules)? {
+            let score = self.greedy.score(rack, &rest) - self.caution * danger(&new_table)?;
+            if score > best.0 {
+                best = (score, Some((new_table, rest)));
+            }
+        }
+        Ok(best.1)
+    }
+}
+
+// Comma separated joker weights, one greedy strategy each
+pub fn parse_weights(text: &str) -> Result<Vec<f64>> {
+    text.split(',')
+        .map(|weight| {
+            weight
+                .trim()
+                .parse()
+                .map_err(|_| eyre!("invalid joker weight: {}", weight))
+        })
+        .collect()
+}
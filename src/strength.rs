@@ -0,0 +1,123 @@
+use crate::bag::Bag;
+use crate::cli::Args;
+use crate::deal;
+use crate::game::GameConfig;
+use crate::notation;
+use crate::solver::{self, Inventory};
+use color_eyre::{eyre::bail, eyre::Report};
+use rand::Rng;
+
+// Draws followed at most when estimating the turns to open, racks that take
+// longer count as taking this many
+const TURN_LIMIT: u32 = 30;
+
+struct Strength {
+    // Points of the best meld the rack holds right now
+    meld_points: u32,
+    // Average number of draws until the rack holds an initial meld
+    turns_to_open: f64,
+}
+
+fn turns_to_open(rack: &Inventory, trials: u32, rng: &mut impl Rng) -> f64 {
+    if solver::has_initial_meld(rack) {
+        return 0.0;
+    }
+    let mut unseen = Inventory::new(2);
+    unseen.subtract(rack);
+    let bag = Bag::of(&unseen);
+    let total = (0..trials)
+        .map(|_| {
+            let (mut bag, mut rack) = (bag.clone(), *rack);
+            let mut turns = 0;
+            while turns < TURN_LIMIT {
+                let Some(tile) = bag.draw(rng) else {
+                    break;
+                };
+                rack.add_tile(&tile);
+                turns += 1;
+                if solver::has_initial_meld(&rack) {
+                    break;
+                }
+            }
+            turns
+        })
+        .sum::<u32>();
+    total as f64 / trials as f64
+}
+
+fn measure(rack: &Inventory, trials: u32, rng: &mut impl Rng) -> Strength {
+    Strength {
+        meld_points: solver::max_points_meld(rack).0,
+        turns_to_open: turns_to_open(rack, trials, rng),
+    }
+}
+
+// Share of the samples below the value in percent, ties count half
+fn percentile(value: f64, samples: &[f64]) -> f64 {
+    let below = samples.iter().filter(|&&sample| sample < value).count() as f64;
+    let equal = samples.iter().filter(|&&sample| sample == value).count() as f64;
+    100.0 * (below + equal / 2.0) / samples.len() as f64
+}
+
+// How the rack compares to random racks of the same size, as one line per
+// measure. `samples` random racks are drawn, and the turns to open of every
+// rack are averaged over `trials` games of drawing alone.
+pub fn report(rack: &Inventory, samples: u32, trials: u32) -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    let size = rack.total_tile_count();
+    let strength = measure(rack, trials, &mut rng);
+    let random = (0..samples)
+        .map(|_| measure(&deal::random_rack(size), trials, &mut rng))
+        .collect::<Vec<Strength>>();
+
+    let points = random
+        .iter()
+        .map(|other| other.meld_points as f64)
+        .collect::<Vec<f64>>();
+    // Fewer turns are better, so the percentile counts the racks that take longer
+    let turns = random
+        .iter()
+        .map(|other| -other.turns_to_open)
+        .collect::<Vec<f64>>();
+    vec![
+        format!(
+            "Initial meld points: {}, better than {:.0}% of random racks",
+            strength.meld_points,
+            percentile(strength.meld_points as f64, &points)
+        ),
+        format!(
+            "Expected turns to open: {:.1}, better than {:.0}% of random racks",
+            strength.turns_to_open,
+            percentile(-strength.turns_to_open, &turns)
+        ),
+    ]
+}
+
+// Percentile of a rack among random racks of its size, for the points of its
+// initial meld and the number of draws it takes to open. The rack has to be
+// of `--rack-size` tiles, so a mistyped rack is not ranked against racks of
+// the wrong size.
+pub fn run(args: &Args) -> Result<(), Report> {
+    let Some(rack) = args.value("--rack") else {
+        bail!("missing --rack, e.g. --rack \"R1 R2 R3 B7 O7 K7 J\"");
+    };
+    let rack = notation::parse_rack(rack)?;
+    let rack_size: u32 = args.parse_or("--rack-size", GameConfig::default().rack_size)?;
+    if rack.total_tile_count() != rack_size {
+        bail!(
+            "the rack has {} tiles instead of {}, pass --rack-size {} to rank it",
+            rack.total_tile_count(),
+            rack_size,
+            rack.total_tile_count()
+        );
+    }
+    let samples: u32 = args.parse_or("--samples", 500)?;
+    let trials: u32 = args.parse_or("--trials", 30)?;
+    if samples == 0 || trials == 0 {
+        bail!("--samples and --trials must be positive");
+    }
+    for line in report(&rack, samples, trials) {
+        println!("{}", line);
+    }
+    Ok(())
+}
@@ -0,0 +1,197 @@
+use crate::cli::Args;
+use crate::events::GameEvent;
+use crate::replay::{self, Replay};
+use crate::solver::{Inventory, Set, Tile};
+use crate::validator;
+use color_eyre::{eyre::bail, eyre::eyre, eyre::Report, eyre::Result};
+use std::fs;
+
+// A lucky draw this unlikely is reported on its own
+const UNLIKELY_DRAW: f64 = 0.05;
+// Players whose draws were useful this many standard deviations more often
+// than chance would have it are reported as suspicious
+const SUSPICIOUS_Z: f64 = 3.0;
+
+// A tile drawn, waiting for the next turn of the player to see if it was used
+struct Draw {
+    tile: Tile,
+    // The bag the tile came from, the tile included
+    bag: Inventory,
+}
+
+#[derive(Default)]
+struct PlayerStats {
+    draws: u32,
+    // Draws laid out on the very next turn
+    hits: u32,
+    // Sum and variance of the chance of each draw being useful
+    expected: f64,
+    variance: f64,
+}
+
+impl PlayerStats {
+    // How many standard deviations the hits are above chance
+    fn anomaly(&self) -> f64 {
+        if self.variance == 0.0 {
+            0.0
+        } else {
+            (self.hits as f64 - self.expected) / self.variance.sqrt()
+        }
+    }
+}
+
+// Sets of three tiles, every run and group a drawn tile could complete
+fn small_sets() -> Vec<[Tile; 3]> {
+    let tile = |color: usize, number: usize| Tile {
+        color: color as u8,
+        number: number as u8,
+        is_joker: false,
+    };
+    let mut sets = Vec::new();
+    for color in 0..4 {
+        for start in 1..=11 {
+            sets.push([
+                tile(color, start),
+                tile(color, start + 1),
+                tile(color, start + 2),
+            ]);
+        }
+    }
+    for number in 1..=13 {
+        for left_out in 0..4 {
+            let mut colors = (0..4).filter(|&color| color != left_out);
+            let mut next = || tile(colors.next().unwrap_or(0), number);
+            sets.push([next(), next(), next()]);
+        }
+    }
+    sets
+}
+
+// Whether the tile lets the player lay out something new: a set of three with
+// tiles of the rack, or, once opened, an extension of a set on the table. A
+// cheap stand-in for solving the whole position for every tile of the bag.
+fn is_useful(
+    tile: &Tile,
+    rack: &Inventory,
+    table: &[Set],
+    opened: bool,
+    sets: &[[Tile; 3]],
+) -> bool {
+    let completes = sets.iter().any(|set| {
+        // Places the rack cannot fill, the tile and the jokers have to
+        let gaps = set.iter().filter(|wanted| rack.count(wanted) == 0).count() as u8;
+        let fills_gap = if tile.is_joker {
+            gaps > 0
+        } else {
+            set.contains(tile) && rack.count(tile) == 0
+        };
+        fills_gap && gaps <= rack.jokers() + 1
+    });
+    completes
+        || opened
+            && table.iter().any(|set| {
+                let mut tiles = set.tiles.to_vec();
+                tiles.push(*tile);
+                validator::complete_set(&tiles).is_ok()
+            })
+}
+
+// Share of the bag that would have been useful on this turn, had it been
+// drawn instead of `drawn`
+fn useful_share(table: &[Set], rack: &Inventory, opened: bool, draw: &Draw) -> f64 {
+    let mut without = *rack;
+    without.remove_tile(&draw.tile);
+    let sets = small_sets();
+    let useful = unique_tiles(&draw.bag)
+        .iter()
+        .filter(|tile| is_useful(tile, &without, table, opened, &sets))
+        .map(|tile| draw.bag.count(tile) as u32)
+        .sum::<u32>();
+    useful as f64 / draw.bag.total_tile_count() as f64
+}
+
+fn unique_tiles(inventory: &Inventory) -> Vec<Tile> {
+    let mut tiles = inventory.tiles();
+    tiles.dedup();
+    tiles
+}
+
+// Follows a replay with every rack known and judges every draw at the next
+// turn of the player who drew it
+fn analyse(game: usize, replay: &Replay, stats: &mut Vec<PlayerStats>) -> Result<()> {
+    let players = replay.racks.len();
+    stats.resize_with(stats.len().max(players), PlayerStats::default);
+    let mut pending: Vec<Option<Draw>> = (0..players).map(|_| None).collect();
+
+    for turn in replay
+        .turns()
+        .map_err(|error| eyre!("game {}: {}", game + 1, error))?
+    {
+        let seat = turn.seat;
+        // The turn after a draw shows whether the drawn tile was useful
+        if let Some(draw) = pending[seat].take() {
+            let share = useful_share(&turn.table, &turn.rack, turn.opened, &draw);
+            let hit = turn.played.count(&draw.tile) > 0;
+            let player = &mut stats[seat];
+            player.draws += 1;
+            player.expected += share;
+            player.variance += share * (1.0 - share);
+            if hit {
+                player.hits += 1;
+                if share < UNLIKELY_DRAW {
+                    println!(
+                        "Game {}: Player {} drew {} and laid it out next turn, \
+                         only {:.1}% of the bag would have helped",
+                        game + 1,
+                        seat + 1,
+                        draw.tile,
+                        share * 100.0
+                    );
+                }
+            }
+        }
+
+        if let GameEvent::TileDrawn { tile, .. } = turn.event {
+            pending[seat] = Some(Draw {
+                tile: *tile,
+                bag: turn.bag.composition(),
+            });
+        }
+    }
+    Ok(())
+}
+
+// Flags players whose draws fit their needs more often than chance allows,
+// e.g. by seeing the bag. A draw counts as a hit when the tile is laid out on
+// the next turn, and its chance is the share of the bag that could have been
+// laid out in its place.
+pub fn run(args: &Args) -> Result<(), Report> {
+    let Some(path) = args.value("--replay") else {
+        bail!("missing --replay with a game log, e.g. from `simulate --replay`");
+    };
+    let replays = replay::parse(&fs::read_to_string(path)?)?;
+
+    let mut stats = Vec::new();
+    for (game, replay) in replays.iter().enumerate() {
+        analyse(game, replay, &mut stats)?;
+    }
+
+    println!("Player | Draws | Hits | Expected | Anomaly");
+    for (seat, player) in stats.iter().enumerate() {
+        let anomaly = player.anomaly();
+        println!(
+            "{:6} | {:5} | {:4} | {:8.1} | {:+7.2}{}",
+            seat + 1,
+            player.draws,
+            player.hits,
+            player.expected,
+            anomaly,
+            if anomaly > SUSPICIOUS_Z {
+                "  suspicious"
+            } else {
+                ""
+            }
+        );
+    }
+    Ok(())
+}
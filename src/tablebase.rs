@@ -0,0 +1,330 @@
+use crate::cli::Args;
+use crate::solver::{self, Inventory, LayoutMemo, Tile, TILE_TYPES};
+use color_eyre::{eyre::bail, eyre::eyre, eyre::Report, eyre::Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+const MAGIC: &[u8; 4] = b"RKTB";
+const HEADER_LEN: usize = 8;
+// Table, rack and best pick, 16 bytes each
+const ENTRY_LEN: usize = 48;
+
+// Tiles counted in 2 bits per column of the encoding, enough for both copies
+type Packed = u128;
+
+// Renaming the colors or counting the numbers down from 13 turns runs into
+// runs and groups into groups, so the 48 ways to do both give positions with
+// the same answer
+#[derive(Clone, Copy)]
+struct Symmetry {
+    colors: [u8; 4],
+    mirrored: bool,
+}
+
+impl Symmetry {
+    fn identity() -> Symmetry {
+        Symmetry {
+            colors: [0, 1, 2, 3],
+            mirrored: false,
+        }
+    }
+
+    fn all() -> Vec<Symmetry> {
+        let mut all = Vec::new();
+        for a in 0..4u8 {
+            for b in (0..4).filter(|&b| b != a) {
+                for c in (0..4).filter(|&c| c != a && c != b) {
+                    let d = 6 - a - b - c;
+                    for mirrored in [false, true] {
+                        all.push(Symmetry {
+                            colors: [a, b, c, d],
+                            mirrored,
+                        });
+                    }
+                }
+            }
+        }
+        all
+    }
+
+    fn apply(&self, tile: &Tile) -> Tile {
+        if tile.is_joker {
+            return *tile;
+        }
+        Tile {
+            color: self
+                .colors
+                .get(tile.color as usize)
+                .copied()
+                .unwrap_or(tile.color),
+            number: if self.mirrored {
+                14 - tile.number
+            } else {
+                tile.number
+            },
+            is_joker: false,
+        }
+    }
+
+    fn inverse(&self) -> Symmetry {
+        let mut colors = [0; 4];
+        for (from, &to) in self.colors.iter().enumerate() {
+            if let Some(color) = colors.get_mut(to as usize) {
+                *color = from as u8;
+            }
+        }
+        Symmetry {
+            colors,
+            mirrored: self.mirrored,
+        }
+    }
+
+    fn pack(&self, tiles: &[Tile]) -> Packed {
+        tiles
+            .iter()
+            .filter_map(|tile| solver::tile_index(&self.apply(tile)))
+            .map(|index| 1 << (2 * index))
+            .sum()
+    }
+
+    fn unpack(&self, packed: Packed) -> Inventory {
+        let mut tiles = Inventory::new(0);
+        for index in 0..TILE_TYPES {
+            for _ in 0..(packed >> (2 * index)) & 3 {
+                tiles.add_tile(&self.apply(&solver::tile_at(index)));
+            }
+        }
+        tiles
+    }
+}
+
+// The symmetries that turn the table into its canonical form, and that form
+fn table_symmetries(symmetries: &[Symmetry], table: &Inventory) -> (Vec<Symmetry>, Packed) {
+    let tiles = table.tiles();
+    let packed = symmetries
+        .iter()
+        .map(|symmetry| symmetry.pack(&tiles))
+        .collect::<Vec<Packed>>();
+    let lowest = packed.iter().copied().min().unwrap_or(0);
+    let fitting = symmetries
+        .iter()
+        .zip(packed)
+        .filter(|(_, packed)| *packed == lowest)
+        .map(|(symmetry, _)| *symmetry)
+        .collect();
+    (fitting, lowest)
+}
+
+// The form of the position shared by all its symmetric ones, and the symmetry
+// that turns the position into it. The table comes first, so only the
+// symmetries of `table_symmetries` are tried on the rack.
+fn canonical(
+    fitting: &[Symmetry],
+    table: Packed,
+    rack: &Inventory,
+) -> Option<((Packed, Packed), Symmetry)> {
+    let tiles = rack.tiles();
+    fitting
+        .iter()
+        .map(|symmetry| ((table, symmetry.pack(&tiles)), *symmetry))
+        .min_by_key(|(key, _)| *key)
+}
+
+// The most rack tiles that can be laid out with every tile of the table,
+// empty when nothing fits
+fn best_pick(table: &Inventory, rack: &Inventory, memo: &mut LayoutMemo) -> Inventory {
+    let mut best = Inventory::new(0);
+    rack.each_subset(|pick| {
+        let better = pick.total_tile_count() > best.total_tile_count();
+        if better && solver::can_lay_out(&table.combined(pick), memo) {
+            best = *pick;
+        }
+    });
+    best
+}
+
+// The best move of every position of an opened player with at most `tiles`
+// tiles on the table and rack together, one entry for all symmetric positions
+struct Tablebase {
+    tiles: u32,
+    symmetries: Vec<Symmetry>,
+    picks: HashMap<(Packed, Packed), Packed>,
+}
+
+// Every pick of up to `left` tiles of the columns from `column` on, no more
+// copies of a tile than `available` holds
+fn each_inventory(
+    column: usize,
+    left: u32,
+    available: &Inventory,
+    tiles: &mut Inventory,
+    visit: &mut impl FnMut(&Inventory),
+) {
+    if column == TILE_TYPES || left == 0 {
+        visit(tiles);
+        return;
+    }
+    let tile = solver::tile_at(column);
+    let most = left.min(available.count(&tile) as u32);
+    each_inventory(column + 1, left, available, tiles, visit);
+    for copies in 1..=most {
+        tiles.add_tile(&tile);
+        each_inventory(column + 1, left - copies, available, tiles, visit);
+    }
+    (0..most).for_each(|_| tiles.remove_tile(&tile));
+}
+
+impl Tablebase {
+    fn build(tiles: u32) -> Tablebase {
+        let symmetries = Symmetry::all();
+        let mut picks = HashMap::new();
+        let mut memo = LayoutMemo::new();
+        let pool = Inventory::new(2);
+        // A position is symmetric to one whose table is in its own canonical
+        // form, only those tables need their racks tried
+        let mut tables = Vec::new();
+        each_inventory(0, tiles, &pool, &mut Inventory::new(0), &mut |table| {
+            let (fitting, packed) = table_symmetries(&symmetries, table);
+            if packed == Symmetry::identity().pack(&table.tiles())
+                && solver::can_lay_out(table, &mut memo)
+            {
+                tables.push((*table, fitting, packed));
+            }
+        });
+
+        for (table, fitting, packed) in tables {
+            let mut available = pool;
+            available.subtract(&table);
+            let left = tiles - table.total_tile_count();
+            each_inventory(0, left, &available, &mut Inventory::new(0), &mut |rack| {
+                if rack.total_tile_count() == 0 {
+                    return;
+                }
+                let Some((key, symmetry)) = canonical(&fitting, packed, rack) else {
+                    return;
+                };
+                picks
+                    .entry(key)
+                    .or_insert_with(|| symmetry.pack(&best_pick(&table, rack, &mut memo).tiles()));
+            });
+        }
+        Tablebase {
+            tiles,
+            symmetries,
+            picks,
+        }
+    }
+
+    // `RKTB`, the tiles as 4 bytes and then every entry
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(self.tiles.to_le_bytes());
+        let mut entries = self.picks.iter().collect::<Vec<_>>();
+        entries.sort();
+        for ((table, rack), pick) in entries {
+            bytes.extend(table.to_le_bytes());
+            bytes.extend(rack.to_le_bytes());
+            bytes.extend(pick.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Tablebase> {
+        let tiles = bytes
+            .get(4..8)
+            .and_then(|tiles| tiles.try_into().ok())
+            .map(u32::from_le_bytes);
+        let fits =
+            || bytes.starts_with(MAGIC) && (bytes.len() - HEADER_LEN).is_multiple_of(ENTRY_LEN);
+        let Some(tiles) = tiles.filter(|_| fits()) else {
+            bail!("not a tablebase file");
+        };
+        let number = |at: usize| {
+            bytes
+                .get(at..at + 16)?
+                .try_into()
+                .ok()
+                .map(Packed::from_le_bytes)
+        };
+        let picks = (HEADER_LEN..bytes.len())
+            .step_by(ENTRY_LEN)
+            .map(|at| Some(((number(at)?, number(at + 16)?), number(at + 32)?)))
+            .collect::<Option<_>>()
+            .ok_or_else(|| eyre!("not a tablebase file"))?;
+        Ok(Tablebase {
+            tiles,
+            symmetries: Symmetry::all(),
+            picks,
+        })
+    }
+
+    fn lookup(&self, table: &Inventory, rack: &Inventory) -> Option<Inventory> {
+        if table.total_tile_count() + rack.total_tile_count() > self.tiles {
+            return None;
+        }
+        let (fitting, packed) = table_symmetries(&self.symmetries, table);
+        let (key, symmetry) = canonical(&fitting, packed, rack)?;
+        let pick = self.picks.get(&key)?;
+        Some(symmetry.inverse().unpack(*pick))
+    }
+}
+
+static PATH: OnceLock<PathBuf> = OnceLock::new();
+// Read on the first position small enough for it
+static TABLEBASE: OnceLock<Result<Tablebase, String>> = OnceLock::new();
+
+// The file of `--tablebase`, used by every search of an opened player
+// afterwards
+pub fn set_path(path: &str) {
+    let _ = PATH.set(PathBuf::from(path));
+}
+
+// The tiles of the rack the best move lays out, empty to draw, or None
+// without a tablebase covering the position
+pub fn best_move(table: &Inventory, rack: &Inventory) -> Result<Option<Inventory>> {
+    let Some(path) = PATH.get() else {
+        return Ok(None);
+    };
+    let tablebase = TABLEBASE.get_or_init(|| {
+        fs::read(path)
+            .map_err(Report::from)
+            .and_then(|bytes| Tablebase::from_bytes(&bytes))
+            .map_err(|error| format!("tablebase {}: {}", path.display(), error))
+    });
+    match tablebase {
+        Ok(tablebase) => Ok(tablebase.lookup(table, rack)),
+        Err(error) => Err(eyre!("{}", error)),
+    }
+}
+
+// Builds the tablebase of positions with up to `--tiles` tiles into the file
+// of `--build`. Searches use it when given with `--tablebase`.
+pub fn run(args: &Args) -> Result<(), Report> {
+    let Some(path) = args.value("--build") else {
+        bail!("missing --build with the file to write the tablebase to");
+    };
+    let tiles: u32 = args.parse_or("--tiles", 5)?;
+    if !(3..=8).contains(&tiles) {
+        bail!("--tiles must be between 3 and 8, got {}", tiles);
+    }
+    let start = Instant::now();
+    let tablebase = Tablebase::build(tiles);
+    fs::write(path, tablebase.to_bytes())?;
+    let going_out = tablebase
+        .picks
+        .iter()
+        .filter(|((_, rack), pick)| rack == *pick)
+        .count();
+    println!(
+        "{} positions of up to {} tiles, {} of them going out, written to {} in {:.1?}",
+        tablebase.picks.len(),
+        tiles,
+        going_out,
+        path,
+        start.elapsed()
+    );
+    Ok(())
+}
@@ -0,0 +1,166 @@
+use crate::cli::Args;
+use crate::render;
+use crate::solver::Tile;
+use color_eyre::eyre::{bail, Result};
+use std::env;
+use std::sync::OnceLock;
+
+// How tiles look in the terminal. Red and orange are hard to tell apart for
+// many people, so the colorblind and large themes put a symbol of its own in
+// front of every color as well.
+struct Theme {
+    name: &'static str,
+    // SGR parameters of the four colors and of the joker, none for plain text
+    styles: Option<[&'static str; 5]>,
+    symbols: bool,
+    // Numbers drawn five rows high in block characters
+    large: bool,
+}
+
+const SYMBOLS: [char; 4] = ['●', '▲', '■', '◆'];
+
+// Vermillion, blue, orange and black of the Okabe-Ito palette, bold, which
+// stay apart with the common kinds of color blindness
+const COLORBLIND: [&str; 5] = ["1;38;5;166", "1;38;5;32", "1;38;5;214", "1", "1;38;5;175"];
+
+static THEMES: [Theme; 4] = [
+    Theme {
+        name: "plain",
+        styles: None,
+        symbols: false,
+        large: false,
+    },
+    Theme {
+        name: "colors",
+        styles: Some(["31", "34", "38;5;208", "1", "35"]),
+        symbols: false,
+        large: false,
+    },
+    Theme {
+        name: "colorblind",
+        styles: Some(COLORBLIND),
+        symbols: true,
+        large: false,
+    },
+    Theme {
+        name: "large",
+        styles: Some(COLORBLIND),
+        symbols: true,
+        large: true,
+    },
+];
+
+// Tiles on a line in the large theme, about 80 columns
+const LARGE_TILES: usize = 5;
+// Columns of a tile in the large theme, two digits of three dots two wide
+const LARGE_WIDTH: usize = 14;
+
+static THEME: OnceLock<&'static Theme> = OnceLock::new();
+
+// `--theme`, plain unless given. Colors are left out when NO_COLOR is set, the
+// symbols stay.
+pub fn init(args: &[String]) -> Result<()> {
+    let theme = match Args::new(args).value("--theme") {
+        None => &THEMES[0],
+        Some(name) => match THEMES.iter().find(|theme| theme.name == name) {
+            Some(theme) => theme,
+            None => bail!(
+                "unknown theme {}, expected one of {}",
+                name,
+                THEMES
+                    .iter()
+                    .map(|theme| theme.name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        },
+    };
+    let _ = THEME.set(theme);
+    Ok(())
+}
+
+fn current() -> &'static Theme {
+    THEME.get().copied().unwrap_or(&THEMES[0])
+}
+
+pub fn is_large() -> bool {
+    current().large
+}
+
+fn styled(text: &str, tile: &Tile) -> String {
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    match current().styles {
+        Some(styles) if !no_color => {
+            let style = if tile.is_joker {
+                styles[4]
+            } else {
+                styles[tile.color as usize]
+            };
+            format!("\x1b[{}m{}\x1b[0m", style, text)
+        }
+        _ => text.to_string(),
+    }
+}
+
+// The tile as it is typed, with the symbol of its color in front
+fn face(tile: &Tile) -> String {
+    match current().symbols && !tile.is_joker {
+        true => format!("{}{}", SYMBOLS[tile.color as usize], tile),
+        false => tile.to_string(),
+    }
+}
+
+pub fn tile(tile: &Tile) -> String {
+    styled(&face(tile), tile)
+}
+
+// Tiles separated by spaces like `notation::tiles_text`, in the theme
+pub fn tiles(tiles: &[Tile]) -> String {
+    tiles.iter().map(tile).collect::<Vec<String>>().join(" ")
+}
+
+fn centered(text: &str, width: usize) -> String {
+    let left = width.saturating_sub(text.chars().count()) / 2;
+    format!(
+        "{:left$}{:<rest$}",
+        "",
+        text,
+        left = left,
+        rest = width - left
+    )
+}
+
+// The tiles in numbers five rows high with the tile itself below them,
+// `LARGE_TILES` to a line, every line indented by four spaces
+pub fn large(tiles: &[Tile]) -> String {
+    let mut text = String::new();
+    for line in tiles.chunks(LARGE_TILES) {
+        for row in 0..5 {
+            let cells = line.iter().map(|tile| {
+                let digits = if tile.is_joker {
+                    "J".to_string()
+                } else {
+                    tile.number.to_string()
+                };
+                let dots = digits
+                    .chars()
+                    .map(|digit| {
+                        let bits = render::glyph(digit)[row];
+                        (0..3)
+                            .rev()
+                            .map(|bit| if bits >> bit & 1 == 1 { "██" } else { "  " })
+                            .collect::<String>()
+                    })
+                    .collect::<Vec<String>>()
+                    .join("  ");
+                styled(&centered(&dots, LARGE_WIDTH), tile)
+            });
+            text += &format!("    {}\n", cells.collect::<Vec<String>>().join("  "));
+        }
+        let labels = line
+            .iter()
+            .map(|tile| styled(&centered(&face(tile), LARGE_WIDTH), tile));
+        text += &format!("    {}\n\n", labels.collect::<Vec<String>>().join("  "));
+    }
+    text
+}
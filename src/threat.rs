@@ -0,0 +1,54 @@
+use crate::bag::Bag;
+use crate::solver::{self, Inventory, LayoutMemo, Set};
+use color_eyre::eyre::Result;
+use rand::Rng;
+
+// Opponents holding this few tiles may go out any turn
+pub const THREAT_TILES: u32 = 3;
+// Racks tried for every opponent, rough but quick
+const SAMPLES: u32 = 40;
+
+// An opponent who could go out on their next turn
+#[derive(Clone, Debug)]
+pub struct Threat {
+    pub seat: usize,
+    pub tiles: u32,
+    pub opened: bool,
+    // Share of the racks they might hold that lay out every tile
+    pub chance: f64,
+}
+
+// How likely a player holding `tiles` tiles goes out on their next turn, with
+// their rack drawn from the tiles the asking player cannot see. The memo can
+// be shared by every question about the same game position.
+pub fn going_out_chance(
+    table: &[Set],
+    unseen: &Inventory,
+    tiles: u32,
+    opened: bool,
+    rng: &mut impl Rng,
+    memo: &mut LayoutMemo,
+) -> Result<f64> {
+    if tiles == 0 || unseen.total_tile_count() < tiles {
+        return Ok(0.0);
+    }
+    let table_tiles = Inventory::from_sets(table);
+    let pool = Bag::of(unseen);
+    let mut out = 0;
+    for _ in 0..SAMPLES {
+        let rack = pool.clone().draw_n(tiles, rng);
+        // Going out lays out every tile, so once opened the table and the rack
+        // together have to make a complete arrangement, which is quicker to
+        // rule out than finding the best move. Before, it is the initial meld.
+        let goes_out = if opened {
+            solver::can_lay_out(&table_tiles.combined(&rack), memo)
+        } else {
+            solver::best_move(table, &rack, opened)?
+                .is_some_and(|(_, rest)| rest.total_tile_count() == 0)
+        };
+        if goes_out {
+            out += 1;
+        }
+    }
+    Ok(out as f64 / SAMPLES as f64)
+}
@@ -0,0 +1,704 @@
+// The core of the solver: tiles, sets and whether tiles can be laid out. It
+// needs nothing beyond `core` and `alloc`, so it builds with `no_std` for
+// devices like a scoreboard and for small WASM hosts; the rest of the library
+// needs the `std` feature.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::{Deref, DerefMut};
+use core::slice;
+
+// Points a joker left on the rack counts against its owner
+pub const JOKER_PENALTY: u32 = 30;
+
+#[derive(PartialEq, Clone, Copy, Eq, Hash, Debug)]
+
+pub struct Tile {
+    pub color: u8,
+    pub number: u8,
+    pub is_joker: bool,
+}
+
+// Kinds of tiles, 13 numbers in 4 colors and the joker
+pub const TILE_TYPES: usize = 53;
+
+// Column of the tile in counts of every kind, color * 13 + number - 1 and the
+// last one for jokers. None for a tile that does not exist.
+pub fn tile_index(tile: &Tile) -> Option<usize> {
+    match tile.is_joker {
+        true => Some(TILE_TYPES - 1),
+        false => slot(tile).map(|(number, color)| color * 13 + number),
+    }
+}
+
+// Place of a tile in the grid of an Inventory, number counted from 0. None for
+// jokers and for tiles that do not exist, such as a 0 or a fifth color.
+fn slot(tile: &Tile) -> Option<(usize, usize)> {
+    let number = (tile.number as usize)
+        .checked_sub(1)
+        .filter(|&number| number < 13)?;
+    let color = tile.color as usize;
+    (!tile.is_joker && color < 4).then_some((number, color))
+}
+
+// The tile of a column, jokers without a color or number
+pub fn tile_at(index: usize) -> Tile {
+    if index == TILE_TYPES - 1 {
+        Tile {
+            color: 0,
+            number: 0,
+            is_joker: true,
+        }
+    } else {
+        Tile {
+            color: (index / 13) as u8,
+            number: (index % 13) as u8 + 1,
+            is_joker: false,
+        }
+    }
+}
+
+// Most tiles a set can hold, a run from 1 to 13
+pub const MAX_SET_TILES: usize = 13;
+
+// The tiles of a set, kept inline so that making a set never allocates and
+// sets can be copied. Derefs to a slice for everything else.
+#[derive(Clone, Copy)]
+pub struct Tiles {
+    tiles: [Tile; MAX_SET_TILES],
+    len: u8,
+}
+
+impl Tiles {
+    const EMPTY: Tile = Tile {
+        color: 0,
+        number: 0,
+        is_joker: false,
+    };
+
+    pub fn new() -> Tiles {
+        Tiles {
+            tiles: [Self::EMPTY; MAX_SET_TILES],
+            len: 0,
+        }
+    }
+
+    // Tiles past MAX_SET_TILES are left out, callers that take tiles from
+    // outside check the length first
+    pub fn from_slice(tiles: &[Tile]) -> Tiles {
+        tiles.iter().copied().collect()
+    }
+
+    // Left out once MAX_SET_TILES are held
+    pub fn push(&mut self, tile: Tile) {
+        if let Some(free) = self.tiles.get_mut(self.len as usize) {
+            *free = tile;
+            self.len += 1;
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<Tile> {
+        self.len = self.len.checked_sub(1)?;
+        self.tiles.get(self.len as usize).copied()
+    }
+}
+
+impl Default for Tiles {
+    fn default() -> Tiles {
+        Tiles::new()
+    }
+}
+
+impl Deref for Tiles {
+    type Target = [Tile];
+
+    fn deref(&self) -> &[Tile] {
+        self.tiles.get(..self.len as usize).unwrap_or_default()
+    }
+}
+
+impl DerefMut for Tiles {
+    fn deref_mut(&mut self) -> &mut [Tile] {
+        self.tiles.get_mut(..self.len as usize).unwrap_or_default()
+    }
+}
+
+// Only the tiles in use count, whatever is left in the rest of the array
+impl PartialEq for Tiles {
+    fn eq(&self, other: &Tiles) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for Tiles {}
+
+impl Hash for Tiles {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl fmt::Debug for Tiles {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl FromIterator<Tile> for Tiles {
+    fn from_iter<I: IntoIterator<Item = Tile>>(tiles: I) -> Tiles {
+        let mut collected = Tiles::new();
+        collected.extend(tiles);
+        collected
+    }
+}
+
+impl Extend<Tile> for Tiles {
+    fn extend<I: IntoIterator<Item = Tile>>(&mut self, tiles: I) {
+        tiles.into_iter().for_each(|tile| self.push(tile));
+    }
+}
+
+impl<'a> IntoIterator for &'a Tiles {
+    type Item = &'a Tile;
+    type IntoIter = slice::Iter<'a, Tile>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Eq, Hash, Debug)]
+pub struct Set {
+    // Group of 3 or 4 tiles with same number and different colors
+    // Or run of 3 or more tiles with same color and consecutive numbers
+    pub tiles: Tiles,
+}
+
+impl Set {
+    pub fn is_group(&self) -> bool {
+        let number = self.tiles.first().map(|tile| tile.number);
+        self.tiles.iter().all(|tile| Some(tile.number) == number)
+    }
+
+    pub fn points(&self) -> u32 {
+        // Jokers count as the tile they stand in for
+        self.tiles.iter().map(|tile| tile.number as u32).sum()
+    }
+
+    // Whether the set is a legal group or run, and what is wrong if not
+    pub fn check(&self) -> Result<(), SetError> {
+        let tiles = &self.tiles;
+        if tiles.len() < 3 {
+            return Err(SetError::TooFew);
+        }
+        if let Some(&tile) = tiles
+            .iter()
+            .find(|tile| tile.color > 3 || !(1..=13).contains(&tile.number))
+        {
+            return Err(SetError::NoSuchTile(tile));
+        }
+
+        if self.is_group() {
+            if tiles.len() > 4 {
+                return Err(SetError::GroupTooLong);
+            }
+            for (index, tile) in tiles.iter().enumerate() {
+                if tiles
+                    .iter()
+                    .skip(index + 1)
+                    .any(|other| other.color == tile.color)
+                {
+                    return Err(SetError::SameColor(tile.color));
+                }
+            }
+        } else {
+            let color = tiles.first().map(|tile| tile.color);
+            if tiles.iter().any(|tile| Some(tile.color) != color) {
+                return Err(SetError::MixedColors);
+            }
+            let mut numbers = tiles.iter().map(|tile| tile.number).collect::<Vec<u8>>();
+            numbers.sort_unstable();
+            if numbers
+                .iter()
+                .zip(numbers.iter().skip(1))
+                .any(|(low, high)| *high != low + 1)
+            {
+                return Err(SetError::NotConsecutive);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Why a set is not a legal group or run, see Set::check
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SetError {
+    TooFew,
+    NoSuchTile(Tile),
+    GroupTooLong,
+    // Two tiles of this color in a group
+    SameColor(u8),
+    MixedColors,
+    NotConsecutive,
+}
+
+// Random key of every kind of tile, 13 numbers in 4 colors and the joker last.
+// Fixed at compile time so keys are the same in every run.
+const TILE_KEYS: [u64; 53] = tile_keys();
+const JOKER_KEY: u64 = TILE_KEYS[52];
+
+// Keys from splitmix64. Only ever run by the compiler, which stops at any
+// index out of bounds.
+#[allow(clippy::indexing_slicing)]
+const fn tile_keys() -> [u64; 53] {
+    let mut keys = [0; 53];
+    let mut state: u64 = 0;
+    let mut index = 0;
+    while index < keys.len() {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut key = state;
+        key = (key ^ (key >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        key = (key ^ (key >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        keys[index] = key ^ (key >> 31);
+        index += 1;
+    }
+    keys
+}
+
+fn tile_key(number: usize, color: usize) -> u64 {
+    TILE_KEYS.get(number * 4 + color).copied().unwrap_or(0)
+}
+
+#[derive(PartialEq, Clone, Copy, Eq, PartialOrd, Ord)]
+
+pub struct Inventory {
+    pub(crate) grid: [[u8; 4]; 13],
+    pub(crate) jokers: u8,
+    // Zobrist key: the sum of the keys of every tile, kept up to date as tiles
+    // come and go so hashing an inventory does not have to look at every count
+    key: u64,
+}
+
+// Only the key is hashed, equal inventories have equal keys
+impl Hash for Inventory {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.key);
+    }
+}
+
+impl Inventory {
+    pub fn new(num: u8) -> Inventory {
+        let all = TILE_KEYS
+            .iter()
+            .fold(0u64, |sum, &key| sum.wrapping_add(key));
+        Inventory {
+            grid: [[num; 4]; 13],
+            jokers: num,
+            key: all.wrapping_mul(num as u64),
+        }
+    }
+
+    pub fn from_sets(sets: &[Set]) -> Inventory {
+        let mut inventory = Inventory::new(0);
+        for tile in sets.iter().flat_map(|set| set.tiles.iter()) {
+            inventory.add_tile(tile);
+        }
+        inventory
+    }
+
+    // Counts stop at 255 copies of a tile
+    pub fn combined(&self, other: &Inventory) -> Inventory {
+        let mut combined = *self;
+        for (number, row) in other.grid.iter().enumerate() {
+            for (color, &count) in row.iter().enumerate() {
+                combined.set_cell(
+                    number,
+                    color,
+                    self.cell(number, color).saturating_add(count),
+                );
+            }
+        }
+        combined.set_jokers(self.jokers.saturating_add(other.jokers));
+        combined
+    }
+
+    // Remove every tile of the other inventory, as many copies as this one holds
+    pub fn subtract(&mut self, other: &Inventory) {
+        for (number, row) in other.grid.iter().enumerate() {
+            for (color, &count) in row.iter().enumerate() {
+                self.set_cell(
+                    number,
+                    color,
+                    self.cell(number, color).saturating_sub(count),
+                );
+            }
+        }
+        self.set_jokers(self.jokers.saturating_sub(other.jokers));
+    }
+
+    // Tiles that do not exist are left out
+    pub fn add_tile(&mut self, tile: &Tile) {
+        if tile.is_joker {
+            self.set_jokers(self.jokers.saturating_add(1));
+        } else if let Some((number, color)) = slot(tile) {
+            self.set_cell(number, color, self.cell(number, color).saturating_add(1));
+        }
+    }
+
+    // Nothing happens if the inventory does not hold the tile
+    pub fn remove_tile(&mut self, tile: &Tile) {
+        if tile.is_joker {
+            self.set_jokers(self.jokers.saturating_sub(1));
+        } else if let Some((number, color)) = slot(tile) {
+            self.take(number, color);
+        }
+    }
+
+    // One copy less of the tile at this place of the grid
+    pub(crate) fn take(&mut self, number: usize, color: usize) {
+        self.set_cell(number, color, self.cell(number, color).saturating_sub(1));
+    }
+
+    // Copies of the tile at this place of the grid, none outside it
+    pub(crate) fn cell(&self, number: usize, color: usize) -> u8 {
+        self.grid
+            .get(number)
+            .and_then(|row| row.get(color))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // Sets the copies of the tile at this place of the grid, keeping the key
+    fn set_cell(&mut self, number: usize, color: usize, count: u8) {
+        let key = tile_key(number, color);
+        if let Some(cell) = self.grid.get_mut(number).and_then(|row| row.get_mut(color)) {
+            self.key = self
+                .key
+                .wrapping_sub(key.wrapping_mul(*cell as u64))
+                .wrapping_add(key.wrapping_mul(count as u64));
+            *cell = count;
+        }
+    }
+
+    // Zobrist key of the tiles, the same for the same tiles in every run
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    // Copies of the tile, jokers counted as jokers whatever they stand in for
+    pub fn count(&self, tile: &Tile) -> u8 {
+        match (tile.is_joker, slot(tile)) {
+            (true, _) => self.jokers,
+            (false, Some((number, color))) => self.cell(number, color),
+            (false, None) => 0,
+        }
+    }
+
+    // Tiles of the other inventory that this one does not hold enough copies of
+    pub fn missing(&self, other: &Inventory) -> Vec<Tile> {
+        let mut missing = Vec::new();
+        for (number, row) in other.grid.iter().enumerate() {
+            for (color, &count) in row.iter().enumerate() {
+                for _ in self.cell(number, color)..count {
+                    missing.push(Tile {
+                        color: color as u8,
+                        number: number as u8 + 1,
+                        is_joker: false,
+                    });
+                }
+            }
+        }
+        for _ in self.jokers..other.jokers {
+            missing.push(Tile {
+                color: 0,
+                number: 0,
+                is_joker: true,
+            });
+        }
+        missing
+    }
+
+    // Every tile one by one, ordered by color and number like a sorted rack
+    pub fn tiles(&self) -> Vec<Tile> {
+        let mut tiles = Vec::new();
+        for color in 0..4 {
+            for number in 0..13 {
+                for _ in 0..self.cell(number, color) {
+                    tiles.push(Tile {
+                        color: color as u8,
+                        number: number as u8 + 1,
+                        is_joker: false,
+                    });
+                }
+            }
+        }
+        for _ in 0..self.jokers {
+            tiles.push(Tile {
+                color: 0,
+                number: 0,
+                is_joker: true,
+            });
+        }
+        tiles
+    }
+
+    // Every different pick of these tiles, from none to all of them
+    pub fn each_subset(&self, mut visit: impl FnMut(&Inventory)) {
+        let mut kinds = self.tiles();
+        kinds.dedup();
+        let kinds = kinds
+            .into_iter()
+            .map(|tile| (tile, self.count(&tile)))
+            .collect::<Vec<_>>();
+        each_pick(&kinds, &mut Inventory::new(0), &mut visit);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jokers == 0 && self.grid.iter().flatten().all(|&tile| tile == 0)
+    }
+
+    // Value of the tiles left on a rack at the end of a game
+    pub fn points(&self) -> u32 {
+        let numbers = self
+            .grid
+            .iter()
+            .enumerate()
+            .map(|(number, row)| {
+                (number as u32 + 1) * row.iter().map(|&count| count as u32).sum::<u32>()
+            })
+            .sum::<u32>();
+        numbers + self.jokers as u32 * JOKER_PENALTY
+    }
+
+    pub fn jokers(&self) -> u8 {
+        self.jokers
+    }
+
+    pub fn set_jokers(&mut self, jokers: u8) {
+        let before = JOKER_KEY.wrapping_mul(self.jokers as u64);
+        self.key = self
+            .key
+            .wrapping_sub(before)
+            .wrapping_add(JOKER_KEY.wrapping_mul(jokers as u64));
+        self.jokers = jokers;
+    }
+
+    pub fn total_tile_count(&self) -> u32 {
+        self.grid
+            .iter()
+            .flat_map(|row| row.iter())
+            .map(|&x| x as u32)
+            .sum::<u32>()
+            + self.jokers as u32
+    }
+
+    pub(crate) fn available_tiles(&self) -> Vec<(usize, usize)> {
+        self.grid
+            .iter()
+            .enumerate()
+            .flat_map(|(number, row)| {
+                row.iter().enumerate().filter_map(move |(color, &count)| {
+                    if count > 0 {
+                        Some((number, color))
+                    } else {
+                        None
+                    }
+                })
+            })
+            .collect()
+    }
+
+    pub fn remove_tiles(&mut self, set: &Set) {
+        for tile in &set.tiles {
+            self.remove_tile(tile);
+        }
+    }
+}
+
+// All sets (including joker variants) whose lowest real tile is the given tile.
+// Lower tiles are assumed to be handled already, so groups only add higher colors.
+pub(crate) fn sets_starting_at(inventory: &Inventory, number: u8, color: u8) -> Vec<Set> {
+    let mut sets = Vec::new();
+    // Numbers start at 1, there is no set of a tile 0
+    let Some(below) = number.checked_sub(1) else {
+        return sets;
+    };
+    let first = Tile {
+        color,
+        number,
+        is_joker: false,
+    };
+
+    let others = (color.saturating_add(1)..4)
+        .filter(|&other| inventory.cell(below as usize, other as usize) > 0)
+        .collect::<Vec<u8>>();
+    for mask in 0..(1 << others.len()) {
+        let mut tiles = Tiles::from_slice(&[first]);
+        for (index, &other) in others.iter().enumerate() {
+            if mask & (1 << index) != 0 {
+                tiles.push(Tile {
+                    color: other,
+                    number,
+                    is_joker: false,
+                });
+            }
+        }
+
+        let missing = (0..4)
+            .filter(|&other| tiles.iter().all(|tile| tile.color != other))
+            .collect::<Vec<u8>>();
+        for jokers in 0..=missing.len().min(inventory.jokers as usize) {
+            if (3..=4).contains(&(tiles.len() + jokers)) {
+                let mut group = tiles;
+                group.extend(missing.iter().take(jokers).map(|&other| Tile {
+                    color: other,
+                    number,
+                    is_joker: true,
+                }));
+                sets.push(Set { tiles: group });
+            }
+        }
+    }
+
+    // Jokers may stand in for numbers below the first real tile of a run
+    for leading in 0..=inventory.jokers.min(below) {
+        let mut tiles = (number - leading..number)
+            .map(|joker_number| Tile {
+                color,
+                number: joker_number,
+                is_joker: true,
+            })
+            .collect::<Tiles>();
+        tiles.push(first);
+        extend_run(inventory, &mut tiles, inventory.jokers - leading, &mut sets);
+    }
+
+    sets
+}
+
+fn extend_run(inventory: &Inventory, tiles: &mut Tiles, jokers_left: u8, sets: &mut Vec<Set>) {
+    if tiles.len() >= 3 {
+        sets.push(Set { tiles: *tiles });
+    }
+
+    let Some(&last) = tiles.last() else {
+        return;
+    };
+    if last.number >= 13 {
+        return;
+    }
+
+    let next = Tile {
+        color: last.color,
+        number: last.number + 1,
+        is_joker: false,
+    };
+    if inventory.cell(last.number as usize, last.color as usize) > 0 {
+        tiles.push(next);
+        extend_run(inventory, tiles, jokers_left, sets);
+        tiles.pop();
+    }
+    if jokers_left > 0 {
+        tiles.push(Tile {
+            is_joker: true,
+            ..next
+        });
+        extend_run(inventory, tiles, jokers_left - 1, sets);
+        tiles.pop();
+    }
+}
+
+// Order of sets that share their lowest real tile, so the same sets are only
+// ever picked in one order
+fn set_key(set: &Set) -> Vec<(u8, u8, bool)> {
+    set.tiles
+        .iter()
+        .map(|tile| (tile.number, tile.color, tile.is_joker))
+        .collect()
+}
+
+fn lowest_real_tile(set: &Set) -> Option<(u8, u8)> {
+    set.tiles
+        .iter()
+        .filter(|tile| !tile.is_joker)
+        .map(|tile| (tile.number, tile.color))
+        .min()
+}
+
+// Sets that may hold the lowest tile left. When the previous set started at
+// the same tile, only sets ordered at or after it count, and it is returned as
+// the constraint on the choice.
+pub(crate) fn next_sets(rest: &Inventory, previous: Option<&Set>) -> (Vec<Set>, Option<Set>) {
+    let Some(&(number, color)) = rest.available_tiles().first() else {
+        return (Vec::new(), None);
+    };
+    let previous = previous
+        .filter(|set| lowest_real_tile(set) == Some((number as u8 + 1, color as u8)))
+        .cloned();
+    let sets = sets_starting_at(rest, number as u8 + 1, color as u8)
+        .into_iter()
+        .filter(|set| {
+            previous
+                .as_ref()
+                .is_none_or(|previous| set_key(set) >= set_key(previous))
+        })
+        .collect();
+    (sets, previous)
+}
+
+// Picks of the kinds from the first on, at most as many of a kind as there
+// are, added to `pick`
+fn each_pick(kinds: &[(Tile, u8)], pick: &mut Inventory, visit: &mut impl FnMut(&Inventory)) {
+    let Some((&(tile, count), rest)) = kinds.split_first() else {
+        visit(pick);
+        return;
+    };
+    each_pick(rest, pick, visit);
+    for _ in 0..count {
+        pick.add_tile(&tile);
+        each_pick(rest, pick, visit);
+    }
+    (0..count).for_each(|_| pick.remove_tile(&tile));
+}
+
+pub(crate) fn removed(inventory: &Inventory, set: &Set) -> Inventory {
+    let mut rest = *inventory;
+    rest.remove_tiles(set);
+    rest
+}
+
+// Where a search keeps what it found out about the tiles it has seen, a
+// BTreeMap here and a HashMap counted by the search statistics in the solver
+pub trait Memo<K, V> {
+    fn recall(&mut self, key: &K) -> Option<V>;
+    fn remember(&mut self, key: K, value: V);
+}
+
+impl<K: Ord, V: Copy> Memo<K, V> for BTreeMap<K, V> {
+    fn recall(&mut self, key: &K) -> Option<V> {
+        self.get(key).copied()
+    }
+
+    fn remember(&mut self, key: K, value: V) {
+        self.insert(key, value);
+    }
+}
+
+// Whether every tile of the inventory can be laid out, trying every set that
+// holds the lowest tile left
+pub fn can_lay_out(inventory: &Inventory, memo: &mut impl Memo<Inventory, bool>) -> bool {
+    if inventory.total_tile_count() == 0 {
+        return true;
+    }
+    if let Some(known) = memo.recall(inventory) {
+        return known;
+    }
+    let (sets, _) = next_sets(inventory, None);
+    let possible = sets
+        .iter()
+        .any(|set| can_lay_out(&removed(inventory, set), memo));
+    memo.remember(*inventory, possible);
+    possible
+}
@@ -0,0 +1,142 @@
+use crate::editor::RawMode;
+use crate::render;
+use crate::replay::{Replay, Turn};
+use crate::review::{self, Blunder};
+use crate::solution::Solution;
+use crate::theme;
+use color_eyre::eyre::{bail, Result};
+use std::io::{self, Read, Write};
+
+const HELP: &str = "left and right or h and l step a turn, n and p jump to the next or previous blunder, g and G to the start or end, q quits";
+
+enum Key {
+    Previous,
+    Next,
+    NextBlunder,
+    PreviousBlunder,
+    First,
+    Last,
+    Quit,
+    Other,
+}
+
+fn read_key(input: &mut impl Read) -> Result<Key> {
+    let mut byte = [0u8];
+    input.read_exact(&mut byte)?;
+    Ok(match byte[0] {
+        b'\x1b' => {
+            // Arrow keys arrive as ESC [ C and ESC [ D, a lone ESC quits
+            let mut sequence = [0u8; 2];
+            if input.read_exact(&mut sequence).is_err() || sequence[0] != b'[' {
+                return Ok(Key::Quit);
+            }
+            match sequence[1] {
+                b'C' => Key::Next,
+                b'D' => Key::Previous,
+                _ => Key::Other,
+            }
+        }
+        b'l' => Key::Next,
+        b'h' => Key::Previous,
+        b'n' => Key::NextBlunder,
+        b'p' => Key::PreviousBlunder,
+        b'g' => Key::First,
+        b'G' => Key::Last,
+        b'q' => Key::Quit,
+        _ => Key::Other,
+    })
+}
+
+// The turns of the game in `width` columns, `!` where a blunder was made and
+// `|` at the turn shown. Columns stand for several turns in long games.
+fn bar(turns: usize, current: usize, blunders: &[Option<Blunder>], width: usize) -> String {
+    let columns = turns.min(width.saturating_sub(2)).max(1);
+    let column_of = |turn: usize| turn * columns / turns.max(1);
+    (0..columns)
+        .map(|column| {
+            if column == column_of(current) {
+                '|'
+            } else if (0..turns).any(|turn| column_of(turn) == column && blunders[turn].is_some()) {
+                '!'
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+// The position before the turn, what was played and, for a blunder, the
+// best move. Lines end in \r\n while the terminal is raw.
+fn screen(turns: &[Turn], current: usize, blunders: &[Option<Blunder>], width: usize) -> String {
+    let turn = &turns[current];
+    let mut text = format!(
+        "\x1b[2J\x1b[HTurn {} of {}, {} tiles in the bag\n[{}]\n\n",
+        current + 1,
+        turns.len(),
+        turn.bag.remaining(),
+        bar(turns.len(), current, blunders, width)
+    );
+    text += "Table:\n";
+    match turn.table.is_empty() {
+        true => text += "  (empty)\n",
+        false => text += &render::unicode(&turn.table, width),
+    }
+    text += "\nRacks:\n";
+    for (seat, rack) in turn.racks.iter().enumerate() {
+        let marker = if seat == turn.seat { ">" } else { " " };
+        text += &format!(
+            "{} Player {}: {}\n",
+            marker,
+            seat + 1,
+            theme::tiles(&rack.tiles())
+        );
+    }
+    text += &format!("\n{}\n", turn.event);
+    if let Some(blunder) = &blunders[current] {
+        text += &format!(
+            "Blunder: {} more tiles could have been laid out, for {} points less on the rack\n  best: {}\n",
+            blunder.tiles_lost,
+            blunder.points_lost,
+            Solution::from(blunder.best.clone())
+        );
+    }
+    text += &format!("\n{}\n", HELP);
+    text.replace('\n', "\r\n")
+}
+
+// Steps through a game of a log turn by turn, the table and every rack drawn
+// as they were before the turn. The blunders of the review are marked on the
+// timeline at the top and can be jumped to.
+pub fn show(replay: &Replay, min_loss: u32, width: usize) -> Result<()> {
+    let turns = replay.turns()?;
+    if turns.is_empty() {
+        bail!("the game has no turns");
+    }
+    println!("Reviewing {} turns...", turns.len());
+    let blunders = review::blunders(&turns, min_loss)?;
+
+    let _raw = RawMode::enable()?;
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout();
+    let mut current = 0;
+    let last = turns.len() - 1;
+    loop {
+        write!(stdout, "{}", screen(&turns, current, &blunders, width))?;
+        stdout.flush()?;
+        current = match read_key(&mut stdin)? {
+            Key::Previous => current.saturating_sub(1),
+            Key::Next => (current + 1).min(last),
+            Key::NextBlunder => (current + 1..turns.len())
+                .find(|&turn| blunders[turn].is_some())
+                .unwrap_or(current),
+            Key::PreviousBlunder => (0..current)
+                .rev()
+                .find(|&turn| blunders[turn].is_some())
+                .unwrap_or(current),
+            Key::First => 0,
+            Key::Last => last,
+            Key::Quit => return Ok(()),
+            Key::Other => current,
+        };
+    }
+}
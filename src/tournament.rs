@@ -0,0 +1,684 @@
+use crate::cli::Args;
+use crate::config;
+use crate::game::{GameConfig, GameState};
+use crate::ladder::{ArchivedGame, Ladder};
+use crate::profile::{self, Profile};
+use crate::rng::SeededRng;
+use crate::stats;
+use crate::strategy::Strategy;
+use color_eyre::{eyre::bail, eyre::eyre, eyre::Report, eyre::Result};
+use rand::{Rng, SeedableRng};
+use std::fs;
+use std::path::Path;
+
+const INITIAL_RATING: f64 = 1500.0;
+// How far a single game moves the Elo ratings
+const RATING_FACTOR: f64 = 32.0;
+
+#[derive(Clone)]
+struct Standing {
+    games: u32,
+    wins: u32,
+    rating: f64,
+    // Swiss points: 1 for winning a pairing, shared on a tie, 1 for a bye
+    points: f64,
+    byes: u32,
+    opponents: Vec<usize>,
+    // Games won against each other entrant, by entrant index
+    beaten: Vec<u32>,
+}
+
+impl Default for Standing {
+    fn default() -> Standing {
+        Standing {
+            games: 0,
+            wins: 0,
+            rating: INITIAL_RATING,
+            points: 0.0,
+            byes: 0,
+            opponents: Vec::new(),
+            beaten: Vec::new(),
+        }
+    }
+}
+
+impl Standing {
+    fn win_rate(&self) -> f64 {
+        self.wins as f64 / self.games.max(1) as f64
+    }
+
+    fn beaten(&self, opponent: usize) -> u32 {
+        self.beaten.get(opponent).copied().unwrap_or(0)
+    }
+
+    fn add_beaten(&mut self, opponent: usize, games: u32) {
+        if self.beaten.len() <= opponent {
+            self.beaten.resize(opponent + 1, 0);
+        }
+        self.beaten[opponent] += games;
+    }
+}
+
+// Progress of a Swiss tournament, saved after every round so it can be
+// resumed. The file also keeps where the generator of the deal seeds stands
+// and the games played so far, so a resumed run deals and archives the same
+// games as one that was never stopped.
+struct SwissState {
+    round: usize,
+    standings: Vec<Standing>,
+}
+
+// The seed of a generator as 64 hex digits
+fn parse_seed(text: &str) -> Option<[u8; 32]> {
+    if text.len() != 64 {
+        return None;
+    }
+    let mut seed = [0; 32];
+    for (index, byte) in seed.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(text.get(2 * index..2 * index + 2)?, 16).ok()?;
+    }
+    Some(seed)
+}
+
+impl SwissState {
+    fn save(&self, path: &Path, entrants: &[Profile], deals: &Deals) -> Result<()> {
+        let seed = deals
+            .seeds
+            .get_seed()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        let mut text = format!(
+            "round = {}\ndeal_seed = \"{}\"\ndeal_word = \"{}\"\n",
+            self.round,
+            seed,
+            deals.seeds.get_word_pos()
+        );
+        for (entrant, standing) in entrants.iter().zip(&self.standings) {
+            let opponents = standing
+                .opponents
+                .iter()
+                .map(|opponent| config::escape(&entrants[*opponent].name))
+                .collect::<Vec<String>>();
+            let beaten = (0..entrants.len())
+                .filter(|&opponent| standing.beaten(opponent) > 0)
+                .map(|opponent| {
+                    format!(
+                        "{}:{}",
+                        config::escape(&entrants[opponent].name),
+                        standing.beaten(opponent)
+                    )
+                })
+                .collect::<Vec<String>>();
+            text.push_str(&format!(
+                "\n[[entrant]]\nname = \"{}\"\nrating = {}\npoints = {}\ngames = {}\nwins = {}\nbyes = {}\nopponents = \"{}\"\nbeaten = \"{}\"\n",
+                config::escape(&entrant.name),
+                standing.rating,
+                standing.points,
+                standing.games,
+                standing.wins,
+                standing.byes,
+                opponents.join(","),
+                beaten.join(",")
+            ));
+        }
+        if !deals.played.is_empty() {
+            text.push('\n');
+        }
+        for game in &deals.played {
+            game.write(&mut text);
+        }
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    // Also puts the deal generator and the games played back into `deals`
+    fn load(path: &Path, entrants: &[Profile], deals: &mut Deals) -> Result<SwissState> {
+        let index_of = |name: &str| {
+            entrants
+                .iter()
+                .position(|entrant| entrant.name == name)
+                .ok_or_else(|| eyre!("{} in {} is not taking part", name, path.display()))
+        };
+
+        let mut state = SwissState {
+            round: 0,
+            standings: vec![Standing::default(); entrants.len()],
+        };
+        let mut seen = vec![false; entrants.len()];
+        for table in config::parse(&fs::read_to_string(path)?)? {
+            let number = |key: &str| -> Result<f64> {
+                table
+                    .number(key)?
+                    .ok_or_else(|| eyre!("line {}: missing {}", table.line, key))
+            };
+            match table.name.as_str() {
+                "" => {
+                    table.check_keys(&["round", "deal_seed", "deal_word"])?;
+                    state.round = number("round")? as usize;
+                    // Files of older versions restart the deals from --seed
+                    if let (Some(seed), Some(word)) =
+                        (table.string("deal_seed")?, table.string("deal_word")?)
+                    {
+                        let seed = parse_seed(seed).ok_or_else(|| {
+                            eyre!("line {}: invalid deal_seed {}", table.line, seed)
+                        })?;
+                        deals.seeds = SeededRng::from_seed(seed);
+                        deals.seeds.set_word_pos(word.parse().map_err(|_| {
+                            eyre!("line {}: invalid deal_word {}", table.line, word)
+                        })?);
+                    }
+                    continue;
+                }
+                "game" => {
+                    deals.played.push(ArchivedGame::read(&table)?);
+                    continue;
+                }
+                "entrant" => {}
+                other => bail!(
+                    "line {}: unknown table [{}] in {}",
+                    table.line,
+                    other,
+                    path.display()
+                ),
+            }
+            table.check_keys(&[
+                "name",
+                "rating",
+                "points",
+                "games",
+                "wins",
+                "byes",
+                "opponents",
+                "beaten",
+            ])?;
+
+            let name = table.string("name")?.unwrap_or_default();
+            let index = index_of(name)?;
+            seen[index] = true;
+            state.standings[index] = Standing {
+                games: number("games")? as u32,
+                wins: number("wins")? as u32,
+                rating: number("rating")?,
+                points: number("points")?,
+                byes: number("byes")? as u32,
+                opponents: table
+                    .string("opponents")?
+                    .unwrap_or_default()
+                    .split(',')
+                    .filter(|name| !name.is_empty())
+                    .map(index_of)
+                    .collect::<Result<Vec<usize>>>()?,
+                beaten: Vec::new(),
+            };
+            // `name:games` for every entrant beaten at least once
+            for entry in table.string("beaten")?.unwrap_or_default().split(',') {
+                if entry.is_empty() {
+                    continue;
+                }
+                let (opponent, games) = entry
+                    .rsplit_once(':')
+                    .ok_or_else(|| eyre!("line {}: invalid beaten entry {}", table.line, entry))?;
+                let games = games
+                    .parse()
+                    .map_err(|_| eyre!("line {}: invalid beaten entry {}", table.line, entry))?;
+                state.standings[index].add_beaten(index_of(opponent)?, games);
+            }
+        }
+
+        if let Some(missing) = seen.iter().position(|&seen| !seen) {
+            bail!(
+                "{} is missing from {}",
+                entrants[missing].name,
+                path.display()
+            );
+        }
+        Ok(state)
+    }
+}
+
+// Every way to pick `size` entries out of `count`, in ascending order
+fn combinations(count: usize, size: usize) -> Vec<Vec<usize>> {
+    if size == 0 {
+        return vec![Vec::new()];
+    }
+    (size - 1..count)
+        .flat_map(|last| {
+            combinations(last, size - 1)
+                .into_iter()
+                .map(move |mut picked| {
+                    picked.push(last);
+                    picked
+                })
+        })
+        .collect()
+}
+
+// Elo update for every pair of players in a finished game, a game without a
+// winner counts as a draw between everybody
+fn update_ratings(standings: &mut [Standing], seats: &[usize], winner: Option<usize>) {
+    let mut changes = vec![0.0; seats.len()];
+    for a in 0..seats.len() {
+        for b in a + 1..seats.len() {
+            let score = match winner {
+                Some(winner) if winner == a => 1.0,
+                Some(winner) if winner == b => 0.0,
+                Some(_) => continue,
+                None => 0.5,
+            };
+            let difference = standings[seats[b]].rating - standings[seats[a]].rating;
+            let expected = 1.0 / (1.0 + 10f64.powf(difference / 400.0));
+            let change = RATING_FACTOR * (score - expected);
+            changes[a] += change;
+            changes[b] -= change;
+        }
+    }
+    for (&index, change) in seats.iter().zip(changes) {
+        standings[index].rating += change;
+    }
+}
+
+// How the games of a group are dealt
+struct Deals {
+    // Deals for every seat rotation of a group
+    count: usize,
+    // Replay each deal in every seat rotation, so no strategy is dealt better
+    // tiles than the others
+    duplicate: bool,
+    seeds: SeededRng,
+    // Every game played, for the `--ladder` archive
+    played: Vec<ArchivedGame>,
+}
+
+// Plays the group on every deal in every seat rotation. Returns how many games
+// each member of the group won.
+fn play_group(
+    config: &GameConfig,
+    entrants: &[Profile],
+    group: &[usize],
+    deals: &mut Deals,
+    standings: &mut [Standing],
+) -> Result<Vec<u32>> {
+    let mut wins = vec![0; group.len()];
+    for _ in 0..deals.count {
+        let duplicate_seed = deals.seeds.gen();
+        for rotation in 0..group.len() {
+            let mut seats = group.to_vec();
+            seats.rotate_left(rotation);
+            let seated = seats
+                .iter()
+                .map(|&index| &entrants[index] as &dyn Strategy)
+                .collect::<Vec<&dyn Strategy>>();
+
+            let seed = if deals.duplicate {
+                duplicate_seed
+            } else {
+                deals.seeds.gen()
+            };
+            let result = GameState::seeded(config, seed).play(&seated)?;
+            for &index in &seats {
+                standings[index].games += 1;
+            }
+            if let Some(winner) = result.winner {
+                standings[seats[winner]].wins += 1;
+                for &loser in seats.iter().filter(|&&index| index != seats[winner]) {
+                    standings[seats[winner]].add_beaten(loser, 1);
+                }
+                wins[(winner + rotation) % group.len()] += 1;
+            }
+            update_ratings(standings, &seats, result.winner);
+            deals.played.push(ArchivedGame {
+                seed,
+                turns: result.turns,
+                players: seats
+                    .iter()
+                    .map(|&index| entrants[index].name.clone())
+                    .collect(),
+                winner: result.winner,
+            });
+        }
+    }
+
+    for &index in group {
+        let others = group.iter().filter(|&&other| other != index);
+        standings[index].opponents.extend(others);
+    }
+    Ok(wins)
+}
+
+fn round_robin(
+    config: &GameConfig,
+    entrants: &[Profile],
+    deals: &mut Deals,
+    standings: &mut [Standing],
+) -> Result<()> {
+    for group in combinations(entrants.len(), config.players) {
+        play_group(config, entrants, &group, deals, standings)?;
+    }
+    Ok(())
+}
+
+// Groups for a Swiss round. The first round is seeded by rating, the strongest
+// entrants meeting ones from the middle of the field. Later rounds group
+// entrants with similar points and avoid rematches where possible. Entrants
+// that do not fill a group get a bye, lowest ranked ones first.
+fn swiss_groups(
+    round: usize,
+    players: usize,
+    standings: &[Standing],
+) -> (Vec<Vec<usize>>, Vec<usize>) {
+    let mut order = (0..standings.len()).collect::<Vec<usize>>();
+    order.sort_by(|&a, &b| {
+        let (a, b) = (&standings[a], &standings[b]);
+        b.points
+            .total_cmp(&a.points)
+            .then(b.rating.total_cmp(&a.rating))
+    });
+
+    let mut byes = Vec::new();
+    for _ in 0..order.len() % players {
+        let fewest = order
+            .iter()
+            .map(|&index| standings[index].byes)
+            .min()
+            .unwrap_or(0);
+        let position = order
+            .iter()
+            .rposition(|&index| standings[index].byes == fewest)
+            .unwrap_or(order.len() - 1);
+        byes.push(order.remove(position));
+    }
+
+    if round == 0 {
+        let count = order.len() / players;
+        let groups = (0..count)
+            .map(|group| {
+                (0..players)
+                    .map(|seat| order[group + seat * count])
+                    .collect()
+            })
+            .collect();
+        return (groups, byes);
+    }
+
+    let mut groups = Vec::new();
+    while !order.is_empty() {
+        let mut group = vec![order.remove(0)];
+        while group.len() < players {
+            let fresh = order.iter().position(|&candidate| {
+                group
+                    .iter()
+                    .all(|member| !standings[*member].opponents.contains(&candidate))
+            });
+            group.push(order.remove(fresh.unwrap_or(0)));
+        }
+        groups.push(group);
+    }
+    (groups, byes)
+}
+
+fn swiss(
+    config: &GameConfig,
+    entrants: &[Profile],
+    deals: &mut Deals,
+    rounds: usize,
+    state_path: Option<&Path>,
+    initial: Vec<Standing>,
+) -> Result<Vec<Standing>> {
+    // The state file refers to entrants by name
+    if state_path.is_some() {
+        check_unique_names(entrants, "a resumable tournament")?;
+    }
+
+    let mut state = match state_path {
+        Some(path) if path.exists() => {
+            let state = SwissState::load(path, entrants, deals)?;
+            println!(
+                "Resuming after round {} from {}",
+                state.round,
+                path.display()
+            );
+            state
+        }
+        _ => SwissState {
+            round: 0,
+            standings: initial,
+        },
+    };
+
+    while state.round < rounds {
+        let (groups, byes) = swiss_groups(state.round, config.players, &state.standings);
+        println!("Round {}", state.round + 1);
+        for group in groups {
+            let wins = play_group(config, entrants, &group, deals, &mut state.standings)?;
+            let most = wins.iter().copied().max().unwrap_or(0);
+            let leaders = wins.iter().filter(|&&won| won == most).count();
+            for (&index, &won) in group.iter().zip(&wins) {
+                if won == most {
+                    state.standings[index].points += 1.0 / leaders as f64;
+                }
+            }
+
+            let results = group
+                .iter()
+                .zip(&wins)
+                .map(|(&index, won)| format!("{} {}", entrants[index].name, won))
+                .collect::<Vec<String>>();
+            println!("  {}", results.join(" - "));
+        }
+        for index in byes {
+            state.standings[index].points += 1.0;
+            state.standings[index].byes += 1;
+            println!("  {} has a bye", entrants[index].name);
+        }
+
+        state.round += 1;
+        if let Some(path) = state_path {
+            state.save(path, entrants, deals)?;
+        }
+    }
+    Ok(state.standings)
+}
+
+fn check_unique_names(entrants: &[Profile], needed_by: &str) -> Result<()> {
+    for (index, entrant) in entrants.iter().enumerate() {
+        if entrants[..index]
+            .iter()
+            .any(|other| other.name == entrant.name)
+        {
+            bail!(
+                "{} takes part twice, {} needs unique names",
+                entrant.name,
+                needed_by
+            );
+        }
+    }
+    Ok(())
+}
+
+// Adds the results of the tournament to the ladder and lists the whole ladder
+fn update_ladder(
+    path: &Path,
+    ladder: &mut Ladder,
+    entrants: &[Profile],
+    standings: &[Standing],
+    played: Vec<ArchivedGame>,
+) -> Result<()> {
+    for (entrant, standing) in entrants.iter().zip(standings) {
+        ladder.record(
+            &entrant.name,
+            standing.rating,
+            standing.games,
+            standing.wins,
+        );
+        for (opponent, other) in entrants.iter().enumerate() {
+            if standing.beaten(opponent) > 0 {
+                ladder.add_beaten(&entrant.name, &other.name, standing.beaten(opponent));
+            }
+        }
+    }
+    played.into_iter().for_each(|game| ladder.archive(game));
+    ladder.save(path)?;
+
+    println!();
+    println!(
+        "Ladder of {} with {} games archived",
+        path.display(),
+        ladder.games().len()
+    );
+    println!(
+        "{:32} | Rating | Games | Wins | Win rate | Beat the next",
+        "Strategy"
+    );
+    let ranking = ladder.ranking();
+    for (position, rated) in ranking.iter().enumerate() {
+        let against_next = match ranking.get(position + 1) {
+            Some(next) => format!(
+                "{}-{}",
+                ladder.beaten(&rated.name, &next.name),
+                ladder.beaten(&next.name, &rated.name)
+            ),
+            None => "-".to_string(),
+        };
+        println!(
+            "{:32} | {:6.0} | {:5} | {:4} | {:7.1}% | {}",
+            rated.name,
+            rated.rating,
+            rated.games,
+            rated.wins,
+            rated.wins as f64 / rated.games.max(1) as f64 * 100.0,
+            against_next
+        );
+    }
+    Ok(())
+}
+
+// Round robin plays every group of strategies against each other, Swiss pairs
+// strategies with similar results each round. Within a pairing every strategy
+// starts equally often, and with `--duplicate` every strategy also gets every
+// rack of a deal once. `--seed` makes the deals repeatable. With `--ladder
+// <file>` the ratings start from those of earlier runs, and the results and
+// games are added to the file afterwards.
+pub fn run(args: &Args) -> Result<(), Report> {
+    let mut deals = Deals {
+        count: args.parse_or("--games", 10)?,
+        duplicate: args.flag("--duplicate"),
+        seeds: match args.value("--seed") {
+            Some(_) => SeededRng::seed_from_u64(args.parse_or("--seed", 0)?),
+            None => SeededRng::from_entropy(),
+        },
+        played: Vec::new(),
+    };
+    let config = GameConfig::from_args(args)?;
+    let entrants = profile::from_args(args, "0,1,2")?;
+    if entrants.len() < config.players {
+        bail!(
+            "a {} player tournament needs at least {} strategies",
+            config.players,
+            config.players
+        );
+    }
+
+    let ladder_path = args.value("--ladder").map(Path::new);
+    let mut ladder = match ladder_path {
+        Some(path) => {
+            // The ladder refers to entrants by name
+            check_unique_names(&entrants, "a ladder")?;
+            Ladder::load(path)?
+        }
+        None => Ladder::default(),
+    };
+    let initial = entrants
+        .iter()
+        .map(|entrant| Standing {
+            rating: ladder.rating(&entrant.name).unwrap_or(INITIAL_RATING),
+            ..Standing::default()
+        })
+        .collect::<Vec<Standing>>();
+
+    let standings = match args.value("--format").unwrap_or("round-robin") {
+        "round-robin" => {
+            let mut standings = initial;
+            round_robin(&config, &entrants, &mut deals, &mut standings)?;
+            standings
+        }
+        "swiss" => {
+            let default_rounds = (entrants.len() as f64).log2().ceil().max(1.0) as usize;
+            let rounds = args.parse_or("--rounds", default_rounds)?;
+            swiss(
+                &config,
+                &entrants,
+                &mut deals,
+                rounds,
+                args.value("--state").map(Path::new),
+                initial,
+            )?
+        }
+        other => bail!(
+            "unknown tournament format {}, use round-robin or swiss",
+            other
+        ),
+    };
+
+    let mut ranking = (0..entrants.len()).collect::<Vec<usize>>();
+    ranking.sort_by(|&a, &b| {
+        let (a, b) = (&standings[a], &standings[b]);
+        b.points
+            .total_cmp(&a.points)
+            .then(b.win_rate().total_cmp(&a.win_rate()))
+            .then(b.rating.total_cmp(&a.rating))
+    });
+
+    println!(
+        "{:32} | Points | Rating | Games | Wins | Win rate | 95% interval  | Moves over budget",
+        "Strategy"
+    );
+    for &index in &ranking {
+        let standing = &standings[index];
+        let (low, high) = stats::wilson_interval(standing.wins, standing.games, stats::Z_95);
+        println!(
+            "{:32} | {:6.1} | {:6.0} | {:5} | {:4} | {:7.1}% | {:5.1}-{:5.1}% | {}",
+            entrants[index].name,
+            standing.points,
+            standing.rating,
+            standing.games,
+            standing.wins,
+            standing.win_rate() * 100.0,
+            low * 100.0,
+            high * 100.0,
+            match entrants[index].time_budget {
+                Some(_) => entrants[index].overruns().to_string(),
+                None => "-".to_string(),
+            }
+        );
+    }
+    println!("Best: {}", entrants[ranking[0]].name);
+
+    // Games one of the two won while both played, tested against an even
+    // split. With many pairs a few will look significant by chance alone.
+    println!();
+    println!("{:67} | Score     | p-value", "Head to head");
+    for (position, &a) in ranking.iter().enumerate() {
+        for &b in &ranking[position + 1..] {
+            let (a_wins, b_wins) = (standings[a].beaten(b), standings[b].beaten(a));
+            if a_wins + b_wins == 0 {
+                continue;
+            }
+            let p = stats::binomial_test(a_wins, a_wins + b_wins);
+            println!(
+                "{:32} - {:32} | {:4}-{:<4} | {:.3}{}",
+                entrants[a].name,
+                entrants[b].name,
+                a_wins,
+                b_wins,
+                p,
+                if p < 0.05 { " significant" } else { "" }
+            );
+        }
+    }
+
+    if let Some(path) = ladder_path {
+        update_ladder(path, &mut ladder, &entrants, &standings, deals.played)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,191 @@
+// Training data from self-play games, written as a NumPy .npz archive with one
+// row per decision. The arrays follow the layout of src/encoding.rs:
+//
+//   version    i32 [1]      ENCODING_VERSION the file was written with
+//   rack       u8  [N, 53]
+//   table      u8  [N, 53]
+//   unseen     u8  [N, 53]
+//   opponents  u8  [N, 3]
+//   bag        u8  [N]
+//   opened     u8  [N]
+//   played     u8  [N, 53]  the chosen move
+//   outcome    i8  [N]      1 if the player won the game, -1 if they lost, 0 for a draw
+//   score      i32 [N]      the player's score for the game by the official rules
+//   game       i32 [N]      number of the game the position is from
+//
+// A model trained on these rows to score `played` in its position can play
+// with the `onnx` feature, see src/onnx.rs for the input it gets.
+
+use crate::cli::Args;
+use crate::encoding::{self, Position, ENCODING_VERSION, MAX_OPPONENTS, TILE_TYPES};
+use crate::game::{GameConfig, GameState};
+use crate::npz::{self, Array, Dtype};
+use crate::profile;
+use crate::scoring;
+use crate::strategy::Strategy;
+use color_eyre::{eyre::bail, eyre::Report, eyre::Result};
+
+struct Dataset {
+    rack: Array,
+    table: Array,
+    unseen: Array,
+    opponents: Array,
+    bag: Array,
+    opened: Array,
+    played: Array,
+    outcome: Array,
+    score: Array,
+    game: Array,
+}
+
+impl Dataset {
+    fn new() -> Dataset {
+        Dataset {
+            rack: Array::new("rack", Dtype::U8, &[TILE_TYPES]),
+            table: Array::new("table", Dtype::U8, &[TILE_TYPES]),
+            unseen: Array::new("unseen", Dtype::U8, &[TILE_TYPES]),
+            opponents: Array::new("opponents", Dtype::U8, &[MAX_OPPONENTS]),
+            bag: Array::new("bag", Dtype::U8, &[]),
+            opened: Array::new("opened", Dtype::U8, &[]),
+            played: Array::new("played", Dtype::U8, &[TILE_TYPES]),
+            outcome: Array::new("outcome", Dtype::I8, &[]),
+            score: Array::new("score", Dtype::I32, &[]),
+            game: Array::new("game", Dtype::I32, &[]),
+        }
+    }
+
+    fn push_position(&mut self, position: &Position, played: &[i32], game: usize) {
+        let (rack, rest) = position.split_at(TILE_TYPES);
+        let (table, rest) = rest.split_at(TILE_TYPES);
+        let (unseen, rest) = rest.split_at(TILE_TYPES);
+        let (opponents, rest) = rest.split_at(MAX_OPPONENTS);
+        self.rack.push(rack);
+        self.table.push(table);
+        self.unseen.push(unseen);
+        self.opponents.push(opponents);
+        self.bag.push(&rest[..1]);
+        self.opened.push(&rest[1..]);
+        self.played.push(played);
+        self.game.push(&[game as i32]);
+    }
+
+    fn push_result(&mut self, outcome: i32, score: i32) {
+        self.outcome.push(&[outcome]);
+        self.score.push(&[score]);
+    }
+
+    fn arrays(self) -> Vec<Array> {
+        let mut version = Array::new("version", Dtype::I32, &[]);
+        version.push(&[ENCODING_VERSION as i32]);
+        vec![
+            version,
+            self.rack,
+            self.table,
+            self.unseen,
+            self.opponents,
+            self.bag,
+            self.opened,
+            self.played,
+            self.outcome,
+            self.score,
+            self.game,
+        ]
+    }
+}
+
+// Plays one game and records every decision. Returns the seat of each
+// recorded position. With `verify` every recorded move is decoded again, to
+// make sure the labels describe moves a model strategy could make.
+fn record_game(
+    game: &mut GameState,
+    strategies: &[&dyn Strategy],
+    dataset: &mut Dataset,
+    number: usize,
+    verify: bool,
+) -> Result<Vec<usize>> {
+    let mut seats = Vec::new();
+    while !game.is_over() {
+        let seat = game.current();
+        let position = encoding::encode_position(game);
+        let chosen = game.strategy_move(strategies[seat])?;
+        let played = encoding::encode_move(game.rack(seat), chosen.as_ref());
+        if verify {
+            let decoded = encoding::decode_move(
+                &played,
+                game.table(),
+                game.rack(seat),
+                game.has_opened(seat),
+            )?;
+            if decoded.map(|(_, rest)| rest) != chosen.as_ref().map(|(_, rest)| *rest) {
+                bail!(
+                    "move of game {} turn {} does not decode to the same rack",
+                    number + 1,
+                    seats.len() + 1
+                );
+            }
+        }
+        dataset.push_position(&position, &played, number);
+
+        match chosen {
+            Some((table, _)) => game.apply_move(table)?,
+            None => game.draw()?,
+        };
+        seats.push(seat);
+    }
+    Ok(seats)
+}
+
+// Self-play games exported for training models outside this crate
+pub fn run(args: &Args) -> Result<(), Report> {
+    let games: usize = args.parse_or("--games", 100)?;
+    let config = GameConfig::from_args(args)?;
+    let Some(path) = args.value("--out") else {
+        bail!("selfplay needs --out for the .npz file");
+    };
+    let seed: Option<u64> = args.value("--seed").map(str::parse).transpose()?;
+    let verify = args.flag("--verify");
+
+    let players = profile::from_args(args, &vec!["0"; config.players].join(","))?;
+    if players.len() != config.players {
+        bail!(
+            "expected {} players, one per seat, got {}",
+            config.players,
+            players.len()
+        );
+    }
+    let strategies = players
+        .iter()
+        .map(|player| player as &dyn Strategy)
+        .collect::<Vec<&dyn Strategy>>();
+
+    let mut dataset = Dataset::new();
+    for number in 0..games {
+        let mut game = match seed {
+            Some(seed) => GameState::seeded(&config, seed.wrapping_add(number as u64)),
+            None => GameState::new(&config),
+        };
+        let seats = record_game(&mut game, &strategies, &mut dataset, number, verify)?;
+
+        let winner = game.winner();
+        let rack_points = (0..game.players())
+            .map(|seat| game.rack(seat).points())
+            .collect::<Vec<u32>>();
+        let scores = scoring::round_scores(&rack_points, winner);
+        for seat in seats {
+            let outcome = match winner {
+                Some(winner) if winner == seat => 1,
+                Some(_) => -1,
+                None => 0,
+            };
+            dataset.push_result(outcome, scores[seat]);
+        }
+    }
+
+    let positions = dataset.game.rows();
+    npz::write(path, &dataset.arrays())?;
+    println!(
+        "{} positions of {} games written to {}",
+        positions, games, path
+    );
+    Ok(())
+}
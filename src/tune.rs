@@ -0,0 +1,143 @@
+use crate::cli::Args;
+use crate::game::{GameConfig, GameState};
+use crate::rng::SeededRng;
+use crate::strategy::{Greedy, Strategy};
+use color_eyre::{eyre::bail, eyre::Report, eyre::Result};
+use rand::{Rng, SeedableRng};
+use std::fs;
+
+// A strategy parameter the tuner may change, with the range that makes sense
+struct Parameter {
+    name: &'static str,
+    min: f64,
+    max: f64,
+}
+
+const GREEDY_PARAMETERS: [Parameter; 1] = [Parameter {
+    name: "joker_weight",
+    min: 0.0,
+    max: 5.0,
+}];
+
+fn greedy(values: &[f64]) -> Greedy {
+    Greedy {
+        joker_weight: values[0],
+        dump_chance: None,
+    }
+}
+
+fn clamp(values: &[f64]) -> Vec<f64> {
+    values
+        .iter()
+        .zip(&GREEDY_PARAMETERS)
+        .map(|(value, parameter)| value.clamp(parameter.min, parameter.max))
+        .collect()
+}
+
+// How much better `plus` did than `minus`, from -1 to 1. Both play every deal
+// in every seat rotation, half the seats each, so the deal luck cancels out.
+fn compare(
+    config: &GameConfig,
+    plus: &dyn Strategy,
+    minus: &dyn Strategy,
+    deals: usize,
+    seeds: &mut SeededRng,
+) -> Result<f64> {
+    let mut difference = 0i32;
+    let mut games = 0;
+    for _ in 0..deals {
+        let seed = seeds.gen();
+        for rotation in 0..config.players {
+            let seated = (0..config.players)
+                .map(|seat| {
+                    if (seat + rotation) % 2 == 0 {
+                        plus
+                    } else {
+                        minus
+                    }
+                })
+                .collect::<Vec<&dyn Strategy>>();
+            let result = GameState::seeded(config, seed).play(&seated)?;
+            if let Some(winner) = result.winner {
+                difference += if (winner + rotation) % 2 == 0 { 1 } else { -1 };
+            }
+            games += 1;
+        }
+    }
+    Ok(difference as f64 / games as f64)
+}
+
+// The tuned parameters as a player for `--profiles`
+fn save(path: &str, values: &[f64]) -> Result<()> {
+    let mut text = String::from("[[player]]\nname = \"tuned\"\nstrategy = \"greedy\"\n");
+    for (parameter, value) in GREEDY_PARAMETERS.iter().zip(values) {
+        text.push_str(&format!("{} = {}\n", parameter.name, value));
+    }
+    fs::write(path, text)?;
+    Ok(())
+}
+
+// Tunes the greedy strategy with SPSA: every iteration nudges all parameters
+// in a random direction and its opposite, plays the two variants against each
+// other and steps towards the stronger one. The parameters are saved after
+// every iteration, so an interrupted run keeps its progress.
+pub fn run(args: &Args) -> Result<(), Report> {
+    let config = GameConfig::from_args(args)?;
+    if config.players % 2 != 0 {
+        bail!("the two variants need the same number of seats, use 2 or 4 players");
+    }
+    let Some(path) = args.value("--out") else {
+        bail!("tune needs --out for the tuned player file");
+    };
+    let iterations: usize = args.parse_or("--iterations", 20)?;
+    let deals: usize = args.parse_or("--games", 10)?;
+    let mut seeds = match args.value("--seed") {
+        Some(_) => SeededRng::seed_from_u64(args.parse_or("--seed", 0)?),
+        None => SeededRng::from_entropy(),
+    };
+    // Step size and perturbation size, decaying with the usual SPSA exponents
+    let step: f64 = args.parse_or("--step", 1.0)?;
+    let perturbation: f64 = args.parse_or("--perturbation", 0.5)?;
+
+    let mut values = clamp(&[args.parse_or("--joker-weight", 1.0)?]);
+    for iteration in 0..iterations {
+        let k = iteration as f64 + 1.0;
+        let step = step / (k + iterations as f64 / 10.0).powf(0.602);
+        let perturbation = perturbation / k.powf(0.101);
+
+        let direction = values
+            .iter()
+            .map(|_| if seeds.gen() { 1.0 } else { -1.0 })
+            .collect::<Vec<f64>>();
+        let shifted = |sign: f64| {
+            let values = values
+                .iter()
+                .zip(&direction)
+                .map(|(value, direction)| value + sign * perturbation * direction)
+                .collect::<Vec<f64>>();
+            clamp(&values)
+        };
+        let (plus, minus) = (greedy(&shifted(1.0)), greedy(&shifted(-1.0)));
+        let advantage = compare(&config, &plus, &minus, deals, &mut seeds)?;
+
+        let stepped = values
+            .iter()
+            .zip(&direction)
+            .map(|(value, direction)| value + step * advantage / (2.0 * perturbation * direction))
+            .collect::<Vec<f64>>();
+        values = clamp(&stepped);
+        save(path, &values)?;
+
+        println!(
+            "Iteration {:3}: {} vs {} scored {:+.2}, now {}",
+            iteration + 1,
+            plus.name(),
+            minus.name(),
+            advantage,
+            greedy(&values).name()
+        );
+    }
+
+    println!("Tuned player written to {}", path);
+    Ok(())
+}
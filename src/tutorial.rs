@@ -0,0 +1,313 @@
+use crate::bag::Bag;
+use crate::cli::Args;
+use crate::i18n::Message;
+use crate::notation;
+use crate::play::{self, clear_screen, print_table};
+use crate::replay;
+use crate::rng::SeededRng;
+use crate::rules::Ruleset;
+use crate::solver::{self, Inventory, Set, Tile};
+use crate::validator;
+use color_eyre::{eyre::bail, eyre::Report, eyre::Result};
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::io::{self, BufRead};
+
+// Tiles of the rack that are no part of the answer, so it has to be found
+const DISTRACTORS: u32 = 3;
+// Tiles of the rack in the lesson on the initial meld
+const OPENING_RACK: u32 = 9;
+
+const HELP: &str = "Commands:
+  hint   show an answer
+  skip   go on to the next exercise
+  quit   stop the tutorial";
+
+#[derive(Clone, Copy)]
+enum Lesson {
+    Groups,
+    Runs,
+    InitialMeld,
+    Manipulation,
+}
+
+const LESSONS: [Lesson; 4] = [
+    Lesson::Groups,
+    Lesson::Runs,
+    Lesson::InitialMeld,
+    Lesson::Manipulation,
+];
+
+// A position of a lesson with one of its answers
+struct Exercise {
+    table: Vec<Set>,
+    rack: Inventory,
+    answer: Vec<Set>,
+}
+
+fn tile(color: u8, number: u8) -> Tile {
+    Tile {
+        color,
+        number,
+        is_joker: false,
+    }
+}
+
+fn set_of(tiles: &[Tile]) -> Result<Set> {
+    validator::complete_set(tiles)
+}
+
+fn inventory(tiles: &[Tile]) -> Inventory {
+    let mut inventory = Inventory::new(0);
+    tiles.iter().for_each(|tile| inventory.add_tile(tile));
+    inventory
+}
+
+// The tiles with a few random others from the rest of the pool
+fn with_distractors(tiles: &[Tile], rng: &mut SeededRng) -> Inventory {
+    let mut rest = Inventory::new(2);
+    rest.subtract(&inventory(tiles));
+    inventory(tiles).combined(&Bag::of(&rest).draw_n(DISTRACTORS, rng))
+}
+
+impl Lesson {
+    fn parse(name: &str) -> Result<Lesson> {
+        match name {
+            "groups" => Ok(Lesson::Groups),
+            "runs" => Ok(Lesson::Runs),
+            "meld" => Ok(Lesson::InitialMeld),
+            "manipulation" => Ok(Lesson::Manipulation),
+            _ => bail!(
+                "unknown lesson {}, expected groups, runs, meld or manipulation",
+                name
+            ),
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Lesson::Groups => "Lesson 1: groups",
+            Lesson::Runs => "Lesson 2: runs",
+            Lesson::InitialMeld => "Lesson 3: the initial meld",
+            Lesson::Manipulation => "Lesson 4: rearranging the table",
+        }
+    }
+
+    fn explanation(self) -> &'static str {
+        match self {
+            Lesson::Groups => {
+                "Tiles are laid out in sets of at least 3. A group holds tiles of the same
+number in different colors, at most one of every color, so 3 or 4 tiles.
+R7 B7 K7 is a group, R7 R7 B7 is not. A joker J stands in for any tile."
+            }
+            Lesson::Runs => {
+                "A run holds consecutive numbers of one color, e.g. B3 B4 B5 B6. It does
+not go on from 13 to 1 and a joker can fill a gap: B3 J B5."
+            }
+            Lesson::InitialMeld => {
+                "Your first move has to lay out sets from your rack worth at least 30
+points, the numbers of the tiles added up. A joker counts as the tile it
+stands in for. Until then the sets on the table cannot be touched.
+Enter the sets separated by `;`, e.g. `R10 R11 R12; B1 O1 K1`."
+            }
+            Lesson::Manipulation => {
+                "After the initial meld the sets on the table belong to everyone: split
+them, take tiles from them and lay them out anew, as long as every set is
+legal at the end of the turn and at least one tile comes from your rack.
+Enter the whole new table, the sets separated by `;`."
+            }
+        }
+    }
+
+    fn task(self) -> &'static str {
+        match self {
+            Lesson::Groups => "lay out a group from your rack",
+            Lesson::Runs => "lay out a run from your rack",
+            Lesson::InitialMeld => "make your initial meld",
+            Lesson::Manipulation => "add your tiles to the table",
+        }
+    }
+
+    // A position with an answer, dealt by the rng
+    fn exercise(self, rng: &mut SeededRng) -> Result<Exercise> {
+        match self {
+            Lesson::Groups => {
+                let mut colors = [0, 1, 2, 3];
+                colors.shuffle(rng);
+                let number = rng.gen_range(1..=13);
+                let tiles = colors[..rng.gen_range(3..=4)]
+                    .iter()
+                    .map(|&color| tile(color, number))
+                    .collect::<Vec<Tile>>();
+                Ok(Exercise {
+                    table: Vec::new(),
+                    rack: with_distractors(&tiles, rng),
+                    answer: vec![set_of(&tiles)?],
+                })
+            }
+            Lesson::Runs => {
+                let color = rng.gen_range(0..4);
+                let first = rng.gen_range(1..=10);
+                let tiles = (first..first + rng.gen_range(3..=4))
+                    .map(|number| tile(color, number))
+                    .collect::<Vec<Tile>>();
+                Ok(Exercise {
+                    table: Vec::new(),
+                    rack: with_distractors(&tiles, rng),
+                    answer: vec![set_of(&tiles)?],
+                })
+            }
+            Lesson::InitialMeld => loop {
+                let rack = Bag::full().draw_n(OPENING_RACK, rng);
+                if let Some((answer, _)) = solver::best_move(&[], &rack, false)? {
+                    return Ok(Exercise {
+                        table: Vec::new(),
+                        rack,
+                        answer,
+                    });
+                }
+            },
+            // A tile that splits a run, or two that take a tile from a group
+            // of four to make a run
+            Lesson::Manipulation if rng.gen_bool(0.5) => {
+                let color = rng.gen_range(0..4);
+                let first = rng.gen_range(1..=8);
+                let split = first + rng.gen_range(2..=3);
+                let run = |numbers: std::ops::RangeInclusive<u8>| {
+                    set_of(
+                        &numbers
+                            .map(|number| tile(color, number))
+                            .collect::<Vec<Tile>>(),
+                    )
+                };
+                Ok(Exercise {
+                    table: vec![run(first..=first + 5)?],
+                    rack: inventory(&[tile(color, split)]),
+                    answer: vec![run(first..=split)?, run(split..=first + 5)?],
+                })
+            }
+            Lesson::Manipulation => {
+                let color = rng.gen_range(0..4);
+                let number = rng.gen_range(3..=13);
+                let group = (0..4)
+                    .map(|other| tile(other, number))
+                    .collect::<Vec<Tile>>();
+                let kept = group
+                    .iter()
+                    .copied()
+                    .filter(|tile| tile.color != color)
+                    .collect::<Vec<Tile>>();
+                let rack = [tile(color, number - 2), tile(color, number - 1)];
+                Ok(Exercise {
+                    table: vec![set_of(&group)?],
+                    rack: inventory(&rack),
+                    answer: vec![
+                        set_of(&kept)?,
+                        set_of(&[rack[0], rack[1], tile(color, number)])?,
+                    ],
+                })
+            }
+        }
+    }
+
+    // Why the answer is wrong, in the words of the validator
+    fn judge(self, exercise: &Exercise, text: &str) -> Result<()> {
+        let rules = Ruleset::default();
+        match self {
+            Lesson::Groups | Lesson::Runs => {
+                let set = notation::parse_set(text)?;
+                match (self, set.is_group()) {
+                    (Lesson::Groups, false) => {
+                        bail!("{} is a run, this lesson is about groups", set)
+                    }
+                    (Lesson::Runs, true) => bail!("{} is a group, this lesson is about runs", set),
+                    _ => {}
+                }
+                validator::validate_move(&exercise.table, &exercise.rack, &[set], true, &rules)?;
+            }
+            Lesson::InitialMeld => {
+                let new_table = notation::parse_table(text)?;
+                validator::validate_move(
+                    &exercise.table,
+                    &exercise.rack,
+                    &new_table,
+                    false,
+                    &rules,
+                )?;
+            }
+            Lesson::Manipulation => {
+                let new_table = notation::parse_table(text)?;
+                let rest = validator::validate_move(
+                    &exercise.table,
+                    &exercise.rack,
+                    &new_table,
+                    true,
+                    &rules,
+                )?;
+                if rest.total_tile_count() > 0 {
+                    bail!(
+                        "legal, but {} is still on your rack",
+                        replay::rack_text(&rest)
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Walks a new player through groups, runs, the initial meld and rearranging
+// the table. Every lesson explains its rule and deals `--exercises` positions,
+// from `--seed` if given, whose answers are checked by the validator, so a
+// wrong one is explained the way a move in a game would be. `--lesson` starts
+// a single lesson.
+pub fn run(args: &Args) -> Result<(), Report> {
+    let exercises: u32 = args.parse_or("--exercises", 2)?;
+    let lessons = match args.value("--lesson") {
+        Some(name) => vec![Lesson::parse(name)?],
+        None => LESSONS.to_vec(),
+    };
+    let mut rng = match args.value("--seed") {
+        Some(_) => SeededRng::seed_from_u64(args.parse_or("--seed", 0)?),
+        None => SeededRng::from_entropy(),
+    };
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    for lesson in lessons {
+        clear_screen();
+        println!("{}\n\n{}\n\n{}", lesson.title(), lesson.explanation(), HELP);
+        for number in 1..=exercises {
+            let exercise = lesson.exercise(&mut rng)?;
+            println!("\nExercise {} of {}: {}", number, exercises, lesson.task());
+            if !exercise.table.is_empty() {
+                println!("{}", Message::Table);
+                print_table(&exercise.table);
+            }
+            play::print_rack(&exercise.rack);
+            loop {
+                let Some(line) = play::prompt(&mut lines, "> ")? else {
+                    return Ok(());
+                };
+                match line.trim() {
+                    "" => println!("{}", HELP),
+                    "hint" => {
+                        println!("{}", Message::SuggestedTable);
+                        print_table(&exercise.answer);
+                    }
+                    "skip" => break,
+                    "quit" => return Ok(()),
+                    text => match lesson.judge(&exercise, text) {
+                        Ok(()) => {
+                            println!("Right!");
+                            break;
+                        }
+                        Err(error) => println!("Not quite: {}", error),
+                    },
+                }
+            }
+        }
+    }
+    println!("\nThat is all there is to the rules. Start a game with `play --hints`.");
+    Ok(())
+}
@@ -0,0 +1,414 @@
+use crate::audit::Audit;
+use crate::diagnostic;
+use crate::notation;
+use crate::rules::Ruleset;
+use crate::solver::{self, Inventory, Set, Tile, Tiles, INITIAL_MELD_POINTS, MAX_SET_TILES};
+use crate::tiles::SetError;
+use color_eyre::eyre::{bail, eyre, Result};
+
+// Checks that a set is a legal group or run, explaining what is wrong otherwise
+pub fn check_set(set: &Set) -> Result<()> {
+    match set.check() {
+        Ok(()) => Ok(()),
+        Err(SetError::TooFew) => bail!("{} has fewer than 3 tiles", set),
+        Err(SetError::NoSuchTile(tile)) => {
+            bail!("{} holds a tile that does not exist: {:?}", set, tile)
+        }
+        Err(SetError::GroupTooLong) => bail!("group {} has more than 4 tiles", set),
+        Err(SetError::SameColor(color)) => bail!(
+            "group {} has two {} tiles",
+            set,
+            notation::color_name(color)
+        ),
+        Err(SetError::MixedColors) => bail!("{} mixes colors and numbers", set),
+        Err(SetError::NotConsecutive) => bail!("run {} is not consecutive", set),
+    }
+}
+
+// Jokers numbered by their position, if the tiles were entered as an ascending run
+fn ordered_run(tiles: &[Tile]) -> Option<Set> {
+    let (index, first) = tiles.iter().enumerate().find(|(_, tile)| !tile.is_joker)?;
+    let start = (first.number as usize).checked_sub(index)?;
+    if start == 0 || start + tiles.len() - 1 > 13 {
+        return None;
+    }
+
+    let run = tiles
+        .iter()
+        .enumerate()
+        .map(|(offset, entered)| Tile {
+            color: first.color,
+            number: (start + offset) as u8,
+            is_joker: entered.is_joker,
+        })
+        .collect::<Tiles>();
+    if run
+        .iter()
+        .zip(tiles)
+        .any(|(tile, entered)| !entered.is_joker && tile != entered)
+    {
+        return None;
+    }
+    Some(Set { tiles: run })
+}
+
+// Jokers filling the gaps between the sorted tiles, then extending the run.
+// None without a tile that is not a joker.
+fn sorted_run(tiles: &[Tile], color: u8) -> Option<Set> {
+    let mut numbers = tiles
+        .iter()
+        .filter(|tile| !tile.is_joker)
+        .map(|tile| tile.number)
+        .collect::<Vec<u8>>();
+    numbers.sort_unstable();
+    let mut jokers = tiles.len() - numbers.len();
+
+    let tile = |number, is_joker| Tile {
+        color,
+        number,
+        is_joker,
+    };
+    let mut run = Vec::new();
+    let &lowest = numbers.first()?;
+    let mut next = lowest;
+    for &number in &numbers {
+        while next < number && jokers > 0 {
+            run.push(tile(next, true));
+            next += 1;
+            jokers -= 1;
+        }
+        run.push(tile(number, false));
+        next = number + 1;
+    }
+
+    let mut low = lowest;
+    for _ in 0..jokers {
+        if next <= 13 {
+            run.push(tile(next, true));
+            next += 1;
+        } else {
+            low -= 1;
+            run.insert(0, tile(low, true));
+        }
+    }
+    Some(Set {
+        tiles: Tiles::from_slice(&run),
+    })
+}
+
+// Jokers taking the colors missing from the group
+fn group(tiles: &[Tile], number: u8) -> Set {
+    let mut missing = (0..4).filter(|&color| {
+        tiles
+            .iter()
+            .all(|tile| tile.is_joker || tile.color != color)
+    });
+    let tiles = tiles
+        .iter()
+        .map(|&tile| {
+            if tile.is_joker {
+                Tile {
+                    color: missing.next().unwrap_or(0),
+                    number,
+                    is_joker: true,
+                }
+            } else {
+                tile
+            }
+        })
+        .collect::<Tiles>();
+    Set { tiles }
+}
+
+// Builds a set from entered tiles, working out which tile every joker stands in for
+pub fn complete_set(tiles: &[Tile]) -> Result<Set> {
+    if tiles.len() > MAX_SET_TILES {
+        bail!("{} is longer than 13 tiles", notation::tiles_text(tiles));
+    }
+    let entered = Set {
+        tiles: Tiles::from_slice(tiles),
+    };
+    let Some(first) = tiles.iter().find(|tile| !tile.is_joker) else {
+        bail!(
+            "cannot tell which tiles the jokers in {} stand in for",
+            entered
+        );
+    };
+
+    if let Some(set) = ordered_run(tiles).filter(|set| check_set(set).is_ok()) {
+        return Ok(set);
+    }
+
+    let real = tiles.iter().filter(|tile| !tile.is_joker);
+    let set = if real.clone().all(|tile| tile.number == first.number) {
+        group(tiles, first.number)
+    } else if real.clone().all(|tile| tile.color == first.color) {
+        sorted_run(tiles, first.color).ok_or_else(|| {
+            eyre!(
+                "cannot tell which tiles the jokers in {} stand in for",
+                entered
+            )
+        })?
+    } else {
+        bail!("{} mixes colors and numbers", entered);
+    };
+    check_set(&set)?;
+    Ok(set)
+}
+
+// Single edit that turns an illegal entered set into a legal one
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SetFix {
+    Swap { index: usize, tile: Tile },
+    InsertJoker { index: usize },
+    Remove { index: usize },
+}
+
+impl SetFix {
+    pub fn apply(&self, tiles: &[Tile]) -> Vec<Tile> {
+        let mut tiles = tiles.to_vec();
+        match *self {
+            SetFix::Swap { index, tile } => {
+                if let Some(old) = tiles.get_mut(index) {
+                    *old = tile;
+                }
+            }
+            SetFix::InsertJoker { index } => tiles.insert(index.min(tiles.len()), JOKER),
+            SetFix::Remove { index } => {
+                if index < tiles.len() {
+                    tiles.remove(index);
+                }
+            }
+        }
+        tiles
+    }
+
+    pub fn describe(&self, tiles: &[Tile]) -> String {
+        match *self {
+            SetFix::Swap { index, tile } => match tiles.get(index) {
+                Some(old) => format!("replace {} with {}", old, tile),
+                None => format!("add {}", tile),
+            },
+            SetFix::InsertJoker { .. } => "add a joker".to_string(),
+            SetFix::Remove { index } => match tiles.get(index) {
+                Some(old) => format!("remove {}", old),
+                None => "keep every tile".to_string(),
+            },
+        }
+    }
+
+    // Likely typos first: a tile off by a number or color, then a missing or
+    // extra tile, then tiles that share nothing with the one they replace
+    fn rank(&self, tiles: &[Tile]) -> (u8, u8) {
+        match *self {
+            SetFix::Swap { index, tile }
+                if !tile.is_joker && tiles.get(index).is_some_and(|old| !old.is_joker) =>
+            {
+                let old = tiles.get(index).copied().unwrap_or(tile);
+                let distance = old.number.abs_diff(tile.number);
+                if old.color == tile.color || distance == 0 {
+                    (0, distance)
+                } else {
+                    (2, distance)
+                }
+            }
+            _ => (1, 0),
+        }
+    }
+}
+
+const JOKER: Tile = Tile {
+    color: 0,
+    number: 0,
+    is_joker: true,
+};
+
+// Minimal edits that make the entered set legal, most likely ones first.
+// Edits leading to the same set are only suggested once.
+pub fn suggest_fixes(tiles: &[Tile]) -> Vec<SetFix> {
+    let all_tiles = (0..4)
+        .flat_map(|color| {
+            (1..=13).map(move |number| Tile {
+                color,
+                number,
+                is_joker: false,
+            })
+        })
+        .chain([JOKER]);
+
+    let mut candidates = Vec::new();
+    for (index, &old) in tiles.iter().enumerate() {
+        candidates.extend(
+            all_tiles
+                .clone()
+                .filter(|&tile| tile != old)
+                .map(|tile| SetFix::Swap { index, tile }),
+        );
+        candidates.push(SetFix::Remove { index });
+    }
+    // Extending the run at the end reads more naturally than in front of it
+    candidates.extend(
+        (0..=tiles.len())
+            .rev()
+            .map(|index| SetFix::InsertJoker { index }),
+    );
+    candidates.sort_by_key(|fix| fix.rank(tiles));
+
+    let mut fixed_sets = Vec::new();
+    let mut fixes = Vec::new();
+    for fix in candidates {
+        if let Ok(fixed) = complete_set(&fix.apply(tiles)) {
+            let fixed = sorted_tiles(&fixed);
+            if !fixed_sets.contains(&fixed) {
+                fixed_sets.push(fixed);
+                fixes.push(fix);
+            }
+        }
+    }
+    fixes
+}
+
+// Checks that the table only holds legal sets and that the table and rack
+// together hold no more copies of a tile than the pool
+pub fn check_position(table: &[Set], rack: &Inventory) -> Result<()> {
+    for (index, set) in table.iter().enumerate() {
+        check_set(set).map_err(|error| eyre!("set {} of the table: {}", index + 1, error))?;
+    }
+
+    let mut audit = Audit::new();
+    for (index, set) in table.iter().enumerate() {
+        let tiles = Inventory::from_sets(std::slice::from_ref(set));
+        audit.record(&format!("set {} of the table", index + 1), &tiles)?;
+    }
+    audit.record("the rack", rack)
+}
+
+fn sorted_tiles(set: &Set) -> Vec<Tile> {
+    let mut tiles = set.tiles.to_vec();
+    tiles.sort_by_key(|tile| (tile.number, tile.color, tile.is_joker));
+    tiles
+}
+
+// Checks that the current player may replace the table with the new one under
+// the rules of the game and returns the rack that remains after the move
+pub fn validate_move(
+    table: &[Set],
+    rack: &Inventory,
+    new_table: &[Set],
+    opened: bool,
+    rules: &Ruleset,
+) -> Result<Inventory> {
+    for (index, set) in new_table.iter().enumerate() {
+        check_set(set).map_err(|error| {
+            diagnostic::coded(
+                "illegal_set",
+                format!("set {} of the new table: {}", index + 1, error),
+            )
+        })?;
+    }
+
+    let before = Inventory::from_sets(table);
+    let after = Inventory::from_sets(new_table);
+    if let Some(tile) = after.missing(&before).first() {
+        return Err(diagnostic::coded(
+            "taken_off_table",
+            format!("{} was taken off the table", tile),
+        ));
+    }
+    let mut played = after;
+    played.subtract(&before);
+    if let Some(tile) = rack.missing(&played).first() {
+        return Err(diagnostic::coded(
+            "not_on_rack",
+            format!("{} is not on the rack", tile),
+        ));
+    }
+    if played.total_tile_count() == 0 {
+        return Err(diagnostic::coded(
+            "nothing_laid_out",
+            "the move does not lay out any tile from the rack".to_string(),
+        ));
+    }
+
+    if !opened && rules.rearrange_on_opening {
+        check_meld_then_rearrange(table, new_table, &played)?;
+    } else if !opened {
+        // The initial meld may not touch the sets already on the table
+        let mut new_sets = new_table.iter().map(sorted_tiles).collect::<Vec<_>>();
+        for set in table {
+            let tiles = sorted_tiles(set);
+            match new_sets.iter().position(|other| *other == tiles) {
+                Some(index) => {
+                    new_sets.swap_remove(index);
+                }
+                None => {
+                    return Err(diagnostic::coded(
+                        "changed_before_meld",
+                        format!("{} cannot be changed before the initial meld", set),
+                    ))
+                }
+            }
+        }
+
+        let points = new_sets
+            .into_iter()
+            .map(|tiles| {
+                Set {
+                    tiles: Tiles::from_slice(&tiles),
+                }
+                .points()
+            })
+            .sum::<u32>();
+        if points < INITIAL_MELD_POINTS {
+            return Err(diagnostic::coded(
+                "meld_too_small",
+                format!(
+                    "the initial meld is worth {} points, it needs at least {}",
+                    points, INITIAL_MELD_POINTS
+                ),
+            ));
+        }
+    }
+
+    let mut rest = *rack;
+    rest.subtract(&played);
+    Ok(rest)
+}
+
+// The turn of the initial meld when the table may be rearranged after it. The
+// tiles from the rack have to make a meld worth enough on their own, laid out
+// before anything else moves, and jokers cannot be taken from the table yet.
+fn check_meld_then_rearrange(table: &[Set], new_table: &[Set], played: &Inventory) -> Result<()> {
+    let (points, _) = solver::max_points_meld(played);
+    if points < INITIAL_MELD_POINTS {
+        return Err(diagnostic::coded(
+            "meld_too_small",
+            format!(
+                "the tiles from the rack make an initial meld worth {} points, it needs at least {}",
+                points, INITIAL_MELD_POINTS
+            ),
+        ));
+    }
+    let mut new_sets = new_table.iter().map(sorted_tiles).collect::<Vec<_>>();
+    for set in table
+        .iter()
+        .filter(|set| set.tiles.iter().any(|tile| tile.is_joker))
+    {
+        let tiles = sorted_tiles(set);
+        match new_sets.iter().position(|other| *other == tiles) {
+            Some(index) => {
+                new_sets.swap_remove(index);
+            }
+            None => {
+                return Err(diagnostic::coded(
+                    "changed_before_meld",
+                    format!(
+                        "{} holds a joker and cannot be changed before the initial meld",
+                        set
+                    ),
+                ))
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,247 @@
+use crate::cli::Args;
+use crate::deal;
+use crate::fixtures;
+use crate::replay;
+use crate::solver::{self, Inventory, LayoutMemo, Objective, Set, Tile};
+use crate::validator;
+use color_eyre::{eyre::bail, eyre::Report};
+use std::collections::BTreeMap;
+use std::fs;
+
+// Racks printed for every kind of disagreement
+const EXAMPLES: usize = 5;
+// Kinds of tiles: 13 numbers in 4 colors and the joker
+const KINDS: usize = 53;
+
+#[derive(Default)]
+struct Findings {
+    checked: u64,
+    // Racks by the check they failed, with how many failed it
+    failures: BTreeMap<&'static str, (u64, Vec<String>)>,
+}
+
+impl Findings {
+    fn fail(&mut self, check: &'static str, rack: &Inventory) {
+        let (count, examples) = self.failures.entry(check).or_default();
+        *count += 1;
+        if examples.len() < EXAMPLES {
+            examples.push(replay::rack_text(rack));
+        }
+    }
+}
+
+// Whether every set is legal and the sets hold exactly the tiles
+fn lays_out(sets: &[Set], tiles: &Inventory) -> bool {
+    sets.iter().all(|set| validator::check_set(set).is_ok()) && Inventory::from_sets(sets) == *tiles
+}
+
+// Runs every backend on the rack and notes where they disagree
+fn verify(rack: &Inventory, findings: &mut Findings) {
+    findings.checked += 1;
+    let total = rack.total_tile_count();
+
+    // The same tiles have the same key however the inventory came about
+    let mut rebuilt = Inventory::new(0);
+    rack.tiles()
+        .iter()
+        .rev()
+        .for_each(|tile| rebuilt.add_tile(tile));
+    let mut others = Inventory::new(2);
+    others.subtract(rack);
+    if rebuilt.key() != rack.key() || others.combined(rack).key() != Inventory::new(2).key() {
+        findings.fail("equal inventories have different keys", rack);
+    }
+
+    // Laying out every tile
+    let search = solver::solve_all(rack).next();
+    let solvable = search.is_some();
+    let count = solver::count_solutions(rack);
+    let arranged = solver::arrange(rack, Objective::MinSets);
+    let first = solver::first_solution(rack);
+    for (check, sets) in [
+        (
+            "solve_all returns sets that do not lay out the rack",
+            &search,
+        ),
+        (
+            "arrange returns sets that do not lay out the rack",
+            &arranged,
+        ),
+        (
+            "first_solution returns sets that do not lay out the rack",
+            &first,
+        ),
+    ] {
+        if sets.as_ref().is_some_and(|sets| !lays_out(sets, rack)) {
+            findings.fail(check, rack);
+        }
+    }
+    if (count > 0) != solvable {
+        findings.fail(
+            "count_solutions and solve_all disagree on solvability",
+            rack,
+        );
+    }
+    if arranged.is_some() != solvable {
+        findings.fail("arrange and solve_all disagree on solvability", rack);
+    }
+    if solver::can_lay_out(rack, &mut LayoutMemo::new()) != solvable {
+        findings.fail("can_lay_out and solve_all disagree on solvability", rack);
+    }
+    // The search of the interactive solver leaves jokers out
+    if first.is_some() != solvable && rack.jokers() == 0 {
+        findings.fail("first_solution and solve_all disagree on solvability", rack);
+    }
+
+    // Laying out as many tiles as possible
+    let (most, meld) = solver::max_tiles_meld(rack);
+    let melded = Inventory::from_sets(&meld);
+    if !meld.iter().all(|set| validator::check_set(set).is_ok())
+        || !rack.missing(&melded).is_empty()
+        || melded.total_tile_count() != most
+    {
+        findings.fail("max_tiles_meld returns sets that are not on the rack", rack);
+    }
+    if (most == total) != solvable {
+        findings.fail(
+            "max_tiles_meld and solve_all disagree on laying out every tile",
+            rack,
+        );
+    }
+    let played =
+        solver::table_play(&[], rack).map_or(0, |(_, rest)| total - rest.total_tile_count());
+    if played != most {
+        findings.fail(
+            "table_play and max_tiles_meld lay out different numbers of tiles",
+            rack,
+        );
+    }
+}
+
+fn kind(index: usize) -> Tile {
+    Tile {
+        color: (index / 13) as u8,
+        number: (index % 13) as u8 + 1,
+        is_joker: index == KINDS - 1,
+    }
+}
+
+// Calls `visit` with every inventory of at most `left` more tiles, taking up
+// to two copies of every kind from `index` on
+fn each_inventory(
+    index: usize,
+    left: u32,
+    rack: &mut Inventory,
+    visit: &mut impl FnMut(&Inventory),
+) {
+    if index == KINDS || left == 0 {
+        visit(rack);
+        return;
+    }
+    let tile = kind(index);
+    each_inventory(index + 1, left, rack, visit);
+    for copies in 1..=left.min(2) {
+        rack.add_tile(&tile);
+        each_inventory(index + 1, left - copies, rack, visit);
+    }
+    for _ in 1..=left.min(2) {
+        rack.remove_tile(&tile);
+    }
+}
+
+// Cross-checks the solver backends: every inventory of up to --exhaustive
+// tiles, then --samples random racks of every size up to --max. The backends
+// have to agree on whether all tiles can be laid out and on the most tiles a
+// meld can hold, and every arrangement they return has to be legal. With
+// --fixtures they have to find the best moves of fixtures/optima.toml instead,
+// or with --fixtures-file those of a file in the same format.
+pub fn run(args: &Args) -> Result<(), Report> {
+    if let Some(path) = args.value("--fixtures-file") {
+        return fixtures::run(&fs::read_to_string(path)?);
+    }
+    if args.flag("--fixtures") {
+        return fixtures::run(fixtures::OPTIMA);
+    }
+    let exhaustive: u32 = args.parse_or("--exhaustive", 3)?;
+    let max: u32 = args.parse_or("--max", 14)?;
+    let samples: u32 = args.parse_or("--samples", 1000)?;
+
+    let mut findings = Findings::default();
+    each_inventory(0, exhaustive, &mut Inventory::new(0), &mut |rack| {
+        verify(rack, &mut findings)
+    });
+    println!(
+        "Checked all {} racks of up to {} tiles",
+        findings.checked, exhaustive
+    );
+    let before = findings.checked;
+    for size in exhaustive + 1..=max {
+        for _ in 0..samples {
+            let rack = deal::random_rack(size);
+            verify(&rack, &mut findings);
+            // Random racks can seldom be laid out completely, the tiles of
+            // their best meld always can
+            let (most, meld) = solver::max_tiles_meld(&rack);
+            if most > 0 && most < size {
+                verify(&Inventory::from_sets(&meld), &mut findings);
+            }
+        }
+    }
+    if max > exhaustive {
+        println!(
+            "Checked {} random racks of {} to {} tiles and their best melds",
+            findings.checked - before,
+            exhaustive + 1,
+            max
+        );
+    }
+
+    if findings.failures.is_empty() {
+        println!("All backends agree");
+        return Ok(());
+    }
+    for (check, (count, examples)) in &findings.failures {
+        println!("{} on {} racks, e.g.", check, count);
+        for example in examples {
+            println!(
+                "  {}",
+                if example.is_empty() {
+                    "(empty)"
+                } else {
+                    example
+                }
+            );
+        }
+    }
+    bail!(
+        "the backends disagree on {} kinds of checks",
+        findings.failures.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deal::Deal;
+
+    // What `verify --exhaustive 3 --max 14 --samples 200` checks, with seeded
+    // racks
+    #[test]
+    fn backends_agree_on_small_racks() {
+        let mut findings = Findings::default();
+        each_inventory(0, 3, &mut Inventory::new(0), &mut |rack| {
+            verify(rack, &mut findings)
+        });
+        for size in 4..=14 {
+            for seed in 0..200 {
+                let rack = Deal::new(1, size, seed).racks[0];
+                verify(&rack, &mut findings);
+                let (most, meld) = solver::max_tiles_meld(&rack);
+                if most > 0 && most < size {
+                    verify(&Inventory::from_sets(&meld), &mut findings);
+                }
+            }
+        }
+        assert!(findings.failures.is_empty(), "{:?}", findings.failures);
+    }
+}
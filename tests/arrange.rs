@@ -0,0 +1,21 @@
+#![cfg(feature = "std")]
+
+use rummikub_bot::notation;
+use rummikub_bot::solver::{self, Objective};
+
+// One long run beats two short ones, and shows before the group
+#[test]
+fn aesthetic_tables_show_the_longest_sets_first() {
+    let tiles = notation::parse_rack("B9 O9 K9 R1 R2 R3 R4 R5 R6").unwrap();
+    let sets = solver::arrange(&tiles, Objective::Aesthetic)
+        .unwrap()
+        .into_sets();
+    let lengths = sets
+        .iter()
+        .map(|set| set.tiles.len())
+        .collect::<Vec<usize>>();
+    assert_eq!(lengths, vec![6, 3]);
+
+    let split = notation::parse_table("R1 R2 R3; R4 R5 R6; B9 O9 K9").unwrap();
+    assert!(Objective::Aesthetic.cost(&sets) < Objective::Aesthetic.cost(&split));
+}
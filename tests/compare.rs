@@ -0,0 +1,54 @@
+#![cfg(feature = "cli")]
+
+use std::env;
+use std::fs;
+use std::process::Command;
+
+// Both configurations solve the corpus and the report compares them
+#[test]
+fn compare_reports_both_configurations() {
+    let directory = env::temp_dir().join(format!("compare-{}", std::process::id()));
+    let corpus = directory.join("corpus");
+    fs::create_dir_all(&corpus).unwrap();
+    fs::write(
+        corpus.join("run.json"),
+        r#"{"rack": "R5 R6 R7 B1", "table": "", "opened": true}"#,
+    )
+    .unwrap();
+    fs::write(directory.join("fast.toml"), "max_nodes = 1000\n").unwrap();
+    fs::write(directory.join("sets.toml"), "objective = \"min-sets\"\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rummikub_bot"))
+        .current_dir(&directory)
+        .args([
+            "compare",
+            "--config",
+            "fast.toml",
+            "--config",
+            "sets.toml",
+            "--corpus",
+            "corpus",
+            "--runs",
+            "2",
+        ])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        stdout.starts_with("A is fast, B is sets, 2 runs"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("run.json"), "{}", stdout);
+    assert!(
+        stdout.contains("Quality: 3.0 tiles played against 3.0"),
+        "{}",
+        stdout
+    );
+    fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,31 @@
+#![cfg(feature = "std")]
+
+// The checks of the fuzz target on inputs made of tile notation, so the
+// parsers are fuzzed a little by every test run as well
+#[path = "../fuzz/fuzz_targets/check.rs"]
+mod check;
+
+// Characters the parsers give a meaning to and a few they do not
+const ALPHABET: &[&str] = &[
+    "R", "B", "O", "K", "J", "?", "1", "3", "9", "10", "13", "0", " ", "  ", ",", ";", "\n", "[",
+    "]", ":", ".", "-", "*", "•", "é", "x",
+];
+
+#[test]
+fn parsers_take_any_notation() {
+    // xorshift, the same inputs on every run
+    let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    for _ in 0..20000 {
+        let length = next() % 24;
+        let text = (0..length)
+            .map(|_| ALPHABET[(next() % ALPHABET.len() as u64) as usize])
+            .collect::<String>();
+        check::parsers(&text);
+    }
+}
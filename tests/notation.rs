@@ -0,0 +1,23 @@
+#![cfg(feature = "std")]
+
+use rummikub_bot::{diagnostic, notation};
+
+// Offsets of the tile that cannot be read, in the rack as typed
+#[test]
+fn spans_of_a_rack_with_ghosts_point_into_the_input() {
+    for (rack, start) in [("R1  X9", 4), ("R1  ?  X9", 7), ("?,X9", 2), ("X9 ?", 0)] {
+        let spans = notation::parse_rack_with_ghosts(rack)
+            .err()
+            .map(|error| diagnostic::spans(&error))
+            .unwrap_or_default();
+        let starts = spans.iter().map(|span| span.start).collect::<Vec<usize>>();
+        assert_eq!(starts, vec![start], "{}", rack);
+    }
+}
+
+#[test]
+fn ghosts_are_counted_apart_from_the_rack() {
+    let parsed = notation::parse_rack_with_ghosts("R1 ? B2 ?").ok();
+    let counts = parsed.map(|(rack, ghosts)| (rack.total_tile_count(), ghosts));
+    assert_eq!(counts, Some((2, 2)));
+}
@@ -0,0 +1,144 @@
+use std::collections::HashSet;
+
+use rummikub_bot::tiles::{Inventory, Set, SetError, Tile, Tiles, MAX_SET_TILES};
+
+fn tile(color: u8, number: u8) -> Tile {
+    Tile {
+        color,
+        number,
+        is_joker: false,
+    }
+}
+
+#[test]
+fn the_longest_run_and_group_fit_inline() {
+    let run = (1..=13)
+        .map(|number| tile(2, number))
+        .collect::<Vec<Tile>>();
+    let group = (0..4).map(|color| tile(color, 7)).collect::<Vec<Tile>>();
+    for tiles in [run, group] {
+        let set = Set {
+            tiles: Tiles::from_slice(&tiles),
+        };
+        let copy = set;
+        assert_eq!(*copy.tiles, *tiles);
+        assert_eq!(copy, set);
+        assert_eq!(set.check(), Ok(()));
+    }
+}
+
+#[test]
+fn tiles_past_the_longest_set_are_left_out() {
+    let mut tiles = (1..=13).map(|number| tile(0, number)).collect::<Tiles>();
+    tiles.push(tile(1, 1));
+    assert_eq!(tiles.len(), MAX_SET_TILES);
+    assert_eq!(tiles.last(), Some(&tile(0, 13)));
+}
+
+#[test]
+fn push_and_pop_keep_the_order() {
+    let mut tiles = Tiles::new();
+    assert_eq!(tiles.pop(), None);
+    tiles.push(tile(0, 1));
+    tiles.push(tile(0, 2));
+    assert_eq!(tiles.pop(), Some(tile(0, 2)));
+    assert_eq!(*tiles, [tile(0, 1)]);
+    assert_eq!(tiles.pop(), Some(tile(0, 1)));
+    assert!(tiles.is_empty());
+}
+
+// Tiles left behind by pop do not count for equality or hashing
+#[test]
+fn only_tiles_in_use_are_compared() {
+    let mut popped = Tiles::from_slice(&[tile(0, 1), tile(0, 2), tile(0, 3), tile(0, 4)]);
+    popped.pop();
+    let fresh = Tiles::from_slice(&[tile(0, 1), tile(0, 2), tile(0, 3)]);
+    assert_eq!(popped, fresh);
+    let sets = [popped, fresh]
+        .into_iter()
+        .map(|tiles| Set { tiles })
+        .collect::<HashSet<Set>>();
+    assert_eq!(sets.len(), 1);
+}
+
+#[test]
+fn check_names_what_is_wrong() {
+    let set = |tiles: &[Tile]| Set {
+        tiles: Tiles::from_slice(tiles),
+    };
+    assert_eq!(
+        set(&[tile(0, 1), tile(0, 2)]).check(),
+        Err(SetError::TooFew)
+    );
+    assert_eq!(
+        set(&[tile(0, 1), tile(1, 2), tile(0, 3)]).check(),
+        Err(SetError::MixedColors)
+    );
+    assert_eq!(
+        set(&[tile(0, 5), tile(1, 5), tile(0, 5)]).check(),
+        Err(SetError::SameColor(0))
+    );
+}
+
+// The key an inventory of these tiles gets when built from nothing
+fn fresh_key(inventory: &Inventory) -> u64 {
+    let mut fresh = Inventory::new(0);
+    for tile in inventory.tiles() {
+        fresh.add_tile(&tile);
+    }
+    fresh.key()
+}
+
+// Tiles come and go one by one as a search tries moves and takes them back
+#[test]
+fn incremental_keys_match_fresh_ones() {
+    // xorshift, the same tiles on every run
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let mut inventory = Inventory::new(1);
+    let mut applied = Vec::new();
+    for _ in 0..500 {
+        let roll = next();
+        let tile = if roll % 20 == 0 {
+            Tile {
+                color: 0,
+                number: 0,
+                is_joker: true,
+            }
+        } else {
+            tile((roll >> 8) as u8 % 4, (roll >> 16) as u8 % 13 + 1)
+        };
+        let added = roll >> 32 & 1 == 0;
+        let before = inventory.key();
+        if added {
+            inventory.add_tile(&tile);
+        } else if inventory.count(&tile) > 0 {
+            inventory.remove_tile(&tile);
+        } else {
+            continue;
+        }
+        assert_eq!(inventory.key(), fresh_key(&inventory));
+        applied.push((tile, added, before));
+    }
+    while let Some((tile, added, before)) = applied.pop() {
+        if added {
+            inventory.remove_tile(&tile);
+        } else {
+            inventory.add_tile(&tile);
+        }
+        assert_eq!(inventory.key(), before);
+        assert_eq!(inventory.key(), fresh_key(&inventory));
+    }
+    assert!(inventory == Inventory::new(1));
+
+    let doubled = inventory.combined(&inventory);
+    assert_eq!(doubled.key(), Inventory::new(2).key());
+    let mut emptied = doubled;
+    emptied.subtract(&doubled);
+    assert_eq!(emptied.key(), Inventory::new(0).key());
+}